@@ -4,32 +4,164 @@
 [[group(1), binding(0)]]
 var<uniform> mesh: Mesh;
 
+struct CellSettings {
+    // x: enabled
+    emissive_mask: vec4<f32>;
+    // x: 0 flat, 1 gradient, 2 starfield, 3 skybox
+    background: vec4<f32>;
+    background_top: vec4<f32>;
+    background_bottom: vec4<f32>;
+    // x: enabled, y: density
+    fog: vec4<f32>;
+    fog_color: vec4<f32>;
+    // x: seconds since startup, y: fade in duration
+    time: vec4<f32>;
+    // x: gap between chunks
+    explode: vec4<f32>;
+    // x: enabled, y: strength
+    face_shading: vec4<f32>;
+    // x: grid center in cells, y: chunk size, z: chunks per axis
+    grid: vec4<f32>;
+};
+
+[[group(2), binding(0)]]
+var<uniform> settings: CellSettings;
+
 struct Vertex {
     [[location(0)]] position: vec3<f32>;
     [[location(1)]] normal: vec3<f32>;
     [[location(2)]] uv: vec2<f32>;
 
-    [[location(3)]] i_pos_scale: vec4<f32>;
+    // xyz: center in half cells from the grid's corner, w: scale in 1/16 cells
+    [[location(3)]] i_pos_scale: vec4<u32>;
     [[location(4)]] i_color: vec4<f32>;
+    [[location(5)]] i_spawn_time: f32;
+    // 0: solid, 1: emissive, 2: translucent. see `CellMaterial`.
+    [[location(6)]] i_material: u32;
 };
 
 struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
     [[location(0)]] color: vec4<f32>;
+    [[location(1)]] uv: vec2<f32>;
+    [[location(2)]] world_position: vec3<f32>;
+    [[location(3)]] normal: vec3<f32>;
+    [[location(4), interpolate(flat)]] material: u32;
 };
 
 [[stage(vertex)]]
 fn vertex(vertex: Vertex) -> VertexOutput {
-    let position = vertex.position * vertex.i_pos_scale.w + vertex.i_pos_scale.xyz;
-    let world_position = mesh.model * vec4<f32>(position, 1.0);
+    var scale = f32(vertex.i_pos_scale.w) / 16.0;
+    if (settings.time.y > 0.0) {
+        scale = scale * clamp((settings.time.x - vertex.i_spawn_time) / settings.time.y, 0.0, 1.0);
+    }
+#ifdef BACKGROUND
+    let center = vec3<f32>(0.0);
+#else
+    let grid_position = vec3<f32>(vertex.i_pos_scale.xyz) * 0.5;
+    // the chunk relative to the center, pushed out by the explode gap.
+    let chunk_offset = floor(grid_position / settings.grid.y) + 0.5 - settings.grid.z * 0.5;
+    let center = grid_position - settings.grid.x + chunk_offset * settings.explode.x;
+#endif
+#ifdef BILLBOARD
+    // span the quad along the camera's right and up axes.
+    let camera_right = view.view[0].xyz;
+    let camera_up = view.view[1].xyz;
+    let offset = camera_right * vertex.position.x + camera_up * vertex.position.y;
+    // the quad is spanned in world space, so a rotated volume doesn't tilt
+    // it. the volume's scale still applies.
+    let model_scale = length(mesh.model[0].xyz);
+    let world_position = mesh.model * vec4<f32>(center, 1.0) + vec4<f32>(offset * scale * model_scale, 0.0);
+#else
+    let world_position = mesh.model * vec4<f32>(vertex.position * scale + center, 1.0);
+#endif
 
     var out: VertexOutput;
     out.clip_position = view.view_proj * world_position;
     out.color = vertex.i_color;
+    out.uv = vertex.uv;
+    out.world_position = world_position.xyz;
+    out.normal = (mesh.model * vec4<f32>(vertex.normal, 0.0)).xyz;
+    out.material = vertex.i_material;
     return out;
 }
 
+fn hash(p: vec3<f32>) -> f32 {
+    return fract(sin(dot(p, vec3<f32>(12.9898, 78.233, 45.164))) * 43758.5453);
+}
+
+fn background(direction: vec3<f32>) -> vec4<f32> {
+    let top = settings.background_top;
+    let bottom = settings.background_bottom;
+    let height = direction.y * 0.5 + 0.5;
+    let mode = settings.background.x;
+
+    if (mode < 0.5) {
+        return top;
+    }
+    if (mode < 1.5) {
+        return mix(bottom, top, height);
+    }
+    if (mode < 2.5) {
+        let sky = mix(bottom, top, height) * 0.15;
+        let star = step(0.998, hash(floor(direction * 400.0)));
+        return vec4<f32>(sky.rgb + vec3<f32>(star), 1.0);
+    }
+
+    // procedural sky: horizon haze, ground, and a sun.
+    let sun_direction = normalize(vec3<f32>(0.3, 0.6, 0.4));
+    let sun = pow(max(dot(direction, sun_direction), 0.0), 256.0);
+    var sky = mix(vec4<f32>(1.0, 1.0, 1.0, 1.0), top, sqrt(max(direction.y, 0.0)));
+    if (direction.y < 0.0) {
+        sky = mix(sky, bottom, min(-direction.y * 8.0, 1.0));
+    }
+    return vec4<f32>(sky.rgb + vec3<f32>(sun), 1.0);
+}
+
 [[stage(fragment)]]
 fn fragment(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-    return in.color;
+#ifdef BACKGROUND
+    return background(normalize(in.world_position - view.world_position));
+#else
+#ifdef BILLBOARD
+    // round points.
+    if (length(in.uv - vec2<f32>(0.5, 0.5)) > 0.5) {
+        discard;
+    }
+#endif
+    // translucent: a screen door, every other pixel is dropped, so the
+    // cells need neither sorting nor blending.
+    if (in.material == 2u && (u32(in.clip_position.x) + u32(in.clip_position.y)) % 2u == 0u) {
+        discard;
+    }
+    var color = in.color;
+#ifndef BILLBOARD
+    // face shading: n.l against a fixed light, times a tint per axis, so
+    // neighboring faces never end up the same color. emissive cells light
+    // themselves.
+    if (settings.face_shading.x > 0.5 && in.material != 1u) {
+        let normal = normalize(in.normal);
+        let light = normalize(vec3<f32>(0.3, 0.8, 0.5));
+        let axis = abs(normal);
+        let tint = axis.x * 0.8 + axis.y * 1.0 + axis.z * 0.65;
+        let shade = tint * (0.6 + 0.4 * max(dot(normal, light), 0.0));
+        color = vec4<f32>(color.rgb * mix(1.0, shade, settings.face_shading.y), color.a);
+    }
+#endif
+    if (in.material == 1u) {
+        color = vec4<f32>(color.rgb * 1.5, color.a);
+    }
+    if (settings.fog.x > 0.5) {
+        let view_distance = length(in.world_position - view.world_position);
+        let fog = 1.0 - exp(-settings.fog.y * view_distance);
+        color = vec4<f32>(mix(color.rgb, settings.fog_color.rgb, fog), color.a);
+    }
+#ifdef EMISSIVE_MASK
+    // the bloom of the post pass reads the emissive cells from alpha.
+    if (settings.emissive_mask.x > 0.5) {
+        color = vec4<f32>(color.rgb, select(1.0, 0.0, in.material == 1u));
+    }
+#endif
+    return color;
+#endif
 }
\ No newline at end of file
@@ -0,0 +1,157 @@
+// builds the cell instances from the 3d texture of the cells, see
+// gpu_instances.rs. a stream compaction in three passes: `count_chunks`
+// counts the live cells of every chunk, `scan_chunks` turns the counts into
+// the first instance of every chunk with a prefix sum, and `write_instances`
+// gives every live cell the slot after the live cells before it. the scan
+// also puts the instance count into the draw's indirect arguments.
+// a workgroup covers one chunk, each thread a run of 128 cells in row major
+// order, so the instances come out in the same order every frame.
+
+struct Params {
+    // x: bounds, y: chunks per axis, z: instance capacity, w: 1 to color by
+    // the distance to the center, 0 by state
+    grid: vec4<u32>;
+};
+
+struct Words {
+    data: array<u32>;
+};
+
+[[group(0), binding(0)]]
+var cells: texture_3d<u32>;
+[[group(0), binding(1)]]
+var<uniform> params: Params;
+// packed rgba8, per state or per 1/255 of the distance to the center.
+[[group(0), binding(2)]]
+var<storage, read> colors: Words;
+// live cells per chunk.
+[[group(0), binding(3)]]
+var<storage, read_write> counts: Words;
+// the first instance of every chunk.
+[[group(0), binding(4)]]
+var<storage, read_write> offsets: Words;
+// `InstanceData`, 5 words each.
+[[group(0), binding(5)]]
+var<storage, read_write> instances: Words;
+// `draw_indexed_indirect` arguments, only the instance count is written here.
+[[group(0), binding(6)]]
+var<storage, read_write> draw: Words;
+
+let CHUNK_SIZE: u32 = 32u;
+let CELLS_PER_THREAD: u32 = 128u;
+
+var<workgroup> partials: array<u32, 256>;
+
+// the position and value of the chunk's cell `i`. the chunks along the far
+// sides reach past the grid, the cells out there are dead.
+fn load_cell(chunk: u32, i: u32) -> vec4<u32> {
+    let radius = params.grid.y;
+    let chunk_min = vec3<u32>(chunk % radius, chunk / radius % radius, chunk / radius / radius) * CHUNK_SIZE;
+    let pos = chunk_min + vec3<u32>(i % CHUNK_SIZE, i / CHUNK_SIZE % CHUNK_SIZE, i / CHUNK_SIZE / CHUNK_SIZE);
+    if (any(pos >= vec3<u32>(params.grid.x))) {
+        return vec4<u32>(pos, 0u);
+    }
+    return vec4<u32>(pos, textureLoad(cells, vec3<i32>(pos), 0).x);
+}
+
+fn count_run(chunk: u32, thread: u32) -> u32 {
+    var count = 0u;
+    for (var i = 0u; i < CELLS_PER_THREAD; i = i + 1u) {
+        if (load_cell(chunk, thread * CELLS_PER_THREAD + i).w != 0u) {
+            count = count + 1u;
+        }
+    }
+    return count;
+}
+
+// x: the sum of `value` over the threads before this one, y: over all of
+// them. a hillis steele scan in workgroup memory.
+fn workgroup_scan(thread: u32, value: u32) -> vec2<u32> {
+    partials[thread] = value;
+    workgroupBarrier();
+    for (var offset = 1u; offset < 256u; offset = offset * 2u) {
+        var before = 0u;
+        if (thread >= offset) {
+            before = partials[thread - offset];
+        }
+        workgroupBarrier();
+        partials[thread] = partials[thread] + before;
+        workgroupBarrier();
+    }
+    return vec2<u32>(partials[thread] - value, partials[255]);
+}
+
+[[stage(compute), workgroup_size(256)]]
+fn count_chunks(
+    [[builtin(workgroup_id)]] group: vec3<u32>,
+    [[builtin(local_invocation_index)]] thread: u32,
+) {
+    let total = workgroup_scan(thread, count_run(group.x, thread)).y;
+    if (thread == 0u) {
+        counts.data[group.x] = total;
+    }
+}
+
+// a single workgroup, each thread sums a run of chunks.
+[[stage(compute), workgroup_size(256)]]
+fn scan_chunks([[builtin(local_invocation_index)]] thread: u32) {
+    let chunks = params.grid.y * params.grid.y * params.grid.y;
+    let per_thread = (chunks + 255u) / 256u;
+    let first = min(thread * per_thread, chunks);
+    let last = min(first + per_thread, chunks);
+    var sum = 0u;
+    for (var i = first; i < last; i = i + 1u) {
+        sum = sum + counts.data[i];
+    }
+    let scan = workgroup_scan(thread, sum);
+    var offset = scan.x;
+    for (var i = first; i < last; i = i + 1u) {
+        offsets.data[i] = offset;
+        offset = offset + counts.data[i];
+    }
+    if (thread == 0u) {
+        draw.data[1] = min(scan.y, params.grid.z);
+    }
+}
+
+fn color(pos: vec3<u32>, value: u32) -> u32 {
+    if (params.grid.w == 0u) {
+        return colors.data[min(value, arrayLength(&colors.data) - 1u)];
+    }
+    // `utils::dist_to_center`, the palette clamps it to 1.
+    let bounds = params.grid.x;
+    let center = vec3<f32>(f32(bounds / 2u));
+    let distance = min(length(vec3<f32>(pos) - center) / (f32(bounds) * 0.5), 1.0);
+    return colors.data[u32(round(distance * 255.0))];
+}
+
+// see `InstanceData::cell`: the center in half cells, a scale of 1 in 1/16
+// cells, no fade in and the solid material.
+fn write_instance(index: u32, pos: vec3<u32>, value: u32) {
+    let half_cells = pos * 2u;
+    let word = index * 5u;
+    instances.data[word] = half_cells.x | (half_cells.y << 16u);
+    instances.data[word + 1u] = half_cells.z | (16u << 16u);
+    instances.data[word + 2u] = color(pos, value);
+    // f32::MIN
+    instances.data[word + 3u] = 0xff7fffffu;
+    instances.data[word + 4u] = 0u;
+}
+
+// counts its run again, cheaper than keeping the counts of every thread
+// between passes. cells past the capacity are dropped.
+[[stage(compute), workgroup_size(256)]]
+fn write_instances(
+    [[builtin(workgroup_id)]] group: vec3<u32>,
+    [[builtin(local_invocation_index)]] thread: u32,
+) {
+    let chunk = group.x;
+    var index = offsets.data[chunk] + workgroup_scan(thread, count_run(chunk, thread)).x;
+    for (var i = 0u; i < CELLS_PER_THREAD; i = i + 1u) {
+        let cell = load_cell(chunk, thread * CELLS_PER_THREAD + i);
+        if (cell.w != 0u && index < params.grid.z) {
+            write_instance(index, cell.xyz, cell.w);
+            index = index + 1u;
+        }
+    }
+}
@@ -0,0 +1,190 @@
+// the post pass on the finished 3d frame, see post_process.rs. every entry
+// point draws one fullscreen triangle.
+
+struct PostSettings {
+    // x: enabled, y: intensity, z: threshold, w: spread in bloom texels
+    bloom: vec4<f32>;
+    // x: enabled, y: focus distance, z: focus range, w: max blur radius in pixels
+    depth_of_field: vec4<f32>;
+    // x: enabled, y: thickness in pixels, z: depth threshold, w: normal threshold
+    outline: vec4<f32>;
+    outline_color: vec4<f32>;
+    // x: near plane, y: projection[0][0], z: projection[1][1]
+    projection: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var source: texture_2d<f32>;
+[[group(0), binding(1)]]
+var source_sampler: sampler;
+[[group(0), binding(2)]]
+var<uniform> settings: PostSettings;
+[[group(0), binding(3)]]
+var bloom: texture_2d<f32>;
+#ifdef MULTISAMPLED
+[[group(0), binding(4)]]
+var depth: texture_depth_multisampled_2d;
+#else
+[[group(0), binding(4)]]
+var depth: texture_depth_2d;
+#endif
+[[group(0), binding(5)]]
+var normals: texture_2d<f32>;
+
+struct FullscreenVertex {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vertex([[builtin(vertex_index)]] index: u32) -> FullscreenVertex {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: FullscreenVertex;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+// the bright parts of the frame, into the half resolution bloom texture.
+// the cell shader writes 0 alpha for emissive cells, those always glow.
+[[stage(fragment)]]
+fn bloom_prefilter(in: FullscreenVertex) -> [[location(0)]] vec4<f32> {
+    let color = textureSampleLevel(source, source_sampler, in.uv, 0.0);
+    let luminance = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    let bright = max(luminance - settings.bloom.z, 0.0) / max(luminance, 0.0001);
+    let emissive = 1.0 - color.a;
+    return vec4<f32>(color.rgb * max(bright, emissive), 1.0);
+}
+
+// 9 taps of a gaussian in 5 bilinear samples.
+fn blur(uv: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    let offset = direction * settings.bloom.w / vec2<f32>(textureDimensions(source));
+    var color = textureSampleLevel(source, source_sampler, uv, 0.0) * 0.2270270270;
+    color = color + textureSampleLevel(source, source_sampler, uv + offset * 1.3846153846, 0.0) * 0.3162162162;
+    color = color + textureSampleLevel(source, source_sampler, uv - offset * 1.3846153846, 0.0) * 0.3162162162;
+    color = color + textureSampleLevel(source, source_sampler, uv + offset * 3.2307692308, 0.0) * 0.0702702703;
+    color = color + textureSampleLevel(source, source_sampler, uv - offset * 3.2307692308, 0.0) * 0.0702702703;
+    return color;
+}
+
+[[stage(fragment)]]
+fn bloom_blur_horizontal(in: FullscreenVertex) -> [[location(0)]] vec4<f32> {
+    return blur(in.uv, vec2<f32>(1.0, 0.0));
+}
+
+[[stage(fragment)]]
+fn bloom_blur_vertical(in: FullscreenVertex) -> [[location(0)]] vec4<f32> {
+    return blur(in.uv, vec2<f32>(0.0, 1.0));
+}
+
+// the first sample when multisampled.
+fn load_depth(pixel: vec2<i32>) -> f32 {
+    let clamped = clamp(pixel, vec2<i32>(0), textureDimensions(depth) - 1);
+    return textureLoad(depth, clamped, 0);
+}
+
+// the view space distance along the camera's forward axis. bevy's projection
+// is an infinite reverse z one, the device depth is near / distance.
+fn linear_depth(device_depth: f32) -> f32 {
+    return settings.projection.x / max(device_depth, 0.000001);
+}
+
+// the view space position of a pixel.
+fn view_position(pixel: vec2<i32>) -> vec3<f32> {
+    let size = vec2<f32>(textureDimensions(depth));
+    let ndc = (vec2<f32>(pixel) + 0.5) / size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    let distance = linear_depth(load_depth(pixel));
+    return vec3<f32>(ndc.x / settings.projection.y, ndc.y / settings.projection.z, -1.0) * distance;
+}
+
+// view space normals reconstructed from the depth, for the outline. of the
+// two neighbors on an axis, the one closer in depth is used, so a pixel on
+// a silhouette gets the normal of its own surface.
+[[stage(fragment)]]
+fn reconstruct_normals(in: FullscreenVertex) -> [[location(0)]] vec4<f32> {
+    let pixel = vec2<i32>(in.position.xy);
+    let center = view_position(pixel);
+    let left = view_position(pixel - vec2<i32>(1, 0));
+    let right = view_position(pixel + vec2<i32>(1, 0));
+    let up = view_position(pixel - vec2<i32>(0, 1));
+    let down = view_position(pixel + vec2<i32>(0, 1));
+    var dx = right - center;
+    if (abs(left.z - center.z) < abs(right.z - center.z)) {
+        dx = center - left;
+    }
+    var dy = down - center;
+    if (abs(up.z - center.z) < abs(down.z - center.z)) {
+        dy = center - up;
+    }
+    // pixel rows go down, so this faces the camera.
+    return vec4<f32>(normalize(cross(dy, dx)), 1.0);
+}
+
+// 1 if the depth jumps or the normal turns between the pixel and `pixel`.
+// grazing faces change depth fast without an edge, the depth threshold
+// grows with the angle.
+fn edge_to(pixel: vec2<i32>, center_depth: f32, center_normal: vec3<f32>) -> f32 {
+    let clamped = clamp(pixel, vec2<i32>(0), textureDimensions(normals) - 1);
+    let depth_jump = abs(linear_depth(load_depth(clamped)) - center_depth) / center_depth;
+    let normal_turn = 1.0 - dot(textureLoad(normals, clamped, 0).xyz, center_normal);
+    let depth_threshold = settings.outline.z / max(center_normal.z, 0.1);
+    return max(step(depth_threshold, depth_jump), step(settings.outline.w, normal_turn));
+}
+
+// both sides of an edge are marked, so the neighbors are half the
+// thickness away.
+fn outline(pixel: vec2<i32>) -> f32 {
+    let r = max(i32(round(settings.outline.y * 0.5)), 1);
+    let center_depth = linear_depth(load_depth(pixel));
+    let center_normal = textureLoad(normals, pixel, 0).xyz;
+    var edge = edge_to(pixel + vec2<i32>(r, 0), center_depth, center_normal);
+    edge = max(edge, edge_to(pixel - vec2<i32>(r, 0), center_depth, center_normal));
+    edge = max(edge, edge_to(pixel + vec2<i32>(0, r), center_depth, center_normal));
+    edge = max(edge, edge_to(pixel - vec2<i32>(0, r), center_depth, center_normal));
+    return edge;
+}
+
+fn blur_radius(pixel: vec2<i32>) -> f32 {
+    let distance = linear_depth(load_depth(pixel));
+    let amount = clamp(abs(distance - settings.depth_of_field.y) / settings.depth_of_field.z, 0.0, 1.0);
+    return amount * settings.depth_of_field.w;
+}
+
+// gathers a disc of samples along a golden angle spiral. a sample only
+// counts if its own blur reaches this pixel, so cells in focus don't bleed
+// into the blur around them.
+fn depth_of_field(uv: vec2<f32>, pixel: vec2<i32>) -> vec3<f32> {
+    let center = textureSampleLevel(source, source_sampler, uv, 0.0).rgb;
+    let radius = blur_radius(pixel);
+    if (radius < 0.5) {
+        return center;
+    }
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+    var sum = center;
+    var weight = 1.0;
+    for (var i: i32 = 0; i < 24; i = i + 1) {
+        let r = sqrt((f32(i) + 0.5) / 24.0) * radius;
+        let angle = f32(i) * 2.39996;
+        let offset = vec2<f32>(cos(angle), sin(angle)) * r;
+        let w = clamp(blur_radius(pixel + vec2<i32>(offset)) - r + 1.0, 0.0, 1.0);
+        sum = sum + textureSampleLevel(source, source_sampler, uv + offset * texel, 0.0).rgb * w;
+        weight = weight + w;
+    }
+    return sum / weight;
+}
+
+[[stage(fragment)]]
+fn composite(in: FullscreenVertex) -> [[location(0)]] vec4<f32> {
+    let pixel = vec2<i32>(in.position.xy);
+    var color = textureSampleLevel(source, source_sampler, in.uv, 0.0).rgb;
+    if (settings.depth_of_field.x > 0.5) {
+        color = depth_of_field(in.uv, pixel);
+    }
+    if (settings.bloom.x > 0.5) {
+        color = color + textureSampleLevel(bloom, source_sampler, in.uv, 0.0).rgb * settings.bloom.y;
+    }
+    if (settings.outline.x > 0.5) {
+        color = mix(color, settings.outline_color.rgb, outline(pixel) * settings.outline_color.a);
+    }
+    return vec4<f32>(color, 1.0);
+}
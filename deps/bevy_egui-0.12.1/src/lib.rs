@@ -215,6 +215,7 @@ pub struct EguiContext {
     user_textures: HashMap<HandleId, u64>,
     last_texture_id: u64,
     mouse_position: Option<(WindowId, egui::Vec2)>,
+    default_window: WindowId,
 }
 
 impl EguiContext {
@@ -224,17 +225,33 @@ impl EguiContext {
             user_textures: Default::default(),
             last_texture_id: 0,
             mouse_position: None,
+            default_window: WindowId::primary(),
         }
     }
 
-    /// Egui context of the primary window.
+    /// Makes [`EguiContext::ctx_mut`] return the context of another window than the primary one.
+    /// Until that window exists, the primary window is used.
+    pub fn set_default_window(&mut self, window: WindowId) {
+        self.default_window = window;
+    }
+
+    fn default_window(&self) -> WindowId {
+        if self.ctx.contains_key(&self.default_window) {
+            self.default_window
+        } else {
+            WindowId::primary()
+        }
+    }
+
+    /// Egui context of the primary window, or of the window set with
+    /// [`EguiContext::set_default_window`].
     ///
     /// This function is only available when the `multi_threaded` feature is enabled.
     /// The preferable way is to use `ctx_mut` to avoid unpredictable blocking inside UI systems.
     #[cfg(feature = "multi_threaded")]
     #[track_caller]
     pub fn ctx(&self) -> &egui::Context {
-        self.ctx.get(&WindowId::primary()).expect("`EguiContext::ctx` was called for an uninitialized context (primary window), consider moving your startup system to the `StartupStage::Startup` stage or run it after the `EguiStartupSystem::InitContexts` system")
+        self.ctx.get(&self.default_window()).expect("`EguiContext::ctx` was called for an uninitialized context (primary window), consider moving your startup system to the `StartupStage::Startup` stage or run it after the `EguiStartupSystem::InitContexts` system")
     }
 
     /// Egui context for a specific window.
@@ -263,10 +280,11 @@ impl EguiContext {
         self.ctx.get(&window)
     }
 
-    /// Egui context of the primary window.
+    /// Egui context of the primary window, or of the window set with
+    /// [`EguiContext::set_default_window`].
     #[track_caller]
     pub fn ctx_mut(&mut self) -> &egui::Context {
-        self.ctx.get(&WindowId::primary()).expect("`EguiContext::ctx_mut` was called for an uninitialized context (primary window), consider moving your startup system to the `StartupStage::Startup` stage or run it after the `EguiStartupSystem::InitContexts` system")
+        self.ctx.get(&self.default_window()).expect("`EguiContext::ctx_mut` was called for an uninitialized context (primary window), consider moving your startup system to the `StartupStage::Startup` stage or run it after the `EguiStartupSystem::InitContexts` system")
     }
 
     /// Egui context for a specific window.
@@ -0,0 +1,107 @@
+# imports a camera path written by the "Camera export" window into blender.
+#
+#   blender --python scripts/blender_import.py -- camera_path.json
+#
+# or open it in blender's text editor, set PATH below and run it. it adds a
+# keyframed camera and, if the file has a captured tick, one mesh with a cube
+# per cell, colored by a "color" attribute. bevy is y up, blender z up, the
+# script converts.
+
+import json
+import math
+import sys
+
+import bpy
+from mathutils import Matrix, Quaternion, Vector
+
+PATH = "camera_path.json"
+VERSION = 1
+
+# bevy (y up) to blender (z up).
+Y_UP_TO_Z_UP = Matrix.Rotation(math.radians(90.0), 4, "X")
+
+
+def transform(translation, rotation, scale=1.0):
+    x, y, z, w = rotation
+    matrix = Quaternion((w, x, y, z)).to_matrix().to_4x4() * scale
+    matrix.translation = Vector(translation)
+    matrix[3][3] = 1.0
+    return Y_UP_TO_Z_UP @ matrix
+
+
+def srgb_to_linear(c):
+    return c / 12.92 if c <= 0.04045 else ((c + 0.055) / 1.055) ** 2.4
+
+
+def import_camera(data):
+    scene = bpy.context.scene
+    scene.render.fps = data["fps"]
+    scene.render.resolution_y = round(scene.render.resolution_x / data["aspect"])
+
+    camera_data = bpy.data.cameras.new("ca3d camera")
+    camera_data.sensor_fit = "VERTICAL"
+    camera = bpy.data.objects.new("ca3d camera", camera_data)
+    scene.collection.objects.link(camera)
+    scene.camera = camera
+
+    camera.rotation_mode = "QUATERNION"
+    for frame in data["frames"]:
+        number = 1 + round(frame["time"] * data["fps"])
+        camera.matrix_world = transform(frame["translation"], frame["rotation"])
+        camera.keyframe_insert("location", frame=number)
+        camera.keyframe_insert("rotation_quaternion", frame=number)
+        camera_data.angle = frame["fov"]
+        camera_data.keyframe_insert("lens", frame=number)
+
+    scene.frame_start = 1
+    scene.frame_end = 1 + round(data["frames"][-1]["time"] * data["fps"])
+
+
+def import_cells(cells):
+    corners = [(x, y, z) for z in (-0.5, 0.5) for y in (-0.5, 0.5) for x in (-0.5, 0.5)]
+    quads = [(0, 2, 3, 1), (4, 5, 7, 6), (0, 1, 5, 4), (2, 6, 7, 3), (0, 4, 6, 2), (1, 3, 7, 5)]
+
+    vertices, faces, colors = [], [], []
+    for instance in cells["instances"]:
+        px, py, pz = instance["position"]
+        size = instance["size"]
+        base = len(vertices)
+        vertices.extend((px + x * size, py + y * size, pz + z * size) for x, y, z in corners)
+        faces.extend(tuple(base + i for i in quad) for quad in quads)
+        r, g, b, a = instance["color"]
+        colors.extend([(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)] * len(quads))
+
+    mesh = bpy.data.meshes.new("ca3d cells tick %d" % cells["tick"])
+    mesh.from_pydata(vertices, [], faces)
+    attribute = mesh.attributes.new("color", "FLOAT_COLOR", "FACE")
+    for data, color in zip(attribute.data, colors):
+        data.color = color
+
+    material = bpy.data.materials.new("ca3d cells")
+    material.use_nodes = True
+    nodes = material.node_tree.nodes
+    color_node = nodes.new("ShaderNodeAttribute")
+    color_node.attribute_name = "color"
+    material.node_tree.links.new(color_node.outputs["Color"], nodes["Principled BSDF"].inputs["Base Color"])
+    mesh.materials.append(material)
+
+    volume = cells["volume"]
+    cells_object = bpy.data.objects.new(mesh.name, mesh)
+    cells_object.matrix_world = transform(volume["translation"], volume["rotation"], volume["scale"])
+    bpy.context.scene.collection.objects.link(cells_object)
+
+
+def main():
+    args = sys.argv[sys.argv.index("--") + 1:] if "--" in sys.argv else []
+    path = args[0] if args else PATH
+    with open(path) as file:
+        data = json.load(file)
+    if data.get("version") != VERSION:
+        raise ValueError("%s: expected version %d, got %s" % (path, VERSION, data.get("version")))
+
+    import_camera(data)
+    if data.get("cells"):
+        import_cells(data["cells"])
+
+
+main()
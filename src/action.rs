@@ -0,0 +1,198 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+
+use crate::{
+    app_state::{has_ui, simulating},
+    cell_renderer::{MaterialThresholds, NeighbourFilter},
+    cells::{DefectSettings, EnergySettings, Sims, SimsUpdate},
+    rule::{CellValue, ColorMethod, Rule, COLOR_METHODS},
+    theme::{Theme, THEMES},
+};
+
+
+// every user facing operation on the simulation. the Celluar window, the
+// keybindings, the command palette, the console and its hooks, timeline
+// playback and network viewers all send these as events, `handle` is the
+// only place that applies them. sent after `SimsUpdate`, they apply on the
+// next frame. the backend and view settings are actions too, so anything
+// that sends actions can drive them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    SpawnNoise,
+    Reset,
+    SetPaused(bool),
+    TogglePause,
+    Step,
+    SetTicksPerFrame(u32),
+    Undo,
+    Redo,
+    SetSim(usize),
+    SetBounds(i32),
+    SetRule(Rule),
+    LoadExample(usize),
+    SetColors(ColorMethod, Color, Color),
+    SetTheme(Theme),
+    SetCell(IVec3, CellValue),
+    // many at once, eg. a rewound frame. not undoable.
+    SetCells(Vec<(IVec3, CellValue)>),
+    SetTransferOnSwitch(bool),
+    SetClampStates(bool),
+    SetDecaySubsteps(u8),
+    SetEnergy(EnergySettings),
+    SetGrowthBias(Vec3),
+    SetDefects(DefectSettings),
+    SetNeighbourFilter(NeighbourFilter),
+    SetMaterials(MaterialThresholds),
+}
+
+impl Action {
+    // everything that can be done right now, with a label each. what the
+    // command palette lists.
+    pub fn available(sims: &Sims) -> Vec<(Action, String)> {
+        let mut actions = vec![
+            (Action::SpawnNoise, "spawn noise".to_string()),
+            (Action::Reset, "reset".to_string()),
+            (Action::TogglePause, match sims.is_paused() {
+                true  => "resume".to_string(),
+                false => "pause".to_string(),
+            }),
+            (Action::Step, "step one tick".to_string()),
+        ];
+        if sims.can_undo() {
+            actions.push((Action::Undo, "undo".to_string()));
+        }
+        if sims.can_redo() {
+            actions.push((Action::Redo, "redo".to_string()));
+        }
+        for (i, name) in sims.sim_names().enumerate() {
+            if name != sims.active_name() {
+                actions.push((Action::SetSim(i), format!("switch backend: {}", name)));
+            }
+        }
+        for (i, example) in sims.examples().iter().enumerate() {
+            actions.push((Action::LoadExample(i), format!("load example: {}", example.name)));
+        }
+        let (color_method, color1, color2) = sims.colors();
+        for method in COLOR_METHODS {
+            if method != color_method {
+                actions.push((Action::SetColors(method, color1, color2), format!("color method: {:?}", method)));
+            }
+        }
+        for theme in THEMES {
+            if theme != sims.theme() {
+                actions.push((Action::SetTheme(theme), format!("theme: {:?}", theme)));
+            }
+        }
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        actions.push((Action::SetTransferOnSwitch(!sims.transfer_on_switch()),
+            format!("keep cells when switching: {}", on_off(!sims.transfer_on_switch()))));
+        actions.push((Action::SetClampStates(!sims.clamp_states()),
+            format!("clamp states when the rule changes: {}", on_off(!sims.clamp_states()))));
+        actions
+    }
+
+    // the destructive ones are undoable.
+    pub fn apply(&self, sims: &mut Sims) {
+        match self {
+            Action::SpawnNoise => {
+                sims.checkpoint();
+                sims.spawn_noise();
+            }
+            Action::Reset => {
+                sims.checkpoint();
+                sims.reset();
+            }
+            Action::SetPaused(paused) => sims.set_paused(*paused),
+            Action::TogglePause => sims.set_paused(!sims.is_paused()),
+            Action::Step => sims.step(),
+            Action::SetTicksPerFrame(ticks) => sims.set_ticks_per_frame(*ticks),
+            Action::Undo => sims.undo(),
+            Action::Redo => sims.redo(),
+            Action::SetSim(index) => {
+                if *index < sims.sim_names().count() {
+                    sims.switch_sim(*index);
+                }
+            }
+            Action::SetBounds(bounds) => {
+                sims.checkpoint_gesture("bounds");
+                sims.set_bounds(*bounds);
+            }
+            Action::SetRule(rule) => {
+                sims.checkpoint_gesture("rule");
+                sims.set_rule(rule.clone());
+            }
+            Action::LoadExample(index) => {
+                if *index < sims.examples().len() {
+                    sims.checkpoint();
+                    sims.set_example(*index);
+                }
+            }
+            Action::SetColors(method, color1, color2) => sims.set_colors(*method, *color1, *color2),
+            Action::SetTheme(theme) => sims.set_theme(*theme),
+            Action::SetCell(pos, value) => sims.set_cell(*pos, *value),
+            Action::SetCells(cells) => sims.set_cells(cells),
+            Action::SetTransferOnSwitch(transfer) => sims.set_transfer_on_switch(*transfer),
+            Action::SetClampStates(clamp) => sims.set_clamp_states(*clamp),
+            Action::SetDecaySubsteps(substeps) => sims.set_decay_substeps(*substeps),
+            Action::SetEnergy(energy) => sims.set_energy(*energy),
+            Action::SetGrowthBias(bias) => sims.set_growth_bias(*bias),
+            Action::SetDefects(defects) => sims.set_defects(*defects),
+            Action::SetNeighbourFilter(filter) => sims.set_neighbour_filter(filter.clone()),
+            Action::SetMaterials(materials) => sims.set_materials(*materials),
+        }
+    }
+}
+
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ActionHandler;
+
+fn handle(
+    mut actions: EventReader<Action>,
+    mut sims: ResMut<Sims>,
+) {
+    for action in actions.iter() {
+        debug!("action: {:?}", action);
+        action.apply(&mut sims);
+    }
+}
+
+// space pauses, period steps, n spawns noise, r resets, ctrl+z and ctrl+y
+// undo and redo.
+fn keybindings(
+    keys: Res<Input<KeyCode>>,
+    mut actions: EventWriter<Action>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    // don't steal keys from text fields.
+    if egui_context.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    let ctrl = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
+    let bindings = [
+        (KeyCode::Space, false, Action::TogglePause),
+        (KeyCode::Period, false, Action::Step),
+        (KeyCode::N, false, Action::SpawnNoise),
+        (KeyCode::R, false, Action::Reset),
+        (KeyCode::Z, true, Action::Undo),
+        (KeyCode::Y, true, Action::Redo),
+    ];
+    for (key, with_ctrl, action) in bindings {
+        if with_ctrl == ctrl && keys.just_pressed(key) {
+            actions.send(action);
+        }
+    }
+}
+
+
+pub struct ActionPlugin;
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_event::<Action>()
+        .add_system(handle.label(ActionHandler).with_run_criteria(simulating).before(SimsUpdate));
+        if has_ui(app) {
+            app.add_system(keybindings.with_run_criteria(simulating).before(ActionHandler));
+        }
+    }
+}
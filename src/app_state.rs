@@ -0,0 +1,197 @@
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    bench::{self, BenchReport},
+    cells::{Sim, Sims, SimsUpdate},
+    project,
+    threading::Threading,
+};
+
+const BENCH_TICKS: u32 = 100;
+
+
+// the app starts in the menu, the backend only allocates its grid once one
+// is picked. `Paused` follows `Sims::is_paused`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AppState {
+    Menu,
+    Running,
+    Paused,
+    // runs every backend once, like `--bench` but in the window.
+    Benchmark,
+}
+
+// false without `EguiPlugin`, eg: in tests/app.rs. the simulation's plugins
+// leave their windows out then, everything else works the same.
+pub fn has_ui(app: &App) -> bool {
+    app.world.contains_resource::<EguiContext>()
+}
+
+// run criterion for the systems that need a running simulator, which is
+// most of them.
+pub fn simulating(state: Res<State<AppState>>) -> ShouldRun {
+    match state.current() {
+        AppState::Running | AppState::Paused => ShouldRun::Yes,
+        AppState::Menu | AppState::Benchmark => ShouldRun::No,
+    }
+}
+
+
+pub struct Menu {
+    pub sim: usize,
+    pub example: usize,
+    pub bounds: i32,
+    backends: fn() -> Vec<(String, Box<dyn Sim>)>,
+    // drawn one frame before the benchmark blocks.
+    bench_pending: bool,
+    report: Option<BenchReport>,
+}
+
+impl Menu {
+    pub fn new(backends: fn() -> Vec<(String, Box<dyn Sim>)>) -> Menu {
+        Menu {
+            sim: 0,
+            example: 0,
+            bounds: 64,
+            backends,
+            bench_pending: false,
+            report: None,
+        }
+    }
+}
+
+
+fn centered(title: &str) -> egui::Window<'static> {
+    egui::Window::new(title)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .collapsible(false)
+        .resizable(false)
+}
+
+pub fn menu(
+    mut this: ResMut<Menu>,
+    mut sims: ResMut<Sims>,
+    mut state: ResMut<State<AppState>>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let mut start = false;
+    let mut benchmark = false;
+
+    centered("3d celluar automata").show(egui_context.ctx_mut(), |ui| {
+        let names: Vec<String> = sims.sim_names().map(String::from).collect();
+        egui::ComboBox::from_label("simulator")
+            .selected_text(names.get(this.sim).map(String::as_str).unwrap_or(""))
+            .show_ui(ui, |ui| {
+                for (i, name) in names.iter().enumerate() {
+                    ui.selectable_value(&mut this.sim, i, name);
+                }
+            });
+
+        let examples = sims.examples();
+        egui::ComboBox::from_label("rule")
+            .selected_text(examples.get(this.example).map(|e| e.name.as_str()).unwrap_or(""))
+            .show_ui(ui, |ui| {
+                for (i, example) in examples.iter().enumerate() {
+                    ui.selectable_value(&mut this.example, i, &example.name);
+                }
+            });
+
+        let max_bounds = sims.max_bounds(this.sim);
+        ui.add(egui::Slider::new(&mut this.bounds, 32..=max_bounds).text("bounding size"));
+
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            start = ui.button("start").clicked();
+            benchmark = ui.button("benchmark the backends").clicked();
+        });
+    });
+
+    if start {
+        sims.start(this.sim, this.example, this.bounds);
+        let _ = state.set(AppState::Running);
+    }
+    else if benchmark {
+        this.report = None;
+        this.bench_pending = true;
+        let _ = state.set(AppState::Benchmark);
+    }
+}
+
+pub fn benchmark(
+    mut this: ResMut<Menu>,
+    threading: Res<Threading>,
+    mut state: ResMut<State<AppState>>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let mut back = false;
+
+    centered("Benchmark").show(egui_context.ctx_mut(), |ui| {
+        match &this.report {
+            None => { ui.label(format!("running every backend for {} ticks at {}³...", BENCH_TICKS, this.bounds)); }
+            Some(report) => {
+                egui::Grid::new("benchmark").num_columns(4).show(ui, |ui| {
+                    ui.strong("ns per cell");
+                    ui.strong("update");
+                    ui.strong("render");
+                    ui.strong("extract");
+                    ui.end_row();
+                    for result in report.results.iter() {
+                        ui.label(&result.backend);
+                        ui.monospace(format!("{:.2}", result.ns_per_cell.update));
+                        ui.monospace(format!("{:.2}", result.ns_per_cell.render));
+                        ui.monospace(format!("{:.2}", result.ns_per_cell.extract));
+                        ui.end_row();
+                    }
+                });
+                back = ui.button("back").clicked();
+            }
+        }
+    });
+
+    if this.bench_pending {
+        this.bench_pending = false;
+        return;
+    }
+    if this.report.is_none() {
+        // blocks the window until it's done.
+        this.report = Some(bench::run((this.backends)(), this.bounds, BENCH_TICKS, &threading));
+    }
+    if back {
+        let _ = state.set(AppState::Menu);
+    }
+}
+
+fn sync_paused(
+    sims: Res<Sims>,
+    mut state: ResMut<State<AppState>>,
+) {
+    let paused = *state.current() == AppState::Paused;
+    if sims.is_paused() != paused {
+        let _ = state.set(if sims.is_paused() { AppState::Paused } else { AppState::Running });
+    }
+}
+
+
+pub struct AppStatePlugin {
+    pub backends: fn() -> Vec<(String, Box<dyn Sim>)>,
+}
+
+impl Plugin for AppStatePlugin {
+    fn build(&self, app: &mut App) {
+        // opening a project skips the menu.
+        let initial = match project::path_from_args(std::env::args().skip(1)) {
+            Some(_) => AppState::Running,
+            None => AppState::Menu,
+        };
+        app
+        .insert_resource(Menu::new(self.backends))
+        .add_state(initial)
+        .add_system(sync_paused.with_run_criteria(simulating).after(SimsUpdate));
+        if has_ui(app) {
+            app
+            .add_system_set(SystemSet::on_update(AppState::Menu).with_system(menu))
+            .add_system_set(SystemSet::on_update(AppState::Benchmark).with_system(benchmark));
+        }
+    }
+}
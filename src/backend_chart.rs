@@ -0,0 +1,239 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_egui::{egui::{self, plot::{Legend, Line, Plot, Value, Values}}, EguiContext};
+
+use crate::{
+    app_state::simulating,
+    cells::{Sims, SimsUpdate},
+};
+
+// samples per backend, older ones are dropped.
+const MAX_SAMPLES: usize = 2000;
+const COLORS: [egui::Color32; 8] = [
+    egui::Color32::from_rgb(255, 196, 0),
+    egui::Color32::from_rgb(0, 170, 255),
+    egui::Color32::from_rgb(255, 80, 80),
+    egui::Color32::from_rgb(90, 220, 90),
+    egui::Color32::from_rgb(200, 110, 255),
+    egui::Color32::from_rgb(255, 140, 40),
+    egui::Color32::from_rgb(40, 220, 200),
+    egui::Color32::from_rgb(230, 230, 230),
+];
+const PNG_SIZE: (u32, u32) = (800, 400);
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChartMetric {
+    UpdateMs,
+    NsPerCell,
+}
+
+impl ChartMetric {
+    fn of(&self, sample: &Sample) -> f64 {
+        match self {
+            ChartMetric::UpdateMs => sample.update_ms,
+            ChartMetric::NsPerCell => sample.ns_per_cell,
+        }
+    }
+}
+
+struct Sample {
+    update_ms: f64,
+    ns_per_cell: f64,
+}
+
+// the update times of one backend on one rule and bounds, one sample per
+// frame that ticked.
+struct Series {
+    backend: String,
+    rule: String,
+    bounds: i32,
+    samples: Vec<Sample>,
+}
+
+// records the update time of whichever backend is active, so switching
+// between them on the same rule and bounds draws them on top of each other.
+// the x axis is the sample, not the time, so backends that ran one after
+// the other line up.
+pub struct BackendChart {
+    pub recording: bool,
+    pub metric: ChartMetric,
+    series: Vec<Series>,
+    last_tick: u64,
+    export_path: String,
+    status: Option<Result<String, String>>,
+}
+
+impl Default for BackendChart {
+    fn default() -> Self {
+        BackendChart {
+            recording: true,
+            metric: ChartMetric::UpdateMs,
+            series: vec![],
+            last_tick: 0,
+            export_path: "backends.png".into(),
+            status: None,
+        }
+    }
+}
+
+impl BackendChart {
+    fn record(&mut self, backend: &str, rule: &str, bounds: i32, update: std::time::Duration) {
+        let index = match self.series.iter().position(|s| s.backend == backend && s.rule == rule && s.bounds == bounds) {
+            Some(index) => index,
+            None => {
+                self.series.push(Series { backend: backend.into(), rule: rule.into(), bounds, samples: vec![] });
+                self.series.len() - 1
+            }
+        };
+        let series = &mut self.series[index];
+        if series.samples.len() == MAX_SAMPLES {
+            series.samples.remove(0);
+        }
+        let cells = (bounds as f64).powi(3).max(1.0);
+        series.samples.push(Sample {
+            update_ms: update.as_secs_f64() * 1000.0,
+            ns_per_cell: update.as_nanos() as f64 / cells,
+        });
+    }
+
+    // the series of the rule and bounds that are running now, with a color
+    // each.
+    fn current<'a>(&'a self, rule: &'a str, bounds: i32) -> impl Iterator<Item = (&'a Series, egui::Color32)> + 'a {
+        self.series.iter()
+            .filter(move |s| s.rule == rule && s.bounds == bounds)
+            .enumerate()
+            .map(|(i, s)| (s, COLORS[i % COLORS.len()]))
+    }
+
+    fn export(&self, path: &Path, rule: &str, bounds: i32) -> Result<String, String> {
+        let series: Vec<_> = self.current(rule, bounds).collect();
+        let (width, height) = PNG_SIZE;
+        let margin = 16;
+        let len = series.iter().map(|(s, _)| s.samples.len()).max().unwrap_or(0).max(2);
+        let max = series.iter()
+            .flat_map(|(s, _)| s.samples.iter().map(|sample| self.metric.of(sample)))
+            .fold(0.0, f64::max).max(f64::MIN_POSITIVE) * 1.1;
+
+        let mut image = image::RgbaImage::from_pixel(width, height, image::Rgba([24, 24, 28, 255]));
+        let (left, right, top, bottom) = (margin, width - margin, margin, height - margin);
+        let axis = image::Rgba([90, 90, 100, 255]);
+        line(&mut image, (left, top), (left, bottom), axis);
+        line(&mut image, (left, bottom), (right, bottom), axis);
+
+        let to_pixel = |i: usize, value: f64| (
+            left + ((right - left) as f64 * i as f64 / (len - 1) as f64) as u32,
+            bottom - ((bottom - top) as f64 * value / max).min((bottom - top) as f64) as u32,
+        );
+        let mut legend = vec![];
+        for (s, color) in series.iter() {
+            let color = image::Rgba([color.r(), color.g(), color.b(), 255]);
+            for (i, pair) in s.samples.windows(2).enumerate() {
+                line(&mut image, to_pixel(i, self.metric.of(&pair[0])), to_pixel(i + 1, self.metric.of(&pair[1])), color);
+            }
+            legend.push(format!("{} #{:02x}{:02x}{:02x}", s.backend, color[0], color[1], color[2]));
+        }
+
+        image.save(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(format!("saved {}, 0 to {:.3} {}: {}", path.display(), max, match self.metric {
+            ChartMetric::UpdateMs => "ms",
+            ChartMetric::NsPerCell => "ns/cell",
+        }, legend.join(", ")))
+    }
+}
+
+// bresenham.
+fn line(image: &mut image::RgbaImage, from: (u32, u32), to: (u32, u32), color: image::Rgba<u8>) {
+    let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+    let (dx, dy) = ((to.0 as i64 - x).abs(), -(to.1 as i64 - y).abs());
+    let (sx, sy) = (if x < to.0 as i64 { 1 } else { -1 }, if y < to.1 as i64 { 1 } else { -1 });
+    let mut error = dx + dy;
+    loop {
+        if x >= 0 && y >= 0 && (x as u32) < image.width() && (y as u32) < image.height() {
+            image.put_pixel(x as u32, y as u32, color);
+        }
+        if x == to.0 as i64 && y == to.1 as i64 {
+            break;
+        }
+        let e2 = 2 * error;
+        if e2 >= dy {
+            error += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            error += dx;
+            y += sy;
+        }
+    }
+}
+
+
+fn record(
+    mut this: ResMut<BackendChart>,
+    sims: Res<Sims>,
+) {
+    if !this.recording || sims.tick() == this.last_tick {
+        return;
+    }
+    this.last_tick = sims.tick();
+    let (update, _, _) = sims.frame_times();
+    this.record(sims.active_name(), &sims.rule().to_string(), sims.bounds(), update);
+}
+
+fn update_ui(
+    mut this: ResMut<BackendChart>,
+    sims: Res<Sims>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let this = &mut *this;
+    let rule = sims.rule().to_string();
+    let bounds = sims.bounds();
+    egui::Window::new("Backend chart").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut this.recording, "record");
+            ui.selectable_value(&mut this.metric, ChartMetric::UpdateMs, "ms per tick");
+            ui.selectable_value(&mut this.metric, ChartMetric::NsPerCell, "ns per cell");
+            if ui.button("clear").clicked() {
+                this.series.clear();
+            }
+        });
+        ui.weak(format!("switch backends to compare them on {}, bounds {}", rule, bounds));
+
+        let metric = this.metric;
+        Plot::new("backend chart")
+            .legend(Legend::default())
+            .height(240.0)
+            .include_y(0.0)
+            .show(ui, |plot_ui| {
+                for (series, color) in this.current(&rule, bounds) {
+                    let values = series.samples.iter().enumerate()
+                        .map(|(i, sample)| Value::new(i as f64, metric.of(sample)));
+                    plot_ui.line(Line::new(Values::from_values_iter(values)).color(color).name(&series.backend));
+                }
+            });
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut this.export_path);
+            if ui.button("export png").clicked() {
+                this.status = Some(this.export(Path::new(&this.export_path), &rule, bounds));
+            }
+        });
+        match &this.status {
+            Some(Ok(message)) => { ui.label(message.as_str()); }
+            Some(Err(error)) => { ui.colored_label(egui::Color32::RED, error.as_str()); }
+            None => (),
+        }
+    });
+}
+
+
+pub struct BackendChartPlugin;
+impl Plugin for BackendChartPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<BackendChart>()
+        .add_system(record.after(SimsUpdate).with_run_criteria(simulating))
+        .add_system(update_ui.with_run_criteria(simulating));
+    }
+}
@@ -0,0 +1,58 @@
+use bevy::{prelude::*, render::view::NoFrustumCulling};
+
+use crate::cell_renderer::{CellRenderMode, CellShaderSettings, InstanceData, InstanceMaterialData};
+
+// stays inside the default camera far plane (1000).
+const BACKGROUND_RADIUS: f32 = 900.0;
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BackgroundMode {
+    Flat,
+    Gradient,
+    Starfield,
+    Skybox,
+}
+
+// marks the background sphere, so it isn't mistaken for the cells.
+#[derive(Component)]
+pub struct Background;
+
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    commands.spawn().insert_bundle((
+        meshes.add(Mesh::from(shape::Icosphere { radius: 1.0, subdivisions: 3 })),
+        Transform::default(),
+        GlobalTransform::default(),
+        // the shader keeps the sphere at the origin, whatever the position.
+        InstanceMaterialData(vec![InstanceData::cell(IVec3::ZERO, BACKGROUND_RADIUS, [0.0; 4], f32::MIN)]),
+        Visibility::default(),
+        ComputedVisibility::default(),
+        NoFrustumCulling,
+        CellRenderMode::Background,
+        Background,
+    ));
+}
+
+// anything the sphere doesn't cover (eg: when zoomed far out) uses the clear color.
+fn update_clear_color(
+    settings: Res<CellShaderSettings>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    if settings.is_changed() {
+        clear_color.0 = settings.background_top;
+    }
+}
+
+
+pub struct BackgroundPlugin;
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_startup_system(setup)
+        .add_system(update_clear_color);
+    }
+}
@@ -0,0 +1,235 @@
+use std::{collections::HashMap, fs, time::Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cell_renderer::{CellRenderer, InstanceColoring, NeighbourFilter},
+    cells::Sim,
+    example_library,
+    graphics::InstanceFilter,
+    rule::{ColorMethod, Rule},
+    theme::{Palette, Theme},
+    threading::Threading,
+};
+
+use bevy::prelude::Color;
+
+const USAGE: &str = "\
+--bench [out.json]              runs every backend without a window
+    --bench-bounds N            (default 64)
+    --bench-ticks N             (default 100)
+    --threads N                 see threading.rs
+--bench-compare OLD NEW         prints the phases that got slower
+    --bench-threshold PERCENT   (default 5), exits with 1 if any regressed";
+
+
+#[derive(Serialize, Deserialize)]
+pub struct Machine {
+    pub os: String,
+    pub arch: String,
+    pub logical_cores: usize,
+    pub threads: usize,
+}
+
+// nanoseconds per cell of the grid (not per live cell), averaged over the
+// ticks.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Phases {
+    pub update: f64,
+    pub render: f64,
+    pub extract: f64,
+}
+
+impl Phases {
+    fn iter(&self) -> [(&'static str, f64); 3] {
+        [("update", self.update), ("render", self.render), ("extract", self.extract)]
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BenchResult {
+    pub backend: String,
+    pub bounds: i32,
+    pub rule: String,
+    pub ticks: u32,
+    pub ns_per_cell: Phases,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BenchReport {
+    pub machine: Machine,
+    // built with `debug-checks`.
+    #[serde(default)]
+    pub checks: bool,
+    pub results: Vec<BenchResult>,
+}
+
+
+// the same phases as a frame of `Sims::update`, always with a full render.
+fn bench_backend(name: &str, sim: &mut dyn Sim, rule: &Rule, bounds: i32, ticks: u32, threading: &Threading) -> BenchResult {
+    let bounds = sim.set_bounds(bounds);
+    sim.spawn_noise(rule);
+
+    let mut renderer = CellRenderer::new();
+    renderer.set_bounds(bounds);
+    let coloring = InstanceColoring {
+        method: ColorMethod::StateLerp,
+        palette: Palette { theme: Theme::Custom, color1: Color::YELLOW, color2: Color::RED },
+        states: rule.states,
+        max_neighbours: rule.neighbour_method.max_neighbours(),
+        defect_color: None,
+        materials: None,
+    };
+    let mut instances = vec![];
+
+    let mut total = Phases::default();
+    for _ in 0..ticks {
+        let t0 = Instant::now();
+        sim.update(rule, threading.pool());
+        let t1 = Instant::now();
+        sim.render(&mut renderer);
+        let t2 = Instant::now();
+        renderer.extract_instances(&coloring, &InstanceFilter::All, &NeighbourFilter::default(),
+            threading.pool(), &mut instances);
+        let t3 = Instant::now();
+
+        total.update  += (t1 - t0).as_nanos() as f64;
+        total.render  += (t2 - t1).as_nanos() as f64;
+        total.extract += (t3 - t2).as_nanos() as f64;
+    }
+
+    let per = ticks.max(1) as f64 * (bounds as f64).powi(3);
+    BenchResult {
+        backend: name.to_string(),
+        bounds,
+        rule: rule.to_string(),
+        ticks,
+        ns_per_cell: Phases {
+            update:  total.update / per,
+            render:  total.render / per,
+            extract: total.extract / per,
+        },
+    }
+}
+
+pub fn run(backends: Vec<(String, Box<dyn Sim>)>, bounds: i32, ticks: u32, threading: &Threading) -> BenchReport {
+    let rule = example_library::fallback_example().rule;
+    let results = backends.into_iter().map(|(name, mut sim)| {
+        let result = bench_backend(&name, sim.as_mut(), &rule, bounds, ticks, threading);
+        println!("{:<48} update {:>8.2} render {:>8.2} extract {:>8.2} ns/cell",
+            result.backend, result.ns_per_cell.update, result.ns_per_cell.render, result.ns_per_cell.extract);
+        result
+    })
+    .collect();
+
+    BenchReport {
+        machine: Machine {
+            os: std::env::consts::OS.into(),
+            arch: std::env::consts::ARCH.into(),
+            logical_cores: bevy::tasks::logical_core_count(),
+            threads: threading.active_threads(),
+        },
+        checks: cfg!(feature = "debug-checks"),
+        results,
+    }
+}
+
+
+fn load(path: &str) -> Result<BenchReport, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+// returns the number of regressions.
+pub fn compare(old: &BenchReport, new: &BenchReport, threshold: f64) -> usize {
+    if old.machine.os != new.machine.os || old.machine.threads != new.machine.threads {
+        println!("note: the results are from different setups ({} {} threads vs {} {} threads)",
+            old.machine.os, old.machine.threads, new.machine.os, new.machine.threads);
+    }
+    if old.checks != new.checks {
+        let checks = |on: bool| if on { "with debug-checks" } else { "without checks" };
+        println!("note: {} vs {}, the difference per backend is the cost of the checks",
+            checks(old.checks), checks(new.checks));
+    }
+
+    let old_results: HashMap<_, _> = old.results.iter()
+        .map(|r| ((r.backend.as_str(), r.bounds, r.rule.as_str()), r))
+        .collect();
+
+    let mut regressions = 0;
+    for result in new.results.iter() {
+        let old = match old_results.get(&(result.backend.as_str(), result.bounds, result.rule.as_str())) {
+            Some(old) => old,
+            None => {
+                println!("{} ({}, {}): new", result.backend, result.bounds, result.rule);
+                continue;
+            }
+        };
+        for ((phase, before), (_, after)) in old.ns_per_cell.iter().iter().zip(result.ns_per_cell.iter()) {
+            if *before <= 0.0 {
+                continue;
+            }
+            let change = 100.0 * (after - before) / before;
+            let marker =
+                if change > threshold { regressions += 1; "REGRESSION" }
+                else if change < -threshold { "faster" }
+                else { "" };
+            println!("{:<48} {:<8} {:>8.2} -> {:>8.2} ns/cell {:>+7.1}% {}",
+                result.backend, phase, before, after, change, marker);
+        }
+    }
+    regressions
+}
+
+
+// handles `--bench` and `--bench-compare`. returns false if neither was
+// given and the app should start normally.
+pub fn run_from_args(args: &[String], backends: impl FnOnce() -> Vec<(String, Box<dyn Sim>)>) -> bool {
+    let value = |name: &str| args.iter().position(|a| a == name).map(|i| args.get(i + 1));
+    let number = |name: &str, default: f64| match value(name) {
+        Some(Some(n)) => n.parse::<f64>().unwrap_or_else(|_| {
+            eprintln!("{} expects a number\n{}", name, USAGE);
+            std::process::exit(2)
+        }),
+        _ => default,
+    };
+
+    if let Some(i) = args.iter().position(|a| a == "--bench-compare") {
+        let (old, new) = match (args.get(i + 1), args.get(i + 2)) {
+            (Some(old), Some(new)) => (old, new),
+            _ => {
+                eprintln!("{}", USAGE);
+                std::process::exit(2);
+            }
+        };
+        let regressions = match (load(old), load(new)) {
+            (Ok(old), Ok(new)) => compare(&old, &new, number("--bench-threshold", 5.0)),
+            (Err(error), _) | (_, Err(error)) => {
+                eprintln!("{}", error);
+                std::process::exit(2);
+            }
+        };
+        println!("{} regression(s)", regressions);
+        std::process::exit(if regressions > 0 { 1 } else { 0 });
+    }
+
+    if args.iter().any(|a| a == "--bench") {
+        let threading = Threading::from_args(args.iter().cloned());
+        let report = run(backends(),
+            number("--bench-bounds", 64.0) as i32,
+            number("--bench-ticks", 100.0) as u32,
+            &threading);
+
+        let out = value("--bench")
+            .flatten()
+            .filter(|path| !path.starts_with("--"))
+            .cloned()
+            .unwrap_or_else(|| "bench.json".into());
+        match fs::write(&out, serde_json::to_string_pretty(&report).unwrap()) {
+            Ok(()) => println!("wrote {}", out),
+            Err(error) => eprintln!("failed to write {}: {}", out, error),
+        }
+        return true;
+    }
+    false
+}
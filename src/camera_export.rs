@@ -0,0 +1,272 @@
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use serde::Serialize;
+
+use crate::{
+    app_state::simulating,
+    background::Background,
+    cell_renderer::{CellRenderMode, DebugInstances, InstanceData, InstanceMaterialData},
+    cells::Sims,
+    gpu_instances::GpuCells,
+    rotating_camera::RotatingCamera,
+    utils,
+    volume::VolumeTransform,
+};
+
+// bump when the layout changes, scripts/blender_import.py checks it.
+const VERSION: u32 = 1;
+
+
+// one camera pose, in bevy's space (y up, the camera looks along -z).
+#[derive(Clone, Copy, Serialize)]
+pub struct CameraFrame {
+    // seconds from the first frame.
+    pub time: f32,
+    pub translation: [f32; 3],
+    // x, y, z, w.
+    pub rotation: [f32; 4],
+    // vertical, in radians.
+    pub fov: f32,
+}
+
+impl CameraFrame {
+    fn new(time: f32, transform: &Transform, fov: f32) -> CameraFrame {
+        CameraFrame {
+            time,
+            translation: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            fov,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CellInstance {
+    // the cell's center in cells from the grid's center, `volume` moves it
+    // to world space.
+    pub position: [f32; 3],
+    // edge length in cells.
+    pub size: f32,
+    // srgb, 0..1.
+    pub color: [f32; 4],
+}
+
+#[derive(Serialize)]
+pub struct VolumeFrame {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: f32,
+}
+
+// the instances as they were drawn on one tick.
+#[derive(Serialize)]
+pub struct CellsCapture {
+    pub tick: u64,
+    pub bounds: i32,
+    pub volume: VolumeFrame,
+    pub instances: Vec<CellInstance>,
+}
+
+// the file, json so a blender script can read it without extra modules.
+#[derive(Serialize)]
+pub struct CameraPathFile<'a> {
+    pub version: u32,
+    pub fps: u32,
+    // width over height of the window.
+    pub aspect: f32,
+    pub frames: &'a [CameraFrame],
+    pub cells: Option<&'a CellsCapture>,
+}
+
+
+// records the camera at a fixed rate, or samples one turn of the turntable,
+// and writes it out for an offline render. see scripts/blender_import.py.
+pub struct CameraExport {
+    pub fps: u32,
+    pub recording: bool,
+    frames: Vec<CameraFrame>,
+    start: f64,
+    cells: Option<CellsCapture>,
+    capture_requested: bool,
+    path: String,
+    status: Option<Result<String, String>>,
+}
+
+impl Default for CameraExport {
+    fn default() -> Self {
+        CameraExport {
+            fps: 30,
+            recording: false,
+            frames: vec![],
+            start: 0.0,
+            cells: None,
+            capture_requested: false,
+            path: "camera_path.json".into(),
+            status: None,
+        }
+    }
+}
+
+impl CameraExport {
+    // the frames a full turn at the camera's current speed takes, from its
+    // current angle. ignores easing, shake and auto-frame.
+    fn sample_turn(&mut self, camera: &RotatingCamera, fov: f32) {
+        self.frames.clear();
+        if camera.speed == 0.0 {
+            return;
+        }
+        let seconds = std::f32::consts::TAU / camera.speed.abs();
+        let count = (seconds * self.fps as f32).ceil() as usize;
+        for i in 0..count {
+            let time = i as f32 / self.fps as f32;
+            let rotation = Quat::from_axis_angle(Vec3::Y, camera.rotation + time * camera.speed);
+            let translation = camera.center + rotation * Vec3::Z * camera.dist + Vec3::Y * camera.height;
+            let transform = Transform::from_translation(translation).looking_at(camera.center, Vec3::Y);
+            self.frames.push(CameraFrame::new(time, &transform, fov));
+        }
+    }
+
+    fn export(&self, path: &Path, aspect: f32) -> Result<String, String> {
+        if self.frames.is_empty() {
+            return Err("no camera frames, record or sample a turn first".into());
+        }
+        let file = CameraPathFile {
+            version: VERSION,
+            fps: self.fps,
+            aspect,
+            frames: &self.frames,
+            cells: self.cells.as_ref(),
+        };
+        let text = serde_json::to_string(&file).map_err(|e| e.to_string())?;
+        fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(format!("saved {} frames{} to {}", self.frames.len(),
+            match &self.cells {
+                Some(cells) => format!(" and {} cells", cells.instances.len()),
+                None => String::new(),
+            },
+            path.display()))
+    }
+}
+
+fn capture(sims: &Sims, volume: &VolumeTransform, instances: &[InstanceData]) -> CellsCapture {
+    let center = utils::center(sims.bounds()).as_vec3();
+    let transform = volume.transform();
+    CellsCapture {
+        tick: sims.tick(),
+        bounds: sims.bounds(),
+        volume: VolumeFrame {
+            translation: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            scale: volume.scale,
+        },
+        instances: instances.iter().map(|instance| {
+            let half_cells = Vec3::new(instance.position[0] as f32, instance.position[1] as f32, instance.position[2] as f32);
+            CellInstance {
+                position: (half_cells / 2.0 - center).to_array(),
+                size: instance.scale as f32 / 16.0,
+                color: instance.color.map(|c| c as f32 / 255.0),
+            }
+        }).collect(),
+    }
+}
+
+
+fn record(
+    mut this: ResMut<CameraExport>,
+    time: Res<Time>,
+    sims: Res<Sims>,
+    volume: Res<VolumeTransform>,
+    cameras: Query<(&Transform, &PerspectiveProjection), With<RotatingCamera>>,
+    cells: Query<(&InstanceMaterialData, Option<&GpuCells>), (With<CellRenderMode>, Without<Background>, Without<DebugInstances>)>,
+) {
+    if std::mem::take(&mut this.capture_requested) {
+        this.cells = cells.iter().next().map(|(instances, gpu_cells)| match gpu_cells {
+            Some(gpu_cells) => capture(&sims, &volume, &gpu_cells.instances()),
+            None => capture(&sims, &volume, &instances.0),
+        });
+    }
+    if !this.recording {
+        return;
+    }
+    let now = time.seconds_since_startup();
+    if this.frames.is_empty() {
+        this.start = now;
+    }
+    let elapsed = (now - this.start) as f32;
+    // one sample per frame of the output, the first one right away.
+    let due = this.frames.last().map(|last| elapsed - last.time >= 1.0 / this.fps as f32).unwrap_or(true);
+    if let (true, Some((transform, projection))) = (due, cameras.iter().next()) {
+        this.frames.push(CameraFrame::new(elapsed, transform, projection.fov));
+    }
+}
+
+fn update_ui(
+    mut this: ResMut<CameraExport>,
+    windows: Res<Windows>,
+    cameras: Query<(&RotatingCamera, &PerspectiveProjection)>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let this = &mut *this;
+    egui::Window::new("Camera export").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.add(egui::Slider::new(&mut this.fps, 1..=120).text("fps"));
+        ui.horizontal(|ui| {
+            let label = if this.recording { "stop" } else { "record" };
+            if ui.button(label).clicked() {
+                if !this.recording {
+                    this.frames.clear();
+                }
+                this.recording = !this.recording;
+            }
+            if ui.add_enabled(!this.recording, egui::Button::new("sample one turn")).clicked() {
+                if let Some((camera, projection)) = cameras.iter().next() {
+                    this.sample_turn(camera, projection.fov);
+                }
+            }
+            if ui.add_enabled(!this.recording, egui::Button::new("clear")).clicked() {
+                this.frames.clear();
+            }
+        });
+        ui.weak(format!("{} frames, {:.1} s", this.frames.len(), this.frames.len() as f32 / this.fps as f32));
+
+        ui.horizontal(|ui| {
+            if ui.button("capture this tick's cells").clicked() {
+                this.capture_requested = true;
+            }
+            if this.cells.is_some() && ui.small_button("drop").clicked() {
+                this.cells = None;
+            }
+        });
+        if let Some(cells) = &this.cells {
+            ui.weak(format!("{} cells of tick {}", cells.instances.len(), cells.tick));
+        }
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut this.path);
+            if ui.button("export").clicked() {
+                let aspect = windows.get_primary()
+                    .map(|window| window.width() / window.height().max(1.0))
+                    .unwrap_or(16.0 / 9.0);
+                this.status = Some(this.export(Path::new(&this.path), aspect));
+            }
+        });
+        ui.weak("import with scripts/blender_import.py");
+        match &this.status {
+            Some(Ok(message)) => { ui.label(message.as_str()); }
+            Some(Err(error)) => { ui.colored_label(egui::Color32::RED, error.as_str()); }
+            None => (),
+        }
+    });
+}
+
+
+pub struct CameraExportPlugin;
+impl Plugin for CameraExportPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<CameraExport>()
+        .add_system(record.with_run_criteria(simulating))
+        .add_system(update_ui.with_run_criteria(simulating));
+    }
+}
@@ -0,0 +1,127 @@
+use bevy::{
+    math::{vec3, Vec3},
+    prelude::{shape, Mesh},
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CellMesh {
+    Cube,
+    Sphere,
+    RoundedCube,
+    Tetrahedron,
+    Gltf,
+}
+
+impl CellMesh {
+    // all built-in meshes fit into the unit cube, like the default cube.
+    // returns None for meshes that have to be loaded.
+    pub fn build(&self) -> Option<Mesh> {
+        match self {
+            CellMesh::Cube        => Some(Mesh::from(shape::Cube { size: 1.0 })),
+            CellMesh::Sphere      => Some(Mesh::from(shape::Icosphere { radius: 0.5, subdivisions: 2 })),
+            CellMesh::RoundedCube => Some(rounded_cube(0.15, 4)),
+            CellMesh::Tetrahedron => Some(tetrahedron()),
+            CellMesh::Gltf        => None,
+        }
+    }
+}
+
+
+// the cell pipeline is specialized from the pbr mesh pipeline, which expects
+// positions, normals and uvs. uvs are unused by the cell shader, so missing
+// ones are filled in instead of rejecting the mesh.
+pub fn validate(mesh: &mut Mesh) -> Result<(), String> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return Err(format!("expected a triangle list, got {:?}", mesh.primitive_topology()));
+    }
+
+    let vertex_count = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(positions) => positions.len(),
+        None => return Err("mesh has no positions".into()),
+    };
+    if mesh.attribute(Mesh::ATTRIBUTE_NORMAL).is_none() {
+        return Err("mesh has no normals".into());
+    }
+    if mesh.attribute(Mesh::ATTRIBUTE_UV_0).is_none() {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0f32, 0.0]; vertex_count]);
+    }
+    Ok(())
+}
+
+
+fn build_mesh(positions: Vec<[f32; 3]>, normals: Vec<[f32; 3]>, indices: Vec<u32>) -> Mesh {
+    let uvs = vec![[0.0f32, 0.0]; positions.len()];
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+// a subdivided unit cube whose vertices are pulled onto a box with rounded
+// edges: clamp to the inner box, then push out by `radius`.
+fn rounded_cube(radius: f32, resolution: u32) -> Mesh {
+    let half  = 0.5;
+    let inner = Vec3::splat(half - radius);
+
+    // (normal, u, v) with u x v = normal, so quads wind counter clockwise.
+    let faces = [
+        (Vec3::X,  Vec3::Y, Vec3::Z),
+        (-Vec3::X, Vec3::Z, Vec3::Y),
+        (Vec3::Y,  Vec3::Z, Vec3::X),
+        (-Vec3::Y, Vec3::X, Vec3::Z),
+        (Vec3::Z,  Vec3::X, Vec3::Y),
+        (-Vec3::Z, Vec3::Y, Vec3::X),
+    ];
+
+    let mut positions = vec![];
+    let mut normals   = vec![];
+    let mut indices   = vec![];
+    let row = resolution + 1;
+    for (normal, u, v) in faces {
+        let base = positions.len() as u32;
+        for j in 0..=resolution {
+            for i in 0..=resolution {
+                let s = i as f32 / resolution as f32 * 2.0 - 1.0;
+                let t = j as f32 / resolution as f32 * 2.0 - 1.0;
+                let p = (normal + s*u + t*v) * half;
+                let clamped = p.max(-inner).min(inner);
+                let dir = (p - clamped).normalize_or_zero();
+                positions.push((clamped + dir*radius).to_array());
+                normals.push(dir.to_array());
+            }
+        }
+        for j in 0..resolution {
+            for i in 0..resolution {
+                let a = base + j*row + i;
+                let b = a + 1;
+                let c = b + row;
+                let d = a + row;
+                indices.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+    }
+    build_mesh(positions, normals, indices)
+}
+
+fn tetrahedron() -> Mesh {
+    let a = vec3( 0.5,  0.5,  0.5);
+    let b = vec3( 0.5, -0.5, -0.5);
+    let c = vec3(-0.5,  0.5, -0.5);
+    let d = vec3(-0.5, -0.5,  0.5);
+
+    let mut positions = vec![];
+    let mut normals   = vec![];
+    for [p0, p1, p2] in [[a, b, c], [a, d, b], [a, c, d], [b, d, c]] {
+        let normal = (p1 - p0).cross(p2 - p0).normalize();
+        for p in [p0, p1, p2] {
+            positions.push(p.to_array());
+            normals.push(normal.to_array());
+        }
+    }
+    let indices = (0..positions.len() as u32).collect();
+    build_mesh(positions, normals, indices)
+}
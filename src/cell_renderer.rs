@@ -1,7 +1,7 @@
 use bevy::{
     core_pipeline::Transparent3d,
     ecs::system::{lifetimeless::*, SystemParamItem},
-    math::prelude::*,
+    math::{ivec3, prelude::*},
     pbr::{MeshPipeline, MeshPipelineKey, MeshUniform, SetMeshBindGroup, SetMeshViewBindGroup},
     prelude::*,
     render::{
@@ -13,14 +13,26 @@ use bevy::{
             SetItemPipeline, TrackedRenderPass,
         },
         render_resource::*,
-        renderer::RenderDevice,
+        renderer::{RenderDevice, RenderQueue},
         view::{ExtractedView, Msaa},
         RenderApp, RenderStage,
     },
+    tasks::TaskPool,
+    utils::HashMap,
 };
 use bytemuck::{Pod, Zeroable};
 
-use crate::utils;
+use crate::{
+    background::BackgroundMode,
+    gpu_instances::GpuCells,
+    graphics::InstanceFilter,
+    grid_hash::GridHash,
+    neighbours::{NeighbourCount, VONNEUMAN_NEIGHBOURS},
+    profiler::SharedRenderTimes,
+    rule::{CellValue, ColorMethod},
+    theme::Palette,
+    utils,
+};
 
 #[derive(Component)]
 pub struct InstanceMaterialData(pub Vec<InstanceData>);
@@ -33,26 +45,297 @@ impl ExtractComponent for InstanceMaterialData {
     }
 }
 
+// how the instances of an entity are drawn. billboards are camera facing
+// quads, which are a lot cheaper than cubes for huge cell counts.
+// the background is a single huge instance seen from the inside.
+// translucent meshes are alpha blended and don't write depth.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CellRenderMode {
+    Mesh,
+    Billboard,
+    Background,
+    Translucent,
+}
+
+impl ExtractComponent for CellRenderMode {
+    type Query = &'static CellRenderMode;
+    type Filter = ();
+
+    fn extract_component(item: bevy::ecs::query::QueryItem<Self::Query>) -> Self {
+        *item
+    }
+}
+
+// instances drawn on top of the cells, eg. the neighbor field. they use the
+// cell pipeline but aren't the cells.
+#[derive(Component)]
+pub struct DebugInstances;
+
 pub struct CellMaterialPlugin;
 
 impl Plugin for CellMaterialPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(ExtractComponentPlugin::<InstanceMaterialData>::default());
+        app.add_plugin(ExtractComponentPlugin::<CellRenderMode>::default());
         app.sub_app_mut(RenderApp)
             .add_render_command::<Transparent3d, DrawCustom>()
             .init_resource::<CellPipeline>()
             .init_resource::<SpecializedMeshPipelines<CellPipeline>>()
+            .init_resource::<InstanceBuffers>()
+            .add_system_to_stage(RenderStage::Extract, extract_cell_settings)
             .add_system_to_stage(RenderStage::Queue, queue_custom)
-            .add_system_to_stage(RenderStage::Prepare, prepare_instance_buffers);
+            .add_system_to_stage(RenderStage::Prepare, prepare_instance_buffers)
+            .add_system_to_stage(RenderStage::Prepare, prepare_cell_uniform);
+        app.init_resource::<CellShaderSettings>();
+        app.add_system(update_shader_time);
+    }
+}
+
+
+// values consumed by cell.wgsl. lives in the main world, copied to the render
+// world every frame.
+#[derive(Clone)]
+pub struct CellShaderSettings {
+    // darkens each cube face by its normal, a fixed light from above plus a
+    // tint per axis. `face_shading_strength` 0 is flat color.
+    pub face_shading: bool,
+    pub face_shading_strength: f32,
+
+    pub background: BackgroundMode,
+    pub background_top: Color,
+    pub background_bottom: Color,
+    pub fog: bool,
+    pub fog_density: f32,
+    pub fog_color: Color,
+
+    pub time: f32,
+    // seconds newly spawned cells take to grow to full size. 0 disables it.
+    pub fade_in: f32,
+    // gap between the chunks of the exploded view, in cells. 0 is off.
+    pub explode: f32,
+    // write 0 alpha for emissive cells and 1 for the rest, for the bloom
+    // of post_process.rs. set from there.
+    pub emissive_mask: bool,
+    // the grid the instances were extracted from, see `InstanceData`.
+    pub bounds: i32,
+}
+
+impl Default for CellShaderSettings {
+    fn default() -> Self {
+        CellShaderSettings {
+            face_shading: true,
+            face_shading_strength: 0.5,
+            background: BackgroundMode::Gradient,
+            background_top: Color::rgb(0.65, 0.9, 0.96),
+            background_bottom: Color::rgb(0.2, 0.3, 0.45),
+            fog: false,
+            fog_density: 0.004,
+            fog_color: Color::rgb(0.2, 0.3, 0.45),
+            time: 0.0,
+            fade_in: 0.4,
+            explode: 0.0,
+            emissive_mask: false,
+            bounds: 0,
+        }
+    }
+}
+
+impl CellShaderSettings {
+    fn uniform(&self) -> CellUniform {
+        let flag = |enabled: bool| if enabled { 1.0 } else { 0.0 };
+        let background = match self.background {
+            BackgroundMode::Flat      => 0.0,
+            BackgroundMode::Gradient  => 1.0,
+            BackgroundMode::Starfield => 2.0,
+            BackgroundMode::Skybox    => 3.0,
+        };
+        CellUniform {
+            emissive_mask: Vec4::new(flag(self.emissive_mask), 0.0, 0.0, 0.0),
+            background: Vec4::new(background, 0.0, 0.0, 0.0),
+            background_top: self.background_top.as_linear_rgba_f32().into(),
+            background_bottom: self.background_bottom.as_linear_rgba_f32().into(),
+            fog: Vec4::new(flag(self.fog), self.fog_density, 0.0, 0.0),
+            fog_color: self.fog_color.as_linear_rgba_f32().into(),
+            time: Vec4::new(self.time, self.fade_in, 0.0, 0.0),
+            explode: Vec4::new(self.explode, 0.0, 0.0, 0.0),
+            face_shading: Vec4::new(flag(self.face_shading), self.face_shading_strength, 0.0, 0.0),
+            grid: Vec4::new(
+                utils::center(self.bounds).x as f32,
+                CHUNK_SIZE as f32,
+                ((self.bounds + CHUNK_SIZE - 1) / CHUNK_SIZE) as f32,
+                0.0),
+        }
     }
 }
 
+// must match `CellSettings` in cell.wgsl. only vec4s, so there is no padding.
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct CellUniform {
+    emissive_mask: Vec4,
+    background: Vec4,
+    background_top: Vec4,
+    background_bottom: Vec4,
+    fog: Vec4,
+    fog_color: Vec4,
+    time: Vec4,
+    explode: Vec4,
+    face_shading: Vec4,
+    grid: Vec4,
+}
+
+pub struct CellUniformBuffer {
+    buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+fn update_shader_time(time: Res<Time>, mut settings: ResMut<CellShaderSettings>) {
+    settings.time = time.seconds_since_startup() as f32;
+}
+
+fn extract_cell_settings(mut commands: Commands, settings: Res<CellShaderSettings>) {
+    commands.insert_resource(settings.clone());
+}
+
+fn prepare_cell_uniform(
+    mut commands: Commands,
+    settings: Res<CellShaderSettings>,
+    pipeline: Res<CellPipeline>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    uniform_buffer: Option<Res<CellUniformBuffer>>,
+) {
+    let uniform = settings.uniform();
+    match uniform_buffer {
+        Some(uniform_buffer) => {
+            render_queue.write_buffer(&uniform_buffer.buffer, 0, bytemuck::bytes_of(&uniform));
+        }
+        None => {
+            let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+                label: Some("cell uniform buffer"),
+                contents: bytemuck::bytes_of(&uniform),
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            });
+            let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+                label: Some("cell uniform bind group"),
+                layout: &pipeline.uniform_layout,
+                entries: &[BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+            commands.insert_resource(CellUniformBuffer { buffer, bind_group });
+        }
+    }
+}
+
+pub struct SetCellUniformBindGroup<const I: usize>;
+impl<const I: usize> EntityRenderCommand for SetCellUniformBindGroup<I> {
+    type Param = SRes<CellUniformBuffer>;
+    #[inline]
+    fn render<'w>(
+        _view: Entity,
+        _item: Entity,
+        uniform_buffer: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        pass.set_bind_group(I, &uniform_buffer.into_inner().bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+// 20 bytes per instance. the position is the instance's center in half
+// cells from the grid's corner, so voxels of an even size fit too. the shader
+// moves it to world space with `CellShaderSettings::bounds`, and derives the
+// chunk for the exploded view from it.
 #[derive(Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
 pub struct InstanceData {
-    pub position: Vec3,
-    pub scale: f32,
-    pub color: [f32; 4],
+    pub position: [u16; 3],
+    // in 1/16 cells.
+    pub scale: u16,
+    pub color: [u8; 4],
+    // seconds since startup, the shader grows the cell in from this time.
+    pub spawn_time: f32,
+    // a `CellMaterial`.
+    pub material: u32,
+}
+
+impl InstanceData {
+    // the cell at `pos`, drawn at `scale`.
+    pub fn cell(pos: IVec3, scale: f32, color: [f32; 4], spawn_time: f32) -> InstanceData {
+        InstanceData::at(pos * 2, scale, color, spawn_time)
+    }
+
+    // `size`³ cells starting at `min`.
+    pub fn voxel(min: IVec3, size: i32, color: [f32; 4]) -> InstanceData {
+        InstanceData::at(min * 2 + (size - 1), size as f32, color, f32::MIN)
+    }
+
+    fn at(half_cells: IVec3, scale: f32, color: [f32; 4], spawn_time: f32) -> InstanceData {
+        let half_cells = half_cells.clamp(IVec3::ZERO, IVec3::splat(u16::MAX as i32));
+        InstanceData {
+            position: [half_cells.x as u16, half_cells.y as u16, half_cells.z as u16],
+            scale: (scale * 16.0).round().clamp(0.0, u16::MAX as f32) as u16,
+            color: color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8),
+            spawn_time,
+            material: CellMaterial::Solid as u32,
+        }
+    }
+
+    pub fn with_material(mut self, material: CellMaterial) -> InstanceData {
+        self.material = material as u32;
+        self
+    }
+}
+
+
+// the shading style of an instance, cell.wgsl branches on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum CellMaterial {
+    Solid = 0,
+    // unshaded and brightened.
+    Emissive = 1,
+    // every other pixel is dropped, which needs no sorting.
+    Translucent = 2,
+}
+
+// picks the material by state, as fractions of the rule's states: the
+// newborn (alive) cells glow, the ones far into their decay fade out, the
+// ones in between are solid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaterialThresholds {
+    pub enabled: bool,
+    // at or above, emissive. 1 is only the alive state.
+    pub emissive_from: f32,
+    // at or below, translucent.
+    pub translucent_to: f32,
+}
+
+impl Default for MaterialThresholds {
+    fn default() -> Self {
+        MaterialThresholds {
+            enabled: false,
+            emissive_from: 1.0,
+            translucent_to: 0.5,
+        }
+    }
+}
+
+impl MaterialThresholds {
+    pub fn material(&self, value: CellValue, states: CellValue) -> CellMaterial {
+        let t = value as f32 / states.max(1) as f32;
+        if t >= self.emissive_from {
+            CellMaterial::Emissive
+        }
+        else if t <= self.translucent_to {
+            CellMaterial::Translucent
+        }
+        else {
+            CellMaterial::Solid
+        }
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -64,7 +347,7 @@ fn queue_custom(
     mut pipeline_cache: ResMut<RenderPipelineCache>,
     meshes: Res<RenderAssets<Mesh>>,
     material_meshes: Query<
-        (Entity, &MeshUniform, &Handle<Mesh>),
+        (Entity, &MeshUniform, &Handle<Mesh>, &CellRenderMode),
         (With<Handle<Mesh>>, With<InstanceMaterialData>),
     >,
     mut views: Query<(&ExtractedView, &mut RenderPhase<Transparent3d>)>,
@@ -79,10 +362,16 @@ fn queue_custom(
     for (view, mut transparent_phase) in views.iter_mut() {
         let view_matrix = view.transform.compute_matrix();
         let view_row_2 = view_matrix.row(2);
-        for (entity, mesh_uniform, mesh_handle) in material_meshes.iter() {
+        for (entity, mesh_uniform, mesh_handle, render_mode) in material_meshes.iter() {
             if let Some(mesh) = meshes.get(mesh_handle) {
-                let key =
-                    msaa_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology);
+                let mut mesh_key = msaa_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology);
+                if *render_mode == CellRenderMode::Translucent {
+                    mesh_key |= MeshPipelineKey::TRANSPARENT_MAIN_PASS;
+                }
+                let key = CellPipelineKey {
+                    mesh: mesh_key,
+                    mode: *render_mode,
+                };
                 let pipeline = pipelines
                     .specialize(&mut pipeline_cache, &custom_pipeline, key, &mesh.layout)
                     .unwrap();
@@ -97,33 +386,97 @@ fn queue_custom(
     }
 }
 
+// buffers the gpu may still be reading from.
+const FRAMES_IN_FLIGHT: usize = 2;
+
 #[derive(Component)]
 pub struct InstanceBuffer {
-    buffer: Buffer,
-    length: usize,
+    pub buffer: Buffer,
+    pub length: usize,
+    // draw arguments the gpu wrote, see gpu_instances.rs. their instance
+    // count is used instead of `length`.
+    pub indirect: Option<Buffer>,
 }
 
+// per instanced entity, `FRAMES_IN_FLIGHT` buffers that are written in turn,
+// so a frame never overwrites the buffer the previous one draws from. they
+// only grow, by powers of two, so most frames just write into them.
+#[derive(Default)]
+struct InstanceBufferRing {
+    buffers: Vec<(Buffer, usize)>,
+    next: usize,
+}
+
+impl InstanceBufferRing {
+    fn write(&mut self, render_device: &RenderDevice, render_queue: &RenderQueue, contents: &[u8]) -> Buffer {
+        if self.buffers.len() < FRAMES_IN_FLIGHT {
+            self.buffers.push((Self::create(render_device, contents.len()), contents.len()));
+        }
+        let slot = self.next % self.buffers.len();
+        self.next = slot + 1;
+
+        let (buffer, capacity) = &mut self.buffers[slot];
+        if *capacity < contents.len() {
+            *capacity = contents.len().next_power_of_two();
+            *buffer = Self::create(render_device, *capacity);
+        }
+        if !contents.is_empty() {
+            render_queue.write_buffer(buffer, 0, contents);
+        }
+        buffer.clone()
+    }
+
+    fn create(render_device: &RenderDevice, size: usize) -> Buffer {
+        render_device.create_buffer(&BufferDescriptor {
+            label: Some("instance data buffer"),
+            // empty buffers can't be bound.
+            size: size.max(std::mem::size_of::<InstanceData>()) as u64,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+}
+
+// lives in the render world, keyed by the main world entity.
+#[derive(Default)]
+pub struct InstanceBuffers {
+    rings: HashMap<Entity, InstanceBufferRing>,
+}
+
+// the cells of a `GpuCells` entity are built by gpu_instances.rs.
 fn prepare_instance_buffers(
     mut commands: Commands,
-    query: Query<(Entity, &InstanceMaterialData)>,
+    query: Query<(Entity, &InstanceMaterialData), Without<GpuCells>>,
+    mut buffers: ResMut<InstanceBuffers>,
     render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    render_times: Res<SharedRenderTimes>,
 ) {
+    let _span = info_span!("prepare_instance_buffers").entered();
+    let t0 = std::time::Instant::now();
+    let mut bytes = 0;
+    // entities that went away, eg. a despawned debug overlay.
+    buffers.rings.retain(|entity, _| query.get(*entity).is_ok());
     for (entity, instance_data) in query.iter() {
-        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
-            label: Some("instance data buffer"),
-            contents: bytemuck::cast_slice(instance_data.0.as_slice()),
-            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-        });
+        bytes += instance_data.0.len() * std::mem::size_of::<InstanceData>();
+        let buffer = buffers.rings.entry(entity).or_default()
+            .write(&render_device, &render_queue, bytemuck::cast_slice(instance_data.0.as_slice()));
         commands.entity(entity).insert(InstanceBuffer {
             buffer,
             length: instance_data.0.len(),
+            indirect: None,
         });
     }
+    render_times.modify(|times| {
+        times.upload = t0.elapsed();
+        times.upload_bytes = bytes;
+    });
 }
 
 pub struct CellPipeline {
     shader: Handle<Shader>,
     mesh_pipeline: MeshPipeline,
+    uniform_layout: BindGroupLayout,
 }
 
 impl FromWorld for CellPipeline {
@@ -135,43 +488,92 @@ impl FromWorld for CellPipeline {
 
         let mesh_pipeline = world.get_resource::<MeshPipeline>().unwrap();
 
+        let render_device = world.get_resource::<RenderDevice>().unwrap();
+        let uniform_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("cell uniform layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: BufferSize::new(std::mem::size_of::<CellUniform>() as u64),
+                },
+                count: None,
+            }],
+        });
+
         CellPipeline {
             shader,
             mesh_pipeline: mesh_pipeline.clone(),
+            uniform_layout,
         }
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CellPipelineKey {
+    mesh: MeshPipelineKey,
+    mode: CellRenderMode,
+}
+
 impl SpecializedMeshPipeline for CellPipeline {
-    type Key = MeshPipelineKey;
+    type Key = CellPipelineKey;
 
     fn specialize(
         &self,
         key: Self::Key,
         layout: &MeshVertexBufferLayout,
     ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
-        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+        let mut descriptor = self.mesh_pipeline.specialize(key.mesh, layout)?;
+        let (shader_def, cull_mode) = match key.mode {
+            CellRenderMode::Mesh | CellRenderMode::Translucent
+                                       => (None, descriptor.primitive.cull_mode),
+            CellRenderMode::Billboard  => (Some("BILLBOARD"), None),
+            CellRenderMode::Background => (Some("BACKGROUND"), Some(Face::Front)),
+        };
+        if let Some(shader_def) = shader_def {
+            descriptor.vertex.shader_defs.push(shader_def.into());
+            descriptor.fragment.as_mut().unwrap().shader_defs.push(shader_def.into());
+        }
+        descriptor.primitive.cull_mode = cull_mode;
+        // alpha blended cells need their alpha.
+        #[cfg(feature = "post_process")]
+        if matches!(key.mode, CellRenderMode::Mesh | CellRenderMode::Billboard) {
+            descriptor.fragment.as_mut().unwrap().shader_defs.push("EMISSIVE_MASK".into());
+        }
         descriptor.vertex.shader = self.shader.clone();
         descriptor.vertex.buffers.push(VertexBufferLayout {
             array_stride: std::mem::size_of::<InstanceData>() as u64,
             step_mode: VertexStepMode::Instance,
             attributes: vec![
                 VertexAttribute {
-                    format: VertexFormat::Float32x4,
+                    format: VertexFormat::Uint16x4,
                     offset: 0,
                     shader_location: 3, // shader locations 0-2 are taken up by Position, Normal and UV attributes
                 },
                 VertexAttribute {
-                    format: VertexFormat::Float32x4,
-                    offset: VertexFormat::Float32x4.size(),
+                    format: VertexFormat::Unorm8x4,
+                    offset: VertexFormat::Uint16x4.size(),
                     shader_location: 4,
                 },
+                VertexAttribute {
+                    format: VertexFormat::Float32,
+                    offset: VertexFormat::Uint16x4.size() + VertexFormat::Unorm8x4.size(),
+                    shader_location: 5,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Uint32,
+                    offset: VertexFormat::Uint16x4.size() + VertexFormat::Unorm8x4.size() + VertexFormat::Float32.size(),
+                    shader_location: 6,
+                },
             ],
         });
         descriptor.fragment.as_mut().unwrap().shader = self.shader.clone();
         descriptor.layout = Some(vec![
             self.mesh_pipeline.view_layout.clone(),
             self.mesh_pipeline.mesh_layout.clone(),
+            self.uniform_layout.clone(),
         ]);
 
         Ok(descriptor)
@@ -182,6 +584,7 @@ type DrawCustom = (
     SetItemPipeline,
     SetMeshViewBindGroup<0>,
     SetMeshBindGroup<1>,
+    SetCellUniformBindGroup<2>,
     DrawMeshInstanced,
 );
 
@@ -210,16 +613,18 @@ impl EntityRenderCommand for DrawMeshInstanced {
         pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
         pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
 
-        match &gpu_mesh.buffer_info {
-            GpuBufferInfo::Indexed {
-                buffer,
-                index_format,
-                count,
-            } => {
+        match (&gpu_mesh.buffer_info, &instance_buffer.indirect) {
+            (GpuBufferInfo::Indexed { buffer, index_format, count }, indirect) => {
                 pass.set_index_buffer(buffer.slice(..), 0, *index_format);
-                pass.draw_indexed(0..*count, 0, 0..instance_buffer.length as u32);
+                match indirect {
+                    Some(indirect) => pass.draw_indexed_indirect(indirect, 0),
+                    None => pass.draw_indexed(0..*count, 0, 0..instance_buffer.length as u32),
+                }
+            }
+            (GpuBufferInfo::NonIndexed { .. }, Some(indirect)) => {
+                pass.draw_indirect(indirect, 0);
             }
-            GpuBufferInfo::NonIndexed { vertex_count } => {
+            (GpuBufferInfo::NonIndexed { vertex_count }, None) => {
                 pass.draw(0..*vertex_count, 0..instance_buffer.length as u32);
             }
         }
@@ -228,10 +633,117 @@ impl EntityRenderCommand for DrawMeshInstanced {
 }
 
 
+// occupancy is tracked per chunk of this size, which is also the unit of
+// work for instance extraction.
+pub const CHUNK_SIZE: i32 = 32;
+const CHUNK_CELL_COUNT: usize = (CHUNK_SIZE*CHUNK_SIZE*CHUNK_SIZE) as usize;
+const CHUNK_WORD_COUNT: usize = CHUNK_CELL_COUNT / 64;
+
+pub struct InstanceColoring {
+    pub method: ColorMethod,
+    pub palette: Palette,
+    pub states: CellValue,
+    pub max_neighbours: NeighbourCount,
+    // overrides the color of defect cells.
+    pub defect_color: Option<Color>,
+    // none draws every cell solid.
+    pub materials: Option<MaterialThresholds>,
+}
+
+// hides cells by neighbor count, dense regions approximate the inside of
+// the structure. with `surface`, only visible cells that touch a hidden or
+// empty cell are kept, which previews the isosurface of that density.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NeighbourFilter {
+    pub enabled: bool,
+    pub min: NeighbourCount,
+    pub max: NeighbourCount,
+    pub surface: bool,
+}
+
+impl Default for NeighbourFilter {
+    fn default() -> Self {
+        NeighbourFilter {
+            enabled: false,
+            min: 20,
+            max: 26,
+            surface: false,
+        }
+    }
+}
+
+impl NeighbourFilter {
+    fn passes(&self, renderer: &CellRenderer, index: usize) -> bool {
+        renderer.values[index] != 0
+            && (self.min..=self.max).contains(&renderer.neighbors[index])
+    }
+
+    pub fn keep(&self, renderer: &CellRenderer, index: usize) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        if !self.passes(renderer, index) {
+            return false;
+        }
+        if !self.surface {
+            return true;
+        }
+        let bounds = renderer.bounds;
+        let pos = utils::index_to_pos(index, bounds);
+        VONNEUMAN_NEIGHBOURS.iter().any(|dir| {
+            let neighbour = pos + *dir;
+            neighbour.min_element() < 0
+                || !utils::is_in_bounds(neighbour, bounds)
+                || !self.passes(renderer, utils::pos_to_index(neighbour, bounds))
+        })
+    }
+}
+
+
+// iterates the indices of the set bits in a word.
+struct Bits(u64);
+
+impl Iterator for Bits {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let bit = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(bit)
+    }
+}
+
+
 pub struct CellRenderer {
     pub bounds: i32,
-    pub values: Vec<u8>,
-    pub neighbors: Vec<u8>,
+    pub values: Vec<CellValue>,
+    pub neighbors: Vec<NeighbourCount>,
+    // optional auxiliary byte per cell (eg: age), written by backends with
+    // the METADATA capability. zero otherwise.
+    pub metadata: Vec<u8>,
+    // progress towards the next lower state in 1/256, see
+    // `Sim::set_decay_substeps`.
+    pub decay: Vec<u8>,
+    // energy left in the cell, 255 is full. written by backends with the
+    // ENERGY capability, see `Sim::set_energy`.
+    pub energy: Vec<u8>,
+    // 1 for defect cells, written by backends with the DEFECTS capability,
+    // see `Sim::set_defects`.
+    pub defects: Vec<u8>,
+    pub spawn_times: Vec<f32>,
+
+    // when set, cells that come alive get the current time as spawn time.
+    record_spawns: bool,
+    time: f32,
+
+    // one bit per cell, set if the value is non zero. chunk major, then
+    // row major inside the chunk.
+    occupancy: Vec<u64>,
+    chunk_radius: i32,
+    hash: GridHash,
 }
 
 impl CellRenderer {
@@ -240,6 +752,16 @@ impl CellRenderer {
             bounds: 0,
             values: vec![],
             neighbors: vec![],
+            metadata: vec![],
+            decay: vec![],
+            energy: vec![],
+            defects: vec![],
+            spawn_times: vec![],
+            record_spawns: false,
+            time: 0.0,
+            occupancy: vec![],
+            chunk_radius: 0,
+            hash: GridHash::new(0),
         }
     }
 
@@ -247,12 +769,27 @@ impl CellRenderer {
         (self.bounds*self.bounds*self.bounds) as usize
     }
 
+    pub fn chunk_radius(&self) -> i32 {
+        self.chunk_radius
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        (self.chunk_radius*self.chunk_radius*self.chunk_radius) as usize
+    }
+
     pub fn set_bounds(&mut self, new_bounds: i32) {
         if new_bounds != self.bounds {
             let new_count = new_bounds*new_bounds*new_bounds;
             self.values.resize(new_count as usize, 0);
             self.neighbors.resize(new_count as usize, 0);
+            self.metadata.resize(new_count as usize, 0);
+            self.decay.resize(new_count as usize, 0);
+            self.energy.resize(new_count as usize, 0);
+            self.defects.resize(new_count as usize, 0);
+            self.spawn_times.resize(new_count as usize, f32::MIN);
             self.bounds = new_bounds;
+            self.chunk_radius = (new_bounds + CHUNK_SIZE - 1) / CHUNK_SIZE;
+            self.clear();
         }
     }
 
@@ -261,14 +798,266 @@ impl CellRenderer {
         self.values.resize(self.cell_count(), 0);
         self.neighbors.truncate(0);
         self.neighbors.resize(self.cell_count(), 0);
+        self.metadata.truncate(0);
+        self.metadata.resize(self.cell_count(), 0);
+        self.decay.truncate(0);
+        self.decay.resize(self.cell_count(), 0);
+        self.energy.truncate(0);
+        self.energy.resize(self.cell_count(), 0);
+        self.defects.truncate(0);
+        self.defects.resize(self.cell_count(), 0);
+        self.spawn_times.truncate(0);
+        self.spawn_times.resize(self.cell_count(), f32::MIN);
+        self.occupancy.truncate(0);
+        self.occupancy.resize(self.chunk_count() * CHUNK_WORD_COUNT, 0);
+        self.hash = GridHash::new(self.bounds);
+    }
+
+    // of the values, see `GridHash`.
+    pub fn hash(&self) -> u64 {
+        self.hash.value()
+    }
+
+    pub fn begin_frame(&mut self, time: f32, record_spawns: bool) {
+        self.time = time;
+        self.record_spawns = record_spawns;
+    }
+
+    pub fn set_metadata(&mut self, index: usize, metadata: u8) {
+        self.metadata[index] = metadata;
+    }
+
+    pub fn set_decay(&mut self, index: usize, decay: u8) {
+        self.decay[index] = decay;
+    }
+
+    pub fn set_energy(&mut self, index: usize, energy: u8) {
+        self.energy[index] = energy;
     }
 
-    pub fn set(&mut self, index: usize, value: u8, neighbors: u8) {
+    pub fn set_defect(&mut self, index: usize, defect: bool) {
+        self.defects[index] = defect as u8;
+    }
+
+    pub fn set(&mut self, index: usize, value: CellValue, neighbors: NeighbourCount) {
+        self.set_pos(utils::index_to_pos(index, self.bounds), value, neighbors);
+    }
+
+    pub fn set_pos(&mut self, pos: IVec3, value: CellValue, neighbors: NeighbourCount) {
+        let index = utils::pos_to_index(pos, self.bounds);
+        if self.record_spawns && self.values[index] == 0 && value != 0 {
+            self.spawn_times[index] = self.time;
+        }
+        self.hash.set(index, self.values[index], value);
         self.values[index]    = value;
         self.neighbors[index] = neighbors;
+
+        let chunk  = utils::pos_to_index(pos / CHUNK_SIZE, self.chunk_radius);
+        let offset = utils::pos_to_index(pos % CHUNK_SIZE, CHUNK_SIZE);
+        let word = &mut self.occupancy[chunk*CHUNK_WORD_COUNT + offset / 64];
+        let bit  = 1u64 << (offset % 64);
+        if value != 0 {
+            *word |= bit;
+        }
+        else {
+            *word &= !bit;
+        }
+    }
+
+    fn chunk_words(&self, chunk_index: usize) -> &[u64] {
+        &self.occupancy[chunk_index*CHUNK_WORD_COUNT .. (chunk_index + 1)*CHUNK_WORD_COUNT]
+    }
+
+    pub fn chunk_is_empty(&self, chunk_index: usize) -> bool {
+        self.chunk_words(chunk_index).iter().all(|word| *word == 0)
+    }
+
+    pub fn chunk_live_cells(&self, chunk_index: usize) -> impl Iterator<Item = IVec3> + '_ {
+        let chunk_pos = CHUNK_SIZE * utils::index_to_pos(chunk_index, self.chunk_radius);
+        self.chunk_words(chunk_index).iter().enumerate().flat_map(move |(i, word)| {
+            Bits(*word).map(move |bit| chunk_pos + utils::index_to_pos(i*64 + bit, CHUNK_SIZE))
+        })
+    }
+
+    pub fn live_cells(&self) -> impl Iterator<Item = IVec3> + '_ {
+        (0..self.chunk_count()).flat_map(move |chunk_index| self.chunk_live_cells(chunk_index))
     }
 
-    pub fn set_pos(&mut self, pos: IVec3, value: u8, neighbors: u8) {
-        self.set(utils::pos_to_index(pos, self.bounds), value, neighbors);
+    // inclusive min and max of the live cells. empty chunks are skipped, and
+    // so are chunks that lie inside the box found so far.
+    pub fn live_aabb(&self) -> Option<(IVec3, IVec3)> {
+        let mut aabb: Option<(IVec3, IVec3)> = None;
+        for chunk_index in 0..self.chunk_count() {
+            if self.chunk_is_empty(chunk_index) {
+                continue;
+            }
+            let chunk_min = CHUNK_SIZE * utils::index_to_pos(chunk_index, self.chunk_radius);
+            let chunk_max = chunk_min + CHUNK_SIZE - 1;
+            if let Some((min, max)) = aabb {
+                if chunk_min.cmpge(min).all() && chunk_max.cmple(max).all() {
+                    continue;
+                }
+            }
+            for pos in self.chunk_live_cells(chunk_index) {
+                aabb = Some(match aabb {
+                    Some((min, max)) => (min.min(pos), max.max(pos)),
+                    None => (pos, pos),
+                });
+            }
+        }
+        aabb
+    }
+
+    // distance along `direction` (normalized) from `origin` to the first live
+    // cell. in the space the instances are drawn in, where cell `pos` is the
+    // unit cube around `pos - center`. ignores the exploded view.
+    pub fn raycast(&self, origin: Vec3, direction: Vec3) -> Option<f32> {
+        let bounds = self.bounds;
+        // cell `pos` spans pos..pos + 1 here.
+        let origin = origin + utils::center(bounds).as_vec3() + 0.5;
+
+        // clip the ray to the grid.
+        let inverse = direction.recip();
+        let t0 = -origin * inverse;
+        let t1 = (Vec3::splat(bounds as f32) - origin) * inverse;
+        let enter = t0.min(t1).max_element().max(0.0);
+        let exit  = t0.max(t1).min_element();
+        if bounds == 0 || enter > exit {
+            return None;
+        }
+
+        // step from cell to cell, always across the nearest boundary.
+        let mut cell = (origin + direction * enter).floor().as_ivec3()
+            .clamp(IVec3::ZERO, IVec3::splat(bounds - 1));
+        let mut step = IVec3::ZERO;
+        let mut next  = Vec3::splat(f32::INFINITY);
+        let mut delta = Vec3::splat(f32::INFINITY);
+        for axis in 0..3 {
+            if direction[axis] > 0.0 {
+                step[axis] = 1;
+                next[axis] = (cell[axis] as f32 + 1.0 - origin[axis]) * inverse[axis];
+            }
+            else if direction[axis] < 0.0 {
+                step[axis] = -1;
+                next[axis] = (cell[axis] as f32 - origin[axis]) * inverse[axis];
+            }
+            delta[axis] = inverse[axis].abs();
+        }
+
+        let mut t = enter;
+        loop {
+            if self.values[utils::pos_to_index(cell, bounds)] != 0 {
+                return Some(t);
+            }
+            let axis = match (next.x < next.y, next.x < next.z, next.y < next.z) {
+                (true, true, _)  => 0,
+                (false, _, true) => 1,
+                _                => 2,
+            };
+            t = next[axis];
+            cell[axis] += step[axis];
+            if t > exit || cell.min_element() < 0 || !utils::is_in_bounds(cell, bounds) {
+                return None;
+            }
+            next[axis] += delta[axis];
+        }
+    }
+
+    pub fn extract_instances(&self,
+        coloring: &InstanceColoring, filter: &InstanceFilter, neighbour_filter: &NeighbourFilter,
+        task_pool: &TaskPool, instances: &mut Vec<InstanceData>,
+    ) {
+        let bounds = self.bounds;
+
+        let chunks = task_pool.scope(|scope| {
+            for chunk_index in 0..self.chunk_count() {
+                if self.chunk_is_empty(chunk_index) {
+                    continue;
+                }
+
+                scope.spawn(async move {
+                    let mut chunk_instances = vec![];
+                    for pos in self.chunk_live_cells(chunk_index) {
+                        let index     = utils::pos_to_index(pos, bounds);
+                        let value     = self.values[index];
+                        let neighbors = self.neighbors[index];
+                        if filter.keep(self, index) && neighbour_filter.keep(self, index) {
+                            let color = |state| coloring.method.color(
+                                &coloring.palette,
+                                coloring.states,
+                                state, neighbors, coloring.max_neighbours,
+                                self.metadata[index], self.energy[index],
+                                utils::dist_to_center(pos, bounds),
+                            );
+                            // part of the way to the next lower state.
+                            let color = match (self.decay[index], coloring.defect_color) {
+                                (_, Some(defect_color)) if self.defects[index] != 0 => defect_color,
+                                (0, _) => color(value),
+                                (decay, _) => utils::lerp_color(color(value), color(value - 1), decay as f32 / 256.0),
+                            };
+                            let material = coloring.materials
+                                .map(|materials| materials.material(value, coloring.states))
+                                .unwrap_or(CellMaterial::Solid);
+                            chunk_instances.push(InstanceData::cell(
+                                pos, 1.0, color.into(), self.spawn_times[index]).with_material(material));
+                        }
+                    }
+                    chunk_instances
+                });
+            }
+        });
+
+        instances.truncate(0);
+        for chunk_instances in chunks {
+            instances.extend(chunk_instances);
+        }
+    }
+
+    // the neighbor counts of the dead cells, as voxels of `scale`³ cells.
+    // a voxel takes the highest count inside that passes `keep`. one task
+    // per layer of voxels.
+    pub fn extract_neighbour_field(&self,
+        scale: i32, keep: impl Fn(NeighbourCount) -> bool + Sync, color: impl Fn(NeighbourCount) -> [f32; 4] + Sync,
+        task_pool: &TaskPool, instances: &mut Vec<InstanceData>,
+    ) {
+        let bounds = self.bounds;
+        let voxels = (bounds + scale - 1) / scale;
+        let (keep, color) = (&keep, &color);
+
+        let layers = task_pool.scope(|scope| {
+            for z in 0..voxels {
+                scope.spawn(async move {
+                    let mut layer_instances = vec![];
+                    for y in 0..voxels {
+                        for x in 0..voxels {
+                            let min = ivec3(x, y, z) * scale;
+                            let max = (min + scale).min(IVec3::splat(bounds));
+                            let mut count = None;
+                            for cz in min.z..max.z {
+                                for cy in min.y..max.y {
+                                    for cx in min.x..max.x {
+                                        let index = utils::pos_to_index(ivec3(cx, cy, cz), bounds);
+                                        let neighbors = self.neighbors[index];
+                                        if self.values[index] == 0 && keep(neighbors) {
+                                            count = count.max(Some(neighbors));
+                                        }
+                                    }
+                                }
+                            }
+
+                            if let Some(count) = count {
+                                layer_instances.push(InstanceData::voxel(min, scale, color(count)));
+                            }
+                        }
+                    }
+                    layer_instances
+                });
+            }
+        });
+
+        instances.truncate(0);
+        for layer_instances in layers {
+            instances.extend(layer_instances);
+        }
     }
 }
@@ -0,0 +1,86 @@
+use bevy::prelude::Color;
+use bevy_egui::egui;
+
+use crate::{neighbours::NeighbourCount, rule::Rule, utils};
+
+
+pub const DEFECT_COLOR: Color = Color::rgb(1.0, 0.0, 1.0);
+
+// a fraction of the cells follows a slightly different rule, to see how
+// robust a pattern is. which cells are defects only depends on the seed and
+// the position, so the same settings give the same defects after a reset.
+// defect cells still count as neighbors like any other cell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DefectSettings {
+    // 0 turns defects off.
+    pub fraction: f32,
+    pub seed: u64,
+    // counts the defect rule adds to the birth and survival rule.
+    pub extra_birth: Option<NeighbourCount>,
+    pub extra_survival: Option<NeighbourCount>,
+    // draw the defect cells in `DEFECT_COLOR`.
+    pub highlight: bool,
+}
+
+impl Default for DefectSettings {
+    fn default() -> Self {
+        DefectSettings {
+            fraction: 0.0,
+            seed: 1,
+            extra_birth: Some(5),
+            extra_survival: None,
+            highlight: false,
+        }
+    }
+}
+
+impl DefectSettings {
+    pub fn enabled(&self) -> bool {
+        self.fraction > 0.0
+    }
+
+    // `index` is the row major index, see `utils::pos_to_index`.
+    pub fn is_defect(&self, index: usize) -> bool {
+        self.enabled()
+            && (utils::hash_u64(self.seed ^ index as u64) >> 40) as f32 / (1u64 << 24) as f32 <= self.fraction
+    }
+
+    // true if `other` picks other cells, ie. backends have to reassign them.
+    pub fn cells_differ(&self, other: &DefectSettings) -> bool {
+        self.fraction != other.fraction || self.seed != other.seed
+    }
+
+    // the rule of the defect cells.
+    pub fn rule(&self, rule: &Rule) -> Rule {
+        let mut defect = rule.clone();
+        if let Some(count) = self.extra_birth {
+            defect.birth_rule.set(count, true);
+        }
+        if let Some(count) = self.extra_survival {
+            defect.survival_rule.set(count, true);
+        }
+        defect
+    }
+
+    // true if anything changed.
+    pub fn ui(&mut self, ui: &mut egui::Ui, max_neighbours: NeighbourCount) -> bool {
+        let before = *self;
+        ui.add(egui::Slider::new(&mut self.fraction, 0.0..=0.2).text("defect cells"));
+        if self.enabled() {
+            for (label, count) in [("extra birth", &mut self.extra_birth), ("extra survival", &mut self.extra_survival)] {
+                ui.horizontal(|ui| {
+                    let mut on = count.is_some();
+                    ui.checkbox(&mut on, label);
+                    let mut value = count.unwrap_or(0).min(max_neighbours);
+                    ui.add_enabled(on, egui::DragValue::new(&mut value).clamp_range(0..=max_neighbours));
+                    *count = on.then(|| value);
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut self.seed).prefix("seed: "));
+                ui.checkbox(&mut self.highlight, "highlight");
+            });
+        }
+        *self != before
+    }
+}
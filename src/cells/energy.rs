@@ -0,0 +1,55 @@
+use bevy_egui::egui;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnergyMode {
+    Off,
+    // every cell has its own energy, a birth spends the energy of the cell
+    // it happens in.
+    PerCell,
+    // one pool for the whole grid. births are paid in index order, so once
+    // the pool runs dry the grid grows from one side.
+    Global,
+}
+
+// births cost energy, which regenerates by a fixed amount per tick. growth
+// that outpaces the regeneration stalls until the energy recovers, so the
+// rules pulse instead of filling the grid.
+// a full cell holds 255, the global pool holds as much as all cells together.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnergySettings {
+    pub mode: EnergyMode,
+    pub birth_cost: u8,
+    // per cell and tick.
+    pub regen: u8,
+}
+
+impl Default for EnergySettings {
+    fn default() -> Self {
+        EnergySettings {
+            mode: EnergyMode::Off,
+            birth_cost: 64,
+            regen: 4,
+        }
+    }
+}
+
+impl EnergySettings {
+    pub fn enabled(&self) -> bool {
+        self.mode != EnergyMode::Off
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label("energy")
+            .selected_text(format!("{:?}", self.mode))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.mode, EnergyMode::Off, "Off");
+                ui.selectable_value(&mut self.mode, EnergyMode::PerCell, "Per cell");
+                ui.selectable_value(&mut self.mode, EnergyMode::Global, "Global");
+            });
+        if self.enabled() {
+            ui.add(egui::Slider::new(&mut self.birth_cost, 1..=255).text("birth cost"));
+            ui.add(egui::Slider::new(&mut self.regen, 0..=64).text("regeneration per tick"));
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::VecDeque;
+
+use bevy::{math::ivec3, tasks::TaskPool};
+use bevy_egui::egui;
+use rand::Rng;
+
+use crate::{
+    cell_renderer::CellRenderer,
+    cells::Sim,
+    neighbours::NeighbourCount,
+    rule::{CellValue, Rule},
+};
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rule1dKind {
+    // wolfram's numbering, bit n is the next state of the neighborhood
+    // (left, center, right) read as the binary number n.
+    Elementary,
+    // bit n is the next state of a cell whose neighborhood of 2 * radius + 1
+    // cells has n live cells.
+    Totalistic { radius: u8 },
+}
+
+// a row of cells along x, with every generation a step further along z. one
+// tick is one generation, once the volume is full the oldest generation
+// scrolls out. the row wraps around. the 3d rule only picks the colors.
+pub struct Elementary1d {
+    pub kind: Rule1dKind,
+    pub number: u64,
+    // spawn noise seeds a random row instead of one cell in the middle.
+    pub random_seed: bool,
+    // cells along y, centered.
+    pub thickness: i32,
+    bounds: i32,
+    // newest last.
+    generations: VecDeque<Vec<bool>>,
+    // of the 3d rule, for the colors.
+    states: CellValue,
+    max_neighbours: NeighbourCount,
+}
+
+impl Elementary1d {
+    pub fn new() -> Elementary1d {
+        Elementary1d {
+            kind: Rule1dKind::Elementary,
+            number: 30,
+            random_seed: false,
+            thickness: 1,
+            bounds: 0,
+            generations: VecDeque::new(),
+            states: 1,
+            max_neighbours: 26,
+        }
+    }
+
+    fn radius(&self) -> i32 {
+        match self.kind {
+            Rule1dKind::Elementary => 1,
+            Rule1dKind::Totalistic { radius } => radius as i32,
+        }
+    }
+
+    // the largest rule number plus one.
+    pub fn rule_count(&self) -> u64 {
+        match self.kind {
+            Rule1dKind::Elementary => 256,
+            Rule1dKind::Totalistic { radius } => 1 << (2 * radius as u64 + 2),
+        }
+    }
+
+    fn next_row(&self, row: &[bool]) -> Vec<bool> {
+        let width = row.len() as i32;
+        let radius = self.radius();
+        let at = |x: i32| row[((x % width + width) % width) as usize];
+        (0..width).map(|x| {
+            let n = match self.kind {
+                Rule1dKind::Elementary => (at(x - 1) as u64) << 2 | (at(x) as u64) << 1 | at(x + 1) as u64,
+                Rule1dKind::Totalistic { .. } => (-radius..=radius).filter(|dx| at(x + dx)).count() as u64,
+            };
+            self.number >> n & 1 != 0
+        }).collect()
+    }
+
+    fn live_neighbors(&self, row: &[bool], x: i32) -> u8 {
+        let width = row.len() as i32;
+        let radius = self.radius();
+        (-radius..=radius)
+            .filter(|dx| *dx != 0 && row[(((x + dx) % width + width) % width) as usize])
+            .count() as u8
+    }
+
+    fn remember(&mut self, rule: &Rule) {
+        self.states = rule.states;
+        self.max_neighbours = rule.neighbour_method.max_neighbours();
+    }
+
+    fn rows(&self) -> std::ops::Range<i32> {
+        let low = (self.bounds - self.thickness.min(self.bounds)) / 2;
+        low..low + self.thickness.min(self.bounds)
+    }
+}
+
+impl Sim for Elementary1d {
+    fn update(&mut self, rule: &Rule, _task_pool: &TaskPool) {
+        self.remember(rule);
+        let next = match self.generations.back() {
+            Some(row) => self.next_row(row),
+            None => return,
+        };
+        if self.generations.len() as i32 >= self.bounds {
+            self.generations.pop_front();
+        }
+        self.generations.push_back(next);
+    }
+
+    fn render(&self, renderer: &mut CellRenderer) {
+        let rows = self.rows();
+        let stale: Vec<_> = renderer.live_cells()
+            .filter(|pos| !rows.contains(&pos.y) || !self.generations.get(pos.z as usize)
+                .map(|row| row[pos.x as usize]).unwrap_or(false))
+            .collect();
+        for pos in stale {
+            renderer.set_pos(pos, 0, 0);
+        }
+        let max = 2 * self.radius() as NeighbourCount;
+        for (z, row) in self.generations.iter().enumerate() {
+            for (x, live) in row.iter().enumerate() {
+                if !*live {
+                    continue;
+                }
+                let neighbors = self.live_neighbors(row, x as i32) as NeighbourCount * self.max_neighbours / max.max(1);
+                for y in self.rows() {
+                    renderer.set_pos(ivec3(x as i32, y, z as i32), self.states, neighbors);
+                }
+            }
+        }
+    }
+
+    fn spawn_noise(&mut self, rule: &Rule) {
+        self.remember(rule);
+        let width = self.bounds.max(0) as usize;
+        let row = match self.random_seed {
+            true => {
+                let mut rand = rand::thread_rng();
+                (0..width).map(|_| rand.gen_bool(0.5)).collect()
+            }
+            false => (0..width).map(|x| x == width / 2).collect(),
+        };
+        self.generations.clear();
+        self.generations.push_back(row);
+    }
+
+    fn cell_count(&self) -> usize {
+        let per_row = self.rows().count();
+        self.generations.iter().map(|row| row.iter().filter(|live| **live).count() * per_row).sum()
+    }
+
+    fn bounds(&self) -> i32 {
+        self.bounds
+    }
+
+    fn set_bounds(&mut self, new_bounds: i32) -> i32 {
+        if new_bounds != self.bounds {
+            self.generations.clear();
+        }
+        self.bounds = new_bounds;
+        new_bounds
+    }
+
+    fn reset(&mut self) {
+        self.generations.clear();
+    }
+
+    fn max_bounds(&self) -> i32 {
+        256
+    }
+
+    // the 3d rule doesn't apply.
+    fn follows_rule(&self) -> bool {
+        false
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.kind, Rule1dKind::Elementary, "elementary");
+            for radius in 1..=3 {
+                ui.selectable_value(&mut self.kind, Rule1dKind::Totalistic { radius }, format!("totalistic r{}", radius));
+            }
+        });
+        let max = self.rule_count() - 1;
+        self.number = self.number.min(max);
+        ui.horizontal(|ui| {
+            ui.label("rule");
+            ui.add(egui::DragValue::new(&mut self.number).clamp_range(0..=max));
+            ui.weak(format!("0..={}", max));
+        });
+        ui.checkbox(&mut self.random_seed, "random first row");
+        ui.add(egui::Slider::new(&mut self.thickness, 1..=32).text("thickness"));
+        ui.weak("spawn noise starts over from a new first row");
+    }
+}
@@ -0,0 +1,194 @@
+mod plane;
+pub use plane::*;
+
+mod elementary;
+pub use elementary::*;
+
+mod stacked;
+pub use stacked::*;
+
+
+use bevy_egui::egui;
+use rand::Rng;
+
+use crate::{neighbours::NeighbourCount, rule::CellValue, utils};
+
+
+// a rule for a plane of cells, in the usual B/S notation with generations:
+// dead cells with a birth count are born at `states`, cells at `states` stay
+// while they survive, everything else decays by one. only cells at `states`
+// count as neighbors. the same transition as `Rule::next_value`, with the 8
+// neighbors of the moore neighborhood in 2d.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rule2d {
+    // bit n set: n neighbors.
+    pub birth: u16,
+    pub survival: u16,
+    pub states: CellValue,
+}
+
+impl Rule2d {
+    pub const fn new(birth: u16, survival: u16, states: CellValue) -> Rule2d {
+        Rule2d { birth, survival, states }
+    }
+
+    pub fn next_value(&self, value: CellValue, neighbors: u8) -> CellValue {
+        let bit = 1 << neighbors;
+        if value == 0 {
+            match self.birth & bit != 0 {
+                true  => self.states,
+                false => 0,
+            }
+        }
+        else if value == self.states && self.survival & bit != 0 {
+            value
+        }
+        else {
+            value - 1
+        }
+    }
+
+    // "B3/S23", with "/C" and the number of states for generations rules.
+    pub fn notation(&self) -> String {
+        let counts = |bits: u16| (0..=8).filter(|n| bits & (1 << n) != 0).map(|n| n.to_string()).collect::<String>();
+        let mut notation = format!("B{}/S{}", counts(self.birth), counts(self.survival));
+        if self.states > 1 {
+            // generations count the dead state too.
+            notation += &format!("/C{}", self.states as u32 + 1);
+        }
+        notation
+    }
+
+    // birth and survival checkboxes, the states and the presets.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("preset");
+            egui::ComboBox::from_id_source("rule 2d preset")
+                .selected_text(PRESETS_2D.iter().find(|(_, rule)| rule == self).map(|(name, _)| *name).unwrap_or("custom"))
+                .show_ui(ui, |ui| {
+                    for (name, rule) in PRESETS_2D {
+                        ui.selectable_value(self, rule, name);
+                    }
+                });
+            ui.weak(self.notation());
+        });
+        for (label, bits) in [("birth", &mut self.birth), ("survival", &mut self.survival)] {
+            ui.horizontal(|ui| {
+                ui.label(label);
+                for n in 0..=8 {
+                    let mut set = *bits & (1 << n) != 0;
+                    if ui.checkbox(&mut set, n.to_string()).changed() {
+                        *bits ^= 1 << n;
+                    }
+                }
+            });
+        }
+        ui.add(egui::Slider::new(&mut self.states, 1..=32).text("states"));
+    }
+}
+
+pub const PRESETS_2D: [(&str, Rule2d); 8] = [
+    ("life",          Rule2d::new(0b0_0000_1000, 0b0_0000_1100, 1)),
+    ("highlife",      Rule2d::new(0b0_0100_1000, 0b0_0000_1100, 1)),
+    ("seeds",         Rule2d::new(0b0_0000_0100, 0b0_0000_0000, 1)),
+    ("day & night",   Rule2d::new(0b1_1100_1000, 0b1_1101_1000, 1)),
+    ("maze",          Rule2d::new(0b0_0000_1000, 0b0_0011_1110, 1)),
+    ("brian's brain", Rule2d::new(0b0_0000_0100, 0b0_0000_0000, 2)),
+    ("star wars",     Rule2d::new(0b0_0000_0100, 0b0_0011_1000, 3)),
+    ("frogs",         Rule2d::new(0b0_0001_1000, 0b0_0000_0110, 2)),
+];
+
+
+// a square plane of `size`² cells that wraps around, row major.
+pub struct Grid2d {
+    pub size: i32,
+    pub cells: Vec<CellValue>,
+    // of the last `step`, for coloring.
+    pub neighbors: Vec<u8>,
+    next: Vec<CellValue>,
+}
+
+impl Grid2d {
+    pub fn new(size: i32) -> Grid2d {
+        let count = (size * size).max(0) as usize;
+        Grid2d {
+            size,
+            cells: vec![0; count],
+            neighbors: vec![0; count],
+            next: vec![0; count],
+        }
+    }
+
+    pub fn index(&self, x: i32, y: i32) -> usize {
+        (y * self.size + x) as usize
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> CellValue {
+        self.cells[self.index(x, y)]
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, value: CellValue) {
+        let index = self.index(x, y);
+        self.cells[index] = value;
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.iter_mut().for_each(|cell| *cell = 0);
+    }
+
+    pub fn live_count(&self) -> usize {
+        self.cells.iter().filter(|cell| **cell != 0).count()
+    }
+
+    pub fn step(&mut self, rule: &Rule2d) {
+        let size = self.size;
+        for y in 0..size {
+            let rows = [(y + size - 1) % size, y, (y + 1) % size];
+            for x in 0..size {
+                let columns = [(x + size - 1) % size, x, (x + 1) % size];
+                let mut neighbors = 0;
+                for row in rows {
+                    for column in columns {
+                        neighbors += (self.cells[(row * size + column) as usize] == rule.states) as u8;
+                    }
+                }
+                let index = (y * size + x) as usize;
+                let value = self.cells[index];
+                neighbors -= (value == rule.states) as u8;
+                self.neighbors[index] = neighbors;
+                self.next[index] = rule.next_value(value, neighbors);
+            }
+        }
+        std::mem::swap(&mut self.cells, &mut self.next);
+    }
+
+    // about half of the cells in a square around the center come alive.
+    pub fn spawn_noise(&mut self, rule: &Rule2d) {
+        let mut rand = rand::thread_rng();
+        let center = self.size / 2;
+        let radius = (utils::NOISE_RADIUS * 2).min(center);
+        for y in center - radius..center + radius {
+            for x in center - radius..center + radius {
+                if rand.gen_bool(0.5) {
+                    self.set(x, y, rule.states);
+                }
+            }
+        }
+    }
+}
+
+// a cell value of a rule with `from` states on a rule with `to` states, so
+// the color methods see the whole range.
+pub fn rescale(value: CellValue, from: CellValue, to: CellValue) -> CellValue {
+    if value == 0 {
+        return 0;
+    }
+    let scaled = (value as u32 * to.max(1) as u32 + from.max(1) as u32 - 1) / from.max(1) as u32;
+    scaled.max(1) as CellValue
+}
+
+// 2d counts go up to 8, the color methods expect up to the neighbors of the
+// 3d rule.
+pub fn rescale_neighbors(neighbors: u8, max: NeighbourCount) -> NeighbourCount {
+    neighbors as NeighbourCount * max / 8
+}
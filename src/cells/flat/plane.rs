@@ -0,0 +1,132 @@
+use bevy::{
+    math::ivec3,
+    tasks::TaskPool,
+};
+use bevy_egui::egui;
+
+use crate::{
+    cell_renderer::CellRenderer,
+    cells::Sim,
+    neighbours::NeighbourCount,
+    rule::{CellValue, Rule},
+};
+
+use super::{rescale, rescale_neighbors, Grid2d, Rule2d, PRESETS_2D};
+
+
+// a 2d automaton on the bottom plane of the volume (x and z), with its own
+// `Rule2d`. the 3d rule only picks the colors. each cell is drawn as a tile
+// `height` cells tall, or as a heightmap with the height following the
+// state.
+pub struct Plane2d {
+    rule: Rule2d,
+    grid: Grid2d,
+    pub height: i32,
+    pub height_by_state: bool,
+    // of the 3d rule, for the colors.
+    states: CellValue,
+    max_neighbours: NeighbourCount,
+}
+
+impl Plane2d {
+    pub fn new() -> Plane2d {
+        Plane2d {
+            rule: PRESETS_2D[0].1,
+            grid: Grid2d::new(0),
+            height: 1,
+            height_by_state: true,
+            states: 1,
+            max_neighbours: 26,
+        }
+    }
+
+    fn column_height(&self, value: CellValue) -> i32 {
+        match self.height_by_state {
+            true  => (self.height * value as i32 + self.rule.states as i32 - 1) / self.rule.states.max(1) as i32,
+            false => self.height,
+        }.clamp(1, self.grid.size.max(1))
+    }
+
+    fn remember(&mut self, rule: &Rule) {
+        self.states = rule.states;
+        self.max_neighbours = rule.neighbour_method.max_neighbours();
+    }
+}
+
+impl Sim for Plane2d {
+    fn update(&mut self, rule: &Rule, _task_pool: &TaskPool) {
+        self.remember(rule);
+        // a rule with fewer states leaves cells above them behind.
+        let states = self.rule.states;
+        self.grid.cells.iter_mut().for_each(|cell| *cell = (*cell).min(states));
+        self.grid.step(&self.rule);
+    }
+
+    fn render(&self, renderer: &mut CellRenderer) {
+        let size = self.grid.size;
+        let stale: Vec<_> = renderer.live_cells()
+            .filter(|pos| {
+                let value = self.grid.get(pos.x, pos.z);
+                value == 0 || pos.y >= self.column_height(value)
+            })
+            .collect();
+        for pos in stale {
+            renderer.set_pos(pos, 0, 0);
+        }
+        for z in 0..size {
+            for x in 0..size {
+                let index = self.grid.index(x, z);
+                let value = self.grid.cells[index];
+                if value == 0 {
+                    continue;
+                }
+                let shown = rescale(value, self.rule.states, self.states);
+                let neighbors = rescale_neighbors(self.grid.neighbors[index], self.max_neighbours);
+                for y in 0..self.column_height(value) {
+                    renderer.set_pos(ivec3(x, y, z), shown, neighbors);
+                }
+            }
+        }
+    }
+
+    fn spawn_noise(&mut self, rule: &Rule) {
+        self.remember(rule);
+        self.grid.spawn_noise(&self.rule);
+    }
+
+    fn cell_count(&self) -> usize {
+        self.grid.live_count()
+    }
+
+    fn bounds(&self) -> i32 {
+        self.grid.size
+    }
+
+    fn set_bounds(&mut self, new_bounds: i32) -> i32 {
+        if new_bounds != self.grid.size {
+            self.grid = Grid2d::new(new_bounds);
+        }
+        new_bounds
+    }
+
+    fn reset(&mut self) {
+        self.grid.clear();
+    }
+
+    fn max_bounds(&self) -> i32 {
+        256
+    }
+
+    // the 3d rule doesn't apply.
+    fn follows_rule(&self) -> bool {
+        false
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        self.rule.ui(ui);
+        ui.horizontal(|ui| {
+            ui.add(egui::Slider::new(&mut self.height, 1..=32).text("tile height"));
+            ui.checkbox(&mut self.height_by_state, "by state");
+        });
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::VecDeque;
+
+use bevy::{math::ivec3, tasks::TaskPool};
+use bevy_egui::egui;
+
+use crate::{
+    cell_renderer::CellRenderer,
+    cells::Sim,
+    neighbours::NeighbourCount,
+    rule::{CellValue, Rule},
+};
+
+use super::{rescale, rescale_neighbors, Grid2d, Rule2d, PRESETS_2D};
+
+
+// a 2d automaton (x and z) whose generations are stacked upwards, oldest at
+// the bottom, so the volume holds its history: a glider leaves a diagonal
+// tube. once the volume is full the oldest generation scrolls out. the 3d
+// rule only picks the colors.
+pub struct Stacked2d {
+    rule: Rule2d,
+    grid: Grid2d,
+    // draw only cells at the alive state, decaying ones blur the tubes.
+    pub alive_only: bool,
+    // newest last, each the grid and its neighbor counts.
+    layers: VecDeque<(Vec<CellValue>, Vec<u8>)>,
+    // of the 3d rule, for the colors.
+    states: CellValue,
+    max_neighbours: NeighbourCount,
+}
+
+impl Stacked2d {
+    pub fn new() -> Stacked2d {
+        Stacked2d {
+            rule: PRESETS_2D[0].1,
+            grid: Grid2d::new(0),
+            alive_only: false,
+            layers: VecDeque::new(),
+            states: 1,
+            max_neighbours: 26,
+        }
+    }
+
+    fn remember(&mut self, rule: &Rule) {
+        self.states = rule.states;
+        self.max_neighbours = rule.neighbour_method.max_neighbours();
+    }
+
+    fn push_layer(&mut self) {
+        if self.layers.len() as i32 >= self.grid.size {
+            self.layers.pop_front();
+        }
+        self.layers.push_back((self.grid.cells.clone(), self.grid.neighbors.clone()));
+    }
+
+    fn shown(&self, value: CellValue) -> bool {
+        match self.alive_only {
+            true  => value == self.rule.states,
+            false => value != 0,
+        }
+    }
+}
+
+impl Sim for Stacked2d {
+    fn update(&mut self, rule: &Rule, _task_pool: &TaskPool) {
+        self.remember(rule);
+        if self.layers.is_empty() {
+            return;
+        }
+        // a rule with fewer states leaves cells above them behind.
+        let states = self.rule.states;
+        self.grid.cells.iter_mut().for_each(|cell| *cell = (*cell).min(states));
+        self.grid.step(&self.rule);
+        self.push_layer();
+    }
+
+    fn render(&self, renderer: &mut CellRenderer) {
+        let size = self.grid.size;
+        let stale: Vec<_> = renderer.live_cells()
+            .filter(|pos| !self.layers.get(pos.y as usize)
+                .map(|(cells, _)| self.shown(cells[(pos.z * size + pos.x) as usize]))
+                .unwrap_or(false))
+            .collect();
+        for pos in stale {
+            renderer.set_pos(pos, 0, 0);
+        }
+        for (y, (cells, neighbors)) in self.layers.iter().enumerate() {
+            for z in 0..size {
+                for x in 0..size {
+                    let index = (z * size + x) as usize;
+                    if self.shown(cells[index]) {
+                        renderer.set_pos(ivec3(x, y as i32, z),
+                            rescale(cells[index], self.rule.states, self.states),
+                            rescale_neighbors(neighbors[index], self.max_neighbours));
+                    }
+                }
+            }
+        }
+    }
+
+    fn spawn_noise(&mut self, rule: &Rule) {
+        self.remember(rule);
+        self.grid.spawn_noise(&self.rule);
+        // the noise replaces the newest layer instead of adding one.
+        self.layers.pop_back();
+        self.push_layer();
+    }
+
+    fn cell_count(&self) -> usize {
+        self.layers.iter().map(|(cells, _)| cells.iter().filter(|value| self.shown(**value)).count()).sum()
+    }
+
+    fn bounds(&self) -> i32 {
+        self.grid.size
+    }
+
+    fn set_bounds(&mut self, new_bounds: i32) -> i32 {
+        if new_bounds != self.grid.size {
+            self.grid = Grid2d::new(new_bounds);
+            self.layers.clear();
+        }
+        new_bounds
+    }
+
+    fn reset(&mut self) {
+        self.grid.clear();
+        self.layers.clear();
+    }
+
+    fn max_bounds(&self) -> i32 {
+        256
+    }
+
+    // the 3d rule doesn't apply.
+    fn follows_rule(&self) -> bool {
+        false
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        self.rule.ui(ui);
+        ui.checkbox(&mut self.alive_only, "only alive cells");
+        ui.weak(format!("{} of {} generations", self.layers.len(), self.grid.size));
+    }
+}
@@ -34,6 +34,46 @@
               collected and done single threaded).
               since the performance was bottlenecked by the serial update, i
               decided to use atomics at the boundaries.
+            - the default `NeighbourPass::Split` goes further and avoids the
+              atomics as well: updates to cells inside a chunk's border layer
+              are written directly, updates to border cells are collected in
+              27 lists (one per surrounding chunk). a second pass then gives
+              each chunk to one task, which applies the lists aimed at it.
+              "compare neighbor passes" in the ui times both.
+            - the old multi-threaded implementation (`LeddooMultiThreaded`,
+              with the serial border step) no longer exists, this backend
+              replaced it, and the split pass has no serial step either.
+    halo exchange (designed, not implemented):
+        - layout: chunk major storage, every chunk owns its s^3 cells plus a
+          one cell halo, a copy of the values of the 26 surrounding chunks
+          that touch it. (s+2)^3 values per chunk, 34^3 = 39304 for s = 32,
+          20% more than the 32768 it owns. the counts stay per owned cell.
+        - a tick, with a barrier (all tasks joined) between the passes:
+            1. values: each task updates its chunk's owned cells from its own
+               counts, writes only those, and records its changed cells.
+            2. exchange: each task fills its own halo from the owned border
+               layers of its neighbors. those are stable after the barrier,
+               and every halo is written by one task, so no atomics.
+            3. counts: each task applies the births and deaths of its owned
+               cells to its owned counts, and those of the changed halo cells
+               (old halo != new halo) as well. nothing crosses a chunk.
+          the same two barriers per tick as the split pass.
+        - cost, for s = 32 at bounds 256 (512 chunks): the exchange copies
+          512 * 6536 = 3.3M halo values every tick, about 20% of a sweep over
+          the grid, whether anything changed or not. pass 3 also has to
+          compare every halo value to find the changes. the split pass only
+          pushes entries for changed cells in a chunk's outer two layers,
+          9 to 26 per cell (16 bytes each), and nothing on quiet borders.
+          per chunk, the halo copy (6536 values) breaks even with the lists
+          at 250 to 700 changed border cells per tick, 4% to 12% of the 5768
+          cells in its outer layer. only dense chaotic rules get there.
+        - everything else pays for the layout: `render`, `get_cell`,
+          `set_cell` and the rebuild index the flat row major grid the
+          renderer uses, with chunk major storage each of them converts
+          every index, and the memory grows by 20% (10 MB at bounds 256).
+        - so the split pass stays. if a profile of a dense rule ever shows
+          the border lists, a third `NeighbourPass` with the halo is the way
+          to compare both on the same rule.
     performance:
         - on my machines (intel 4c/6c), the performance scales roughly with the
           number of physical cores. that seems reasonable, as there isn't much
@@ -48,104 +88,237 @@
 */
 
 use bevy::{
+    log::info_span,
     math::{ivec3, IVec3},
-    tasks::{TaskPool},
+    tasks::{logical_core_count, TaskPool, TaskPoolBuilder},
 };
 
+use bevy_egui::egui;
 use futures_lite::future;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use crate::{
     cell_renderer::{CellRenderer},
-    rule::Rule,
+    example_library,
+    neighbours::NeighbourCount,
+    rule::{CellValue, Rule},
     utils::{self},
 };
 
-use std::sync::{atomic::{AtomicU8, Ordering}, Arc};
+use super::NeighbourRebuild;
+
+use std::sync::{atomic::{AtomicU16, Ordering}, Arc};
 use std::cell::UnsafeCell;
+use std::fmt;
+use std::time::{Duration, Instant};
+
 
+// the chunk size is a setting, smaller chunks mean more tasks and more
+// border cells, larger ones fewer tasks than threads on small grids. which
+// is faster depends on the machine and the rule, see `tune_chunk_size`.
+pub const CHUNK_SIZES: [usize; 4] = [8, 16, 32, 64];
+const DEFAULT_CHUNK_SIZE: usize = 32;
 
-const CHUNK_SIZE:       usize = 32;
-const CHUNK_CELL_COUNT: usize = CHUNK_SIZE*CHUNK_SIZE*CHUNK_SIZE;
+fn bounds_to_chunk_radius(bounds: i32, chunk_size: usize) -> usize {
+    (bounds as usize + chunk_size - 1) / chunk_size
+}
 
-fn bounds_to_chunk_radius(bounds: i32) -> usize {
-    (bounds as usize + CHUNK_SIZE - 1) / CHUNK_SIZE
+fn chunk_offset_to_pos(offset: usize, chunk_size: usize) -> IVec3 {
+    #[cfg(feature = "bounds_audit")]
+    utils::audit::check_index(offset, chunk_size*chunk_size*chunk_size, "chunk_offset_to_pos");
+    utils::index_to_pos(offset, chunk_size as i32)
 }
 
-fn chunk_offset_to_pos(offset: usize) -> IVec3 {
-    utils::index_to_pos(offset, CHUNK_SIZE as i32)
+fn chunk_is_border_pos(pos: IVec3, offset: i32, chunk_size: usize) -> bool {
+    let last = chunk_size as i32 - 1;
+    pos.x - offset <= 0 || pos.x + offset >= last ||
+    pos.y - offset <= 0 || pos.y + offset >= last ||
+    pos.z - offset <= 0 || pos.z + offset >= last
 }
 
-fn chunk_is_border_pos(pos: IVec3, offset: i32) -> bool {
-    pos.x - offset <= 0 || pos.x + offset >= CHUNK_SIZE as i32 - 1 ||
-    pos.y - offset <= 0 || pos.y + offset >= CHUNK_SIZE as i32 - 1 ||
-    pos.z - offset <= 0 || pos.z + offset >= CHUNK_SIZE as i32 - 1
+// a chunk and its 26 surrounding chunks, `dir` in -1..=1.
+fn chunk_dir_to_index(dir: IVec3) -> usize {
+    ((dir.x + 1) + 3*(dir.y + 1) + 9*(dir.z + 1)) as usize
 }
 
+fn chunk_index_to_dir(index: usize) -> IVec3 {
+    utils::index_to_pos(index, 3) - 1
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NeighbourPass {
+    // border-adjacent cells update their neighbors with atomics.
+    Atomic,
+    // direct writes inside the chunks, border cells in a second pass.
+    Split,
+}
 
+// neighbor updates (cell index, increment) aimed at the border cells of the
+// chunk in direction `chunk_dir_to_index`.
+type BorderUpdates = Vec<Vec<(usize, bool)>>;
+
+
+
+// has to match `CellValue`.
+#[cfg(not(feature = "wide_states"))]
+type AtomicValue = std::sync::atomic::AtomicU8;
+#[cfg(feature = "wide_states")]
+type AtomicValue = AtomicU16;
 
 #[derive(Clone)]
-struct Values (Arc<Vec<UnsafeCell<AtomicU8>>>);
+struct Values (Arc<Vec<UnsafeCell<AtomicValue>>>);
 
 unsafe impl Sync for Values {}
 unsafe impl Send for Values {}
 
 impl Values {
     fn new(length: usize) -> Values {
-        Values(Arc::new((0..length).map(|_| UnsafeCell::new(AtomicU8::new(0))).collect()))
+        Values(Arc::new((0..length).map(|_| UnsafeCell::new(AtomicValue::new(0))).collect()))
+    }
+
+    fn read(&self, index: usize) -> CellValue {
+        unsafe { *(*self.0[index].get()).get_mut() }
     }
 
-    fn read(&self, index: usize) -> u8 {
+    fn write(&self, index: usize) -> &mut CellValue {
+        unsafe { (*self.0[index].get()).get_mut() }
+    }
+}
+
+// the neighbor counts, updated atomically at chunk borders. the atomic has
+// to match `NeighbourCount`.
+#[derive(Clone)]
+struct Counts (Arc<Vec<UnsafeCell<AtomicU16>>>);
+
+unsafe impl Sync for Counts {}
+unsafe impl Send for Counts {}
+
+impl Counts {
+    fn new(length: usize) -> Counts {
+        Counts(Arc::new((0..length).map(|_| UnsafeCell::new(AtomicU16::new(0))).collect()))
+    }
+
+    fn read(&self, index: usize) -> NeighbourCount {
         unsafe { *(*self.0[index].get()).get_mut() }
     }
 
-    fn write(&self, index: usize) -> &mut u8 {
+    fn write(&self, index: usize) -> &mut NeighbourCount {
         unsafe { (*self.0[index].get()).get_mut() }
     }
 
-    fn atomic(&self,index: usize) -> &mut AtomicU8 {
+    fn atomic(&self,index: usize) -> &mut AtomicU16 {
         unsafe { &mut *self.0[index].get() }
     }
 }
 
 
-fn cell_is_dead(value: u8) -> bool {
+fn cell_is_dead(value: CellValue) -> bool {
     value == 0
 }
 
 
 pub struct LeddooAtomic {
     values:    Values,
-    neighbors: Values,
+    neighbors: Counts,
+    chunk_size:   usize,
     chunk_radius: usize,
     chunk_count:  usize,
+    // per chunk indices of the cells that changed during the last update.
+    changed: Vec<Vec<usize>>,
+    // live cells, each chunk task reports its births and the cells that
+    // decayed to zero.
+    live: usize,
+    rebuild: NeighbourRebuild,
+    pass: NeighbourPass,
+    // skips chunks that didn't change for `freeze_after` ticks, see
+    // `update_quiet`.
+    pub freeze: bool,
+    pub freeze_after: u32,
+    // per chunk, ticks in a row without a change in or next to it.
+    quiet: Vec<u32>,
+    // an edit, a new rule or new bounds, every chunk runs again.
+    wake_all: bool,
+    last_rule: Option<Rule>,
+    benchmark: Option<String>,
+    determinism: Option<String>,
+    // auto-tune runs on the next update, which has the rule and the pool.
+    tune_requested: bool,
+    tuning: Option<String>,
 }
 
 impl LeddooAtomic {
     pub fn new() -> Self {
         LeddooAtomic {
             values:    Values::new(0),
-            neighbors: Values::new(0),
+            neighbors: Counts::new(0),
+            chunk_size: DEFAULT_CHUNK_SIZE,
             chunk_radius: 0,
             chunk_count: 0,
+            changed: vec![],
+            live: 0,
+            rebuild: NeighbourRebuild::new(),
+            pass: NeighbourPass::Split,
+            freeze: false,
+            freeze_after: 2,
+            quiet: vec![],
+            wake_all: true,
+            last_rule: None,
+            benchmark: None,
+            determinism: None,
+            tune_requested: false,
+            tuning: None,
         }
     }
 
     pub fn set_bounds(&mut self, new_bounds: i32) -> i32 {
-        let radius = bounds_to_chunk_radius(new_bounds);
-        let bounds = radius * CHUNK_SIZE;
+        let radius = bounds_to_chunk_radius(new_bounds, self.chunk_size);
+        let bounds = radius * self.chunk_size;
         self.values    = Values::new(bounds*bounds*bounds);
-        self.neighbors = Values::new(bounds*bounds*bounds);
+        self.neighbors = Counts::new(bounds*bounds*bounds);
         self.chunk_radius = radius;
         self.chunk_count  = radius*radius*radius;
+        self.changed.clear();
+        self.live = 0;
+        self.rebuild.cancel();
+        self.wake_all = true;
         bounds as i32
     }
 
     pub fn bounds(&self) -> i32 {
-        (self.chunk_radius * CHUNK_SIZE) as i32
+        (self.chunk_radius * self.chunk_size) as i32
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    fn chunk_cell_count(&self) -> usize {
+        self.chunk_size * self.chunk_size * self.chunk_size
+    }
+
+    // only sizes that divide the bounds can change without touching the
+    // cells, the flat layout stays the same and only the grouping changes.
+    pub fn fits_chunk_size(&self, chunk_size: usize) -> bool {
+        CHUNK_SIZES.contains(&chunk_size) && self.bounds() as usize % chunk_size == 0
+    }
+
+    // returns false and changes nothing if the size doesn't fit the bounds,
+    // see `fits_chunk_size`. before the first `set_bounds` every size fits.
+    pub fn set_chunk_size(&mut self, chunk_size: usize) -> bool {
+        if !self.fits_chunk_size(chunk_size) {
+            return false;
+        }
+        let radius = self.bounds() as usize / chunk_size;
+        self.chunk_radius = radius;
+        self.chunk_count  = radius*radius*radius;
+        self.chunk_size = chunk_size;
+        self.wake_all = true;
+        true
     }
 
     pub fn total_cell_count(&self) -> usize {
-        self.chunk_count * CHUNK_CELL_COUNT
+        self.chunk_count * self.chunk_cell_count()
     }
 
     pub fn center(&self) -> IVec3 {
@@ -154,24 +327,18 @@ impl LeddooAtomic {
     }
 
     pub fn cell_count(&self) -> usize {
-        let mut result = 0;
-        for index in 0..self.total_cell_count() {
-            if !cell_is_dead(self.values.read(index)) {
-                result += 1;
-            }
-        }
-        result
+        self.live
     }
 
 
     fn update_neighbors(
-        neighbors: &Values,
-        index: usize, bounds: i32,
+        neighbors: &Counts,
+        index: usize, bounds: i32, chunk_size: usize,
         rule: &Rule, inc: bool
     ) {
         let pos   = utils::index_to_pos(index, bounds);
-        let local = pos % CHUNK_SIZE as i32;
-        if chunk_is_border_pos(local, 1) {
+        let local = pos % chunk_size as i32;
+        if chunk_is_border_pos(local, 1, chunk_size) {
             for dir in rule.neighbour_method.get_neighbour_iter() {
                 let neighbor_pos = utils::wrap(pos + *dir, bounds);
                 let index = utils::pos_to_index(neighbor_pos, bounds);
@@ -201,15 +368,83 @@ impl LeddooAtomic {
         }
     }
 
+    // `update_neighbors` for writes outside of `update`, which may happen
+    // during a rebuild. counts that aren't recounted yet are left alone.
+    fn write_neighbors(&self, rule: &Rule, index: usize, inc: bool) {
+        if !self.rebuild.in_progress() {
+            Self::update_neighbors(&self.neighbors, index, self.bounds(), self.chunk_size, rule, inc);
+            return;
+        }
+        let bounds = self.bounds();
+        let recounted = self.rebuild.recounted();
+        let pos = utils::index_to_pos(index, bounds);
+        for dir in rule.neighbour_method.get_neighbour_iter() {
+            let index = utils::pos_to_index(utils::wrap(pos + *dir, bounds), bounds);
+            if index < recounted {
+                let neighbors = self.neighbors.write(index);
+                if inc {
+                    *neighbors += 1;
+                }
+                else {
+                    *neighbors -= 1;
+                }
+            }
+        }
+    }
+
+    // like `update_neighbors`, but writes to the border layer of any chunk
+    // are deferred to `border`.
+    fn update_neighbors_split(
+        neighbors: &Counts,
+        index: usize, bounds: i32, chunk_size: usize,
+        rule: &Rule, inc: bool,
+        border: &mut BorderUpdates,
+    ) {
+        let pos   = utils::index_to_pos(index, bounds);
+        let local = pos % chunk_size as i32;
+        let near_border = chunk_is_border_pos(local, 1, chunk_size);
+        for dir in rule.neighbour_method.get_neighbour_iter() {
+            let neighbor_pos = match near_border {
+                true  => utils::wrap(pos + *dir, bounds),
+                false => pos + *dir,
+            };
+            let index = utils::pos_to_index(neighbor_pos, bounds);
+
+            if near_border {
+                let size      = chunk_size as i32;
+                let target    = local + *dir;
+                let chunk_dir = ivec3(
+                    target.x.div_euclid(size),
+                    target.y.div_euclid(size),
+                    target.z.div_euclid(size));
+                let target = target - chunk_dir*size;
+                if chunk_dir != IVec3::ZERO || chunk_is_border_pos(target, 0, chunk_size) {
+                    border[chunk_dir_to_index(chunk_dir)].push((index, inc));
+                    continue;
+                }
+            }
+
+            let neighbors = neighbors.write(index);
+            if inc {
+                *neighbors += 1;
+            }
+            else {
+                *neighbors -= 1;
+            }
+        }
+    }
+
+    // returns the number of cells that decayed to zero.
     fn update_values(
-        values: &Values, neighbors: &Values,
-        chunk_index: usize, chunk_radius: usize, bounds: i32,
+        values: &Values, neighbors: &Counts,
+        chunk_index: usize, chunk_radius: usize, chunk_size: usize, bounds: i32,
         rule: &Rule,
-        spawns: &mut Vec<usize>, deaths: &mut Vec<usize>,
-    ) {
-        let chunk_pos = CHUNK_SIZE as i32 * utils::index_to_pos(chunk_index, chunk_radius as i32);
-        for offset in 0..CHUNK_CELL_COUNT {
-            let pos   = chunk_pos + chunk_offset_to_pos(offset);
+        spawns: &mut Vec<usize>, deaths: &mut Vec<usize>, changes: &mut Vec<usize>,
+    ) -> usize {
+        let mut vanished = 0;
+        let chunk_pos = chunk_size as i32 * utils::index_to_pos(chunk_index, chunk_radius as i32);
+        for offset in 0..chunk_size*chunk_size*chunk_size {
+            let pos   = chunk_pos + chunk_offset_to_pos(offset, chunk_size);
             let index = utils::pos_to_index(pos, bounds);
 
             let value     = values.write(index);
@@ -219,6 +454,7 @@ impl LeddooAtomic {
                 if rule.birth_rule.in_range(neighbors) {
                     *value = rule.states;
                     spawns.push(index);
+                    changes.push(index);
                 }
             }
             else {
@@ -227,64 +463,162 @@ impl LeddooAtomic {
                         deaths.push(index);
                     }
 
-                    *value -= 1;
+                    *value = value.saturating_sub(1);
+                    changes.push(index);
+                    if cell_is_dead(*value) {
+                        vanished += 1;
+                    }
                 }
             }
         }
+        vanished
+    }
+
+    fn count_neighbors(values: &Values, index: usize, bounds: i32, rule: &Rule) -> NeighbourCount {
+        let pos = utils::index_to_pos(index, bounds);
+        let mut neighbors = 0;
+        for dir in rule.neighbour_method.get_neighbour_iter() {
+            let neighbor_pos = utils::wrap(pos + *dir, bounds);
+            if values.read(utils::pos_to_index(neighbor_pos, bounds)) == rule.states {
+                neighbors += 1;
+            }
+        }
+        neighbors
+    }
+
+    // recounts a part of the grid if the neighbor method changed, one task
+    // per chunk sized piece. every count is written by exactly one task and
+    // only the values are read, so no atomics are needed. returns false
+    // until the counts are up to date.
+    fn rebuild_neighbors(&mut self, rule: &Rule, tasks: &TaskPool) -> bool {
+        self.rebuild.check(rule, self.total_cell_count());
+        if !self.rebuild.in_progress() {
+            return true;
+        }
+
+        let _span = info_span!("rebuild_neighbors").entered();
+        let bounds = self.bounds();
+        let chunk_cell_count = self.chunk_cell_count();
+        let (values, neighbors) = (&self.values, &self.neighbors);
+        self.rebuild.step(chunk_cell_count * tasks.thread_num(), |range| {
+            let mut rebuild_tasks = vec![];
+            for start in range.clone().step_by(chunk_cell_count) {
+                let end = (start + chunk_cell_count).min(range.end);
+                let values    = values.clone();
+                let neighbors = neighbors.clone();
+                let rule = rule.clone(); // shrug
+                rebuild_tasks.push(tasks.spawn(async move {
+                    for index in start..end {
+                        *neighbors.write(index) = Self::count_neighbors(&values, index, bounds, &rule);
+                    }
+                }));
+            }
+            for task in rebuild_tasks {
+                future::block_on(task);
+            }
+        })
     }
 
     pub fn update(&mut self, rule: &Rule, tasks: &TaskPool) {
+        if self.tune_requested {
+            self.tune_requested = false;
+            self.auto_tune(rule, tasks);
+        }
+        if !self.rebuild_neighbors(rule, tasks) {
+            self.changed.clear();
+            self.wake_all = true;
+            return;
+        }
+        if self.last_rule.as_ref() != Some(rule) {
+            self.last_rule = Some(rule.clone());
+            self.wake_all = true;
+        }
+        if self.wake_all || self.quiet.len() != self.chunk_count {
+            self.wake_all = false;
+            self.quiet.clear();
+            self.quiet.resize(self.chunk_count, 0);
+        }
+
         // update values.
+        let span = info_span!("update_values").entered();
         let mut value_tasks = vec![];
         for chunk_index in 0..self.chunk_count {
+            if self.freeze && self.quiet[chunk_index] >= self.freeze_after.max(1) {
+                value_tasks.push(None);
+                continue;
+            }
             let values    = self.values.clone();
             let neighbors = self.neighbors.clone();
             let chunk_radius = self.chunk_radius;
+            let chunk_size   = self.chunk_size;
             let bounds = self.bounds();
 
             let rule = rule.clone(); // shrug
-            let mut chunk_spawns = vec![];
-            let mut chunk_deaths = vec![];
+            let mut chunk_spawns  = vec![];
+            let mut chunk_deaths  = vec![];
+            let mut chunk_changes = vec![];
 
-            value_tasks.push(tasks.spawn(async move {
-                Self::update_values(
+            value_tasks.push(Some(tasks.spawn(async move {
+                let vanished = Self::update_values(
                     &values, &neighbors,
-                    chunk_index, chunk_radius, bounds,
+                    chunk_index, chunk_radius, chunk_size, bounds,
                     &rule,
-                    &mut chunk_spawns, &mut chunk_deaths);
-                (chunk_spawns, chunk_deaths)
-            }));
+                    &mut chunk_spawns, &mut chunk_deaths, &mut chunk_changes);
+                (chunk_spawns, chunk_deaths, chunk_changes, vanished)
+            })));
         }
 
         // collect spawns & deaths.
         let mut chunk_spawns = vec![];
         let mut chunk_deaths = vec![];
+        self.changed.clear();
         for task in value_tasks {
-            let (spawns, deaths) = future::block_on(task);
+            // frozen chunks report nothing.
+            let (spawns, deaths, changes, vanished) = task.map(future::block_on).unwrap_or_default();
+            self.live = self.live + spawns.len() - vanished;
             chunk_spawns.push(spawns);
             chunk_deaths.push(deaths);
+            self.changed.push(changes);
         }
+        self.update_quiet(&chunk_spawns, &chunk_deaths);
+        span.exit();
 
 
         // update neighbors.
+        let span = info_span!("update_neighbors").entered();
+        match self.pass {
+            NeighbourPass::Split => self.update_neighbors_split_passes(rule, tasks, chunk_spawns, chunk_deaths),
+            NeighbourPass::Atomic => self.update_neighbors_atomic(rule, tasks, chunk_spawns, chunk_deaths),
+        }
+        span.exit();
+
+        #[cfg(feature = "debug-checks")]
+        self.validate(rule);
+    }
+
+    fn update_neighbors_atomic(&mut self,
+        rule: &Rule, tasks: &TaskPool,
+        chunk_spawns: Vec<Vec<usize>>, chunk_deaths: Vec<Vec<usize>>,
+    ) {
         let mut neighbor_tasks = vec![];
         for (spawns, deaths) in chunk_spawns.into_iter().zip(chunk_deaths) {
             let neighbors = self.neighbors.clone();
             let bounds = self.bounds();
+            let chunk_size = self.chunk_size;
             let rule = rule.clone(); // shrug
 
             neighbor_tasks.push(tasks.spawn(async move {
                 for index in spawns.iter() {
                     Self::update_neighbors(
                         &neighbors,
-                        *index, bounds,
+                        *index, bounds, chunk_size,
                         &rule, true);
                 }
 
                 for index in deaths.iter() {
                     Self::update_neighbors(
                         &neighbors,
-                        *index, bounds,
+                        *index, bounds, chunk_size,
                         &rule, false);
                 }
             }));
@@ -296,28 +630,163 @@ impl LeddooAtomic {
     }
 
 
-    // TEMP: move to sims.
-    #[allow(dead_code)]
-    fn validate(&self, rule: &Rule) {
-        for index in 0..self.total_cell_count() {
-            let pos = utils::index_to_pos(index, self.bounds());
+    // a chunk whose cells didn't change and whose neighbor counts didn't
+    // change ends up the same again, so skipping it is exact. the counts
+    // change with births and deaths in the chunk, or in the border layer of
+    // the chunks around it. `freeze_after` above 1 only waits longer.
+    fn update_quiet(&mut self, chunk_spawns: &[Vec<usize>], chunk_deaths: &[Vec<usize>]) {
+        if !self.freeze {
+            return;
+        }
+        let bounds = self.bounds();
+        let size   = self.chunk_size as i32;
+        let radius = self.chunk_radius as i32;
+        let mut woken = vec![false; self.chunk_count];
+        for (chunk_index, changes) in self.changed.iter().enumerate() {
+            woken[chunk_index] |= !changes.is_empty();
+        }
+        for index in chunk_spawns.iter().chain(chunk_deaths).flatten() {
+            let pos   = utils::index_to_pos(*index, bounds);
+            let chunk = pos / size;
+            let local = pos % size;
+            // the directions the cell borders on, per axis.
+            let sides = |x: i32| match x {
+                0 => -1..=0,
+                x if x == size - 1 => 0..=1,
+                _ => 0..=0,
+            };
+            for dz in sides(local.z) {
+                for dy in sides(local.y) {
+                    for dx in sides(local.x) {
+                        let neighbor = utils::wrap(chunk + ivec3(dx, dy, dz), radius);
+                        woken[utils::pos_to_index(neighbor, radius)] = true;
+                    }
+                }
+            }
+        }
+        for (quiet, woken) in self.quiet.iter_mut().zip(woken) {
+            *quiet = match woken {
+                true  => 0,
+                false => quiet.saturating_add(1),
+            };
+        }
+    }
 
-            let mut neighbors = 0;
-            for dir in rule.neighbour_method.get_neighbour_iter() {
-                let neighbor_pos = utils::wrap(pos + *dir, self.bounds());
-                let index = utils::pos_to_index(neighbor_pos, self.bounds());
+    pub fn frozen_chunks(&self) -> usize {
+        match self.freeze {
+            true  => self.quiet.iter().filter(|quiet| **quiet >= self.freeze_after.max(1)).count(),
+            false => 0,
+        }
+    }
 
-                let value = self.values.read(index);
-                if value == rule.states {
-                    neighbors += 1;
+    // times the chunk sizes that fit the bounds on a copy of the rule, and
+    // keeps the fastest.
+    fn auto_tune(&mut self, rule: &Rule, tasks: &TaskPool) {
+        let results = tune_chunk_size(rule, self.bounds(), 20, tasks);
+        let best = results.iter().min_by_key(|(_, dt)| *dt).map(|(size, _)| *size);
+        let mut lines: Vec<_> = results.iter()
+            .map(|(size, dt)| format!("{}: {:.2?} per tick", size, dt))
+            .collect();
+        if let Some(best) = best {
+            self.set_chunk_size(best);
+            lines.push(format!("picked {}", best));
+        }
+        self.tuning = Some(lines.join("\n"));
+    }
+
+
+    fn update_neighbors_split_passes(&mut self,
+        rule: &Rule, tasks: &TaskPool,
+        chunk_spawns: Vec<Vec<usize>>, chunk_deaths: Vec<Vec<usize>>,
+    ) {
+        let bounds = self.bounds();
+        let chunk_size = self.chunk_size;
+
+        // interior: every chunk writes the cells inside its border layer.
+        let mut interior_tasks = vec![];
+        for (spawns, deaths) in chunk_spawns.into_iter().zip(chunk_deaths) {
+            let neighbors = self.neighbors.clone();
+            let rule = rule.clone(); // shrug
+
+            interior_tasks.push(tasks.spawn(async move {
+                let mut border = vec![vec![]; 27];
+                for index in spawns.iter() {
+                    Self::update_neighbors_split(&neighbors, *index, bounds, chunk_size, &rule, true, &mut border);
                 }
-            }
+                for index in deaths.iter() {
+                    Self::update_neighbors_split(&neighbors, *index, bounds, chunk_size, &rule, false, &mut border);
+                }
+                border
+            }));
+        }
+        let border: Arc<Vec<BorderUpdates>> = Arc::new(interior_tasks.into_iter()
+            .map(future::block_on)
+            .collect());
+
+        // border: every chunk applies what its neighbors (and itself) sent
+        // to it. a chunk's border cells are only written by its own task.
+        let chunk_radius = self.chunk_radius as i32;
+        let mut border_tasks = vec![];
+        for chunk_index in 0..self.chunk_count {
+            let neighbors = self.neighbors.clone();
+            let border = border.clone();
+
+            border_tasks.push(tasks.spawn(async move {
+                let chunk_pos = utils::index_to_pos(chunk_index, chunk_radius);
+                for dir_index in 0..27 {
+                    let source = utils::wrap(chunk_pos - chunk_index_to_dir(dir_index), chunk_radius);
+                    let source = utils::pos_to_index(source, chunk_radius);
+                    for (index, inc) in border[source][dir_index].iter() {
+                        let neighbors = neighbors.write(*index);
+                        if *inc {
+                            *neighbors += 1;
+                        }
+                        else {
+                            *neighbors -= 1;
+                        }
+                    }
+                }
+            }));
+        }
+        for task in border_tasks {
+            future::block_on(task);
+        }
+    }
 
+
+    // panics if a stored neighbor count is off. after every update with
+    // `debug-checks`.
+    #[cfg(any(test, feature = "debug-checks"))]
+    fn validate(&self, rule: &Rule) {
+        for index in 0..self.total_cell_count() {
+            let neighbors = Self::count_neighbors(&self.values, index, self.bounds(), rule);
             assert_eq!(neighbors, self.neighbors.read(index));
         }
     }
 
+    pub fn get_cell(&self, pos: IVec3) -> CellValue {
+        let bounds = self.bounds();
+        self.values.read(utils::pos_to_index(utils::wrap_any(pos, bounds), bounds))
+    }
+
+    pub fn set_cell(&mut self, rule: &Rule, pos: IVec3, value: CellValue) {
+        self.rebuild.check(rule, self.total_cell_count());
+        self.wake_all = true;
+        let bounds = self.bounds();
+        let index = utils::pos_to_index(utils::wrap_any(pos, bounds), bounds);
+        let old = std::mem::replace(self.values.write(index), value);
+        self.live = self.live + !cell_is_dead(value) as usize - !cell_is_dead(old) as usize;
+        if old == rule.states && value != rule.states {
+            self.write_neighbors(rule, index, false);
+        }
+        if old != rule.states && value == rule.states {
+            self.write_neighbors(rule, index, true);
+        }
+    }
+
     pub fn spawn_noise(&mut self, rule: &Rule) {
+        self.rebuild.check(rule, self.total_cell_count());
+        self.wake_all = true;
         let center = self.center();
         let bounds = self.bounds();
 
@@ -326,10 +795,8 @@ impl LeddooAtomic {
             let value = self.values.write(index);
             if cell_is_dead(*value) {
                 *value = rule.states;
-                Self::update_neighbors(
-                    &self.neighbors,
-                    index, self.bounds(),
-                    rule, true);
+                self.live += 1;
+                self.write_neighbors(rule, index, true);
             }
         });
     }
@@ -349,10 +816,26 @@ impl crate::cells::Sim for LeddooAtomic {
         }
     }
 
+    fn render_delta(&self, renderer: &mut CellRenderer) {
+        for index in self.changed.iter().flatten() {
+            renderer.set(*index,
+                self.values.read(*index),
+                self.neighbors.read(*index));
+        }
+    }
+
     fn spawn_noise(&mut self, rule: &Rule) {
         self.spawn_noise(rule);
     }
 
+    fn get_cell(&self, pos: IVec3) -> CellValue {
+        self.get_cell(pos)
+    }
+
+    fn set_cell(&mut self, rule: &Rule, pos: IVec3, value: CellValue) {
+        self.set_cell(rule, pos, value);
+    }
+
     fn cell_count(&self) -> usize {
         self.cell_count()
     }
@@ -364,5 +847,220 @@ impl crate::cells::Sim for LeddooAtomic {
     fn set_bounds(&mut self, new_bounds: i32) -> i32 {
         self.set_bounds(new_bounds)
     }
+
+    fn capabilities(&self) -> crate::cells::SimCapabilities {
+        crate::cells::SimCapabilities::SET_CELL
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label("neighbor pass")
+            .selected_text(format!("{:?}", self.pass))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.pass, NeighbourPass::Split, "Split");
+                ui.selectable_value(&mut self.pass, NeighbourPass::Atomic, "Atomic");
+            });
+        let mut chunk_size = self.chunk_size;
+        egui::ComboBox::from_label("chunk size")
+            .selected_text(chunk_size.to_string())
+            .show_ui(ui, |ui| {
+                for size in CHUNK_SIZES {
+                    let label = egui::SelectableLabel::new(chunk_size == size, size.to_string());
+                    if ui.add_enabled(self.fits_chunk_size(size), label)
+                        .on_disabled_hover_text("the bounds aren't a multiple of it")
+                        .clicked()
+                    {
+                        chunk_size = size;
+                    }
+                }
+            });
+        if chunk_size != self.chunk_size {
+            self.set_chunk_size(chunk_size);
+        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.freeze, "freeze still chunks");
+            ui.add_enabled(self.freeze, egui::Slider::new(&mut self.freeze_after, 1..=16).text("after ticks"));
+        });
+        if self.freeze {
+            ui.weak(format!("{} of {} chunks frozen", self.frozen_chunks(), self.chunk_count));
+        }
+        if ui.button("auto-tune chunk size").on_hover_text("times each size for a few ticks of the current rule, blocks meanwhile").clicked() {
+            self.tune_requested = true;
+        }
+        if let Some(tuning) = &self.tuning {
+            ui.label(tuning);
+        }
+        if ui.button("compare neighbor passes").clicked() {
+            let rule = example_library::fallback_example().rule;
+            self.benchmark = Some(compare_neighbour_passes(&rule, 128, 30).iter()
+                .map(|(pass, dt)| format!("{:?}: {:.2?} per tick", pass, dt))
+                .collect::<Vec<_>>()
+                .join("\n"));
+        }
+        if let Some(benchmark) = &self.benchmark {
+            ui.label(benchmark);
+        }
+        if ui.button("check determinism").clicked() {
+            let rule = example_library::fallback_example().rule;
+            let threads = [1, logical_core_count().max(2)];
+            self.determinism = Some(match check_determinism(&rule, self.pass, 64, 100, threads) {
+                None => format!("{:?}: identical for 100 ticks on {} and {} threads",
+                    self.pass, threads[0], threads[1]),
+                Some(divergence) => divergence.to_string(),
+            });
+        }
+        if let Some(determinism) = &self.determinism {
+            ui.label(determinism);
+        }
+        if self.rebuild.in_progress() {
+            ui.label(format!("recounting neighbors: {:.0}%", 100.0 * self.rebuild.progress()));
+        }
+    }
+}
+
+
+// same setup as `compare_layouts` in single_threaded.rs, at a size where
+// the neighbor pass dominates.
+pub fn compare_neighbour_passes(rule: &Rule, bounds: i32, ticks: u32) -> Vec<(NeighbourPass, Duration)> {
+    let tasks = TaskPool::new();
+    [NeighbourPass::Atomic, NeighbourPass::Split].iter().map(|pass| {
+        let mut sim = LeddooAtomic::new();
+        sim.pass = *pass;
+        sim.set_bounds(bounds);
+        sim.spawn_noise(rule);
+
+        let t0 = Instant::now();
+        for _ in 0..ticks {
+            sim.update(rule, &tasks);
+        }
+        (*pass, t0.elapsed() / ticks)
+    })
+    .collect()
+}
+
+
+
+// like `compare_neighbour_passes`, for the chunk sizes that divide `bounds`,
+// on the pool the sims run on.
+pub fn tune_chunk_size(rule: &Rule, bounds: i32, ticks: u32, tasks: &TaskPool) -> Vec<(usize, Duration)> {
+    CHUNK_SIZES.iter()
+        .filter(|size| bounds as usize % **size == 0)
+        .map(|size| {
+            let mut sim = LeddooAtomic::new();
+            sim.set_chunk_size(*size);
+            sim.set_bounds(bounds);
+            sim.spawn_noise(rule);
+
+            let t0 = Instant::now();
+            for _ in 0..ticks {
+                sim.update(rule, tasks);
+            }
+            (*size, t0.elapsed() / ticks)
+        })
+        .collect()
+}
+
+
+
+// the first cell where two runs disagree, see `check_determinism`.
+pub struct Divergence {
+    pub tick: u32,
+    pub pos: IVec3,
+    // value and neighbor count in the first and second run.
+    pub a: (CellValue, NeighbourCount),
+    pub b: (CellValue, NeighbourCount),
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "diverged at tick {}, cell {}: value {} vs {}, neighbors {} vs {}",
+            self.tick, self.pos, self.a.0, self.b.0, self.a.1, self.b.1)
+    }
 }
 
+// runs the same seeded simulation on two task pools with different thread
+// counts and compares the grids after every tick. tick 0 is the seed.
+// the neighbor counts are sums of +-1, so they can only differ if an update
+// was lost or applied twice.
+pub fn check_determinism(rule: &Rule, pass: NeighbourPass, bounds: i32, ticks: u32, threads: [usize; 2]) -> Option<Divergence> {
+    let mut runs: Vec<(LeddooAtomic, TaskPool)> = threads.iter().map(|threads| {
+        let mut sim = LeddooAtomic::new();
+        sim.pass = pass;
+        sim.set_bounds(bounds);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let center = sim.center();
+        for _ in 0..12*12*12 {
+            let pos = center + ivec3(rng.gen_range(-6..=6), rng.gen_range(-6..=6), rng.gen_range(-6..=6));
+            sim.set_cell(rule, pos, rule.states);
+        }
+
+        let tasks = TaskPoolBuilder::new()
+            .num_threads(*threads)
+            .thread_name("determinism".into())
+            .build();
+        (sim, tasks)
+    })
+    .collect();
+
+    for tick in 0..=ticks {
+        if tick > 0 {
+            for (sim, tasks) in runs.iter_mut() {
+                sim.update(rule, tasks);
+            }
+        }
+
+        let (a, b) = (&runs[0].0, &runs[1].0);
+        for index in 0..a.total_cell_count() {
+            let cell_a = (a.values.read(index), a.neighbors.read(index));
+            let cell_b = (b.values.read(index), b.neighbors.read(index));
+            if cell_a != cell_b {
+                return Some(Divergence {
+                    tick,
+                    pos: utils::index_to_pos(index, a.bounds()),
+                    a: cell_a,
+                    b: cell_b,
+                });
+            }
+        }
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neighbours::NeighbourMethod;
+
+    #[test]
+    fn chunk_sizes_that_dont_fit_change_nothing() {
+        let mut sim = LeddooAtomic::new();
+        assert!(sim.set_chunk_size(16));
+        assert_eq!(sim.set_bounds(48), 48);
+        assert!(!sim.set_chunk_size(32));
+        assert_eq!((sim.chunk_size(), sim.bounds()), (16, 48));
+        assert_eq!(sim.total_cell_count(), 48*48*48);
+        assert!(sim.set_chunk_size(8));
+        assert_eq!((sim.chunk_size(), sim.bounds()), (8, 48));
+    }
+
+    #[test]
+    fn set_cell_during_a_rebuild() {
+        let tasks = TaskPool::new();
+        let mut rule = example_library::fallback_example().rule;
+        rule.neighbour_method = NeighbourMethod::VonNeuman;
+        let mut sim = LeddooAtomic::new();
+        sim.set_bounds(32);
+        sim.set_cell(&rule, IVec3::splat(4), rule.states);
+
+        rule.neighbour_method = NeighbourMethod::Moore;
+        sim.set_cell(&rule, IVec3::splat(4), 0);
+        sim.spawn_noise(&rule);
+        assert!(sim.rebuild.in_progress());
+
+        while sim.rebuild.in_progress() {
+            sim.update(&rule, &tasks);
+        }
+        sim.validate(&rule);
+    }
+}
@@ -0,0 +1,430 @@
+/*
+    how it works:
+        - for rules with 1 or 2 states, a cell is either dead, alive, or
+          dying (only with 2 states). that fits in two bit planes, `alive`
+          and `dying`, instead of a byte per value and a byte per neighbor
+          count.
+        - a row along x is stored as a single u128 (bounds are at most 128),
+          wrapping in x is a rotation of the row.
+        - neighbor counts are never stored. each update adds the shifted
+          alive planes of every neighbor direction into 5 bit sliced counters
+          (a ripple carry adder per word), and the rule is evaluated on those
+          counters, 128 cells at a time.
+        - rules with more states fall back to `LeddooSingleThreaded`. the
+          cells are moved over when the rule changes.
+*/
+
+use bevy::{
+    log::info_span,
+    math::{ivec3, IVec3},
+    tasks::TaskPool,
+};
+
+use crate::{
+    cell_renderer::CellRenderer,
+    cells::{Sim, SimCapabilities},
+    neighbours::{NeighbourCount, NeighbourMethod},
+    rule::{CellValue, Rule, Value},
+    utils,
+};
+
+use super::LeddooSingleThreaded;
+
+
+// the moore neighborhood has 26 cells, which needs 5 bits. larger
+// neighborhoods use the fallback.
+const COUNT_BITS: usize = 5;
+
+type Counts = [u128; COUNT_BITS];
+
+
+fn row_mask(bounds: i32) -> u128 {
+    match bounds {
+        128 => !0,
+        _ => (1u128 << bounds) - 1,
+    }
+}
+
+// bit x of the result is bit (x + dx) mod bounds of the row.
+fn rotate_row(row: u128, dx: i32, bounds: i32) -> u128 {
+    let k = dx.rem_euclid(bounds) as u32;
+    if k == 0 {
+        return row;
+    }
+    ((row >> k) | (row << (bounds as u32 - k))) & row_mask(bounds)
+}
+
+fn add_bit(counts: &mut Counts, bits: u128) {
+    let mut carry = bits;
+    for count in counts.iter_mut() {
+        let next = *count & carry;
+        *count ^= carry;
+        carry = next;
+    }
+}
+
+// the cells whose count is one of the enabled entries of `value`.
+fn matching(counts: &Counts, value: &Value, neighbour_count: usize) -> u128 {
+    let mut result = 0;
+    for n in 0..=neighbour_count {
+        if !value.in_range(n as NeighbourCount) {
+            continue;
+        }
+        let mut equal = !0;
+        for (bit, count) in counts.iter().enumerate() {
+            equal &= if n & (1 << bit) != 0 { *count } else { !*count };
+        }
+        result |= equal;
+    }
+    result
+}
+
+fn count_at(counts: &Counts, x: i32) -> NeighbourCount {
+    let mut result = 0;
+    for (bit, count) in counts.iter().enumerate() {
+        result |= (((count >> x) & 1) as NeighbourCount) << bit;
+    }
+    result
+}
+
+fn bits(mut row: u128) -> impl Iterator<Item = i32> {
+    std::iter::from_fn(move || {
+        if row == 0 {
+            return None;
+        }
+        let x = row.trailing_zeros();
+        row &= row - 1;
+        Some(x as i32)
+    })
+}
+
+
+pub struct LeddooBitPacked {
+    bounds: i32,
+    // both indexed by `y + z*bounds`.
+    alive: Vec<u128>,
+    dying: Vec<u128>,
+    // the rows that changed during the last update, xor of old and new.
+    changed: Vec<(usize, u128)>,
+    // alive and dying cells, kept up to date by `update` and `write`.
+    live: usize,
+    // for the neighbor counts in `render`, which doesn't get the rule.
+    neighbour_method: NeighbourMethod,
+    states: CellValue,
+
+    fallback: Option<LeddooSingleThreaded>,
+}
+
+impl LeddooBitPacked {
+    pub fn new() -> Self {
+        LeddooBitPacked {
+            bounds: 0,
+            alive: vec![],
+            dying: vec![],
+            changed: vec![],
+            live: 0,
+            neighbour_method: NeighbourMethod::Moore,
+            states: 1,
+            fallback: None,
+        }
+    }
+
+    pub fn supports(rule: &Rule) -> bool {
+        rule.states <= 2 && rule.neighbour_method.max_neighbours() < 1 << COUNT_BITS
+    }
+
+    fn row_index(&self, y: i32, z: i32) -> usize {
+        (y + z*self.bounds) as usize
+    }
+
+    fn counts(&self, y: i32, z: i32) -> Counts {
+        let mut counts = [0; COUNT_BITS];
+        for dir in self.neighbour_method.get_neighbour_iter() {
+            let row = self.alive[self.row_index(
+                (y + dir.y).rem_euclid(self.bounds),
+                (z + dir.z).rem_euclid(self.bounds))];
+            add_bit(&mut counts, rotate_row(row, dir.x, self.bounds));
+        }
+        counts
+    }
+
+    fn use_rule(&mut self, rule: &Rule) {
+        self.neighbour_method = rule.neighbour_method;
+        self.states = rule.states;
+    }
+
+    // moves the cells between the packed planes and the fallback, if the
+    // rule changed which one applies. cells at the states of the last rule
+    // are alive on either side, all others are decaying. with a single
+    // state, nothing can be dying, so they are dropped.
+    fn switch_storage(&mut self, rule: &Rule) {
+        let old_states = self.states;
+        match (Self::supports(rule), self.fallback.take()) {
+            (true, Some(fallback)) => {
+                let bounds = self.bounds;
+                self.alive.iter_mut().for_each(|row| *row = 0);
+                self.dying.iter_mut().for_each(|row| *row = 0);
+                self.live = 0;
+                self.use_rule(rule);
+                for index in 0..(bounds*bounds*bounds) as usize {
+                    let pos = utils::index_to_pos(index, bounds);
+                    let value = match fallback.get_cell(pos) {
+                        0 => continue,
+                        value if value == old_states => rule.states,
+                        _ if rule.states == 2 => 1,
+                        _ => continue,
+                    };
+                    self.write(pos, value);
+                }
+            }
+            (false, None) => {
+                let mut fallback = LeddooSingleThreaded::new();
+                fallback.set_bounds(self.bounds);
+                let cells: Vec<_> = self.live_cells()
+                    .map(|(pos, value)| (pos, if value == old_states { rule.states } else { value }))
+                    .collect();
+                fallback.set_cells(rule, &cells);
+                self.fallback = Some(fallback);
+            }
+            (_, fallback) => self.fallback = fallback,
+        }
+        // the fallback doesn't call `use_rule`, but the states are needed
+        // to tell its alive cells when switching back.
+        self.states = rule.states;
+    }
+
+    fn live_cells(&self) -> impl Iterator<Item = (IVec3, CellValue)> + '_ {
+        let bounds = self.bounds;
+        let states = self.states;
+        (0..self.alive.len()).flat_map(move |row| {
+            let (y, z) = (row as i32 % bounds, row as i32 / bounds);
+            bits(self.alive[row]).map(move |x| (ivec3(x, y, z), states))
+                .chain(bits(self.dying[row]).map(move |x| (ivec3(x, y, z), 1)))
+        })
+    }
+
+    fn write(&mut self, pos: IVec3, value: CellValue) {
+        let row = self.row_index(pos.y, pos.z);
+        let bit = 1u128 << pos.x;
+        if (self.alive[row] | self.dying[row]) & bit != 0 {
+            self.live -= 1;
+        }
+        self.alive[row] &= !bit;
+        self.dying[row] &= !bit;
+        if value != 0 && value == self.states {
+            self.alive[row] |= bit;
+        }
+        else if value != 0 {
+            self.dying[row] |= bit;
+        }
+        if value != 0 {
+            self.live += 1;
+        }
+    }
+
+    pub fn update(&mut self, rule: &Rule) {
+        self.use_rule(rule);
+        let neighbour_count = rule.neighbour_method.get_neighbour_iter().len();
+        let mask = row_mask(self.bounds);
+
+        let span = info_span!("update_values").entered();
+        let mut alive = vec![0; self.alive.len()];
+        let mut dying = vec![0; self.dying.len()];
+        for z in 0..self.bounds {
+            for y in 0..self.bounds {
+                let row = self.row_index(y, z);
+                let counts = self.counts(y, z);
+
+                let old_alive = self.alive[row];
+                let old_dying = self.dying[row];
+                let dead = !old_alive & !old_dying & mask;
+                let survive = old_alive & matching(&counts, &rule.survival_rule, neighbour_count);
+                let born    = dead & matching(&counts, &rule.birth_rule, neighbour_count);
+
+                alive[row] = survive | born;
+                // dying cells become dead, with a single state alive cells do too.
+                if rule.states == 2 {
+                    dying[row] = old_alive & !survive;
+                }
+            }
+        }
+        span.exit();
+
+        self.changed.clear();
+        for row in 0..alive.len() {
+            let changed = (alive[row] ^ self.alive[row]) | (dying[row] ^ self.dying[row]);
+            if changed != 0 {
+                self.changed.push((row, changed));
+                let (old, new) = (self.alive[row] | self.dying[row], alive[row] | dying[row]);
+                self.live = self.live + new.count_ones() as usize - old.count_ones() as usize;
+            }
+        }
+        self.alive = alive;
+        self.dying = dying;
+    }
+
+    fn render_rows(&self, renderer: &mut CellRenderer, rows: impl Iterator<Item = (usize, u128)>) {
+        for (row, changed) in rows {
+            let (y, z) = (row as i32 % self.bounds, row as i32 / self.bounds);
+            let counts = self.counts(y, z);
+            for x in bits(changed) {
+                let bit = 1u128 << x;
+                let value =
+                    if self.alive[row] & bit != 0 { self.states }
+                    else if self.dying[row] & bit != 0 { 1 }
+                    else { 0 };
+                renderer.set_pos(ivec3(x, y, z), value, count_at(&counts, x));
+            }
+        }
+    }
+}
+
+
+impl Sim for LeddooBitPacked {
+    fn update(&mut self, rule: &Rule, task_pool: &TaskPool) {
+        self.switch_storage(rule);
+        match &mut self.fallback {
+            Some(fallback) => Sim::update(fallback, rule, task_pool),
+            None => self.update(rule),
+        }
+    }
+
+    fn render(&self, renderer: &mut CellRenderer) {
+        match &self.fallback {
+            Some(fallback) => fallback.render(renderer),
+            None => {
+                let mask = row_mask(self.bounds);
+                self.render_rows(renderer, (0..self.alive.len()).map(|row| (row, mask)));
+            }
+        }
+    }
+
+    fn render_delta(&self, renderer: &mut CellRenderer) {
+        match &self.fallback {
+            Some(fallback) => fallback.render_delta(renderer),
+            None => self.render_rows(renderer, self.changed.iter().copied()),
+        }
+    }
+
+    fn spawn_noise(&mut self, rule: &Rule) {
+        self.switch_storage(rule);
+        match &mut self.fallback {
+            Some(fallback) => fallback.spawn_noise(rule),
+            None => {
+                self.use_rule(rule);
+                utils::make_some_noise_default(utils::center(self.bounds), |pos| {
+                    let pos = utils::wrap(pos, self.bounds);
+                    if self.get_cell(pos) == 0 {
+                        self.write(pos, rule.states);
+                    }
+                });
+            }
+        }
+    }
+
+    fn get_cell(&self, pos: IVec3) -> CellValue {
+        if let Some(fallback) = &self.fallback {
+            return fallback.get_cell(pos);
+        }
+        let pos = utils::wrap_any(pos, self.bounds);
+        let row = self.row_index(pos.y, pos.z);
+        let bit = 1u128 << pos.x;
+        if self.alive[row] & bit != 0 { self.states }
+        else if self.dying[row] & bit != 0 { 1 }
+        else { 0 }
+    }
+
+    fn set_cell(&mut self, rule: &Rule, pos: IVec3, value: CellValue) {
+        self.switch_storage(rule);
+        match &mut self.fallback {
+            Some(fallback) => fallback.set_cell(rule, pos, value),
+            None => {
+                self.use_rule(rule);
+                self.write(utils::wrap_any(pos, self.bounds), value.min(rule.states));
+            }
+        }
+    }
+
+    fn cell_count(&self) -> usize {
+        match &self.fallback {
+            Some(fallback) => fallback.cell_count(),
+            None => self.live,
+        }
+    }
+
+    fn bounds(&self) -> i32 {
+        self.bounds
+    }
+
+    fn set_bounds(&mut self, new_bounds: i32) -> i32 {
+        let new_bounds = new_bounds.min(self.max_bounds());
+        if let Some(fallback) = &mut self.fallback {
+            fallback.set_bounds(new_bounds);
+        }
+        if new_bounds != self.bounds {
+            let rows = (new_bounds*new_bounds) as usize;
+            self.alive = vec![0; rows];
+            self.dying = vec![0; rows];
+            self.changed.clear();
+            self.live = 0;
+            self.bounds = new_bounds;
+        }
+        self.bounds
+    }
+
+    fn capabilities(&self) -> SimCapabilities {
+        SimCapabilities::SET_CELL
+    }
+
+    // a row has to fit in a u128.
+    fn max_bounds(&self) -> i32 {
+        128
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::example_library::fallback_example;
+
+    #[test]
+    fn switching_storage_keeps_decaying_cells_decaying() {
+        let mut rule = fallback_example().rule;
+        rule.states = 5;
+        let mut sim = LeddooBitPacked::new();
+        sim.set_bounds(16);
+        sim.set_cell(&rule, IVec3::splat(1), 5);
+        sim.set_cell(&rule, IVec3::splat(2), 3);
+        assert!(sim.fallback.is_some());
+
+        rule.states = 2;
+        sim.switch_storage(&rule);
+        assert!(sim.fallback.is_none());
+        assert_eq!((sim.get_cell(IVec3::splat(1)), sim.get_cell(IVec3::splat(2))), (2, 1));
+        assert_eq!(sim.cell_count(), 2);
+
+        rule.states = 5;
+        sim.switch_storage(&rule);
+        assert_eq!((sim.get_cell(IVec3::splat(1)), sim.get_cell(IVec3::splat(2))), (5, 1));
+
+        rule.states = 1;
+        sim.switch_storage(&rule);
+        assert_eq!((sim.get_cell(IVec3::splat(1)), sim.get_cell(IVec3::splat(2))), (1, 0));
+        assert_eq!(sim.cell_count(), 1);
+    }
+
+    #[test]
+    fn the_live_count_follows_updates() {
+        let mut rule = fallback_example().rule;
+        rule.states = 2;
+        let mut sim = LeddooBitPacked::new();
+        sim.set_bounds(32);
+        sim.spawn_noise(&rule);
+        for _ in 0..8 {
+            sim.update(&rule);
+            let counted: u32 = sim.alive.iter().chain(sim.dying.iter()).map(|row| row.count_ones()).sum();
+            assert_eq!(sim.cell_count(), counted as usize);
+        }
+    }
+}
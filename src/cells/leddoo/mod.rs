@@ -4,3 +4,9 @@ pub use single_threaded::*;
 mod atomic;
 pub use atomic::*;
 
+
+mod bit_packed;
+pub use bit_packed::*;
+
+mod rebuild;
+pub use rebuild::*;
@@ -0,0 +1,81 @@
+use std::{ops::Range, time::{Duration, Instant}};
+
+use crate::{neighbours::NeighbourMethod, rule::Rule};
+
+
+// how long a single update may spend on recounting. the sim doesn't tick
+// until the rebuild is done, so big grids take a few frames.
+const BUDGET: Duration = Duration::from_millis(4);
+
+
+// tracks which neighbor method the stored counts were made with, and the
+// progress of recounting them after the method changed.
+// counts past `recounted` may still be made with the old method, writes
+// while the rebuild is in progress must only update the ones before it.
+// the rest is recounted from the values later, so it ends up correct anyway.
+pub struct NeighbourRebuild {
+    method: Option<NeighbourMethod>,
+    next: Option<usize>,
+    total: usize,
+}
+
+impl NeighbourRebuild {
+    pub fn new() -> Self {
+        NeighbourRebuild { method: None, next: None, total: 0 }
+    }
+
+    // call before anything that reads or updates neighbor counts. starts a
+    // rebuild over `total` cells if the method changed.
+    pub fn check(&mut self, rule: &Rule, total: usize) {
+        match self.method {
+            // no counts have been made yet, so none are stale.
+            None => self.method = Some(rule.neighbour_method),
+            Some(method) if method != rule.neighbour_method => {
+                self.method = Some(rule.neighbour_method);
+                self.next  = Some(0);
+                self.total = total;
+            }
+            Some(_) => (),
+        }
+    }
+
+    // after a resize, every count is zero again.
+    pub fn cancel(&mut self) {
+        self.next = None;
+    }
+
+    pub fn in_progress(&self) -> bool {
+        self.next.is_some()
+    }
+
+    // the counts at indices below this are up to date.
+    pub fn recounted(&self) -> usize {
+        self.next.unwrap_or(usize::MAX)
+    }
+
+    pub fn progress(&self) -> f32 {
+        match self.next {
+            Some(next) => next as f32 / self.total.max(1) as f32,
+            None => 1.0,
+        }
+    }
+
+    // hands out ranges of at most `batch` cells to `recount` until the
+    // budget is used up. returns true once all cells are recounted.
+    pub fn step(&mut self, batch: usize, mut recount: impl FnMut(Range<usize>)) -> bool {
+        let mut next = match self.next {
+            Some(next) => next,
+            None => return true,
+        };
+
+        let t0 = Instant::now();
+        while next < self.total && t0.elapsed() < BUDGET {
+            let end = (next + batch).min(self.total);
+            recount(next..end);
+            next = end;
+        }
+
+        self.next = (next < self.total).then(|| next);
+        self.next.is_none()
+    }
+}
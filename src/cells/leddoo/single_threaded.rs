@@ -7,43 +7,125 @@
             - each tick updates the cells, and then only has to update the
             neighbors of cells that actually changed.
         - on my machine, this is ~20x faster than tantan's single threaded impl.
+        - the cells can be stored in morton order instead of row major (see
+          `CellLayout`), so more neighbors share a cache line. the storage is
+          padded to a power of two, padding cells are never born. "compare
+          layouts" in the ui times both.
+        - when the rule's neighbor method changes, the counts are recounted
+          from the values over the next few updates (see rebuild.rs).
+        - with energy enabled, births also need the cell (or the global pool)
+          to have `birth_cost` energy left. cells that have enough but can't
+          pay still count as dead, nothing else changes.
+        - each cell also keeps the sum of the directions to its live
+          neighbors, updated together with the counts. with a growth bias,
+          births see `neighbors - dot(sum, bias)`, so cells on the bias side
+          of a structure are born more easily.
+        - defect cells (see `DefectSettings`) are flagged per cell and use
+          the defect rule for their own birth and survival.
 */
 
+use std::time::{Duration, Instant};
+
 use bevy::{
-    math::{IVec3},
+    log::info_span,
+    math::{IVec3, Vec3},
     tasks::TaskPool,
 };
+use bevy_egui::egui;
 
 use crate::{
+    cells::{DefectSettings, EnergyMode, EnergySettings},
     cell_renderer::{CellRenderer},
-    rule::Rule,
-    utils,
+    example_library,
+    neighbours::NeighbourCount,
+    rule::{CellValue, Rule},
+    utils::{self, CellLayout},
 };
 
+use super::NeighbourRebuild;
+
 
 #[derive(Clone, Copy)]
 struct Cell {
-    value: u8,
-    neighbors: u8,
+    value: CellValue,
+    neighbors: NeighbourCount,
+    // ticks since birth, saturating. exposed as metadata.
+    age: u8,
+    // progress towards `value - 1` in 1/256, with decay sub-steps.
+    decay: u8,
+    // see `EnergyMode::PerCell`.
+    energy: u8,
+    // sum of the directions to the live neighbors.
+    directions: [i8; 3],
+    defect: bool,
 }
 
 impl Cell {
     fn is_dead(self) -> bool {
         self.value == 0
     }
+
+    // the neighbor count births see, see `Sim::set_growth_bias`.
+    fn biased_neighbors(self, bias: Vec3) -> NeighbourCount {
+        let [x, y, z] = self.directions;
+        let directions = Vec3::new(x as f32, y as f32, z as f32);
+        (self.neighbors as f32 - directions.dot(bias)).round().max(0.0) as NeighbourCount
+    }
+}
+
+// the neighbor count and the sum of the directions to the live neighbors.
+fn count_neighbors(cells: &[Cell], layout: CellLayout, bounds: i32, rule: &Rule, pos: IVec3) -> (NeighbourCount, [i8; 3]) {
+    let mut neighbors = 0;
+    let mut directions = IVec3::ZERO;
+    for dir in rule.neighbour_method.get_neighbour_iter() {
+        let index = layout.pos_to_index(utils::wrap(pos + *dir, bounds), bounds);
+        if cells[index].value == rule.states {
+            neighbors += 1;
+            directions += *dir;
+        }
+    }
+    (neighbors, [directions.x as i8, directions.y as i8, directions.z as i8])
 }
 
 
 pub struct LeddooSingleThreaded {
     cells: Vec<Cell>,
     bounds: i32,
+    layout: CellLayout,
+    // indices of the cells that changed during the last update.
+    changed: Vec<usize>,
+    // live cells, kept up to date by every write.
+    live: usize,
+    rebuild: NeighbourRebuild,
+    // 256 / decay sub-steps.
+    decay_step: u16,
+    energy: EnergySettings,
+    // see `EnergyMode::Global`, full at `cells.len() * 255`.
+    energy_pool: u64,
+    growth_bias: Vec3,
+    defects: DefectSettings,
+    benchmark: Option<String>,
 }
 
 impl LeddooSingleThreaded {
     pub fn new() -> Self {
+        Self::with_layout(CellLayout::RowMajor)
+    }
+
+    pub fn with_layout(layout: CellLayout) -> Self {
         LeddooSingleThreaded {
             cells: vec![],
             bounds: 0,
+            layout,
+            changed: vec![],
+            live: 0,
+            rebuild: NeighbourRebuild::new(),
+            decay_step: 256,
+            energy: EnergySettings::default(),
+            energy_pool: 0,
+            growth_bias: Vec3::ZERO,
+            defects: DefectSettings::default(),
+            benchmark: None,
         }
     }
 
@@ -51,30 +133,56 @@ impl LeddooSingleThreaded {
         if new_bounds != self.bounds {
             self.cells.clear();
             self.cells.resize(
-                (new_bounds*new_bounds*new_bounds) as usize,
-                Cell { value: 0, neighbors: 0 });
+                self.layout.storage_size(new_bounds),
+                Cell { value: 0, neighbors: 0, age: 0, decay: 0, energy: 255, directions: [0; 3], defect: false });
+            self.energy_pool = self.energy_capacity();
             self.bounds = new_bounds;
+            self.assign_defects();
+            self.changed.clear();
+            self.live = 0;
+            self.rebuild.cancel();
         }
         self.bounds
     }
 
     pub fn cell_count(&self) -> usize {
-        let mut result = 0;
-        for cell in &self.cells {
-            if !cell.is_dead() {
-                result += 1;
-            }
-        }
-        result
+        self.live
     }
 
 
     fn index_to_pos(&self, index: usize) -> IVec3 {
-        utils::index_to_pos(index, self.bounds)
+        self.layout.index_to_pos(index, self.bounds)
     }
 
     fn pos_to_index(&self, vec: IVec3) -> usize {
-        utils::pos_to_index(vec, self.bounds)
+        self.layout.pos_to_index(vec, self.bounds)
+    }
+
+    fn energy_capacity(&self) -> u64 {
+        self.cells.len() as u64 * 255
+    }
+
+    // the energy shown for a cell, the pool's fill level in global mode.
+    fn energy_of(&self, cell: Cell) -> u8 {
+        match self.energy.mode {
+            EnergyMode::Off     => 255,
+            EnergyMode::PerCell => cell.energy,
+            EnergyMode::Global  => (self.energy_pool * 255 / self.energy_capacity().max(1)) as u8,
+        }
+    }
+
+    // flags the defect cells, by their row major index so both layouts
+    // agree.
+    fn assign_defects(&mut self) {
+        let (layout, bounds, defects) = (self.layout, self.bounds, self.defects);
+        for (index, cell) in self.cells.iter_mut().enumerate() {
+            let pos = layout.index_to_pos(index, bounds);
+            cell.defect = utils::is_in_bounds(pos, bounds) && defects.is_defect(utils::pos_to_index(pos, bounds));
+        }
+    }
+
+    fn is_padded(&self) -> bool {
+        self.cells.len() != (self.bounds*self.bounds*self.bounds) as usize
     }
 
     pub fn wrap(&self, pos: IVec3) -> IVec3 {
@@ -84,77 +192,231 @@ impl LeddooSingleThreaded {
 
     fn update_neighbors(&mut self, rule: &Rule, index: usize, inc: bool) {
         let pos = self.index_to_pos(index);
+        let recounted = self.rebuild.recounted();
         for dir in rule.neighbour_method.get_neighbour_iter() {
             let neighbor_pos = self.wrap(pos + *dir);
 
+            // seen from the neighbor, this cell is in the opposite direction.
             let index = self.pos_to_index(neighbor_pos);
+            if index >= recounted {
+                continue;
+            }
+            let cell = &mut self.cells[index];
             if inc {
-                self.cells[index].neighbors += 1;
+                cell.neighbors += 1;
+                for axis in 0..3 {
+                    cell.directions[axis] -= dir[axis] as i8;
+                }
             }
             else {
-                self.cells[index].neighbors -= 1;
+                cell.neighbors -= 1;
+                for axis in 0..3 {
+                    cell.directions[axis] += dir[axis] as i8;
+                }
             }
         }
     }
 
+    // recounts a part of the grid if the neighbor method changed. returns
+    // false until the counts are up to date.
+    fn rebuild_neighbors(&mut self, rule: &Rule) -> bool {
+        self.rebuild.check(rule, self.cells.len());
+        if !self.rebuild.in_progress() {
+            return true;
+        }
+
+        let _span = info_span!("rebuild_neighbors").entered();
+        let (layout, bounds) = (self.layout, self.bounds);
+        let cells = &mut self.cells;
+        self.rebuild.step(4096, |range| {
+            for index in range {
+                let pos = layout.index_to_pos(index, bounds);
+                if utils::is_in_bounds(pos, bounds) {
+                    let (neighbors, directions) = count_neighbors(cells, layout, bounds, rule, pos);
+                    cells[index].neighbors = neighbors;
+                    cells[index].directions = directions;
+                }
+            }
+        })
+    }
+
     pub fn update(&mut self, rule: &Rule) {
-        // TODO: detect neighbor rule change.
+        if !self.rebuild_neighbors(rule) {
+            self.changed.clear();
+            return;
+        }
 
         let mut spawns = vec![];
         let mut deaths = vec![];
+        self.changed.clear();
 
         // update values.
+        let span = info_span!("update_values").entered();
+        let (layout, bounds, padded, decay_step) = (self.layout, self.bounds, self.is_padded(), self.decay_step);
+        let (energy, capacity) = (self.energy, self.energy_capacity());
+        let mut pool = self.energy_pool;
+        let growth_bias = self.growth_bias;
+        let defect_rule = self.defects.rule(rule);
+        let mut live = self.live;
+        if energy.mode == EnergyMode::Global {
+            pool = (pool + energy.regen as u64 * self.cells.len() as u64).min(capacity);
+        }
         for (index, cell) in self.cells.iter_mut().enumerate() {
+            if padded && !utils::is_in_bounds(layout.index_to_pos(index, bounds), bounds) {
+                continue;
+            }
+            // the states are the same, only the counts differ.
+            let own_rule = if cell.defect { &defect_rule } else { rule };
+            if energy.mode == EnergyMode::PerCell {
+                cell.energy = cell.energy.saturating_add(energy.regen);
+            }
             if cell.is_dead() {
-                if rule.birth_rule.in_range(cell.neighbors) {
+                let paid = match energy.mode {
+                    EnergyMode::Off => true,
+                    EnergyMode::PerCell => cell.energy >= energy.birth_cost,
+                    EnergyMode::Global  => pool >= energy.birth_cost as u64,
+                };
+                let neighbors = match growth_bias == Vec3::ZERO {
+                    true  => cell.neighbors,
+                    false => cell.biased_neighbors(growth_bias),
+                };
+                if own_rule.birth_rule.in_range(neighbors) && paid {
+                    match energy.mode {
+                        EnergyMode::Off => (),
+                        EnergyMode::PerCell => cell.energy -= energy.birth_cost,
+                        EnergyMode::Global  => pool -= energy.birth_cost as u64,
+                    }
                     cell.value = rule.states;
+                    cell.age = 0;
+                    cell.decay = 0;
                     spawns.push(index);
+                    self.changed.push(index);
+                    live += 1;
                 }
             }
             else {
-                if cell.value < rule.states || !rule.survival_rule.in_range(cell.neighbors) {
-                    if cell.value == rule.states {
-                        deaths.push(index);
-                    }
+                cell.age = cell.age.saturating_add(1);
+                if cell.value == rule.states && !own_rule.survival_rule.in_range(cell.neighbors) {
+                    deaths.push(index);
                     cell.value -= 1;
+                    cell.decay = 0;
+                    self.changed.push(index);
+                }
+                else if cell.value != rule.states {
+                    let decay = cell.decay as u16 + decay_step;
+                    if decay >= 256 {
+                        cell.value = cell.value.saturating_sub(1);
+                    }
+                    cell.decay = if cell.value == 0 { 0 } else { (decay % 256) as u8 };
+                    self.changed.push(index);
+                }
+                if cell.is_dead() {
+                    live -= 1;
                 }
             }
         }
 
+        self.energy_pool = pool;
+        self.live = live;
+        span.exit();
+
         // update neighbors.
+        let _span = info_span!("update_neighbors").entered();
         for index in spawns {
             self.update_neighbors(rule, index, true);
         }
         for index in deaths {
             self.update_neighbors(rule, index, false);
         }
+
+        #[cfg(feature = "debug-checks")]
+        self.validate(rule);
     }
 
-    // TEMP: move to sims.
-    #[allow(dead_code)]
+    // panics if a stored neighbor count is off. after every update with
+    // `debug-checks`.
+    #[cfg(any(test, feature = "debug-checks"))]
     pub fn validate(&self, rule: &Rule) {
         for index in 0..self.cells.len() {
             let pos = self.index_to_pos(index);
+            if !utils::is_in_bounds(pos, self.bounds) {
+                continue;
+            }
 
-            let mut neighbors = 0;
-            for dir in rule.neighbour_method.get_neighbour_iter() {
-                let neighbor_pos = self.wrap(pos + *dir);
+            let (neighbors, directions) = count_neighbors(&self.cells, self.layout, self.bounds, rule, pos);
+            assert_eq!(neighbors, self.cells[index].neighbors);
+            assert_eq!(directions, self.cells[index].directions);
+        }
+    }
 
-                let index = self.pos_to_index(neighbor_pos);
-                if self.cells[index].value == rule.states {
-                    neighbors += 1;
-                }
+    pub fn get_cell(&self, pos: IVec3) -> CellValue {
+        self.cells[self.pos_to_index(utils::wrap_any(pos, self.bounds))].value
+    }
+
+    pub fn set_cell(&mut self, rule: &Rule, pos: IVec3, value: CellValue) {
+        self.rebuild.check(rule, self.cells.len());
+        let index = self.pos_to_index(utils::wrap_any(pos, self.bounds));
+        let old = self.cells[index].value;
+        self.live = self.live + (value != 0) as usize - (old != 0) as usize;
+        self.cells[index].value = value;
+        self.cells[index].decay = 0;
+        if old == 0 {
+            self.cells[index].age = 0;
+        }
+        if old == rule.states && value != rule.states {
+            self.update_neighbors(rule, index, false);
+        }
+        if old != rule.states && value == rule.states {
+            self.update_neighbors(rule, index, true);
+        }
+    }
+
+    // recomputes all neighbor counts from the values.
+    pub fn recount_neighbors(&mut self, rule: &Rule) {
+        self.rebuild.check(rule, self.cells.len());
+        self.rebuild.cancel();
+        for cell in self.cells.iter_mut() {
+            cell.neighbors = 0;
+            cell.directions = [0; 3];
+        }
+        for index in 0..self.cells.len() {
+            if self.cells[index].value == rule.states {
+                self.update_neighbors(rule, index, true);
             }
+        }
+    }
 
-            assert_eq!(neighbors, self.cells[index].neighbors);
+    pub fn set_cells(&mut self, rule: &Rule, cells: &[(IVec3, CellValue)]) {
+        // past some point, recounting everything once is cheaper than
+        // updating the neighbors of every written cell.
+        if cells.len() < self.cells.len() / 8 {
+            for (pos, value) in cells {
+                self.set_cell(rule, *pos, *value);
+            }
+            return;
+        }
+        for (pos, value) in cells {
+            let index = self.pos_to_index(utils::wrap_any(*pos, self.bounds));
+            let cell = &mut self.cells[index];
+            if cell.value == 0 {
+                cell.age = 0;
+            }
+            self.live = self.live + (*value != 0) as usize - (cell.value != 0) as usize;
+            cell.value = *value;
+            cell.decay = 0;
         }
+        self.recount_neighbors(rule);
     }
 
     pub fn spawn_noise(&mut self, rule: &Rule) {
+        self.rebuild.check(rule, self.cells.len());
         utils::make_some_noise_default(utils::center(self.bounds), |pos| {
             let index = self.pos_to_index(self.wrap(pos));
             if self.cells[index].is_dead() {
                 self.cells[index].value = rule.states;
+                self.cells[index].age = 0;
+                self.cells[index].decay = 0;
+                self.live += 1;
                 self.update_neighbors(rule, index, true);
             }
         });
@@ -167,9 +429,30 @@ impl crate::cells::Sim for LeddooSingleThreaded {
         self.update(rule);
     }
 
+    // the renderer is always row major.
     fn render(&self, renderer: &mut CellRenderer) {
         for (index, cell) in self.cells.iter().enumerate() {
-            renderer.set(index, cell.value, cell.neighbors);
+            let pos = self.index_to_pos(index);
+            if !utils::is_in_bounds(pos, self.bounds) {
+                continue;
+            }
+            renderer.set_pos(pos, cell.value, cell.neighbors);
+            renderer.set_metadata(utils::pos_to_index(pos, self.bounds), cell.age);
+            renderer.set_decay(utils::pos_to_index(pos, self.bounds), cell.decay);
+            renderer.set_energy(utils::pos_to_index(pos, self.bounds), self.energy_of(*cell));
+            renderer.set_defect(utils::pos_to_index(pos, self.bounds), cell.defect);
+        }
+    }
+
+    fn render_delta(&self, renderer: &mut CellRenderer) {
+        for index in self.changed.iter() {
+            let cell = self.cells[*index];
+            let pos = self.index_to_pos(*index);
+            renderer.set_pos(pos, cell.value, cell.neighbors);
+            renderer.set_metadata(utils::pos_to_index(pos, self.bounds), cell.age);
+            renderer.set_decay(utils::pos_to_index(pos, self.bounds), cell.decay);
+            renderer.set_energy(utils::pos_to_index(pos, self.bounds), self.energy_of(cell));
+            renderer.set_defect(utils::pos_to_index(pos, self.bounds), cell.defect);
         }
     }
 
@@ -177,6 +460,18 @@ impl crate::cells::Sim for LeddooSingleThreaded {
         self.spawn_noise(rule);
     }
 
+    fn get_cell(&self, pos: IVec3) -> CellValue {
+        self.get_cell(pos)
+    }
+
+    fn set_cell(&mut self, rule: &Rule, pos: IVec3, value: CellValue) {
+        self.set_cell(rule, pos, value);
+    }
+
+    fn set_cells(&mut self, rule: &Rule, cells: &[(IVec3, CellValue)]) {
+        self.set_cells(rule, cells);
+    }
+
     fn cell_count(&self) -> usize {
         self.cell_count()
     }
@@ -188,4 +483,94 @@ impl crate::cells::Sim for LeddooSingleThreaded {
     fn set_bounds(&mut self, new_bounds: i32) -> i32 {
         self.set_bounds(new_bounds)
     }
+
+    fn capabilities(&self) -> crate::cells::SimCapabilities {
+        crate::cells::SimCapabilities::SET_CELL | crate::cells::SimCapabilities::METADATA
+            | crate::cells::SimCapabilities::FRACTIONAL_DECAY | crate::cells::SimCapabilities::ENERGY
+            | crate::cells::SimCapabilities::GROWTH_BIAS | crate::cells::SimCapabilities::DEFECTS
+    }
+
+    fn set_decay_substeps(&mut self, substeps: u8) {
+        self.decay_step = 256 / substeps.max(1) as u16;
+    }
+
+    fn set_energy(&mut self, energy: EnergySettings) {
+        self.energy = energy;
+    }
+
+    fn set_growth_bias(&mut self, bias: Vec3) {
+        self.growth_bias = bias;
+    }
+
+    fn set_defects(&mut self, defects: DefectSettings) {
+        let reassign = defects.cells_differ(&self.defects);
+        self.defects = defects;
+        if reassign {
+            self.assign_defects();
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!("layout: {:?}", self.layout));
+        if self.rebuild.in_progress() {
+            ui.label(format!("recounting neighbors: {:.0}%", 100.0 * self.rebuild.progress()));
+        }
+        if ui.button("compare layouts").clicked() {
+            let rule = example_library::fallback_example().rule;
+            self.benchmark = Some(compare_layouts(&rule, 64, 30).iter()
+                .map(|(layout, dt)| format!("{:?}: {:.2?} per tick", layout, dt))
+                .collect::<Vec<_>>()
+                .join("\n"));
+        }
+        if let Some(benchmark) = &self.benchmark {
+            ui.label(benchmark);
+        }
+    }
+}
+
+
+// seeds both layouts with noise and returns the time per tick. the
+// rule is the builder example, it keeps a large part of the grid busy.
+pub fn compare_layouts(rule: &Rule, bounds: i32, ticks: u32) -> Vec<(CellLayout, Duration)> {
+    [CellLayout::RowMajor, CellLayout::Morton].iter().map(|layout| {
+        let mut sim = LeddooSingleThreaded::with_layout(*layout);
+        sim.set_bounds(bounds);
+        sim.spawn_noise(rule);
+
+        let t0 = Instant::now();
+        for _ in 0..ticks {
+            sim.update(rule);
+        }
+        (*layout, t0.elapsed() / ticks)
+    })
+    .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neighbours::NeighbourMethod;
+
+    #[test]
+    fn set_cell_during_a_rebuild() {
+        let mut rule = example_library::fallback_example().rule;
+        rule.neighbour_method = NeighbourMethod::VonNeuman;
+        let mut sim = LeddooSingleThreaded::new();
+        sim.set_bounds(16);
+        sim.set_cell(&rule, IVec3::splat(4), rule.states);
+        sim.spawn_noise(&rule);
+
+        // starts the rebuild, the diagonal neighbors still have von neumann
+        // counts of zero.
+        rule.neighbour_method = NeighbourMethod::Moore;
+        sim.set_cell(&rule, IVec3::splat(4), 0);
+        sim.set_cell(&rule, IVec3::splat(12), rule.states);
+        assert!(sim.rebuild.in_progress());
+
+        while sim.rebuild.in_progress() {
+            sim.update(&rule);
+        }
+        sim.validate(&rule);
+    }
 }
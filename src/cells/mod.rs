@@ -1,11 +1,35 @@
-use bevy::{tasks::TaskPool};
-use crate::{rule::Rule, cell_renderer::CellRenderer};
+use bevy::{math::{IVec3, Vec3}, tasks::TaskPool};
+use bevy_egui::egui;
+use crate::{rule::{CellValue, Rule}, cell_renderer::CellRenderer};
 
 
+bitflags::bitflags! {
+    pub struct SimCapabilities: u32 {
+        const SET_CELL          = 1 << 0;
+        // `render` fills `CellRenderer::metadata`.
+        const METADATA          = 1 << 1;
+        // `set_decay_substeps` works, `render` fills `CellRenderer::decay`.
+        const FRACTIONAL_DECAY  = 1 << 2;
+        // `set_energy` works, `render` fills `CellRenderer::energy`.
+        const ENERGY            = 1 << 3;
+        // `set_growth_bias` works.
+        const GROWTH_BIAS       = 1 << 4;
+        // `set_defects` works, `render` fills `CellRenderer::defects`.
+        const DEFECTS           = 1 << 5;
+    }
+}
+
 pub trait Sim: Send + Sync {
     fn update(&mut self, rule: &Rule, task_pool: &TaskPool);
     fn render(&self, data: &mut CellRenderer);
 
+    // only writes the cells whose value changed during the last update.
+    // neighbor counts of unchanged cells may be stale.
+    // backends that don't track changes fall back to a full render.
+    fn render_delta(&self, data: &mut CellRenderer) {
+        self.render(data);
+    }
+
     fn reset(&mut self) {
         let bounds = self.bounds();
         self.set_bounds(0);
@@ -14,15 +38,77 @@ pub trait Sim: Send + Sync {
 
     fn spawn_noise(&mut self, rule: &Rule);
 
+    // only meaningful if `capabilities` contains SET_CELL.
+    fn get_cell(&self, _pos: IVec3) -> CellValue { 0 }
+    fn set_cell(&mut self, _rule: &Rule, _pos: IVec3, _value: CellValue) {}
+
+    // bulk version of `set_cell`, backends may override it with something
+    // faster than updating the neighbors cell by cell.
+    fn set_cells(&mut self, rule: &Rule, cells: &[(IVec3, CellValue)]) {
+        for (pos, value) in cells {
+            self.set_cell(rule, *pos, *value);
+        }
+    }
+
+    // live cells. shown every frame, so backends track it as cells change
+    // instead of scanning the volume.
     fn cell_count(&self) -> usize;
 
     fn bounds(&self) -> i32;
     fn set_bounds(&mut self, new_bounds: i32) -> i32;
+
+    fn capabilities(&self) -> SimCapabilities {
+        SimCapabilities::empty()
+    }
+
+    fn max_bounds(&self) -> i32 {
+        128
+    }
+
+    // how many cells each task works on, for backends that split the work
+    // by cell count. 0 splits the cells evenly over the threads.
+    fn set_cells_per_task(&mut self, _cells_per_task: usize) {}
+
+    // decaying cells take `substeps` ticks per state instead of one, so long
+    // decay chains fade smoothly. cells leave the `states` state right away,
+    // so the neighbor counts don't change, but dying cells block births for
+    // longer. 1 is the normal rule.
+    fn set_decay_substeps(&mut self, _substeps: u8) {}
+
+    // births cost energy, see `EnergySettings`. called every frame, backends
+    // keep their energy when the settings change.
+    fn set_energy(&mut self, _energy: EnergySettings) {}
+
+    // births count the neighbors on the `bias` side of a cell less, and the
+    // ones on the other side more, by the length of `bias` per neighbor. eg.
+    // +y lets structures grow upwards like plants. survival is unaffected.
+    fn set_growth_bias(&mut self, _bias: Vec3) {}
+
+    // defect cells follow `DefectSettings::rule`. called every frame,
+    // backends reassign the defect cells when `cells_differ`.
+    fn set_defects(&mut self, _defects: DefectSettings) {}
+
+    // false for sims that differ from the plain rule on purpose, eg. with a
+    // modifier that writes cells. see self_test.rs.
+    fn follows_rule(&self) -> bool {
+        true
+    }
+
+    // backend specific settings, drawn below the simulator selection.
+    fn ui(&mut self, _ui: &mut egui::Ui) {}
 }
 
 
+mod energy;
+pub use energy::*;
+
+mod defects;
+pub use defects::*;
+
 pub mod sims;
 pub use sims::*;
 
 pub mod tantan;
 pub mod leddoo;
+pub mod flat;
+pub mod modifiers;
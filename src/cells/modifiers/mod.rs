@@ -0,0 +1,154 @@
+use bevy::{math::{IVec3, Vec3}, tasks::TaskPool};
+use bevy_egui::egui;
+use crate::{
+    cells::{DefectSettings, EnergySettings, Sim, SimCapabilities},
+    cell_renderer::CellRenderer,
+    rule::{CellValue, Rule},
+};
+
+mod symmetry;
+pub use symmetry::*;
+
+mod statistics;
+pub use statistics::*;
+
+
+// cross-cutting behavior that works with any backend, without touching it.
+// modifiers only see the backend through the `Sim` trait.
+pub trait SimModifier: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn after_update(&mut self, _sim: &mut dyn Sim, _rule: &Rule) {}
+
+    // return true to replace the backend's own noise.
+    fn spawn_noise(&mut self, _sim: &mut dyn Sim, _rule: &Rule) -> bool {
+        false
+    }
+
+    // true if `after_update` writes cells, which the backend's change
+    // lists don't know about.
+    fn writes_cells(&self) -> bool {
+        false
+    }
+
+    fn ui(&mut self, _sim: &dyn Sim, _ui: &mut egui::Ui) {}
+}
+
+
+// a sim that owns another sim and runs modifiers around it, in order.
+pub struct Modified {
+    inner: Box<dyn Sim>,
+    modifiers: Vec<Box<dyn SimModifier>>,
+}
+
+impl Modified {
+    pub fn new(inner: Box<dyn Sim>) -> Modified {
+        Modified { inner, modifiers: vec![] }
+    }
+
+    pub fn with(mut self, modifier: Box<dyn SimModifier>) -> Modified {
+        self.modifiers.push(modifier);
+        self
+    }
+}
+
+impl Sim for Modified {
+    fn update(&mut self, rule: &Rule, task_pool: &TaskPool) {
+        self.inner.update(rule, task_pool);
+        for modifier in self.modifiers.iter_mut() {
+            modifier.after_update(&mut *self.inner, rule);
+        }
+    }
+
+    fn render(&self, data: &mut CellRenderer) {
+        self.inner.render(data);
+    }
+
+    fn render_delta(&self, data: &mut CellRenderer) {
+        match self.modifiers.iter().any(|m| m.writes_cells()) {
+            true  => self.inner.render(data),
+            false => self.inner.render_delta(data),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn spawn_noise(&mut self, rule: &Rule) {
+        let mut spawned = false;
+        for modifier in self.modifiers.iter_mut() {
+            if !spawned {
+                spawned = modifier.spawn_noise(&mut *self.inner, rule);
+            }
+        }
+        if !spawned {
+            self.inner.spawn_noise(rule);
+        }
+    }
+
+    fn get_cell(&self, pos: IVec3) -> CellValue {
+        self.inner.get_cell(pos)
+    }
+
+    fn set_cell(&mut self, rule: &Rule, pos: IVec3, value: CellValue) {
+        self.inner.set_cell(rule, pos, value);
+    }
+
+    fn set_cells(&mut self, rule: &Rule, cells: &[(IVec3, CellValue)]) {
+        self.inner.set_cells(rule, cells);
+    }
+
+    fn cell_count(&self) -> usize {
+        self.inner.cell_count()
+    }
+
+    fn bounds(&self) -> i32 {
+        self.inner.bounds()
+    }
+
+    fn set_bounds(&mut self, new_bounds: i32) -> i32 {
+        self.inner.set_bounds(new_bounds)
+    }
+
+    fn capabilities(&self) -> SimCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_bounds(&self) -> i32 {
+        self.inner.max_bounds()
+    }
+
+    fn set_cells_per_task(&mut self, cells_per_task: usize) {
+        self.inner.set_cells_per_task(cells_per_task)
+    }
+
+    fn set_decay_substeps(&mut self, substeps: u8) {
+        self.inner.set_decay_substeps(substeps)
+    }
+
+    fn set_energy(&mut self, energy: EnergySettings) {
+        self.inner.set_energy(energy)
+    }
+
+    fn set_growth_bias(&mut self, bias: Vec3) {
+        self.inner.set_growth_bias(bias)
+    }
+
+    fn set_defects(&mut self, defects: DefectSettings) {
+        self.inner.set_defects(defects)
+    }
+
+    fn follows_rule(&self) -> bool {
+        self.inner.follows_rule() && !self.modifiers.iter().any(|m| m.writes_cells())
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        self.inner.ui(ui);
+        for modifier in self.modifiers.iter_mut() {
+            ui.collapsing(modifier.name().to_string(), |ui| {
+                modifier.ui(&*self.inner, ui);
+            });
+        }
+    }
+}
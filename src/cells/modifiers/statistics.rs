@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+use bevy_egui::egui;
+use crate::{
+    cells::{Sim, modifiers::SimModifier},
+    rule::Rule,
+};
+
+const HISTORY_LENGTH: usize = 256;
+
+
+// records the population after every update.
+pub struct Statistics {
+    pub history: VecDeque<usize>,
+    pub updates: u64,
+}
+
+impl Statistics {
+    pub fn new() -> Statistics {
+        Statistics {
+            history: VecDeque::with_capacity(HISTORY_LENGTH),
+            updates: 0,
+        }
+    }
+}
+
+impl SimModifier for Statistics {
+    fn name(&self) -> &str {
+        "statistics"
+    }
+
+    fn after_update(&mut self, sim: &mut dyn Sim, _rule: &Rule) {
+        if self.history.len() == HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(sim.cell_count());
+        self.updates += 1;
+    }
+
+    fn ui(&mut self, _sim: &dyn Sim, ui: &mut egui::Ui) {
+        ui.label(format!("updates: {}", self.updates));
+        if let (Some(min), Some(max)) = (self.history.iter().min(), self.history.iter().max()) {
+            let mean = self.history.iter().sum::<usize>() / self.history.len();
+            ui.label(format!("population over the last {} updates: {} .. {}, mean {}",
+                self.history.len(), min, max, mean));
+        }
+        if ui.small_button("clear").clicked() {
+            self.history.clear();
+            self.updates = 0;
+        }
+    }
+}
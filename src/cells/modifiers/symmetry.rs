@@ -0,0 +1,133 @@
+use bevy::math::{ivec3, IVec3};
+use bevy_egui::egui;
+use crate::{
+    cells::{Sim, SimCapabilities, modifiers::SimModifier},
+    rule::Rule,
+    utils,
+};
+
+
+// the seed is made symmetric and, if `enforce` is set, the volume is
+// re-symmetrized after every update by copying each orbit's canonical cell
+// (lowest index) to the other cells.
+// needs a backend with cell access, otherwise it does nothing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Symmetry {
+    pub mirror_x: bool,
+    pub mirror_y: bool,
+    pub mirror_z: bool,
+    // 4-fold rotation around the y axis.
+    pub rotate_y: bool,
+    pub enforce: bool,
+}
+
+impl Default for Symmetry {
+    fn default() -> Self {
+        Symmetry {
+            mirror_x: true,
+            mirror_y: false,
+            mirror_z: true,
+            rotate_y: false,
+            enforce: true,
+        }
+    }
+}
+
+impl Symmetry {
+    // all positions `pos` is mapped to, including itself. may contain duplicates.
+    pub fn images(&self, pos: IVec3, bounds: i32) -> Vec<IVec3> {
+        let max = bounds - 1;
+        let mut images = vec![pos];
+        fn mirror(images: &mut Vec<IVec3>, f: impl Fn(IVec3) -> IVec3) {
+            let count = images.len();
+            for i in 0..count {
+                images.push(f(images[i]));
+            }
+        }
+        if self.mirror_x { mirror(&mut images, |p| ivec3(max - p.x, p.y, p.z)); }
+        if self.mirror_y { mirror(&mut images, |p| ivec3(p.x, max - p.y, p.z)); }
+        if self.mirror_z { mirror(&mut images, |p| ivec3(p.x, p.y, max - p.z)); }
+        if self.rotate_y {
+            let count = images.len();
+            for i in 0..count {
+                let mut p = images[i];
+                for _ in 0..3 {
+                    p = ivec3(max - p.z, p.y, p.x);
+                    images.push(p);
+                }
+            }
+        }
+        images
+    }
+
+    pub fn is_identity(&self) -> bool {
+        !(self.mirror_x || self.mirror_y || self.mirror_z || self.rotate_y)
+    }
+
+    fn supported(sim: &dyn Sim) -> bool {
+        sim.capabilities().contains(SimCapabilities::SET_CELL)
+    }
+
+    fn symmetrize(&self, sim: &mut dyn Sim, rule: &Rule) {
+        let bounds = sim.bounds();
+        for index in 0..(bounds*bounds*bounds) as usize {
+            let pos = utils::index_to_pos(index, bounds);
+            let images = self.images(pos, bounds);
+            if images.iter().any(|p| utils::pos_to_index(*p, bounds) < index) {
+                continue;
+            }
+            let value = sim.get_cell(pos);
+            for image in images {
+                if sim.get_cell(image) != value {
+                    sim.set_cell(rule, image, value);
+                }
+            }
+        }
+    }
+}
+
+impl SimModifier for Symmetry {
+    fn name(&self) -> &str {
+        "symmetry"
+    }
+
+    fn after_update(&mut self, sim: &mut dyn Sim, rule: &Rule) {
+        if self.enforce && Symmetry::supported(sim) && !self.is_identity() {
+            self.symmetrize(sim, rule);
+        }
+    }
+
+    fn spawn_noise(&mut self, sim: &mut dyn Sim, rule: &Rule) -> bool {
+        if !Symmetry::supported(sim) {
+            return false;
+        }
+        let bounds = sim.bounds();
+        let mut seed = vec![];
+        utils::make_some_noise_default(utils::center(bounds), |pos| seed.push(pos));
+        for pos in seed {
+            for image in self.images(pos, bounds) {
+                sim.set_cell(rule, image, rule.states);
+            }
+        }
+        true
+    }
+
+    fn writes_cells(&self) -> bool {
+        self.enforce
+    }
+
+    fn ui(&mut self, sim: &dyn Sim, ui: &mut egui::Ui) {
+        if !Symmetry::supported(sim) {
+            ui.weak("symmetry needs a backend with cell access");
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label("mirror");
+            ui.checkbox(&mut self.mirror_x, "x");
+            ui.checkbox(&mut self.mirror_y, "y");
+            ui.checkbox(&mut self.mirror_z, "z");
+        });
+        ui.checkbox(&mut self.rotate_y, "rotate around y");
+        ui.checkbox(&mut self.enforce, "keep updates symmetric");
+    }
+}
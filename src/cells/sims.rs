@@ -1,24 +1,84 @@
 use bevy::{
-    prelude::{Plugin, Res, ResMut, Query, Color},
-    tasks::AsyncComputeTaskPool,
+    log::{debug, info_span},
+    math::{IVec3, Vec3},
+    prelude::{Plugin, Res, ResMut, Query, Color, SystemLabel, ParallelSystemDescriptorCoercion, Without, Time, EventWriter, Commands, Entity},
 };
 use bevy_egui:: {egui, EguiContext};
+use serde::{Deserialize, Serialize};
 use crate::{
-    cells::Sim,
-    rule::{Rule, ColorMethod},
+    action::Action,
+    app_state::{has_ui, simulating},
+    background::Background,
+    cells::{DefectSettings, EnergySettings, Sim, SimCapabilities, DEFECT_COLOR},
+    rule::{CellValue, Rule, ColorMethod, Value},
+    rule_analysis,
     neighbours::NeighbourMethod,
-    cell_renderer::{InstanceMaterialData, InstanceData, CellRenderer},
+    cell_renderer::{CellShaderSettings, DebugInstances, InstanceMaterialData, InstanceColoring, CellRenderer, NeighbourFilter, MaterialThresholds},
+    gpu_instances::{self, GpuCells},
+    graphics::{GraphicsSettings, InstanceFilter},
+    theme::{Palette, Theme, THEMES},
+    threading::Threading,
     utils,
 };
 
+// the end of the states slider. wide states are for long decay gradients.
+#[cfg(not(feature = "wide_states"))]
+const MAX_STATES: CellValue = 50;
+#[cfg(feature = "wide_states")]
+const MAX_STATES: CellValue = 2000;
 
-#[derive(Clone)]
+const STATES_HELP: &str = "cells are born at the highest state and only cells in it count as neighbors. \
+    once a cell stops surviving it loses one state per tick, so states - 1 is the length of its decay \
+    trail. decaying cells can't be born again until they reach 0.";
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Example {
     pub name: String,
     pub rule: Rule,
     pub color_method: ColorMethod,
     pub color1: Color,
     pub color2: Color,
+    #[serde(default)]
+    pub theme: Theme,
+    // set for examples that come from rule packs.
+    #[serde(default)]
+    pub author: Option<String>,
+    // free form, eg. "crystal". see `all_tags` for the ones the rule implies.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Example {
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut tags = self.tags.clone();
+        for tag in rule_analysis::tags(&self.rule) {
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.to_string());
+            }
+        }
+        tags
+    }
+
+    // every word of `filter` has to be part of the name, the author or a tag.
+    pub fn matches(&self, filter: &str) -> bool {
+        let tags = self.all_tags();
+        filter.split_whitespace().all(|word| {
+            let word = word.to_lowercase();
+            self.name.to_lowercase().contains(&word)
+                || self.author.as_ref().map(|a| a.to_lowercase().contains(&word)).unwrap_or(false)
+                || tags.iter().any(|tag| tag.to_lowercase().contains(&word))
+        })
+    }
+}
+
+const UNDO_LIMIT: usize = 16;
+
+// what undo and redo restore. the cells come from the renderer, which holds
+// the grid as of the last update.
+struct Snapshot {
+    rule: Rule,
+    bounds: i32,
+    cells: Vec<(IVec3, CellValue)>,
 }
 
 pub struct Sims {
@@ -26,15 +86,66 @@ pub struct Sims {
     active_sim: usize,
     bounds: i32,
     update_dt: std::time::Duration,
+    render_dt: std::time::Duration,
+    extract_dt: std::time::Duration,
+
+    // keep the grid when switching backends in the ui.
+    transfer_on_switch: bool,
+
+    paused: bool,
+    step: bool,
+    tick: u64,
+    ticks_per_frame: u32,
+    // ticks a decaying cell takes per state, for backends with
+    // `FRACTIONAL_DECAY`.
+    decay_substeps: u8,
+    // for backends with `ENERGY`.
+    energy: EnergySettings,
+    // for backends with `GROWTH_BIAS`.
+    growth_bias: Vec3,
+    // for backends with `DEFECTS`.
+    defects: DefectSettings,
 
     renderer: Option<Box<CellRenderer>>, // rust...
+    // set by anything that changes cells outside of `Sim::update`, in which
+    // case the change lists of the backends are incomplete.
+    full_render: bool,
+    // cells that come alive on frames with fresh noise grow in, see
+    // `CellRenderer::begin_frame`.
+    spawned_noise: bool,
+    // frames that had fresh noise, for systems that want to notice it.
+    noise_frames: u64,
+    // resets, examples and rule changes, for systems that keep a history of
+    // the current run.
+    restarts: u64,
 
     rule: Option<Rule>, // this is really quite dumb. maybe Cell would have been a good idea.
     color_method: ColorMethod,
     color1: Color,
     color2: Color,
+    theme: Theme,
+    neighbour_filter: NeighbourFilter,
+    materials: MaterialThresholds,
+    // render every cell next frame, so the neighbor counts of dead cells are
+    // up to date too. set every frame by the systems that need it, eg.
+    // neighbour_field.rs, and cleared after each update.
+    pub keep_neighbors: bool,
+    // move the states into `rule_analysis::suggested_states` whenever the
+    // neighbor method or the birth counts change.
+    clamp_states: bool,
+    suggested_for: Option<(NeighbourMethod, Value)>,
 
     examples: Vec<Example>,
+    example_filter: String,
+    sim_filter: String,
+    // of the current setup, from the last example or project.
+    tags: Vec<String>,
+
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    // what the last checkpoint was for, while the pointer is held. see
+    // `checkpoint_gesture`.
+    gesture: Option<&'static str>,
 }
 
 impl Sims {
@@ -44,12 +155,39 @@ impl Sims {
             active_sim: usize::MAX,
             bounds: 64,
             update_dt: std::time::Duration::from_secs(0),
+            render_dt: std::time::Duration::from_secs(0),
+            extract_dt: std::time::Duration::from_secs(0),
+            transfer_on_switch: true,
+            paused: false,
+            step: false,
+            tick: 0,
+            ticks_per_frame: 1,
+            decay_substeps: 1,
+            energy: EnergySettings::default(),
+            growth_bias: Vec3::ZERO,
+            defects: DefectSettings::default(),
             renderer: Some(Box::new(CellRenderer::new())),
+            full_render: true,
+            spawned_noise: true,
+            noise_frames: 0,
+            restarts: 0,
             rule: None,
             color_method: ColorMethod::DistToCenter,
             color1: Color::YELLOW,
             color2: Color::RED,
+            theme: Theme::Custom,
+            neighbour_filter: NeighbourFilter::default(),
+            materials: MaterialThresholds::default(),
+            keep_neighbors: false,
+            clamp_states: false,
+            suggested_for: None,
             examples: vec![],
+            example_filter: String::new(),
+            sim_filter: String::new(),
+            tags: vec![],
+            undo: vec![],
+            redo: vec![],
+            gesture: None,
         }
     }
 
@@ -61,6 +199,11 @@ impl Sims {
         self.examples.push(example);
     }
 
+    // the active rule is kept, examples are only applied when picked.
+    pub fn set_examples(&mut self, examples: Vec<Example>) {
+        self.examples = examples;
+    }
+
     pub fn set_sim(&mut self, index: usize) {
         if self.active_sim < self.sims.len() {
             self.sims[self.active_sim].1.reset();
@@ -72,14 +215,130 @@ impl Sims {
         self.sims[index].1.spawn_noise(&rule);
         self.renderer.as_mut().unwrap().set_bounds(self.bounds);
         self.rule = Some(rule);
+        self.full_render = true;
+        self.spawned_noise = true;
+    }
+
+    // switches backends without restarting the simulation: the current grid
+    // is copied into the new backend through the cell accessors.
+    // falls back to `set_sim` if either backend lacks cell access.
+    pub fn transfer_sim(&mut self, index: usize) {
+        let can_transfer = self.active_sim < self.sims.len()
+            && self.capabilities().contains(SimCapabilities::SET_CELL)
+            && self.sims[index].1.capabilities().contains(SimCapabilities::SET_CELL);
+        if !can_transfer {
+            self.set_sim(index);
+            return;
+        }
+
+        // the renderer holds the grid as of the last update.
+        let renderer = self.renderer.as_mut().unwrap();
+        let cells: Vec<(IVec3, CellValue)> = renderer.live_cells()
+            .map(|pos| (pos, renderer.values[utils::pos_to_index(pos, renderer.bounds)]))
+            .collect();
+
+        self.sims[self.active_sim].1.reset();
+
+        let rule = self.rule.take().unwrap();
+        self.active_sim = index;
+        let sim = &mut self.sims[index].1;
+        self.bounds = sim.set_bounds(self.bounds);
+        for (pos, value) in cells {
+            if utils::is_in_bounds(pos, self.bounds) {
+                sim.set_cell(&rule, pos, value);
+            }
+        }
+        renderer.set_bounds(self.bounds);
+        self.rule = Some(rule);
+        self.full_render = true;
+    }
+
+    pub fn transfer_on_switch(&self) -> bool {
+        self.transfer_on_switch
+    }
+
+    pub fn set_transfer_on_switch(&mut self, transfer: bool) {
+        self.transfer_on_switch = transfer;
+    }
+
+    pub fn decay_substeps(&self) -> u8 {
+        self.decay_substeps
+    }
+
+    pub fn set_decay_substeps(&mut self, substeps: u8) {
+        self.decay_substeps = substeps.clamp(1, 16);
+    }
+
+    pub fn energy(&self) -> EnergySettings {
+        self.energy
+    }
+
+    pub fn set_energy(&mut self, energy: EnergySettings) {
+        self.energy = energy;
+    }
+
+    pub fn growth_bias(&self) -> Vec3 {
+        self.growth_bias
+    }
+
+    pub fn set_growth_bias(&mut self, bias: Vec3) {
+        self.growth_bias = bias.clamp(Vec3::splat(-2.0), Vec3::splat(2.0));
+    }
+
+    pub fn defects(&self) -> DefectSettings {
+        self.defects
+    }
+
+    pub fn set_defects(&mut self, defects: DefectSettings) {
+        // the defect flags of unchanged cells aren't in the change lists.
+        if defects != self.defects {
+            self.full_render = true;
+        }
+        self.defects = defects;
+    }
+
+    pub fn clamp_states(&self) -> bool {
+        self.clamp_states
+    }
+
+    pub fn set_clamp_states(&mut self, clamp: bool) {
+        self.clamp_states = clamp;
+    }
+
+    pub fn neighbour_filter(&self) -> &NeighbourFilter {
+        &self.neighbour_filter
+    }
+
+    pub fn set_neighbour_filter(&mut self, mut filter: NeighbourFilter) {
+        filter.max = filter.max.max(filter.min);
+        self.neighbour_filter = filter;
+    }
+
+    pub fn materials(&self) -> MaterialThresholds {
+        self.materials
+    }
+
+    pub fn set_materials(&mut self, mut materials: MaterialThresholds) {
+        materials.translucent_to = materials.translucent_to.min(materials.emissive_from);
+        self.materials = materials;
+    }
+
+    // what picking a backend in the ui does, see `transfer_on_switch`.
+    pub fn switch_sim(&mut self, index: usize) {
+        match self.transfer_on_switch {
+            true  => self.transfer_sim(index),
+            false => self.set_sim(index),
+        }
     }
 
     pub fn set_example(&mut self, index: usize) {
         let example = self.examples[index].clone();
-        let rule = example.rule;
+        let rule = example.rule.sanitized();
         self.color_method = example.color_method;
         self.color1 = example.color1;
         self.color2 = example.color2;
+        self.theme = example.theme;
+        self.tags = example.tags;
 
         if self.active_sim < self.sims.len() {
             let sim = &mut self.sims[self.active_sim].1;
@@ -87,84 +346,493 @@ impl Sims {
             sim.spawn_noise(&rule);
         }
         self.rule = Some(rule);
+        self.full_render = true;
+        self.spawned_noise = true;
+        self.restarts += 1;
+    }
+
+    // the choices of the start menu, see app_state.rs. allocates the grid.
+    pub fn start(&mut self, sim: usize, example: usize, bounds: i32) {
+        self.set_example(example);
+        self.bounds = bounds;
+        self.set_sim(sim);
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        self.tags = tags;
+    }
+
+    pub fn max_bounds(&self, sim: usize) -> i32 {
+        self.sims.get(sim).map(|(_, sim)| sim.max_bounds()).unwrap_or(32)
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    // cpu time of the last frame: per tick for the update, and for the
+    // render into the renderer and the instance extraction.
+    pub fn frame_times(&self) -> (std::time::Duration, std::time::Duration, std::time::Duration) {
+        (self.update_dt, self.render_dt, self.extract_dt)
+    }
+
+    pub fn noise_frames(&self) -> u64 {
+        self.noise_frames
+    }
+
+    pub fn restarts(&self) -> u64 {
+        self.restarts
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    // one tick on the next frame, pauses.
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.step = true;
+    }
+
+    pub fn ticks_per_frame(&self) -> u32 {
+        self.ticks_per_frame
+    }
+
+    pub fn set_ticks_per_frame(&mut self, ticks_per_frame: u32) {
+        self.ticks_per_frame = ticks_per_frame.max(1);
+    }
+
+    pub fn colors(&self) -> (ColorMethod, Color, Color) {
+        (self.color_method, self.color1, self.color2)
+    }
+
+    pub fn set_colors(&mut self, color_method: ColorMethod, color1: Color, color2: Color) {
+        self.color_method = color_method;
+        self.color1 = color1;
+        self.color2 = color2;
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    pub fn palette(&self) -> Palette {
+        Palette { theme: self.theme, color1: self.color1, color2: self.color2 }
+    }
+
+    pub fn examples(&self) -> &[Example] {
+        &self.examples
+    }
+
+    pub fn renderer(&self) -> &CellRenderer {
+        self.renderer.as_ref().unwrap()
+    }
+
+    pub fn bounds(&self) -> i32 {
+        self.bounds
+    }
+
+    pub fn rule(&self) -> &Rule {
+        self.rule.as_ref().unwrap()
+    }
+
+    // changing the states restarts the simulation, just like editing it in
+    // the ui does. the backends recount their neighbors themselves when the
+    // neighbor method changes.
+    pub fn set_rule(&mut self, rule: Rule) {
+        let rule = rule.sanitized();
+        let old_rule = self.rule.replace(rule.clone()).unwrap();
+        if old_rule.states != rule.states {
+            self.reset();
+            self.spawn_noise();
+        }
+        if old_rule != rule {
+            self.restarts += 1;
+        }
+        self.full_render = true;
+    }
+
+    fn active(&mut self) -> Option<&mut Box<dyn Sim>> {
+        let active_sim = self.active_sim;
+        self.sims.get_mut(active_sim).map(|(_, sim)| sim)
+    }
+
+    pub fn sim_names(&self) -> impl Iterator<Item = &str> {
+        self.sims.iter().map(|(name, _)| name.as_str())
+    }
+
+    // resizes the active backend and starts it over with fresh noise.
+    pub fn set_bounds(&mut self, bounds: i32) {
+        let rule = self.rule.take().unwrap();
+        let bounds = self.active().map(|sim| {
+            let bounds = sim.set_bounds(bounds);
+            sim.spawn_noise(&rule);
+            bounds
+        });
+        if let Some(bounds) = bounds {
+            self.bounds = bounds;
+            self.renderer.as_mut().unwrap().set_bounds(bounds);
+        }
+        self.rule = Some(rule);
+        self.full_render = true;
+        self.spawned_noise = true;
+    }
+
+    pub fn active_max_bounds(&self) -> i32 {
+        self.max_bounds(self.active_sim)
+    }
+
+    pub fn active_name(&self) -> &str {
+        self.sims.get(self.active_sim).map(|(name, _)| name.as_str()).unwrap_or("")
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.sims.get(self.active_sim).map(|(_, sim)| sim.cell_count()).unwrap_or(0)
+    }
+
+    pub fn reset(&mut self) {
+        if let Some(sim) = self.active() {
+            sim.reset();
+        }
+        self.full_render = true;
+        self.restarts += 1;
+    }
+
+    pub fn spawn_noise(&mut self) {
+        let rule = self.rule.take().unwrap();
+        if let Some(sim) = self.active() {
+            sim.spawn_noise(&rule);
+        }
+        self.rule = Some(rule);
+        self.full_render = true;
+        self.spawned_noise = true;
+    }
+
+    pub fn capabilities(&self) -> SimCapabilities {
+        self.sims.get(self.active_sim).map(|(_, sim)| sim.capabilities()).unwrap_or(SimCapabilities::empty())
+    }
+
+    pub fn get_cell(&self, pos: IVec3) -> CellValue {
+        self.sims.get(self.active_sim).map(|(_, sim)| sim.get_cell(pos)).unwrap_or(0)
+    }
+
+    // like `set_cell`, values past the rule's states are clamped.
+    pub fn set_cells(&mut self, cells: &[(IVec3, CellValue)]) {
+        let rule = self.rule.take().unwrap();
+        let clamped: Vec<_>;
+        let cells = match cells.iter().any(|(_, value)| *value > rule.states) {
+            true => {
+                clamped = cells.iter().map(|(pos, value)| (*pos, (*value).min(rule.states))).collect();
+                &clamped
+            }
+            false => cells,
+        };
+        if let Some(sim) = self.active() {
+            sim.set_cells(&rule, cells);
+        }
+        self.rule = Some(rule);
+        self.full_render = true;
+        self.spawned_noise = true;
+    }
+
+    // only backends with cell access can be restored.
+    fn snapshot(&self, rule: &Rule) -> Option<Snapshot> {
+        if !self.capabilities().contains(SimCapabilities::SET_CELL) {
+            return None;
+        }
+        // the ui takes the renderer while a frame is rendered.
+        self.renderer.as_ref()?;
+        Some(Snapshot { rule: rule.clone(), bounds: self.bounds, cells: self.cells() })
+    }
+
+    // the cells as of the last render.
+    pub fn cells(&self) -> Vec<(IVec3, CellValue)> {
+        let renderer = self.renderer();
+        renderer.live_cells()
+            .map(|pos| (pos, renderer.values[utils::pos_to_index(pos, renderer.bounds)]))
+            .collect()
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        let rule = snapshot.rule;
+        let bounds = self.active().map(|sim| {
+            let bounds = sim.set_bounds(snapshot.bounds);
+            sim.reset();
+            let cells: Vec<_> = snapshot.cells.into_iter()
+                .filter(|(pos, _)| utils::is_in_bounds(*pos, bounds))
+                .collect();
+            sim.set_cells(&rule, &cells);
+            bounds
+        });
+        if let Some(bounds) = bounds {
+            self.bounds = bounds;
+            self.renderer.as_mut().unwrap().set_bounds(bounds);
+        }
+        self.rule = Some(rule);
+        self.full_render = true;
+    }
+
+    // `rule` is passed in, since the ui holds on to the rule while editing.
+    fn checkpoint_with(&mut self, rule: &Rule) {
+        if let Some(snapshot) = self.snapshot(rule) {
+            if self.undo.len() == UNDO_LIMIT {
+                self.undo.remove(0);
+            }
+            self.undo.push(snapshot);
+            self.redo.clear();
+        }
+    }
+
+    // call before destructive changes to make them undoable.
+    pub fn checkpoint(&mut self) {
+        let rule = self.rule().clone();
+        self.checkpoint_with(&rule);
+        self.gesture = None;
+    }
+
+    // `checkpoint`, but only once per pointer gesture for `kind`: dragging
+    // the states or bounds slider sends an action every frame, each of which
+    // would push a snapshot and crowd out the real history.
+    pub fn checkpoint_gesture(&mut self, kind: &'static str) {
+        if self.gesture != Some(kind) {
+            self.checkpoint();
+            self.gesture = Some(kind);
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(snapshot) = self.undo.pop() {
+            if let Some(current) = self.snapshot(self.rule()) {
+                self.redo.push(current);
+            }
+            self.restore(snapshot);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(snapshot) = self.redo.pop() {
+            if let Some(current) = self.snapshot(self.rule()) {
+                self.undo.push(current);
+            }
+            self.restore(snapshot);
+        }
+    }
+
+    pub fn set_cell(&mut self, pos: IVec3, value: CellValue) {
+        let rule = self.rule.take().unwrap();
+        if let Some(sim) = self.active() {
+            sim.set_cell(&rule, pos, value.min(rule.states));
+        }
+        self.rule = Some(rule);
+        self.full_render = true;
     }
 }
 
 
-pub fn update(
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimsUpdate;
+
+
+// the Celluar window, after the tick so it shows this frame. edits go out as
+// actions, so they apply next frame.
+pub fn update_ui(
     mut this: ResMut<Sims>,
-    mut query: Query<&mut InstanceMaterialData>,
-    task_pool: Res<AsyncComputeTaskPool>,
+    mut actions: EventWriter<Action>,
     mut egui_context: ResMut<EguiContext>
 ) {
-    if this.active_sim > this.sims.len() {
-        this.set_sim(0);
+    // the pointer was let go, the next slider drag is a new gesture.
+    if !egui_context.ctx_mut().input().pointer.any_down() {
+        this.gesture = None;
     }
 
-    let mut bounds = this.bounds;
-    let mut active_sim = this.active_sim;
-
     egui::Window::new("Celluar!").show(egui_context.ctx_mut(), |ui| {
-        let old_bounds = bounds;
-        let old_active = active_sim;
-
         ui.label("Simulator:"); {
+            let mut active_sim = this.active_sim;
             egui::ComboBox::from_id_source("simulator")
                 .selected_text(&this.sims[active_sim].0)
                 .show_ui(ui, |ui| {
+                    let mut filter = std::mem::take(&mut this.sim_filter);
+                    ui.add(egui::TextEdit::singleline(&mut filter).hint_text("filter"));
                     for (i, (name, _)) in this.sims.iter().enumerate() {
-                        ui.selectable_value(&mut active_sim, i, name);
+                        if name.to_lowercase().contains(&filter.to_lowercase()) {
+                            ui.selectable_value(&mut active_sim, i, name);
+                        }
                     }
+                    this.sim_filter = filter;
                 });
 
-            if active_sim != old_active {
-                this.set_sim(active_sim);
-                bounds = this.bounds; // i don't like it.
+            let mut transfer = this.transfer_on_switch;
+            if ui.checkbox(&mut transfer, "keep cells when switching").changed() {
+                actions.send(Action::SetTransferOnSwitch(transfer));
             }
 
-            let update_dt = this.update_dt;
-            let rule = this.rule.take().unwrap();
-            let sim = &mut this.sims[active_sim].1;
+            if active_sim != this.active_sim {
+                actions.send(Action::SetSim(active_sim));
+            }
 
-            let cell_count = sim.cell_count();
+            let update_dt = this.update_dt;
+            let cell_count = this.cell_count();
             ui.label(format!("cells: {}", cell_count));
             ui.label(format!("update: {:.2?} per cell", update_dt / cell_count.max(1) as u32));
 
+            ui.horizontal(|ui| {
+                let mut paused = this.paused;
+                if ui.checkbox(&mut paused, "paused").changed() {
+                    actions.send(Action::SetPaused(paused));
+                }
+                if ui.add_enabled(this.paused, egui::Button::new("step")).clicked() {
+                    actions.send(Action::Step);
+                }
+                ui.label(format!("tick: {}", this.tick));
+            });
+            let mut ticks_per_frame = this.ticks_per_frame;
+            ui.add(egui::Slider::new(&mut ticks_per_frame, 1..=16)
+                .text("ticks per frame"));
+            if ticks_per_frame != this.ticks_per_frame {
+                actions.send(Action::SetTicksPerFrame(ticks_per_frame));
+            }
+            if this.capabilities().contains(SimCapabilities::FRACTIONAL_DECAY) {
+                let mut substeps = this.decay_substeps;
+                ui.add(egui::Slider::new(&mut substeps, 1..=16)
+                    .text("decay sub-steps"));
+                if substeps != this.decay_substeps {
+                    actions.send(Action::SetDecaySubsteps(substeps));
+                }
+            }
+            if this.capabilities().contains(SimCapabilities::ENERGY) {
+                let mut energy = this.energy;
+                energy.ui(ui);
+                if energy != this.energy {
+                    actions.send(Action::SetEnergy(energy));
+                }
+            }
+            if this.capabilities().contains(SimCapabilities::GROWTH_BIAS) {
+                let mut bias = this.growth_bias;
+                ui.horizontal(|ui| {
+                    ui.label("growth bias");
+                    ui.add(egui::DragValue::new(&mut bias.x).speed(0.01).clamp_range(-2.0..=2.0).prefix("x: "));
+                    ui.add(egui::DragValue::new(&mut bias.y).speed(0.01).clamp_range(-2.0..=2.0).prefix("y: "));
+                    ui.add(egui::DragValue::new(&mut bias.z).speed(0.01).clamp_range(-2.0..=2.0).prefix("z: "));
+                    if ui.small_button("up").clicked() {
+                        bias = Vec3::new(0.0, 0.25, 0.0);
+                    }
+                    if ui.small_button("off").clicked() {
+                        bias = Vec3::ZERO;
+                    }
+                });
+                if bias != this.growth_bias {
+                    actions.send(Action::SetGrowthBias(bias));
+                }
+            }
+            if this.capabilities().contains(SimCapabilities::DEFECTS) {
+                let max_neighbours = this.rule().neighbour_method.max_neighbours();
+                let mut defects = this.defects;
+                if defects.ui(ui, max_neighbours) {
+                    actions.send(Action::SetDefects(defects));
+                }
+            }
+
+            let active_sim = this.active_sim;
+            this.sims[active_sim].1.ui(ui);
             if ui.button("reset").clicked() {
-                sim.reset();
+                actions.send(Action::Reset);
             }
             if ui.button("spawn noise").clicked() {
-                sim.spawn_noise(&rule);
+                actions.send(Action::SpawnNoise);
             }
 
-            ui.add(egui::Slider::new(&mut bounds, 32..=128)
-                .text("bounding size"));
-            if bounds != old_bounds {
-                bounds = sim.set_bounds(bounds);
-                sim.spawn_noise(&rule);
-                this.renderer.as_mut().unwrap().set_bounds(bounds);
+            let sim = &this.sims[active_sim].1;
+            let mut bounds = this.bounds;
+            ui.add(egui::Slider::new(&mut bounds, 32..=sim.max_bounds())
+                .text("bounding size"))
+                .on_hover_text("resizing restarts the simulation");
+            if bounds != this.bounds {
+                actions.send(Action::SetBounds(bounds));
             }
-
-            this.rule = Some(rule);
         }
 
         ui.add_space(24.0);
 
         ui.label("Rules:"); {
+            let (mut color_method, mut color1, mut color2) = this.colors();
             egui::ComboBox::from_label("color method")
-                .selected_text(format!("{:?}", this.color_method))
+                .selected_text(format!("{:?}", color_method))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut color_method, ColorMethod::Single, "Single");
+                    ui.selectable_value(&mut color_method, ColorMethod::StateLerp, "State Lerp");
+                    ui.selectable_value(&mut color_method, ColorMethod::DistToCenter, "Distance to Center");
+                    ui.selectable_value(&mut color_method, ColorMethod::Neighbour, "Neighbors");
+                    ui.selectable_value(&mut color_method, ColorMethod::Metadata, "Metadata (age)");
+                    ui.selectable_value(&mut color_method, ColorMethod::Energy, "Energy");
+                });
+
+            let mut theme = this.theme;
+            egui::ComboBox::from_label("theme")
+                .selected_text(format!("{:?}", theme))
                 .show_ui(ui, |ui| {
-                    ui.selectable_value(&mut this.color_method, ColorMethod::Single, "Single");
-                    ui.selectable_value(&mut this.color_method, ColorMethod::StateLerp, "State Lerp");
-                    ui.selectable_value(&mut this.color_method, ColorMethod::DistToCenter, "Distance to Center");
-                    ui.selectable_value(&mut this.color_method, ColorMethod::Neighbour, "Neighbors");
+                    for theme_option in THEMES {
+                        ui.selectable_value(&mut theme, theme_option, format!("{:?}", theme_option));
+                    }
                 });
+            if theme != this.theme {
+                actions.send(Action::SetTheme(theme));
+            }
+            let mut colors_changed = color_method != this.color_method;
+            if this.theme == Theme::Custom {
+                colors_changed |= color_picker(ui, &mut color1);
+                colors_changed |= color_picker(ui, &mut color2);
+            }
+            if colors_changed {
+                actions.send(Action::SetColors(color_method, color1, color2));
+            }
+
+            let max_neighbours = this.rule().neighbour_method.max_neighbours();
+            let mut filter = this.neighbour_filter.clone();
+            ui.checkbox(&mut filter.enabled, "filter by neighbors");
+            if filter.enabled {
+                ui.add(egui::Slider::new(&mut filter.min, 0..=max_neighbours).text("min neighbors"));
+                ui.add(egui::Slider::new(&mut filter.max, 0..=max_neighbours).text("max neighbors"));
+                ui.checkbox(&mut filter.surface, "isosurface only");
+            }
+            if filter != this.neighbour_filter {
+                actions.send(Action::SetNeighbourFilter(filter));
+            }
 
-            color_picker(ui, &mut this.color1);
-            color_picker(ui, &mut this.color2);
+            let states = this.rule().states;
+            let mut materials = this.materials;
+            ui.checkbox(&mut materials.enabled, "materials by state");
+            if materials.enabled {
+                ui.add(egui::Slider::new(&mut materials.emissive_from, 0.0..=1.0)
+                    .text(format!("emissive from state {}", (materials.emissive_from * states as f32).ceil())));
+                ui.add(egui::Slider::new(&mut materials.translucent_to, 0.0..=1.0)
+                    .text(format!("translucent to state {}", (materials.translucent_to * states as f32).floor())));
+            }
+            if materials != this.materials {
+                actions.send(Action::SetMaterials(materials));
+            }
 
 
-            let mut rule = this.rule.take().unwrap();
+            let mut rule = this.rule().clone();
             let old_rule = rule.clone();
 
             egui::ComboBox::from_label("Neighbor method")
@@ -174,66 +842,212 @@ pub fn update(
                     ui.selectable_value(&mut rule.neighbour_method, NeighbourMethod::VonNeuman, "Von Neumann");
                 });
 
-            ui.add(egui::Slider::new(&mut rule.states, 1..=50)
-                .text("states"));
+            ui.add(egui::Slider::new(&mut rule.states, 1..=MAX_STATES)
+                .logarithmic(cfg!(feature = "wide_states"))
+                .text("states (decay length)"))
+                .on_hover_text(STATES_HELP);
+            let suggested = rule_analysis::suggested_states(&rule);
+            let clamped = rule.states.clamp(*suggested.start(), *suggested.end());
+            ui.horizontal(|ui| {
+                ui.weak(format!("suggested: {} to {}", suggested.start(), suggested.end()));
+                if clamped != rule.states && ui.small_button("use").clicked() {
+                    rule.states = clamped;
+                }
+                let mut clamp = this.clamp_states;
+                if ui.checkbox(&mut clamp, "clamp when the rule changes").changed() {
+                    actions.send(Action::SetClampStates(clamp));
+                }
+            });
+            let suggested_for = Some((rule.neighbour_method, rule.birth_rule.clone()));
+            if this.clamp_states && this.suggested_for != suggested_for {
+                rule.states = clamped;
+            }
+            this.suggested_for = suggested_for;
+            for note in rule.notes() {
+                ui.colored_label(egui::Color32::YELLOW, note);
+            }
+            for warning in rule_analysis::analyze(&rule) {
+                ui.colored_label(egui::Color32::YELLOW, warning.title)
+                    .on_hover_text(warning.explanation);
+            }
 
             // TODO: survival & birth rule.
 
             if rule != old_rule {
-                let sim = &mut this.sims[active_sim].1;
-                sim.reset();
-                sim.spawn_noise(&rule);
+                actions.send(Action::SetRule(rule));
             }
-
-            this.rule = Some(rule);
         }
 
         ui.add_space(24.0);
 
         ui.label("Examples:");
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut this.example_filter).hint_text("filter, eg. crystal VN"));
+            if ui.small_button("clear").clicked() {
+                this.example_filter.clear();
+            }
+        });
+        let mut all_tags: Vec<String> = this.examples.iter().flat_map(Example::all_tags).collect();
+        all_tags.sort_by_key(|tag| tag.to_lowercase());
+        all_tags.dedup_by_key(|tag| tag.to_lowercase());
+        ui.horizontal_wrapped(|ui| {
+            for tag in all_tags {
+                let selected = this.example_filter.split_whitespace().any(|word| word.eq_ignore_ascii_case(&tag));
+                if ui.selectable_label(selected, &tag).clicked() {
+                    this.example_filter = match selected {
+                        true  => this.example_filter.split_whitespace()
+                            .filter(|word| !word.eq_ignore_ascii_case(&tag))
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                        false => format!("{} {}", this.example_filter.trim(), tag).trim().to_string(),
+                    };
+                }
+            }
+        });
         for i in 0..this.examples.len() {
             let example = &this.examples[i];
-            if ui.button(&example.name).clicked() {
-                this.set_example(i);
+            if !example.matches(&this.example_filter) {
+                continue;
+            }
+            let mut hover = example.all_tags().join(", ");
+            if let Some(author) = &example.author {
+                hover = format!("by {}\n{}", author, hover);
+            }
+            if ui.button(&example.name).on_hover_text(hover).clicked() {
+                actions.send(Action::LoadExample(i));
             }
         }
+
+        ui.add_space(24.0);
+
+        ui.horizontal(|ui| {
+            if ui.add_enabled(this.can_undo(), egui::Button::new("undo")).clicked() {
+                actions.send(Action::Undo);
+            }
+            if ui.add_enabled(this.can_redo(), egui::Button::new("redo")).clicked() {
+                actions.send(Action::Redo);
+            }
+        });
     });
+}
+
+pub fn update(
+    mut commands: Commands,
+    mut this: ResMut<Sims>,
+    mut query: Query<(Entity, &mut InstanceMaterialData, Option<&GpuCells>), (Without<Background>, Without<DebugInstances>)>,
+    threading: Res<Threading>,
+    graphics: Res<GraphicsSettings>,
+    mut shader_settings: ResMut<CellShaderSettings>,
+    time: Res<Time>,
+) {
+    if this.active_sim > this.sims.len() {
+        this.set_sim(0);
+    }
 
+    let active_sim = this.active_sim;
     let rule = this.rule.take().unwrap();
     let mut renderer = this.renderer.take().unwrap();
 
-    let sim = &mut this.sims[active_sim].1;
+    let run_tick = !this.paused || this.step;
+    let ticks = if this.paused { 1 } else { this.ticks_per_frame };
+    let mut update_dt = this.update_dt;
+    // change lists only cover the last tick, and the neighbor, metadata and
+    // energy color methods (and the neighbor filter) need up to date values
+    // for every cell.
+    let full_render = this.full_render || ticks > 1
+        || this.color_method == ColorMethod::Neighbour
+        || this.color_method == ColorMethod::Metadata
+        || this.color_method == ColorMethod::Energy
+        || this.neighbour_filter.enabled
+        || this.keep_neighbors;
+
+    let spawned_noise = this.spawned_noise;
+    renderer.begin_frame(time.seconds_since_startup() as f32, spawned_noise);
+    if spawned_noise {
+        this.noise_frames += 1;
+    }
+
+    #[cfg(feature = "bounds_audit")]
+    utils::audit::set_backend(&this.sims[active_sim].0);
+
+    let decay_substeps = this.decay_substeps;
+    let energy = this.energy;
+    let growth_bias = this.growth_bias;
+    let defects = this.defects;
+    let (name, sim) = &mut this.sims[active_sim];
+
+    sim.set_cells_per_task(threading.cells_per_task);
+    sim.set_decay_substeps(decay_substeps);
+    sim.set_energy(energy);
+    sim.set_growth_bias(growth_bias);
+    sim.set_defects(defects);
+
+    if run_tick {
+        let _span = info_span!("sim_update", sim = name.as_str(), ticks = ticks as u64).entered();
+        let t0 = std::time::Instant::now();
+        for _ in 0..ticks {
+            sim.update(&rule, threading.pool());
+        }
+        update_dt = t0.elapsed() / ticks;
+        debug!("{}: {} ticks, {:?} per tick", name, ticks, update_dt);
+    }
 
     let t0 = std::time::Instant::now();
-    sim.update(&rule, &task_pool.0);
-    let update_dt = t0.elapsed();
-
-    sim.render(&mut renderer);
-
-    let instance_data = &mut query.iter_mut().next().unwrap().0;
-    instance_data.truncate(0);
-    for index in 0..renderer.cell_count() {
-        let value     = renderer.values[index];
-        let neighbors = renderer.neighbors[index];
-
-        if value != 0 {
-            let pos = utils::index_to_pos(index, bounds);
-            instance_data.push(InstanceData {
-                position: (pos - utils::center(bounds)).as_vec3(),
-                scale: 1.0,
-                color: this.color_method.color(
-                    this.color1, this.color2,
-                    rule.states,
-                    value, neighbors,
-                    utils::dist_to_center(pos, bounds),
-                ).into(),
-            });
+    {
+        let _span = info_span!("render", full = full_render).entered();
+        if full_render {
+            sim.render(&mut renderer);
+        }
+        else {
+            sim.render_delta(&mut renderer);
+        }
+    }
+    let render_dt = t0.elapsed();
+    let live_cells = sim.cell_count();
+    let filter = graphics.instance_filter(live_cells);
+
+    let coloring = InstanceColoring {
+        method: this.color_method,
+        palette: this.palette(),
+        states: rule.states,
+        max_neighbours: rule.neighbour_method.max_neighbours(),
+        defect_color: (defects.enabled() && defects.highlight).then(|| DEFECT_COLOR),
+        materials: this.materials.enabled.then(|| this.materials),
+    };
+    // decay blends two states, which the gpu doesn't.
+    let gpu_colors = (graphics.gpu_instances && decay_substeps <= 1
+        && matches!(filter, InstanceFilter::All) && !this.neighbour_filter.enabled)
+        .then(|| gpu_instances::gpu_colors(&coloring))
+        .flatten();
+    let (entity, mut instance_data, gpu_cells) = query.iter_mut().next().unwrap();
+    let t0 = std::time::Instant::now();
+    match gpu_colors {
+        Some(colors) => {
+            let _span = info_span!("gpu_cells").entered();
+            instance_data.0.clear();
+            commands.entity(entity).insert(GpuCells::new(&renderer, gpu_cells, colors, live_cells));
+        }
+        None => {
+            let _span = info_span!("extract_instances").entered();
+            renderer.extract_instances(&coloring, &filter, &this.neighbour_filter, threading.pool(), &mut instance_data.0);
+            if gpu_cells.is_some() {
+                commands.entity(entity).remove::<GpuCells>();
+            }
         }
     }
+    shader_settings.bounds = renderer.bounds;
+    let extract_dt = t0.elapsed();
 
-    this.bounds     = bounds;
-    this.active_sim = active_sim;
     this.update_dt  = update_dt;
+    this.render_dt  = render_dt;
+    this.extract_dt = extract_dt;
+    this.full_render = false;
+    this.spawned_noise = false;
+    this.keep_neighbors = false;
+    if run_tick {
+        this.tick += ticks as u64;
+        this.step  = false;
+    }
     this.renderer   = Some(renderer);
     this.rule       = Some(rule);
 }
@@ -244,19 +1058,26 @@ impl Plugin for SimsPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app
         .insert_resource(Sims::new())
-        .add_system(update);
+        .add_system(update.label(SimsUpdate).with_run_criteria(simulating));
+        if has_ui(app) {
+            app.add_system(update_ui.with_run_criteria(simulating).after(SimsUpdate));
+        }
     }
 }
 
 
-fn color_picker(ui: &mut egui::Ui, color: &mut Color) {
+// true when the color was edited.
+pub fn color_picker(ui: &mut egui::Ui, color: &mut Color) -> bool {
     let mut c = [
         (color.r() * 255.0) as u8,
         (color.g() * 255.0) as u8,
         (color.b() * 255.0) as u8,
     ];
-    egui::color_picker::color_edit_button_srgb(ui, &mut c);
-    color.set_r(c[0] as f32 / 255.0);
-    color.set_g(c[1] as f32 / 255.0);
-    color.set_b(c[2] as f32 / 255.0);
+    let changed = egui::color_picker::color_edit_button_srgb(ui, &mut c).changed();
+    if changed {
+        color.set_r(c[0] as f32 / 255.0);
+        color.set_g(c[1] as f32 / 255.0);
+        color.set_b(c[2] as f32 / 255.0);
+    }
+    changed
 }
@@ -5,14 +5,17 @@ mod multi_threaded;
 pub use multi_threaded::*;
 
 
+use crate::{neighbours::NeighbourCount, rule::CellValue};
+
+
 #[derive(Debug)]
 struct CellState {
-    value: u8,
-    neighbours: u8,
+    value: CellValue,
+    neighbours: NeighbourCount,
 }
 
 impl CellState {
-    pub fn new(value: u8, neighbours: u8) -> Self {
+    pub fn new(value: CellValue, neighbours: NeighbourCount) -> Self {
         CellState {
             value,
             neighbours,
@@ -9,7 +9,8 @@ use std::sync::{Arc, RwLock};
 
 use crate::{
     cell_renderer::{CellRenderer},
-    rule::Rule,
+    neighbours::NeighbourCount,
+    rule::{CellValue, Rule},
     utils,
 };
 
@@ -21,7 +22,7 @@ pub struct CellsMultithreaded {
     bounding_size: i32,
 
     // cached data used for calculating state
-    neighbours: Arc<RwLock<HashMap<IVec3, u8>>>,
+    neighbours: Arc<RwLock<HashMap<IVec3, NeighbourCount>>>,
     changes: HashMap<IVec3, StateChange>,
     change_mask: HashMap<IVec3, bool>,
 
@@ -29,13 +30,16 @@ pub struct CellsMultithreaded {
     neighbour_results_cache: Vec<Arc<Mutex<Vec<IVec3>>>>,
 
     position_thread_cache: Vec<Arc<Mutex<Vec<IVec3>>>>,
+
+    // 0 splits the cells evenly over the threads.
+    cells_per_task: usize,
 }
 
 pub enum StateChange {
     Decay,
     Spawn {
         // metadata
-        neighbours: u8,
+        neighbours: NeighbourCount,
     },
 }
 
@@ -50,6 +54,14 @@ impl CellsMultithreaded {
             position_thread_cache: Vec::new(),
             change_results_cache: Vec::new(),
             neighbour_results_cache: Vec::new(),
+            cells_per_task: 0,
+        }
+    }
+
+    fn job_count(&self, cell_count: usize, task_pool: &TaskPool) -> usize {
+        match self.cells_per_task {
+            0 => task_pool.thread_num(),
+            n => ((cell_count + n - 1) / n).max(1),
         }
     }
 
@@ -120,8 +132,8 @@ impl CellsMultithreaded {
     pub fn calculate_neighbours(&mut self, rule: &Rule, task_pool: &TaskPool)
         -> Vec<Task<()>>
     {
+        let job_count = self.job_count(self.states.read().unwrap().len(), task_pool);
         let states = self.states.read().unwrap();
-        let job_count = task_pool.thread_num();
         let chunk_size = ((states.len() as f32 / job_count as f32).ceil() as usize).max(1);
         // i dynamically size the position_thread_cache in case the async_compute_task_pool threads increases
         while self.position_thread_cache.len() < job_count {
@@ -177,7 +189,7 @@ impl CellsMultithreaded {
     pub fn calculate_changes(&mut self, rule: &Rule, task_pool: &TaskPool)
         -> Vec<Task<()>>
     {
-        let job_count = task_pool.thread_num();
+        let job_count = self.job_count(self.change_mask.len(), task_pool);
         let chunk_size =
             ((self.change_mask.len() as f32 / job_count as f32).ceil() as usize).max(1);
         // i dynamically size the position_thread_cache in case the async_compute_task_pool threads increases
@@ -221,7 +233,7 @@ impl CellsMultithreaded {
                     };
                     match states.get(cell_pos) {
                         Some(cell) => {
-                            if !(rule_survival_rule.in_range_incorrect(neighbours)
+                            if !(rule_survival_rule.in_range(neighbours)
                                 && cell.value == rule_start_state_value)
                             {
                                 change_results_cache.push((*cell_pos, StateChange::Decay));
@@ -229,7 +241,7 @@ impl CellsMultithreaded {
                         }
                         None => {
                             // check if should spawn
-                            if rule_birth_rule.in_range_incorrect(neighbours) {
+                            if rule_birth_rule.in_range(neighbours) {
                                 if utils::is_in_bounds(*cell_pos, rule_bounding) {
                                     change_results_cache
                                         .push((*cell_pos, StateChange::Spawn { neighbours }));
@@ -255,7 +267,7 @@ impl CellsMultithreaded {
                     // DECAY BY 1 value
                     let value = cell.value as i32 - 1;
                     let value = i32::min(value, rule.states as i32);
-                    cell.value = value as u8;
+                    cell.value = value as CellValue;
                 }
                 StateChange::Spawn { neighbours } => {
                     // TODODDKJ
@@ -287,9 +299,18 @@ impl crate::cells::Sim for CellsMultithreaded {
         self.tick(&rule, &task_pool);
     }
 
+    fn set_cells_per_task(&mut self, cells_per_task: usize) {
+        self.cells_per_task = cells_per_task;
+    }
+
     fn render(&self, renderer: &mut CellRenderer) {
-        renderer.clear();
-        for cell in self.states.read().unwrap().iter() {
+        let states = self.states.read().unwrap();
+        // only clear stale cells, so the renderer can tell which cells are new.
+        let stale: Vec<_> = renderer.live_cells().filter(|pos| !states.contains_key(pos)).collect();
+        for pos in stale {
+            renderer.set_pos(pos, 0, 0);
+        }
+        for cell in states.iter() {
             renderer.set_pos(*cell.0, cell.1.value, cell.1.neighbours);
         }
     }
@@ -301,6 +322,23 @@ impl crate::cells::Sim for CellsMultithreaded {
         });
     }
 
+    fn get_cell(&self, pos: IVec3) -> CellValue {
+        let pos = utils::wrap_any(pos, self.bounding_size);
+        self.states.read().unwrap().get(&pos).map(|c| c.value).unwrap_or(0)
+    }
+
+    fn set_cell(&mut self, _rule: &Rule, pos: IVec3, value: CellValue) {
+        // the map takes any key, but the renderer only has the grid.
+        let pos = utils::wrap_any(pos, self.bounding_size);
+        let mut states = self.states.write().unwrap();
+        if value == 0 {
+            states.remove(&pos);
+        }
+        else {
+            states.insert(pos, CellState::new(value, 0));
+        }
+    }
+
     fn cell_count(&self) -> usize {
         self.states.read().unwrap().len()
     }
@@ -316,4 +354,8 @@ impl crate::cells::Sim for CellsMultithreaded {
         self.bounding_size = new_bounds;
         new_bounds
     }
+
+    fn capabilities(&self) -> crate::cells::SimCapabilities {
+        crate::cells::SimCapabilities::SET_CELL
+    }
 }
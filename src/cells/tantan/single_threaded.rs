@@ -7,7 +7,8 @@ use bevy::{
 
 use crate::{
     cell_renderer::{CellRenderer},
-    rule::Rule,
+    neighbours::NeighbourCount,
+    rule::{CellValue, Rule},
     utils,
 };
 
@@ -17,9 +18,9 @@ pub struct CellsSinglethreaded {
     states: HashMap<IVec3, CellState>,
     bounding_size: i32,
     // cached datta used for calculating state
-    neighbours: HashMap<IVec3, u8>,
+    neighbours: HashMap<IVec3, NeighbourCount>,
     changes: HashMap<IVec3, i32>,
-    spawn: Vec<(IVec3, u8)>, // neighbours
+    spawn: Vec<(IVec3, NeighbourCount)>, // neighbours
 }
 
 impl CellsSinglethreaded {
@@ -68,7 +69,7 @@ impl CellsSinglethreaded {
                     };
                     match self.states.get(&cell_pos) {
                         Some(cell) => {
-                            if !(rule.survival_rule.in_range_incorrect(neighbours)
+                            if !(rule.survival_rule.in_range(neighbours)
                                 && cell.value == rule.states)
                             {
                                 self.changes.insert(cell_pos, -1i32);
@@ -76,7 +77,7 @@ impl CellsSinglethreaded {
                         }
                         None => {
                             // check if should spawn
-                            if rule.birth_rule.in_range_incorrect(neighbours) {
+                            if rule.birth_rule.in_range(neighbours) {
                                 // cell_pos is in bounds, because we iterate over the bounds.
                                 self.spawn.push((cell_pos, neighbours));
                             }
@@ -103,7 +104,7 @@ impl CellsSinglethreaded {
             let mut cell = self.states.get_mut(changes.0).unwrap();
             let value = cell.value as i32 + changes.1;
             let value = i32::min(value, rule.states as i32);
-            cell.value = value as u8;
+            cell.value = value as CellValue;
         }
         // remove dead
         self.states.retain(|_, c| c.value > 0);
@@ -122,7 +123,11 @@ impl crate::cells::Sim for CellsSinglethreaded {
     }
 
     fn render(&self, renderer: &mut CellRenderer) {
-        renderer.clear();
+        // only clear stale cells, so the renderer can tell which cells are new.
+        let stale: Vec<_> = renderer.live_cells().filter(|pos| !self.states.contains_key(pos)).collect();
+        for pos in stale {
+            renderer.set_pos(pos, 0, 0);
+        }
         for cell in self.states.iter() {
             renderer.set_pos(*cell.0, cell.1.value, cell.1.neighbours);
         }
@@ -134,6 +139,22 @@ impl crate::cells::Sim for CellsSinglethreaded {
         });
     }
 
+    fn get_cell(&self, pos: IVec3) -> CellValue {
+        let pos = utils::wrap_any(pos, self.bounding_size);
+        self.states.get(&pos).map(|c| c.value).unwrap_or(0)
+    }
+
+    fn set_cell(&mut self, _rule: &Rule, pos: IVec3, value: CellValue) {
+        // the map takes any key, but the renderer only has the grid.
+        let pos = utils::wrap_any(pos, self.bounding_size);
+        if value == 0 {
+            self.states.remove(&pos);
+        }
+        else {
+            self.states.insert(pos, CellState::new(value, 0));
+        }
+    }
+
     fn cell_count(&self) -> usize {
         self.states.len()
     }
@@ -149,5 +170,9 @@ impl crate::cells::Sim for CellsSinglethreaded {
         self.bounding_size = new_bounds;
         new_bounds
     }
+
+    fn capabilities(&self) -> crate::cells::SimCapabilities {
+        crate::cells::SimCapabilities::SET_CELL
+    }
 }
 
@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{action::Action, app_state::simulating, cells::Sims};
+
+const MAX_SHOWN: usize = 12;
+
+
+// ctrl+p opens a search over every `Action`. arrows pick, enter runs,
+// escape closes.
+pub struct CommandPalette {
+    open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl Default for CommandPalette {
+    fn default() -> Self {
+        CommandPalette {
+            open: false,
+            query: String::new(),
+            selected: 0,
+        }
+    }
+}
+
+
+// subsequence match, case insensitive. consecutive letters and letters at
+// the start of a word score higher, a shorter label breaks ties.
+pub fn fuzzy_score(query: &str, label: &str) -> Option<i32> {
+    let label: Vec<char> = label.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut last_match: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let index = next + label[next..].iter().position(|l| *l == c)?;
+        if last_match.map(|last| last + 1) == Some(index) {
+            score += 5;
+        }
+        if index == 0 || !label[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score += 1;
+        last_match = Some(index);
+        next = index + 1;
+    }
+    Some(score * 100 - label.len() as i32)
+}
+
+fn matches(query: &str, sims: &Sims) -> Vec<(Action, String)> {
+    let mut scored: Vec<_> = Action::available(sims).into_iter()
+        .filter_map(|(action, label)| fuzzy_score(query, &label).map(|score| (score, action, label)))
+        .collect();
+    // stable, so equal scores keep the order of `available`.
+    scored.sort_by_key(|(score, _, _)| -*score);
+    scored.into_iter().map(|(_, action, label)| (action, label)).collect()
+}
+
+
+pub fn update(
+    mut this: ResMut<CommandPalette>,
+    sims: Res<Sims>,
+    keys: Res<Input<KeyCode>>,
+    mut actions: EventWriter<Action>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let ctrl = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
+    if ctrl && keys.just_pressed(KeyCode::P) {
+        this.open = !this.open;
+        this.query.clear();
+        this.selected = 0;
+    }
+    if !this.open {
+        return;
+    }
+
+    let found = matches(&this.query, &sims);
+    let mut run = None;
+    let mut close = false;
+    egui::Area::new("command palette")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 48.0))
+        .order(egui::Order::Foreground)
+        .show(egui_context.ctx_mut(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_width(360.0);
+                let response = ui.add(egui::TextEdit::singleline(&mut this.query)
+                    .hint_text("type a command")
+                    .desired_width(f32::INFINITY));
+                response.request_focus();
+                if response.changed() {
+                    this.selected = 0;
+                }
+
+                if ui.input().key_pressed(egui::Key::ArrowDown) {
+                    this.selected = (this.selected + 1).min(found.len().min(MAX_SHOWN).saturating_sub(1));
+                }
+                if ui.input().key_pressed(egui::Key::ArrowUp) {
+                    this.selected = this.selected.saturating_sub(1);
+                }
+                let enter = ui.input().key_pressed(egui::Key::Enter);
+                close = ui.input().key_pressed(egui::Key::Escape);
+
+                for (i, (action, label)) in found.iter().take(MAX_SHOWN).enumerate() {
+                    if ui.selectable_label(i == this.selected, label).clicked() {
+                        run = Some(action.clone());
+                    }
+                }
+                if found.is_empty() {
+                    ui.weak("no matching command");
+                }
+                if enter {
+                    run = found.get(this.selected).map(|(action, _)| action.clone());
+                }
+            });
+        });
+
+    if let Some(action) = run {
+        actions.send(action);
+        close = true;
+    }
+    if close {
+        this.open = false;
+    }
+}
+
+
+pub struct CommandPalettePlugin;
+impl Plugin for CommandPalettePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<CommandPalette>()
+        .add_system(update.with_run_criteria(simulating));
+    }
+}
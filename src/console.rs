@@ -0,0 +1,219 @@
+use bevy::{math::ivec3, prelude::*};
+use bevy_egui::{egui, EguiContext};
+
+use crate::{action::Action, app_state::simulating, cells::{Sims, SimCapabilities}, neighbours::NeighbourCount, rule::CellValue, utils};
+
+const HELP: &str = "\
+sim.get_cell(x, y, z)
+sim.set_cell(x, y, z, value)
+sim.reset()
+sim.spawn_noise()
+sim.pause() / sim.resume()
+stats.population()
+stats.tick()
+rule
+rule.birth.add(n) / rule.birth.remove(n)
+rule.survival.add(n) / rule.survival.remove(n)
+rule.states(n)";
+
+
+pub struct Console {
+    input: String,
+    output: Vec<String>,
+    history: Vec<String>,
+    history_index: usize,
+}
+
+impl Console {
+    pub fn new() -> Console {
+        Console {
+            input: String::new(),
+            output: vec!["type `help` for a list of commands.".into()],
+            history: vec![],
+            history_index: 0,
+        }
+    }
+
+    fn submit(&mut self, sims: &Sims, actions: &mut EventWriter<Action>) {
+        let line = std::mem::take(&mut self.input);
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+
+        self.output.push(format!("> {}", line));
+        match execute(line, sims, actions) {
+            Ok(result) => {
+                if !result.is_empty() {
+                    self.output.push(result);
+                }
+            }
+            Err(error) => self.output.push(format!("error: {}", error)),
+        }
+
+        if self.history.last().map(|l| l.as_str()) != Some(line) {
+            self.history.push(line.to_string());
+        }
+        self.history_index = self.history.len();
+    }
+
+    fn browse_history(&mut self, up: bool) {
+        if up && self.history_index > 0 {
+            self.history_index -= 1;
+        }
+        if !up && self.history_index < self.history.len() {
+            self.history_index += 1;
+        }
+        self.input = self.history.get(self.history_index).cloned().unwrap_or_default();
+    }
+}
+
+
+// commands look like `path.to.function(arg, arg)`, all arguments are integers.
+fn parse(line: &str) -> Result<(&str, Vec<i32>), String> {
+    let (path, args) = match line.find('(') {
+        Some(open) => {
+            if !line.ends_with(')') {
+                return Err("missing `)`".into());
+            }
+            (&line[..open], &line[open + 1..line.len() - 1])
+        }
+        None => (line, ""),
+    };
+
+    let args = args.split(',')
+        .map(str::trim)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| arg.parse::<i32>().map_err(|_| format!("`{}` is not a number", arg)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((path.trim(), args))
+}
+
+fn to_value(value: i32) -> Result<CellValue, String> {
+    CellValue::try_from(value).map_err(|_| format!("{} is out of range", value))
+}
+
+fn to_count(value: i32) -> Result<NeighbourCount, String> {
+    NeighbourCount::try_from(value).map_err(|_| format!("{} is out of range", value))
+}
+
+// the commands that change something send an `Action`, so they apply on
+// the next frame. rule commands answer with the rule as it will be.
+pub fn execute(line: &str, sims: &Sims, actions: &mut EventWriter<Action>) -> Result<String, String> {
+    let (path, args) = parse(line)?;
+    let bounds = sims.bounds();
+
+    if path.ends_with("_cell") && !sims.capabilities().contains(SimCapabilities::SET_CELL) {
+        return Err(format!("`{}` doesn't support cell access", sims.active_name()));
+    }
+
+    match (path, args.as_slice()) {
+        ("help", []) => Ok(HELP.into()),
+
+        ("sim.get_cell", [x, y, z]) => {
+            let pos = ivec3(*x, *y, *z);
+            if pos.min_element() < 0 || !utils::is_in_bounds(pos, bounds) {
+                return Err(format!("{} is outside the bounds ({})", pos, bounds));
+            }
+            Ok(sims.get_cell(pos).to_string())
+        }
+        ("sim.set_cell", [x, y, z, value]) => {
+            let pos = ivec3(*x, *y, *z);
+            if pos.min_element() < 0 || !utils::is_in_bounds(pos, bounds) {
+                return Err(format!("{} is outside the bounds ({})", pos, bounds));
+            }
+            actions.send(Action::SetCell(pos, to_value(*value)?));
+            Ok(String::new())
+        }
+        ("sim.reset", []) => {
+            actions.send(Action::Reset);
+            Ok(String::new())
+        }
+        ("sim.spawn_noise", []) => {
+            actions.send(Action::SpawnNoise);
+            Ok(String::new())
+        }
+        ("sim.pause", []) => {
+            actions.send(Action::SetPaused(true));
+            Ok(String::new())
+        }
+        ("sim.resume", []) => {
+            actions.send(Action::SetPaused(false));
+            Ok(String::new())
+        }
+
+        ("stats.population", []) => Ok(sims.cell_count().to_string()),
+        ("stats.tick", []) => Ok(sims.tick().to_string()),
+
+        ("rule", []) => Ok(sims.rule().to_string()),
+        ("rule.birth.add", [n]) | ("rule.birth.remove", [n])
+        | ("rule.survival.add", [n]) | ("rule.survival.remove", [n]) => {
+            let n = to_count(*n)?;
+            let mut rule = sims.rule().clone();
+            let value = match path.starts_with("rule.birth") {
+                true  => &mut rule.birth_rule,
+                false => &mut rule.survival_rule,
+            };
+            value.set(n, path.ends_with(".add"));
+            let rule = rule.sanitized();
+            let result = rule.to_string();
+            actions.send(Action::SetRule(rule));
+            Ok(result)
+        }
+        ("rule.states", [n]) => {
+            let mut rule = sims.rule().clone();
+            rule.states = to_value(*n)?.max(1);
+            let rule = rule.sanitized();
+            let result = rule.to_string();
+            actions.send(Action::SetRule(rule));
+            Ok(result)
+        }
+
+        _ => Err(format!("unknown command `{}` with {} argument(s)", path, args.len())),
+    }
+}
+
+
+pub fn update(
+    mut this: ResMut<Console>,
+    sims: Res<Sims>,
+    mut actions: EventWriter<Action>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Console").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+            for line in this.output.iter() {
+                ui.monospace(line);
+            }
+        });
+
+        let response = ui.add(egui::TextEdit::singleline(&mut this.input)
+            .code_editor()
+            .desired_width(f32::INFINITY));
+
+        if response.has_focus() {
+            if ui.input().key_pressed(egui::Key::ArrowUp) {
+                this.browse_history(true);
+            }
+            if ui.input().key_pressed(egui::Key::ArrowDown) {
+                this.browse_history(false);
+            }
+        }
+
+        if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+            this.submit(&sims, &mut actions);
+            response.request_focus();
+        }
+    });
+}
+
+
+pub struct ConsolePlugin;
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(Console::new())
+        .add_system(update.with_run_criteria(simulating));
+    }
+}
@@ -0,0 +1,103 @@
+// `--controls-window` moves the egui windows into a second os window, so
+// the primary window only shows the cells, eg. for capture or a projector.
+// the overlay captions stay on the primary window.
+//
+// the second window gets a 2d camera that only clears it, egui draws on top.
+
+use bevy::{
+    core_pipeline::{draw_2d_graph, node, Transparent2d},
+    prelude::*,
+    render::{
+        camera::{ActiveCameras, ExtractedCameraNames},
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotValue},
+        render_phase::RenderPhase,
+        renderer::RenderContext,
+        RenderApp, RenderStage,
+    },
+    window::{CreateWindow, WindowId},
+};
+use bevy_egui::{EguiContext, RenderGraphConfig};
+
+const CAMERA: &str = "controls_camera";
+const PASS_DRIVER: &str = "controls_pass_driver";
+const EGUI_PASS: &str = "controls_egui_pass";
+
+
+pub struct ControlsWindow {
+    pub window: WindowId,
+}
+
+
+fn setup(
+    this: Res<ControlsWindow>,
+    mut create_window: EventWriter<CreateWindow>,
+    mut active_cameras: ResMut<ActiveCameras>,
+    mut egui_context: ResMut<EguiContext>,
+    mut commands: Commands,
+) {
+    create_window.send(CreateWindow {
+        id: this.window,
+        descriptor: WindowDescriptor {
+            width: 420.0,
+            height: 900.0,
+            title: "celluar automata - controls".into(),
+            ..Default::default()
+        },
+    });
+
+    let mut camera = OrthographicCameraBundle::new_2d();
+    camera.camera.window = this.window;
+    camera.camera.name = Some(CAMERA.into());
+    commands.spawn_bundle(camera);
+    active_cameras.add(CAMERA);
+
+    egui_context.set_default_window(this.window);
+}
+
+fn extract_camera_phases(
+    mut commands: Commands,
+    active_cameras: Res<ActiveCameras>,
+) {
+    if let Some(entity) = active_cameras.get(CAMERA).and_then(|camera| camera.entity) {
+        commands.get_or_spawn(entity).insert(RenderPhase::<Transparent2d>::default());
+    }
+}
+
+
+struct ControlsPassDriver;
+
+impl Node for ControlsPassDriver {
+    fn run(&self,
+        graph: &mut RenderGraphContext,
+        _render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let cameras = world.get_resource::<ExtractedCameraNames>().unwrap();
+        if let Some(camera) = cameras.entities.get(CAMERA) {
+            graph.run_sub_graph(draw_2d_graph::NAME, vec![SlotValue::Entity(*camera)])?;
+        }
+        Ok(())
+    }
+}
+
+
+pub struct ControlsWindowPlugin;
+impl Plugin for ControlsWindowPlugin {
+    fn build(&self, app: &mut App) {
+        if !std::env::args().any(|arg| arg == "--controls-window") {
+            return;
+        }
+        let window = WindowId::new();
+        app
+        .insert_resource(ControlsWindow { window })
+        .add_startup_system(setup);
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.add_system_to_stage(RenderStage::Extract, extract_camera_phases);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node(PASS_DRIVER, ControlsPassDriver);
+        graph.add_node_edge(node::MAIN_PASS_DEPENDENCIES, PASS_DRIVER).unwrap();
+        bevy_egui::setup_pipeline(&mut *graph, RenderGraphConfig { window_id: window, egui_pass: EGUI_PASS });
+        graph.add_node_edge(PASS_DRIVER, EGUI_PASS).unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{app_state::simulating, cells::{Sims, SimsUpdate}};
+
+
+// cycles through the examples for running unattended.
+// F5 toggles demo mode, the arrow keys skip back and forth.
+pub struct Demo {
+    pub enabled: bool,
+    pub interval: f32,
+    pub show_label: bool,
+    index: usize,
+    elapsed: f32,
+}
+
+impl Demo {
+    pub fn new() -> Demo {
+        Demo {
+            enabled: false,
+            interval: 20.0,
+            show_label: true,
+            index: 0,
+            elapsed: 0.0,
+        }
+    }
+
+    fn show(&mut self, sims: &mut Sims, index: usize) {
+        if sims.examples().is_empty() {
+            return;
+        }
+        self.index = index % sims.examples().len();
+        self.elapsed = 0.0;
+        sims.set_example(self.index);
+    }
+
+    fn step(&mut self, sims: &mut Sims, forward: bool) {
+        let count = sims.examples().len().max(1);
+        let index = match forward {
+            true  => self.index + 1,
+            false => self.index + count - 1,
+        };
+        self.show(sims, index);
+    }
+}
+
+
+pub fn update(
+    mut this: ResMut<Demo>,
+    mut sims: ResMut<Sims>,
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    // don't steal keys from text fields.
+    if !egui_context.ctx_mut().wants_keyboard_input() {
+        if keys.just_pressed(KeyCode::F5) {
+            this.enabled = !this.enabled;
+            if this.enabled {
+                let index = this.index;
+                this.show(&mut sims, index);
+            }
+        }
+        if this.enabled && keys.just_pressed(KeyCode::Right) {
+            this.step(&mut sims, true);
+        }
+        if this.enabled && keys.just_pressed(KeyCode::Left) {
+            this.step(&mut sims, false);
+        }
+    }
+
+    if this.enabled {
+        this.elapsed += time.delta_seconds();
+        if this.elapsed >= this.interval {
+            this.step(&mut sims, true);
+        }
+    }
+
+    egui::Window::new("Demo").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        let was_enabled = this.enabled;
+        ui.checkbox(&mut this.enabled, "demo mode (F5)");
+        if this.enabled && !was_enabled {
+            let index = this.index;
+            this.show(&mut sims, index);
+        }
+        ui.add(egui::Slider::new(&mut this.interval, 2.0..=300.0)
+            .logarithmic(true)
+            .text("seconds per example"));
+        ui.checkbox(&mut this.show_label, "show rule label");
+        ui.weak("left/right arrows skip while running.");
+    });
+
+    if this.enabled && this.show_label {
+        if let Some(example) = sims.examples().get(this.index) {
+            let rule = sims.rule().to_string();
+            egui::Area::new("demo label")
+                .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(16.0, -16.0))
+                .interactable(false)
+                .show(egui_context.ctx_mut(), |ui| {
+                    ui.label(egui::RichText::new(&example.name).size(32.0).strong());
+                    ui.label(egui::RichText::new(rule).size(20.0).monospace());
+                });
+        }
+    }
+}
+
+
+pub struct DemoPlugin;
+impl Plugin for DemoPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(Demo::new())
+        .add_system(update.before(SimsUpdate).with_run_criteria(simulating));
+    }
+}
@@ -0,0 +1,188 @@
+// the "copy diagnostic bundle" button: everything a bug report about
+// performance or rendering needs, as markdown for a github issue.
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use bevy::{
+    prelude::*,
+    render::{options::WgpuOptions, renderer::RenderDevice},
+};
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    app_state::simulating,
+    cells::Sims,
+    profiler::SharedRenderTimes,
+    seeding::Seeding,
+    threading::Threading,
+};
+
+// the bundle has the last this many.
+const LOG_LINES: usize = 50;
+
+static RECENT_LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+// bevy 0.6's log plugin can't take an extra tracing layer, so the app's own
+// messages go through `logged!`, which logs them and keeps the recent ones.
+pub fn record(level: &str, message: &str) {
+    if let Ok(mut log) = RECENT_LOG.lock() {
+        if log.len() == LOG_LINES {
+            log.pop_front();
+        }
+        log.push_back(format!("{} {}", level.to_uppercase(), message));
+    }
+}
+
+pub fn recent_log() -> Vec<String> {
+    RECENT_LOG.lock().map(|log| log.iter().cloned().collect()).unwrap_or_default()
+}
+
+// eg: `logged!(warn, "network: {}", error)`.
+#[macro_export]
+macro_rules! logged {
+    ($level:ident, $($arg:tt)*) => {{
+        let message = format!($($arg)*);
+        bevy::log::$level!("{}", message);
+        $crate::diagnostics::record(stringify!($level), &message);
+    }};
+}
+
+
+fn features() -> Vec<&'static str> {
+    let mut features = vec![];
+    let mut add = |enabled: bool, name| if enabled { features.push(name) };
+    add(cfg!(feature = "post_process"), "post_process");
+    add(cfg!(feature = "rule_packs"), "rule_packs");
+    add(cfg!(feature = "wide_states"), "wide_states");
+    add(cfg!(feature = "bounds_audit"), "bounds_audit");
+    add(cfg!(feature = "debug-checks"), "debug-checks");
+    add(cfg!(feature = "trace"), "trace");
+    add(cfg!(feature = "gpu_timing"), "gpu_timing");
+    features
+}
+
+struct Bundle<'a> {
+    sims: &'a Sims,
+    seeding: &'a Seeding,
+    threading: &'a Threading,
+    wgpu_options: Option<&'a WgpuOptions>,
+    render_device: Option<&'a RenderDevice>,
+    render_times: &'a SharedRenderTimes,
+    frame: std::time::Duration,
+}
+
+impl Bundle<'_> {
+    fn text(&self) -> String {
+        let mut lines = vec![];
+        let mut line = |text: String| lines.push(text);
+
+        line("### Environment".into());
+        line(format!("- version: {}{}", env!("CARGO_PKG_VERSION"),
+            if cfg!(debug_assertions) { " (debug build)" } else { "" }));
+        line(format!("- features: {}", features().join(", ")));
+        line(format!("- os: {} {}", std::env::consts::OS, std::env::consts::ARCH));
+        line(format!("- threads: {}{}", self.threading.threads,
+            if self.threading.single_thread { " (single thread)" } else { "" }));
+        // bevy 0.6 only logs the adapter's name at startup, it isn't kept.
+        line("- adapter: (paste the `AdapterInfo` line from the console)".into());
+        if let Some(options) = self.wgpu_options {
+            line(format!("- wgpu: backends {:?}, power preference {:?}", options.backends, options.power_preference));
+        }
+        if let Some(device) = self.render_device {
+            line(format!("- device features: {:?}", device.features()));
+            line(format!("- max texture size: {}", device.limits().max_texture_dimension_2d));
+        }
+
+        line(String::new());
+        line("### Simulation".into());
+        line(format!("- backend: {}", self.sims.active_name()));
+        line(format!("- rule: `{}`", self.sims.rule()));
+        line(format!("- bounds: {}", self.sims.bounds()));
+        line(format!("- seed: {} ({:?})", self.seeding.seed, self.seeding.generator));
+        line(format!("- tick {}, {} cells", self.sims.tick(), self.sims.cell_count()));
+        line(format!("- {} ticks per frame{}", self.sims.ticks_per_frame(),
+            if self.sims.is_paused() { ", paused" } else { "" }));
+
+        let (update, render, extract) = self.sims.frame_times();
+        let times = self.render_times.get();
+        line(String::new());
+        line("### Timings".into());
+        line(format!("- frame: {:.2?}", self.frame));
+        line(format!("- sim update (per tick): {:.2?}", update));
+        line(format!("- render: {:.2?}", render));
+        line(format!("- extract instances: {:.2?}", extract));
+        line(format!("- instance upload: {:.2?} ({} KiB)", times.upload, times.upload_bytes >> 10));
+        if let Some(pass) = times.gpu_pass {
+            line(format!("- gpu main pass: {:.2?}", pass));
+        }
+
+        line(String::new());
+        line("### Recent log".into());
+        line("```text".into());
+        lines.extend(recent_log());
+        lines.push("```".into());
+        lines.join("\n")
+    }
+}
+
+
+#[derive(Default)]
+struct Diagnostics {
+    preview: Option<String>,
+    status: Option<String>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_ui(
+    mut this: ResMut<Diagnostics>,
+    sims: Res<Sims>,
+    seeding: Res<Seeding>,
+    threading: Res<Threading>,
+    wgpu_options: Option<Res<WgpuOptions>>,
+    render_device: Option<Res<RenderDevice>>,
+    render_times: Res<SharedRenderTimes>,
+    time: Res<Time>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Diagnostics").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.label("for bug reports: paste it into the github issue.");
+        let bundle = || Bundle {
+            sims: &sims,
+            seeding: &seeding,
+            threading: &threading,
+            wgpu_options: wgpu_options.as_deref(),
+            render_device: render_device.as_deref(),
+            render_times: &render_times,
+            frame: time.delta(),
+        }.text();
+
+        ui.horizontal(|ui| {
+            if ui.button("copy diagnostic bundle").clicked() {
+                let text = bundle();
+                this.status = Some(format!("copied {} lines.", text.lines().count()));
+                ui.output().copied_text = text;
+            }
+            if ui.button("preview").clicked() {
+                this.preview = Some(bundle());
+            }
+        });
+        if let Some(status) = &this.status {
+            ui.label(status);
+        }
+        if let Some(preview) = &mut this.preview {
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                ui.add(egui::TextEdit::multiline(preview).code_editor().desired_width(f32::INFINITY));
+            });
+        }
+    });
+}
+
+
+pub struct DiagnosticsPlugin;
+impl Plugin for DiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<Diagnostics>()
+        .add_system(update_ui.with_run_criteria(simulating));
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use bevy::prelude::*;
+
+use crate::{
+    app_state::simulating,
+    cells::{Example, Sims},
+    logged,
+    neighbours::NeighbourMethod,
+    rule::{ColorMethod, Rule, Value},
+    theme::Theme,
+};
+
+pub const EXAMPLE_DIR: &str = "examples";
+
+// seconds between directory scans.
+const SCAN_INTERVAL: f64 = 1.0;
+
+
+// used when the example directory is missing or empty, the sims need at
+// least one example to start with.
+pub fn fallback_example() -> Example {
+    Example {
+        name: "builder".into(),
+        rule: Rule {
+            survival_rule: Value::new(&[2, 6, 9]),
+            birth_rule: Value::new(&[4, 6, 8, 9, 10]),
+            states: 10,
+            neighbour_method: NeighbourMethod::Moore,
+        },
+        color_method: ColorMethod::DistToCenter,
+        color1: Color::YELLOW,
+        color2: Color::RED,
+        theme: Theme::Custom,
+        author: None,
+        tags: vec!["growth".into()],
+    }
+}
+
+fn example_paths(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map(|e| e == "ron").unwrap_or(false))
+            .collect(),
+        Err(_) => vec![],
+    };
+    // file names decide the order, eg: "00_builder.ron".
+    paths.sort();
+    paths
+}
+
+pub fn load_example(path: &Path) -> Result<Example, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    ron::from_str(&text).map_err(|e| e.to_string())
+}
+
+// files that fail to parse are skipped with a warning.
+pub fn load_examples(dir: impl AsRef<Path>) -> Vec<Example> {
+    example_paths(dir.as_ref()).iter()
+        .filter_map(|path| match load_example(path) {
+            Ok(example) => Some(example),
+            Err(error) => {
+                logged!(warn, "skipping example {}: {}", path.display(), error);
+                None
+            }
+        })
+        .collect()
+}
+
+
+// polls the example directory and reloads the list when files are added,
+// removed or modified.
+pub struct ExampleLibrary {
+    dir: PathBuf,
+    last_scan: f64,
+    modified: HashMap<PathBuf, SystemTime>,
+    local: Vec<Example>,
+    // downloaded from rule packs, kept across reloads of the directory.
+    remote: Vec<Example>,
+}
+
+impl ExampleLibrary {
+    pub fn new(dir: impl Into<PathBuf>) -> ExampleLibrary {
+        let mut library = ExampleLibrary {
+            dir: dir.into(),
+            last_scan: 0.0,
+            modified: HashMap::new(),
+            local: vec![],
+            remote: vec![],
+        };
+        library.scan();
+        library.local = load_examples(&library.dir);
+        library
+    }
+
+    pub fn examples(&self) -> Vec<Example> {
+        self.local.iter().chain(self.remote.iter()).cloned().collect()
+    }
+
+    // replaces examples with the same name, so fetching a pack twice
+    // doesn't duplicate it.
+    #[cfg_attr(not(feature = "rule_packs"), allow(dead_code))]
+    pub fn add_remote(&mut self, examples: Vec<Example>) {
+        for example in examples {
+            self.remote.retain(|e| e.name != example.name);
+            self.remote.push(example);
+        }
+    }
+
+    // returns true if anything changed since the last scan.
+    fn scan(&mut self) -> bool {
+        let modified: HashMap<PathBuf, SystemTime> = example_paths(&self.dir).into_iter()
+            .filter_map(|path| {
+                let time = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                Some((path, time))
+            })
+            .collect();
+        let changed = modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+}
+
+
+pub fn update(
+    mut this: ResMut<ExampleLibrary>,
+    mut sims: ResMut<Sims>,
+    time: Res<Time>,
+) {
+    let now = time.seconds_since_startup();
+    if now - this.last_scan < SCAN_INTERVAL {
+        return;
+    }
+    this.last_scan = now;
+
+    if this.scan() {
+        let examples = load_examples(&this.dir);
+        if !examples.is_empty() {
+            logged!(info, "reloaded {} examples from {}", examples.len(), this.dir.display());
+            this.local = examples;
+            sims.set_examples(this.examples());
+        }
+    }
+}
+
+
+pub struct ExampleLibraryPlugin;
+impl Plugin for ExampleLibraryPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(ExampleLibrary::new(EXAMPLE_DIR))
+        .add_system(update.with_run_criteria(simulating));
+    }
+}
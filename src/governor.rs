@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    action::Action,
+    app_state::simulating,
+    cells::{Sims, SimsUpdate},
+    throttle::Throttle,
+};
+
+// seconds between adjustments, the times are averaged over it.
+const INTERVAL: f64 = 0.5;
+// the sims window's slider goes this far.
+const MAX_TICKS_PER_FRAME: u32 = 16;
+
+
+// picks the ticks per frame that keep the frame under `budget_ms`, so a
+// change of bounds or backend doesn't need the slider. the time outside the
+// updates (rendering, ui) is what the frame took minus the ticks.
+pub struct Governor {
+    pub enabled: bool,
+    pub budget_ms: f32,
+    last_adjust: f64,
+    last_tick: u64,
+    frames: u32,
+    frame_seconds: f64,
+    update_seconds: f64,
+    ticks_per_second: f64,
+}
+
+impl Default for Governor {
+    fn default() -> Self {
+        Governor {
+            enabled: false,
+            budget_ms: 16.0,
+            last_adjust: 0.0,
+            last_tick: 0,
+            frames: 0,
+            frame_seconds: 0.0,
+            update_seconds: 0.0,
+            ticks_per_second: 0.0,
+        }
+    }
+}
+
+impl Governor {
+    // `frame` and `update` are averages over the last interval, `update`
+    // per tick, in seconds.
+    fn ticks_for(&self, ticks_per_frame: u32, frame: f64, update: f64) -> u32 {
+        let budget = self.budget_ms as f64 / 1000.0;
+        let other = (frame - update * ticks_per_frame as f64).max(0.0);
+        let fit = ((budget - other) / update.max(1e-9)).floor() as u32;
+        // one step at a time downwards too, a single slow frame (a big
+        // birth wave) shouldn't drop to one tick.
+        let target = fit.clamp(1, MAX_TICKS_PER_FRAME);
+        match target.cmp(&ticks_per_frame) {
+            std::cmp::Ordering::Greater => ticks_per_frame + 1,
+            std::cmp::Ordering::Less => (ticks_per_frame - 1).max(target),
+            std::cmp::Ordering::Equal => ticks_per_frame,
+        }
+    }
+}
+
+
+fn update(
+    mut this: ResMut<Governor>,
+    sims: Res<Sims>,
+    throttle: Res<Throttle>,
+    time: Res<Time>,
+    mut actions: EventWriter<Action>,
+) {
+    this.frames += 1;
+    this.frame_seconds += time.delta_seconds_f64();
+    this.update_seconds += sims.frame_times().0.as_secs_f64();
+
+    let now = time.seconds_since_startup();
+    let elapsed = now - this.last_adjust;
+    if elapsed < INTERVAL {
+        return;
+    }
+    let frames = this.frames.max(1) as f64;
+    let (frame, update) = (this.frame_seconds / frames, this.update_seconds / frames);
+    // a jump back (undo, load) isn't a negative rate.
+    this.ticks_per_second = sims.tick().saturating_sub(this.last_tick) as f64 / elapsed;
+    this.last_adjust = now;
+    this.last_tick = sims.tick();
+    this.frames = 0;
+    this.frame_seconds = 0.0;
+    this.update_seconds = 0.0;
+
+    // the auto-throttle holds the ticks at one while it's engaged.
+    if !this.enabled || sims.is_paused() || throttle.engaged() {
+        return;
+    }
+    let ticks = this.ticks_for(sims.ticks_per_frame(), frame, update);
+    if ticks != sims.ticks_per_frame() {
+        actions.send(Action::SetTicksPerFrame(ticks));
+    }
+}
+
+fn update_ui(
+    mut this: ResMut<Governor>,
+    sims: Res<Sims>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Speed governor").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.checkbox(&mut this.enabled, "adjust ticks per frame");
+        ui.add(egui::Slider::new(&mut this.budget_ms, 4.0..=100.0).text("frame budget (ms)"));
+        ui.label(format!("{} ticks per frame, {:.1} ticks/s", sims.ticks_per_frame(), this.ticks_per_second));
+    });
+}
+
+
+pub struct GovernorPlugin;
+impl Plugin for GovernorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<Governor>()
+        .add_system(update.after(SimsUpdate).with_run_criteria(simulating))
+        .add_system(update_ui.with_run_criteria(simulating));
+    }
+}
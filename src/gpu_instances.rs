@@ -0,0 +1,535 @@
+// builds the cell instances on the gpu instead of `extract_instances`. the
+// cells go up as a 3d texture when their hash changes, and a compute pass
+// compacts the live ones into the instance buffer with a prefix sum over the
+// chunks, see gpu_instances.wgsl.
+//
+// only while `GraphicsSettings::gpu_instances` is set and the frame needs
+// nothing but the state and position of a cell: every cell drawn, colored by
+// state or by the distance to the center, no neighbor filter, materials,
+// defects or decay, see `gpu_colors`. noise doesn't fade in. other frames
+// extract on the cpu like before.
+//
+// the scan also writes the instance count into the draw's indirect
+// arguments, so the count never comes back to the cpu before the draw. the
+// profiler's label reads it back a few frames late, see `Readback`.
+
+use std::{future::Future, num::NonZeroU32, pin::Pin, sync::{Arc, Mutex}};
+
+use bevy::{
+    core_pipeline::node,
+    prelude::*,
+    render::{
+        mesh::GpuBufferInfo,
+        render_asset::RenderAssets,
+        render_component::{ExtractComponent, ExtractComponentPlugin},
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+        render_resource::*,
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        RenderApp, RenderStage,
+    },
+    utils::HashMap,
+};
+
+use crate::{
+    cell_renderer::{CellRenderer, InstanceBuffer, InstanceColoring, InstanceData, CHUNK_SIZE},
+    profiler::SharedRenderTimes,
+    rule::{CellValue, ColorMethod},
+    utils,
+};
+
+const NODE: &str = "gpu_instances";
+// the palette for the distance to the center, in steps of 1/255.
+const DISTANCE_COLORS: usize = 256;
+
+#[cfg(not(feature = "wide_states"))]
+const VALUE_FORMAT: TextureFormat = TextureFormat::R8Uint;
+#[cfg(feature = "wide_states")]
+const VALUE_FORMAT: TextureFormat = TextureFormat::R16Uint;
+
+
+// on the cells entity in place of its instances, see `sims::update`.
+#[derive(Component, Clone)]
+pub struct GpuCells {
+    pub bounds: i32,
+    // `CellRenderer::values`, only copied when `hash` changes.
+    pub values: Arc<Vec<CellValue>>,
+    pub hash: u64,
+    // packed rgba8, see `gpu_colors`.
+    pub colors: Arc<Vec<u32>>,
+    pub by_distance: bool,
+    pub live: usize,
+}
+
+impl GpuCells {
+    pub fn new(renderer: &CellRenderer, previous: Option<&GpuCells>, (colors, by_distance): (Vec<u32>, bool), live: usize) -> GpuCells {
+        let values = match previous {
+            Some(previous) if previous.hash == renderer.hash() && previous.bounds == renderer.bounds => previous.values.clone(),
+            _ => Arc::new(renderer.values.clone()),
+        };
+        let colors = match previous {
+            Some(previous) if *previous.colors == colors => previous.colors.clone(),
+            _ => Arc::new(colors),
+        };
+        GpuCells {
+            bounds: renderer.bounds,
+            values,
+            hash: renderer.hash(),
+            colors,
+            by_distance,
+            live,
+        }
+    }
+
+    // what gpu_instances.wgsl writes, in row major order. for the camera
+    // export, which can't read back the gpu's.
+    pub fn instances(&self) -> Vec<InstanceData> {
+        self.values.iter().enumerate().filter(|(_, value)| **value != 0).map(|(index, &value)| {
+            let pos = utils::index_to_pos(index, self.bounds);
+            let color = match self.by_distance {
+                true => self.colors[(utils::dist_to_center(pos, self.bounds).min(1.0) * 255.0).round() as usize],
+                false => self.colors[(value as usize).min(self.colors.len() - 1)],
+            };
+            InstanceData { color: color.to_le_bytes(), ..InstanceData::cell(pos, 1.0, [0.0; 4], f32::MIN) }
+        }).collect()
+    }
+}
+
+impl ExtractComponent for GpuCells {
+    type Query = &'static GpuCells;
+    type Filter = ();
+
+    fn extract_component(item: bevy::ecs::query::QueryItem<Self::Query>) -> Self {
+        item.clone()
+    }
+}
+
+// the colors gpu_instances.wgsl looks up, per state or per step of the
+// distance to the center, and whether it's the latter. none if `coloring`
+// needs more than that.
+pub fn gpu_colors(coloring: &InstanceColoring) -> Option<(Vec<u32>, bool)> {
+    if coloring.defect_color.is_some() || coloring.materials.is_some() {
+        return None;
+    }
+    let pack = |color: Color| {
+        let color: [f32; 4] = color.into();
+        u32::from_le_bytes(color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+    };
+    let color = |state, dist_to_center| pack(coloring.method.color(
+        &coloring.palette, coloring.states, state, 0, coloring.max_neighbours, 0, 0, dist_to_center));
+    match coloring.method {
+        ColorMethod::Single | ColorMethod::StateLerp => {
+            Some(((0..=coloring.states).map(|state| color(state, 0.0)).collect(), false))
+        }
+        ColorMethod::DistToCenter => {
+            let step = 1.0 / (DISTANCE_COLORS - 1) as f32;
+            Some(((0..DISTANCE_COLORS).map(|i| color(1, i as f32 * step)).collect(), true))
+        }
+        ColorMethod::Neighbour | ColorMethod::Metadata | ColorMethod::Energy => None,
+    }
+}
+
+
+pub struct GpuInstancesPipeline {
+    layout: BindGroupLayout,
+    count_chunks: ComputePipeline,
+    scan_chunks: ComputePipeline,
+    write_instances: ComputePipeline,
+    // what fits in the largest storage binding.
+    max_instances: usize,
+}
+
+impl FromWorld for GpuInstancesPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.get_resource::<RenderDevice>().unwrap();
+        let shader = render_device.create_shader_module(&ShaderModuleDescriptor {
+            label: Some("gpu instances shader"),
+            source: ShaderSource::Wgsl(include_str!("../assets/shaders/gpu_instances.wgsl").into()),
+        });
+
+        let entry = |binding, ty| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty,
+            count: None,
+        };
+        let storage = |binding, read_only| entry(binding, BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        });
+        let layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("gpu instances layout"),
+            entries: &[
+                entry(0, BindingType::Texture {
+                    sample_type: TextureSampleType::Uint,
+                    view_dimension: TextureViewDimension::D3,
+                    multisampled: false,
+                }),
+                entry(1, BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: BufferSize::new(std::mem::size_of::<[u32; 4]>() as u64),
+                }),
+                storage(2, true),
+                storage(3, false),
+                storage(4, false),
+                storage(5, false),
+                storage(6, false),
+            ],
+        });
+        let pipeline_layout = render_device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("gpu instances pipeline layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point| render_device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some(entry_point),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point,
+        });
+
+        GpuInstancesPipeline {
+            count_chunks: pipeline("count_chunks"),
+            scan_chunks: pipeline("scan_chunks"),
+            write_instances: pipeline("write_instances"),
+            max_instances: render_device.limits().max_storage_buffer_binding_size as usize
+                / std::mem::size_of::<InstanceData>(),
+            layout,
+        }
+    }
+}
+
+
+// the instance count on its way back for the profiler. the node copies it
+// out of the draw arguments, after the frame is submitted it gets mapped, and
+// a later frame reads it once the map is done. submitting polls the device,
+// so that happens without waiting for the gpu.
+enum Readback {
+    Idle,
+    Copied,
+    Mapping(Pin<Box<dyn Future<Output = bool> + Send>>),
+}
+
+// the gpu side of a `GpuCells`. the buffers are recreated when the bounds
+// change or the instances outgrow them, by powers of two.
+struct GpuGrid {
+    bounds: i32,
+    chunks: u32,
+    // of the values in `cells`.
+    hash: Option<u64>,
+    colors: Arc<Vec<u32>>,
+    color_capacity: usize,
+    capacity: usize,
+    cells: Texture,
+    params: Buffer,
+    color_buffer: Buffer,
+    instances: Buffer,
+    // `draw_indexed_indirect` arguments, the scan writes the instance count.
+    indirect: Buffer,
+    readback: Buffer,
+    readback_state: Mutex<Readback>,
+    bind_group: BindGroup,
+}
+
+impl GpuGrid {
+    fn new(render_device: &RenderDevice, pipeline: &GpuInstancesPipeline, bounds: i32, capacity: usize, color_capacity: usize) -> GpuGrid {
+        let chunk_radius = ((bounds + CHUNK_SIZE - 1) / CHUNK_SIZE) as u32;
+        let chunks = chunk_radius * chunk_radius * chunk_radius;
+        let cells = render_device.create_texture(&TextureDescriptor {
+            label: Some("gpu instances cells"),
+            size: Extent3d {
+                width: bounds as u32,
+                height: bounds as u32,
+                depth_or_array_layers: bounds as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D3,
+            format: VALUE_FORMAT,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+        });
+        let buffer = |label, size: usize, usage| render_device.create_buffer(&BufferDescriptor {
+            label: Some(label),
+            size: size as u64,
+            usage,
+            mapped_at_creation: false,
+        });
+        let word = std::mem::size_of::<u32>();
+        let params = buffer("gpu instances params", 4 * word, BufferUsages::UNIFORM | BufferUsages::COPY_DST);
+        let color_buffer = buffer("gpu instances colors", color_capacity * word, BufferUsages::STORAGE | BufferUsages::COPY_DST);
+        let counts = buffer("gpu instances chunk counts", chunks as usize * word, BufferUsages::STORAGE);
+        let offsets = buffer("gpu instances chunk offsets", chunks as usize * word, BufferUsages::STORAGE);
+        let instances = buffer("gpu instance data", capacity * std::mem::size_of::<InstanceData>(),
+            BufferUsages::STORAGE | BufferUsages::VERTEX);
+        let indirect = buffer("gpu instances draw arguments", 5 * word,
+            BufferUsages::STORAGE | BufferUsages::INDIRECT | BufferUsages::COPY_DST | BufferUsages::COPY_SRC);
+        let readback = buffer("gpu instances count readback", word, BufferUsages::MAP_READ | BufferUsages::COPY_DST);
+
+        let view = cells.create_view(&TextureViewDescriptor::default());
+        let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+            label: Some("gpu instances bind group"),
+            layout: &pipeline.layout,
+            entries: &[
+                BindGroupEntry { binding: 0, resource: BindingResource::TextureView(&view) },
+                BindGroupEntry { binding: 1, resource: params.as_entire_binding() },
+                BindGroupEntry { binding: 2, resource: color_buffer.as_entire_binding() },
+                BindGroupEntry { binding: 3, resource: counts.as_entire_binding() },
+                BindGroupEntry { binding: 4, resource: offsets.as_entire_binding() },
+                BindGroupEntry { binding: 5, resource: instances.as_entire_binding() },
+                BindGroupEntry { binding: 6, resource: indirect.as_entire_binding() },
+            ],
+        });
+
+        GpuGrid {
+            bounds,
+            chunks,
+            hash: None,
+            colors: Arc::default(),
+            color_capacity,
+            capacity,
+            cells,
+            params,
+            color_buffer,
+            instances,
+            indirect,
+            readback,
+            readback_state: Mutex::new(Readback::Idle),
+            bind_group,
+        }
+    }
+}
+
+// lives in the render world, keyed by the main world entity.
+#[derive(Default)]
+pub struct GpuGrids {
+    grids: HashMap<Entity, GpuGrid>,
+}
+
+fn prepare_gpu_instances(
+    mut commands: Commands,
+    query: Query<(Entity, &GpuCells, &Handle<Mesh>)>,
+    mut grids: ResMut<GpuGrids>,
+    pipeline: Res<GpuInstancesPipeline>,
+    meshes: Res<RenderAssets<Mesh>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let _span = info_span!("prepare_gpu_instances").entered();
+    grids.grids.retain(|entity, _| matches!(query.get(*entity), Ok((_, cells, _)) if cells.bounds > 0));
+    for (entity, cells, mesh) in query.iter() {
+        if cells.bounds <= 0 {
+            continue;
+        }
+        let capacity = cells.live.max(1).next_power_of_two().min(pipeline.max_instances);
+        let outgrown = |grid: &GpuGrid| grid.bounds != cells.bounds
+            || grid.capacity < capacity
+            || grid.color_capacity < cells.colors.len();
+        if grids.grids.get(&entity).map_or(true, outgrown) {
+            let color_capacity = cells.colors.len().next_power_of_two();
+            grids.grids.insert(entity, GpuGrid::new(&render_device, &pipeline, cells.bounds, capacity, color_capacity));
+        }
+        let grid = grids.grids.get_mut(&entity).unwrap();
+
+        if grid.hash != Some(cells.hash) {
+            let bounds = cells.bounds as u32;
+            render_queue.write_texture(
+                ImageCopyTexture {
+                    texture: &grid.cells,
+                    mip_level: 0,
+                    origin: Origin3d::ZERO,
+                    aspect: TextureAspect::All,
+                },
+                bytemuck::cast_slice(cells.values.as_slice()),
+                ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(bounds * std::mem::size_of::<CellValue>() as u32),
+                    rows_per_image: NonZeroU32::new(bounds),
+                },
+                Extent3d {
+                    width: bounds,
+                    height: bounds,
+                    depth_or_array_layers: bounds,
+                },
+            );
+            grid.hash = Some(cells.hash);
+        }
+        if !Arc::ptr_eq(&grid.colors, &cells.colors) {
+            render_queue.write_buffer(&grid.color_buffer, 0, bytemuck::cast_slice(cells.colors.as_slice()));
+            grid.colors = cells.colors.clone();
+        }
+        let chunk_radius = (cells.bounds + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let params = [cells.bounds as u32, chunk_radius as u32, grid.capacity as u32, cells.by_distance as u32];
+        render_queue.write_buffer(&grid.params, 0, bytemuck::cast_slice(&params));
+        // the vertex or index count, then the instance count the scan fills in,
+        // the rest starts at 0. the mesh may still be loading.
+        let count = match meshes.get(mesh).map(|mesh| &mesh.buffer_info) {
+            Some(GpuBufferInfo::Indexed { count, .. }) => *count,
+            Some(GpuBufferInfo::NonIndexed { vertex_count }) => *vertex_count,
+            None => 0,
+        };
+        render_queue.write_buffer(&grid.indirect, 0, bytemuck::cast_slice(&[count, 0, 0, 0, 0]));
+
+        commands.entity(entity).insert(InstanceBuffer {
+            buffer: grid.instances.clone(),
+            length: grid.capacity,
+            indirect: Some(grid.indirect.clone()),
+        });
+    }
+}
+
+
+// before the main pass, so it draws this frame's instances.
+struct GpuInstancesNode;
+
+impl Node for GpuInstancesNode {
+    fn run(&self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let grids = world.get_resource::<GpuGrids>().unwrap();
+        if grids.grids.is_empty() {
+            return Ok(());
+        }
+        let pipeline = world.get_resource::<GpuInstancesPipeline>().unwrap();
+        {
+            let mut pass = render_context.command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("gpu instances"),
+            });
+            for grid in grids.grids.values() {
+                pass.set_bind_group(0, &grid.bind_group, &[]);
+                pass.set_pipeline(&pipeline.count_chunks);
+                pass.dispatch(grid.chunks, 1, 1);
+                pass.set_pipeline(&pipeline.scan_chunks);
+                pass.dispatch(1, 1, 1);
+                pass.set_pipeline(&pipeline.write_instances);
+                pass.dispatch(grid.chunks, 1, 1);
+            }
+        }
+        for grid in grids.grids.values() {
+            let mut state = grid.readback_state.lock().unwrap();
+            if let Readback::Idle = *state {
+                let word = std::mem::size_of::<u32>() as u64;
+                render_context.command_encoder.copy_buffer_to_buffer(&grid.indirect, word, &grid.readback, 0, word);
+                *state = Readback::Copied;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+// runs after the frame was submitted.
+fn read_back_instance_counts(grids: Res<GpuGrids>, render_times: Res<SharedRenderTimes>) {
+    if grids.grids.is_empty() {
+        render_times.modify(|times| times.gpu_instances = None);
+    }
+    for grid in grids.grids.values() {
+        let mut state = grid.readback_state.lock().unwrap();
+        match &mut *state {
+            Readback::Idle => {}
+            Readback::Copied => {
+                let mapping = grid.readback.slice(..).map_async(MapMode::Read);
+                *state = Readback::Mapping(Box::pin(async move { mapping.await.is_ok() }));
+            }
+            Readback::Mapping(mapping) => {
+                let mapped = match futures_lite::future::block_on(futures_lite::future::poll_once(mapping)) {
+                    Some(mapped) => mapped,
+                    None => continue,
+                };
+                if mapped {
+                    let count = {
+                        let data = grid.readback.slice(..).get_mapped_range();
+                        bytemuck::cast_slice::<u8, u32>(&data)[0]
+                    };
+                    grid.readback.unmap();
+                    render_times.modify(|times| times.gpu_instances = Some(count));
+                }
+                *state = Readback::Idle;
+            }
+        }
+    }
+}
+
+
+pub struct GpuInstancesPlugin;
+impl Plugin for GpuInstancesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(ExtractComponentPlugin::<GpuCells>::default());
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .init_resource::<GpuInstancesPipeline>()
+            .init_resource::<GpuGrids>()
+            .add_system_to_stage(RenderStage::Prepare, prepare_gpu_instances)
+            .add_system_to_stage(RenderStage::Cleanup, read_back_instance_counts);
+
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        graph.add_node(NODE, GpuInstancesNode);
+        graph.add_node_edge(NODE, node::MAIN_PASS_DEPENDENCIES).unwrap();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{math::ivec3, tasks::TaskPool};
+    use crate::{cell_renderer::NeighbourFilter, graphics::InstanceFilter, theme::{Palette, Theme}};
+
+    fn coloring(method: ColorMethod) -> InstanceColoring {
+        InstanceColoring {
+            method,
+            palette: Palette { theme: Theme::Viridis, color1: Color::WHITE, color2: Color::BLACK },
+            states: 5,
+            max_neighbours: 26,
+            defect_color: None,
+            materials: None,
+        }
+    }
+
+    // the same colors `extract_instances` gives each state.
+    #[test]
+    fn state_colors_match_the_cpu() {
+        let coloring = coloring(ColorMethod::StateLerp);
+        let (colors, by_distance) = gpu_colors(&coloring).unwrap();
+        assert!(!by_distance);
+        assert_eq!(colors.len(), 6);
+        for state in 0..=coloring.states {
+            let color = coloring.method.color(&coloring.palette, coloring.states, state, 0, 26, 0, 0, 0.0);
+            let instance = InstanceData::cell(IVec3::ZERO, 1.0, color.into(), 0.0);
+            assert_eq!(colors[state as usize], u32::from_le_bytes(instance.color));
+        }
+    }
+
+    // the instances the compute pass builds, as far as the cpu can tell.
+    #[test]
+    fn instances_match_the_cpu_extraction() {
+        let mut renderer = CellRenderer::new();
+        // partial chunks along the far sides.
+        renderer.set_bounds(40);
+        for (i, pos) in [ivec3(0, 0, 0), ivec3(39, 1, 2), ivec3(31, 32, 33), ivec3(5, 39, 39)].into_iter().enumerate() {
+            renderer.set(utils::pos_to_index(pos, 40), i as CellValue + 1, 0);
+        }
+        let coloring = coloring(ColorMethod::StateLerp);
+        let mut cpu = vec![];
+        renderer.extract_instances(&coloring, &InstanceFilter::All, &NeighbourFilter::default(), &TaskPool::new(), &mut cpu);
+        let gpu = GpuCells::new(&renderer, None, gpu_colors(&coloring).unwrap(), 4).instances();
+
+        let key = |instance: &InstanceData| (instance.position, instance.scale, instance.color, instance.material);
+        let mut cpu: Vec<_> = cpu.iter().map(key).collect();
+        cpu.sort();
+        assert_eq!(cpu, gpu.iter().map(key).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn per_cell_inputs_stay_on_the_cpu() {
+        assert_eq!(gpu_colors(&coloring(ColorMethod::DistToCenter)).unwrap().0.len(), DISTANCE_COLORS);
+        assert!(gpu_colors(&coloring(ColorMethod::Neighbour)).is_none());
+        assert!(gpu_colors(&coloring(ColorMethod::Energy)).is_none());
+        let mut defects = coloring(ColorMethod::StateLerp);
+        defects.defect_color = Some(Color::RED);
+        assert!(gpu_colors(&defects).is_none());
+    }
+}
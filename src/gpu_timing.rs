@@ -0,0 +1,138 @@
+// gpu timestamps around the main 3d pass, which draws the cells and the
+// background. needs `wgpu::Features::TIMESTAMP_QUERY`, requesting it on a
+// gpu without it fails to create the device, hence the feature flag.
+//
+// the timestamps are read back right after the frame is submitted, which
+// waits for the gpu. only done while the profiler asks for it.
+
+use std::time::Duration;
+
+use bevy::{
+    core_pipeline::draw_3d_graph,
+    prelude::*,
+    render::{
+        options::WgpuOptions,
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+        render_resource::WgpuFeatures,
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        RenderApp, RenderStage,
+    },
+};
+
+use crate::profiler::SharedRenderTimes;
+
+const BEGIN: &str = "gpu_timing_begin";
+const END: &str = "gpu_timing_end";
+
+
+// has to be inserted before the `DefaultPlugins`.
+pub fn wgpu_options() -> WgpuOptions {
+    WgpuOptions {
+        features: WgpuFeatures::TIMESTAMP_QUERY,
+        ..Default::default()
+    }
+}
+
+
+struct GpuTimer {
+    queries: wgpu::QuerySet,
+    resolve: wgpu::Buffer,
+    readback: wgpu::Buffer,
+}
+
+impl GpuTimer {
+    fn new(device: &RenderDevice) -> Option<GpuTimer> {
+        if !device.features().contains(WgpuFeatures::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let device = device.wgpu_device();
+        let buffer = |label, usage| device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: 2 * std::mem::size_of::<u64>() as u64,
+            usage,
+            mapped_at_creation: false,
+        });
+        Some(GpuTimer {
+            queries: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("gpu timing"),
+                ty: wgpu::QueryType::Timestamp,
+                count: 2,
+            }),
+            resolve: buffer("gpu timing resolve", wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC),
+            readback: buffer("gpu timing readback", wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST),
+        })
+    }
+}
+
+
+struct TimestampNode {
+    index: u32,
+}
+
+impl Node for TimestampNode {
+    fn run(&self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let measure = world.get_resource::<SharedRenderTimes>().map(|t| t.get().measure_gpu).unwrap_or(false);
+        if let (true, Some(timer)) = (measure, world.get_resource::<GpuTimer>()) {
+            let encoder = &mut render_context.command_encoder;
+            encoder.write_timestamp(&timer.queries, self.index);
+            if self.index == 1 {
+                encoder.resolve_query_set(&timer.queries, 0..2, &timer.resolve, 0);
+                encoder.copy_buffer_to_buffer(&timer.resolve, 0, &timer.readback, 0, 2 * std::mem::size_of::<u64>() as u64);
+            }
+        }
+        Ok(())
+    }
+}
+
+
+// runs after the frame was submitted.
+fn read_timestamps(
+    timer: Option<Res<GpuTimer>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    render_times: Res<SharedRenderTimes>,
+) {
+    let timer = match timer {
+        Some(timer) if render_times.get().measure_gpu => timer,
+        _ => {
+            render_times.modify(|times| times.gpu_pass = None);
+            return;
+        }
+    };
+
+    let slice = timer.readback.slice(..);
+    let mapping = slice.map_async(wgpu::MapMode::Read);
+    render_device.wgpu_device().poll(wgpu::Maintain::Wait);
+    if futures_lite::future::block_on(mapping).is_err() {
+        return;
+    }
+    let pass = {
+        let data = slice.get_mapped_range();
+        let timestamps: &[u64] = bytemuck::cast_slice(&data);
+        let ticks = timestamps[1].saturating_sub(timestamps[0]);
+        Duration::from_nanos((ticks as f64 * render_queue.get_timestamp_period() as f64) as u64)
+    };
+    timer.readback.unmap();
+    render_times.modify(|times| times.gpu_pass = Some(pass));
+}
+
+
+pub fn build(app: &mut App) {
+    let render_app = app.sub_app_mut(RenderApp);
+    let timer = GpuTimer::new(render_app.world.get_resource::<RenderDevice>().unwrap());
+    if let Some(timer) = timer {
+        render_app.insert_resource(timer);
+    }
+    render_app.add_system_to_stage(RenderStage::Cleanup, read_timestamps);
+
+    let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+    let draw_3d = graph.get_sub_graph_mut(draw_3d_graph::NAME).unwrap();
+    draw_3d.add_node(BEGIN, TimestampNode { index: 0 });
+    draw_3d.add_node(END, TimestampNode { index: 1 });
+    draw_3d.add_node_edge(BEGIN, draw_3d_graph::node::MAIN_PASS).unwrap();
+    draw_3d.add_node_edge(draw_3d_graph::node::MAIN_PASS, END).unwrap();
+}
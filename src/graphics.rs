@@ -0,0 +1,273 @@
+use bevy::{asset::LoadState, prelude::*};
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    app_state::simulating,
+    background::{Background, BackgroundMode},
+    cell_mesh::{self, CellMesh},
+    cell_renderer::{CellRenderer, CellRenderMode, CellShaderSettings, DebugInstances, InstanceMaterialData},
+    cells::color_picker,
+    neighbours::VONNEUMAN_NEIGHBOURS,
+    utils,
+};
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BudgetMode {
+    Off,
+    Stochastic,
+    Surface,
+}
+
+pub struct GraphicsSettings {
+    pub instance_budget: usize,
+    pub budget_mode: BudgetMode,
+    pub sample_percent: f32,
+    // build the instances with a compute pass, see gpu_instances.rs.
+    pub gpu_instances: bool,
+
+    pub render_mode: CellRenderMode,
+    pub cell_mesh: CellMesh,
+    pub gltf_path: String,
+    applied_mesh: Option<(CellMesh, CellRenderMode)>,
+    loading_mesh: Option<Handle<Mesh>>,
+    mesh_error: Option<String>,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        GraphicsSettings {
+            instance_budget: 1_000_000,
+            budget_mode: BudgetMode::Off,
+            sample_percent: 50.0,
+            gpu_instances: false,
+            render_mode: CellRenderMode::Mesh,
+            cell_mesh: CellMesh::Cube,
+            gltf_path: "models/cell.glb".into(),
+            applied_mesh: Some((CellMesh::Cube, CellRenderMode::Mesh)),
+            loading_mesh: None,
+            mesh_error: None,
+        }
+    }
+}
+
+impl GraphicsSettings {
+    pub fn instance_filter(&self, live_cells: usize) -> InstanceFilter {
+        if live_cells <= self.instance_budget {
+            return InstanceFilter::All;
+        }
+        match self.budget_mode {
+            BudgetMode::Off => InstanceFilter::All,
+            BudgetMode::Stochastic => {
+                let threshold = (self.sample_percent / 100.0).clamp(0.0, 1.0) * u32::MAX as f32;
+                InstanceFilter::Sample(threshold as u32)
+            }
+            BudgetMode::Surface => InstanceFilter::Surface,
+        }
+    }
+}
+
+
+// decides which live cells get an instance when the budget is exceeded.
+pub enum InstanceFilter {
+    All,
+    // keep cells whose index hashes below the threshold. the hash is stable,
+    // so the same cells stay visible from frame to frame.
+    Sample(u32),
+    // keep cells with at least one empty face.
+    Surface,
+}
+
+impl InstanceFilter {
+    pub fn keep(&self, renderer: &CellRenderer, index: usize) -> bool {
+        match self {
+            InstanceFilter::All => true,
+            InstanceFilter::Sample(threshold) => {
+                (utils::hash_u64(index as u64) >> 32) as u32 <= *threshold
+            }
+            InstanceFilter::Surface => {
+                let bounds = renderer.bounds;
+                let pos = utils::index_to_pos(index, bounds);
+                VONNEUMAN_NEIGHBOURS.iter().any(|dir| {
+                    let neighbour = pos + *dir;
+                    neighbour.min_element() < 0
+                        || !utils::is_in_bounds(neighbour, bounds)
+                        || renderer.values[utils::pos_to_index(neighbour, bounds)] == 0
+                })
+            }
+        }
+    }
+}
+
+
+fn apply_cell_mesh(
+    settings: &mut GraphicsSettings,
+    meshes: &mut Assets<Mesh>,
+    asset_server: &AssetServer,
+    instanced: &mut Query<(&mut Handle<Mesh>, &mut CellRenderMode), (With<InstanceMaterialData>, Without<Background>, Without<DebugInstances>)>,
+) {
+    let wanted = (settings.cell_mesh, settings.render_mode);
+    if settings.applied_mesh != Some(wanted) {
+        settings.applied_mesh = Some(wanted);
+        settings.mesh_error = None;
+        settings.loading_mesh = None;
+
+        // billboards are always quads, the cell mesh only applies to mesh mode.
+        let mesh = match settings.render_mode {
+            CellRenderMode::Billboard => Some(Mesh::from(shape::Quad::new(Vec2::ONE))),
+            // the ui only offers mesh and billboards.
+            _ => settings.cell_mesh.build(),
+        };
+        match mesh {
+            Some(mesh) => {
+                let handle = meshes.add(mesh);
+                for (mut mesh_handle, mut render_mode) in instanced.iter_mut() {
+                    *mesh_handle = handle.clone();
+                    *render_mode = settings.render_mode;
+                }
+            }
+            None => {
+                let path = format!("{}#Mesh0/Primitive0", settings.gltf_path);
+                settings.loading_mesh = Some(asset_server.load(path.as_str()));
+            }
+        }
+    }
+
+    // custom meshes are only swapped in once they're loaded and known to
+    // work with the cell pipeline's vertex layout.
+    if let Some(handle) = settings.loading_mesh.clone() {
+        let result = match meshes.get_mut(&handle) {
+            Some(mesh) => Some(cell_mesh::validate(mesh)),
+            None => match asset_server.get_load_state(&handle) {
+                LoadState::Failed => Some(Err(format!("failed to load {}", settings.gltf_path))),
+                _ => None,
+            },
+        };
+
+        match result {
+            Some(Ok(())) => {
+                for (mut mesh_handle, mut render_mode) in instanced.iter_mut() {
+                    *mesh_handle = handle.clone();
+                    *render_mode = CellRenderMode::Mesh;
+                }
+                settings.loading_mesh = None;
+            }
+            Some(Err(error)) => {
+                settings.mesh_error = Some(error);
+                settings.loading_mesh = None;
+            }
+            None => (),
+        }
+    }
+}
+
+
+pub fn update(
+    mut settings: ResMut<GraphicsSettings>,
+    mut shader_settings: ResMut<CellShaderSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    asset_server: Res<AssetServer>,
+    mut instanced: Query<(&mut Handle<Mesh>, &mut CellRenderMode), (With<InstanceMaterialData>, Without<Background>, Without<DebugInstances>)>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    apply_cell_mesh(&mut settings, &mut meshes, &asset_server, &mut instanced);
+
+    egui::Window::new("Graphics").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.add(egui::Slider::new(&mut settings.instance_budget, 10_000..=10_000_000)
+            .logarithmic(true)
+            .text("instance budget"));
+
+        egui::ComboBox::from_label("over budget")
+            .selected_text(format!("{:?}", settings.budget_mode))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut settings.budget_mode, BudgetMode::Off, "Render everything");
+                ui.selectable_value(&mut settings.budget_mode, BudgetMode::Stochastic, "Random subset");
+                ui.selectable_value(&mut settings.budget_mode, BudgetMode::Surface, "Surface cells only");
+            });
+
+        if settings.budget_mode == BudgetMode::Stochastic {
+            ui.add(egui::Slider::new(&mut settings.sample_percent, 1.0..=100.0)
+                .text("% of cells"));
+        }
+
+        ui.checkbox(&mut settings.gpu_instances, "build instances on the gpu");
+        if settings.gpu_instances {
+            ui.weak("colored by state or distance to center only, without the noise fade in. \
+                other color methods, filters, materials, defects and decay extract on the cpu.");
+        }
+
+        ui.add_space(12.0);
+
+        egui::ComboBox::from_label("render mode")
+            .selected_text(format!("{:?}", settings.render_mode))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut settings.render_mode, CellRenderMode::Mesh, "Mesh");
+                ui.selectable_value(&mut settings.render_mode, CellRenderMode::Billboard, "Billboard points");
+            });
+
+        egui::ComboBox::from_label("cell mesh")
+            .selected_text(format!("{:?}", settings.cell_mesh))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut settings.cell_mesh, CellMesh::Cube, "Cube");
+                ui.selectable_value(&mut settings.cell_mesh, CellMesh::Sphere, "Sphere");
+                ui.selectable_value(&mut settings.cell_mesh, CellMesh::RoundedCube, "Rounded cube");
+                ui.selectable_value(&mut settings.cell_mesh, CellMesh::Tetrahedron, "Tetrahedron");
+                ui.selectable_value(&mut settings.cell_mesh, CellMesh::Gltf, "glTF file");
+            });
+
+        if settings.cell_mesh == CellMesh::Gltf {
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut settings.gltf_path);
+                if ui.button("load").clicked() {
+                    settings.applied_mesh = None;
+                }
+            });
+        }
+        if let Some(error) = &settings.mesh_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+
+        ui.add_space(12.0);
+
+        egui::ComboBox::from_label("background")
+            .selected_text(format!("{:?}", shader_settings.background))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut shader_settings.background, BackgroundMode::Flat, "Flat");
+                ui.selectable_value(&mut shader_settings.background, BackgroundMode::Gradient, "Gradient");
+                ui.selectable_value(&mut shader_settings.background, BackgroundMode::Starfield, "Starfield");
+                ui.selectable_value(&mut shader_settings.background, BackgroundMode::Skybox, "Skybox");
+            });
+        ui.horizontal(|ui| {
+            color_picker(ui, &mut shader_settings.background_top);
+            color_picker(ui, &mut shader_settings.background_bottom);
+        });
+
+        ui.add(egui::Slider::new(&mut shader_settings.explode, 0.0..=32.0)
+            .text("explode gap"));
+        ui.add(egui::Slider::new(&mut shader_settings.fade_in, 0.0..=2.0)
+            .text("noise fade in (s)"));
+
+        ui.checkbox(&mut shader_settings.face_shading, "face shading");
+        if shader_settings.face_shading {
+            ui.add(egui::Slider::new(&mut shader_settings.face_shading_strength, 0.0..=1.0)
+                .text("strength"));
+        }
+
+        ui.checkbox(&mut shader_settings.fog, "fog");
+        if shader_settings.fog {
+            ui.add(egui::Slider::new(&mut shader_settings.fog_density, 0.0..=0.02)
+                .text("density"));
+            color_picker(ui, &mut shader_settings.fog_color);
+        }
+    });
+}
+
+
+pub struct GraphicsPlugin;
+impl Plugin for GraphicsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(GraphicsSettings::default())
+        .add_system(update.with_run_criteria(simulating));
+    }
+}
@@ -0,0 +1,326 @@
+// the cells of large grids, next to the project file. a 256³ grid as a ron
+// list of cells is hundreds of MB and takes seconds to write, so the grid is
+// copied once and written by a thread, one z slice at a time, run length
+// encoded. little endian:
+//   "CA3DGRID", version u8, bounds i32, bytes per value u8,
+//   then per z slice: length u32, runs of (count varint, value).
+
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+};
+
+use bevy::math::IVec3;
+
+use crate::{rule::CellValue, utils};
+
+pub const EXTENSION: &str = "ca3dgrid";
+// smaller grids stay inside the project file.
+pub const MIN_BOUNDS: i32 = 128;
+
+const MAGIC: &[u8; 8] = b"CA3DGRID";
+const VERSION: u8 = 1;
+const VALUE_BYTES: usize = std::mem::size_of::<CellValue>();
+
+
+fn write_varint(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], at: &mut usize) -> Result<u32, String> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes.get(*at).ok_or("truncated run")?;
+        *at += 1;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    Err("run length too long".into())
+}
+
+// run length encoding, also used by rewind.rs.
+pub fn encode_runs(values: &[CellValue], out: &mut Vec<u8>) {
+    out.clear();
+    let mut i = 0;
+    while i < values.len() {
+        let value = values[i];
+        let run = values[i..].iter().take_while(|v| **v == value).count();
+        write_varint(out, run as u32);
+        out.extend(&value.to_le_bytes());
+        i += run;
+    }
+}
+
+// appends at most `len` values to `out`.
+pub fn decode_runs(bytes: &[u8], len: usize, out: &mut Vec<CellValue>) -> Result<(), String> {
+    let start = out.len();
+    let mut at = 0;
+    while at < bytes.len() {
+        let run = read_varint(bytes, &mut at)? as usize;
+        if out.len() - start + run > len {
+            return Err(format!("more than {} cells", len));
+        }
+        let value = bytes.get(at..at + VALUE_BYTES).ok_or("truncated value")?;
+        at += VALUE_BYTES;
+        let value = CellValue::from_le_bytes(value.try_into().unwrap());
+        out.extend(std::iter::repeat(value).take(run));
+    }
+    Ok(())
+}
+
+
+// a save in progress. dropping it doesn't stop the thread, `cancel` does.
+// a cancelled or failed save deletes the partial file.
+pub struct GridSave {
+    pub path: PathBuf,
+    slices: usize,
+    done: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+    result: Mutex<mpsc::Receiver<Result<(), String>>>,
+}
+
+impl GridSave {
+    // `values` is the grid in `utils::pos_to_index` order, eg. the renderer's.
+    pub fn start(path: &Path, bounds: i32, values: Vec<CellValue>) -> GridSave {
+        let slices = bounds.max(0) as usize;
+        let done = Arc::new(AtomicUsize::new(0));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let (sender, result) = mpsc::channel();
+        {
+            let path = path.to_path_buf();
+            let done = done.clone();
+            let cancelled = cancelled.clone();
+            thread::spawn(move || {
+                let result = write(&path, bounds, &values, &done, &cancelled);
+                if result.is_err() {
+                    fs::remove_file(&path).ok();
+                }
+                sender.send(result).ok();
+            });
+        }
+
+        GridSave { path: path.to_path_buf(), slices, done, cancelled, result: Mutex::new(result) }
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.done.load(Ordering::Relaxed) as f32 / self.slices.max(1) as f32
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    // the result once the thread is done, only once.
+    pub fn poll(&self) -> Option<Result<(), String>> {
+        match self.result.lock().unwrap().try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err("the save thread panicked".into())),
+        }
+    }
+}
+
+fn write(path: &Path, bounds: i32, values: &[CellValue], done: &AtomicUsize, cancelled: &AtomicBool) -> Result<(), String> {
+    let error = |e: std::io::Error| format!("{}: {}", path.display(), e);
+    let mut file = BufWriter::new(File::create(path).map_err(error)?);
+    file.write_all(MAGIC).map_err(error)?;
+    file.write_all(&[VERSION]).map_err(error)?;
+    file.write_all(&bounds.to_le_bytes()).map_err(error)?;
+    file.write_all(&[VALUE_BYTES as u8]).map_err(error)?;
+
+    let slice_len = (bounds * bounds) as usize;
+    let mut encoded = vec![];
+    for slice in values.chunks(slice_len.max(1)) {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err("cancelled".into());
+        }
+        encode_runs(slice, &mut encoded);
+        file.write_all(&(encoded.len() as u32).to_le_bytes()).map_err(error)?;
+        file.write_all(&encoded).map_err(error)?;
+        done.fetch_add(1, Ordering::Relaxed);
+    }
+    file.flush().map_err(error)
+}
+
+
+// the live cells, like `Project::cells`.
+pub fn load(path: &Path) -> Result<(i32, Vec<(IVec3, CellValue)>), String> {
+    let error = |e: std::io::Error| format!("{}: {}", path.display(), e);
+    let mut file = BufReader::new(File::open(path).map_err(error)?);
+
+    let mut header = [0u8; 14];
+    file.read_exact(&mut header).map_err(error)?;
+    if &header[0..8] != MAGIC {
+        return Err(format!("{}: not a grid file", path.display()));
+    }
+    if header[8] != VERSION {
+        return Err(format!("{}: unknown version {}", path.display(), header[8]));
+    }
+    let bounds = i32::from_le_bytes(header[9..13].try_into().unwrap());
+    if header[13] as usize != VALUE_BYTES {
+        return Err(format!("{}: saved with {} byte states, this build uses {} (see the wide_states feature)",
+            path.display(), header[13], VALUE_BYTES));
+    }
+    if !(0..=1024).contains(&bounds) {
+        return Err(format!("{}: bounds of {}", path.display(), bounds));
+    }
+
+    let slice_len = (bounds * bounds) as usize;
+    let mut cells = vec![];
+    let mut encoded = vec![];
+    let mut slice = Vec::with_capacity(slice_len);
+    for z in 0..bounds {
+        let mut len = [0u8; 4];
+        file.read_exact(&mut len).map_err(error)?;
+        // a run per cell is the worst case, anything longer isn't a slice.
+        let len = u32::from_le_bytes(len) as usize;
+        if len > slice_len * (5 + VALUE_BYTES) {
+            return Err(format!("{}: slice {} is {} bytes long", path.display(), z, len));
+        }
+        encoded.resize(len, 0);
+        file.read_exact(&mut encoded).map_err(error)?;
+
+        slice.clear();
+        decode_runs(&encoded, slice_len, &mut slice).map_err(|e| format!("{}: slice {}: {}", path.display(), z, e))?;
+        if slice.len() != slice_len {
+            return Err(format!("{}: slice {} has {} cells, expected {}", path.display(), z, slice.len(), slice_len));
+        }
+        let offset = z as usize * slice_len;
+        cells.extend(slice.iter().enumerate()
+            .filter(|(_, value)| **value != 0)
+            .map(|(i, value)| (utils::index_to_pos(offset + i, bounds), *value)));
+    }
+    Ok((bounds, cells))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(values: &[CellValue]) -> Vec<CellValue> {
+        let mut encoded = vec![];
+        encode_runs(values, &mut encoded);
+        let mut decoded = vec![];
+        decode_runs(&encoded, values.len(), &mut decoded).unwrap();
+        decoded
+    }
+
+    // a grid file in the temp dir, removed again when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> TempFile {
+            TempFile(std::env::temp_dir().join(format!("{}-{}.{}", name, std::process::id(), EXTENSION)))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            fs::remove_file(&self.0).ok();
+        }
+    }
+
+    fn grid(bounds: i32) -> Vec<CellValue> {
+        (0..bounds*bounds*bounds).map(|i| if i % 7 < 3 { (i % 5) as CellValue } else { 0 }).collect()
+    }
+
+    fn save(file: &TempFile, bounds: i32, values: &[CellValue]) -> Vec<u8> {
+        write(&file.0, bounds, values, &AtomicUsize::new(0), &AtomicBool::new(false)).unwrap();
+        fs::read(&file.0).unwrap()
+    }
+
+    #[test]
+    fn runs_round_trip() {
+        let long = vec![3; 1000];
+        let max = vec![CellValue::MAX; 129];
+        let mixed: Vec<_> = (0..500).map(|i| (i / 3 % 4) as CellValue).collect();
+        let cases: [&[CellValue]; 6] = [&[], &[0], &[1, 2, 3], &long, &max, &mixed];
+        for values in cases {
+            assert_eq!(round_trip(values), values);
+        }
+    }
+
+    #[test]
+    fn runs_past_the_length_fail() {
+        let mut encoded = vec![];
+        encode_runs(&[1; 10], &mut encoded);
+        assert!(decode_runs(&encoded, 9, &mut vec![]).is_err());
+    }
+
+    #[test]
+    fn truncated_runs_fail() {
+        let mut encoded = vec![];
+        encode_runs(&[0, 0, 1, 1, 1, 2], &mut encoded);
+        for end in 1..encoded.len() {
+            let mut decoded = vec![];
+            // a cut between two runs decodes fine, but then cells are missing.
+            let complete = decode_runs(&encoded[..end], 6, &mut decoded).is_ok() && decoded.len() == 6;
+            assert!(!complete, "cut at {}", end);
+        }
+    }
+
+    #[test]
+    fn load_round_trip() {
+        let file = TempFile::new("load_round_trip");
+        let values = grid(6);
+        save(&file, 6, &values);
+        let (bounds, cells) = load(&file.0).unwrap();
+        assert_eq!(bounds, 6);
+        let expected: Vec<_> = values.iter().enumerate()
+            .filter(|(_, value)| **value != 0)
+            .map(|(i, value)| (utils::index_to_pos(i, 6), *value))
+            .collect();
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn truncated_file_fails() {
+        let file = TempFile::new("truncated_file_fails");
+        let bytes = save(&file, 4, &grid(4));
+        for end in [0, 5, 13, 14, 20, bytes.len() - 1] {
+            fs::write(&file.0, &bytes[..end]).unwrap();
+            assert!(load(&file.0).is_err(), "cut at {}", end);
+        }
+    }
+
+    #[test]
+    fn oversized_file_fails() {
+        let file = TempFile::new("oversized_file_fails");
+        let bytes = save(&file, 4, &grid(4));
+
+        // bounds past the limit.
+        let mut huge = bytes.clone();
+        huge[9..13].copy_from_slice(&2000i32.to_le_bytes());
+        fs::write(&file.0, &huge).unwrap();
+        assert!(load(&file.0).is_err());
+
+        // a slice length that would allocate gigabytes.
+        let mut long = bytes.clone();
+        long[14..18].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&file.0, &long).unwrap();
+        assert!(load(&file.0).is_err());
+
+        // more cells than the slice has, in one run.
+        let mut runs = vec![];
+        encode_runs(&[1; 17], &mut runs);
+        let mut wide = bytes[..14].to_vec();
+        wide.extend(&(runs.len() as u32).to_le_bytes());
+        wide.extend(&runs);
+        fs::write(&file.0, &wide).unwrap();
+        assert!(load(&file.0).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use bevy::math::IVec3;
+
+use crate::{rule::CellValue, utils};
+
+
+// a hash of a whole grid that follows single cell changes in constant time:
+// the wrapping sum of a hash per live cell, of its index and value. dead
+// cells add nothing, so the order the cells were set in doesn't matter, and
+// a grid hashes the same no matter which backend produced it. the renderer
+// keeps one up to date, see `CellRenderer::hash`, so period detection, the
+// golden values of headless.rs and the network diff don't rescan the grid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GridHash {
+    bounds: i32,
+    sum: u64,
+}
+
+fn cell_hash(index: usize, value: CellValue) -> u64 {
+    match value {
+        0 => 0,
+        // `CellValue` is at most 16 bits wide.
+        value => utils::hash_u64((index as u64) << 16 | value as u64),
+    }
+}
+
+impl GridHash {
+    // an empty grid.
+    pub fn new(bounds: i32) -> GridHash {
+        GridHash { bounds, sum: 0 }
+    }
+
+    pub fn of_values(bounds: i32, values: &[CellValue]) -> GridHash {
+        let mut hash = GridHash::new(bounds);
+        for (index, value) in values.iter().enumerate() {
+            hash.sum = hash.sum.wrapping_add(cell_hash(index, *value));
+        }
+        hash
+    }
+
+    pub fn of_cells(bounds: i32, cells: &[(IVec3, CellValue)]) -> GridHash {
+        let mut hash = GridHash::new(bounds);
+        for (pos, value) in cells.iter() {
+            hash.sum = hash.sum.wrapping_add(cell_hash(utils::pos_to_index(*pos, bounds), *value));
+        }
+        hash
+    }
+
+    // the cell at `index` changed from `old` to `new`.
+    pub fn set(&mut self, index: usize, old: CellValue, new: CellValue) {
+        if old != new {
+            self.sum = self.sum.wrapping_sub(cell_hash(index, old)).wrapping_add(cell_hash(index, new));
+        }
+    }
+
+    pub fn value(&self) -> u64 {
+        utils::hash_u64(self.sum ^ self.bounds as u64)
+    }
+}
@@ -0,0 +1,85 @@
+// runs the cpu backends without a bevy app, for integration tests and
+// external tools. eg. a known rule, seed and tick count always ends up with
+// the same `GridSnapshot::hash`, which catches silent behavior changes.
+
+use bevy::{math::{ivec3, IVec3}, tasks::logical_core_count};
+use rand::RngCore;
+
+use crate::{
+    cell_renderer::CellRenderer,
+    cells::{leddoo::LeddooSingleThreaded, Sim, SimCapabilities},
+    grid_hash::GridHash,
+    rule::{CellValue, Rule},
+    threading::Threading,
+    utils::{self, SplitMix64},
+};
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridSnapshot {
+    pub bounds: i32,
+    pub ticks: u32,
+    // live cells in index order.
+    pub cells: Vec<(IVec3, CellValue)>,
+}
+
+impl GridSnapshot {
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+
+    // stable across runs, platforms and dependency versions, for golden
+    // values (see tests/headless.rs). the same as `CellRenderer::hash` of the
+    // grid.
+    pub fn hash(&self) -> u64 {
+        GridHash::of_cells(self.bounds, &self.cells).value()
+    }
+}
+
+
+// the default noise of `Sim::spawn_noise`, but from a seed. splitmix and a
+// plain modulo instead of rand's generators and `gen_range`, which may change
+// between rand versions and would move the golden values.
+pub fn seeded_noise(rule: &Rule, seed: u64, bounds: i32) -> Vec<(IVec3, CellValue)> {
+    let mut rng = SplitMix64::new(seed);
+    let span = 2*utils::NOISE_RADIUS as u64 + 1;
+    let mut offset = || (rng.next_u64() % span) as i32 - utils::NOISE_RADIUS;
+    let center = utils::center(bounds);
+    (0..utils::NOISE_AMOUNT)
+        .map(|_| center + ivec3(offset(), offset(), offset()))
+        .filter(|pos| pos.min_element() >= 0 && utils::is_in_bounds(*pos, bounds))
+        .map(|pos| (pos, rule.states))
+        .collect()
+}
+
+// on the single-threaded leddoo backend, the reference for the others.
+pub fn run_headless(rule: &Rule, seed: u64, bounds: i32, ticks: u32) -> GridSnapshot {
+    run_headless_with(&mut LeddooSingleThreaded::new(), rule, seed, bounds, ticks)
+        .expect("the reference backend supports set_cell")
+}
+
+// the backend needs SET_CELL to be seeded. `bounds` may be clamped by the
+// backend, see `GridSnapshot::bounds`.
+pub fn run_headless_with(sim: &mut dyn Sim, rule: &Rule, seed: u64, bounds: i32, ticks: u32) -> Result<GridSnapshot, String> {
+    if !sim.capabilities().contains(SimCapabilities::SET_CELL) {
+        return Err("the backend can't be seeded, it has no set_cell".into());
+    }
+    let rule = rule.clone().sanitized();
+    let threading = Threading::new(logical_core_count());
+
+    let bounds = sim.set_bounds(bounds);
+    sim.set_cells(&rule, &seeded_noise(&rule, seed, bounds));
+    for _ in 0..ticks {
+        sim.update(&rule, threading.pool());
+    }
+
+    let mut renderer = CellRenderer::new();
+    renderer.set_bounds(bounds);
+    sim.render(&mut renderer);
+    let mut cells: Vec<(IVec3, CellValue)> = renderer.live_cells()
+        .map(|pos| (pos, renderer.values[utils::pos_to_index(pos, bounds)]))
+        .collect();
+    cells.sort_by_key(|(pos, _)| utils::pos_to_index(*pos, bounds));
+
+    Ok(GridSnapshot { bounds, ticks, cells })
+}
@@ -0,0 +1,268 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    action::Action,
+    app_state::simulating,
+    cells::{Sims, SimsUpdate},
+    console,
+    rule::Rule,
+    triggers,
+};
+
+// how many lines of script output are kept.
+const LOG_LENGTH: usize = 64;
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HookKind {
+    TickComplete,
+    RuleChanged,
+    ComponentBorn,
+}
+
+pub const HOOK_KINDS: [HookKind; 3] = [
+    HookKind::TickComplete, HookKind::RuleChanged, HookKind::ComponentBorn,
+];
+
+// also sent as a bevy event, so systems can read them with an
+// `EventReader<HookEvent>` instead of subscribing.
+#[derive(Clone)]
+pub enum HookEvent {
+    // with several ticks per frame, only the last tick of the frame is seen.
+    TickComplete { tick: u64, population: usize },
+    RuleChanged { tick: u64, rule: Rule },
+    // `count` new 6-connected components, `components` in total.
+    ComponentBorn { tick: u64, count: usize, components: usize },
+}
+
+impl HookEvent {
+    pub fn kind(&self) -> HookKind {
+        match self {
+            HookEvent::TickComplete { .. }  => HookKind::TickComplete,
+            HookEvent::RuleChanged { .. }   => HookKind::RuleChanged,
+            HookEvent::ComponentBorn { .. } => HookKind::ComponentBorn,
+        }
+    }
+
+    pub fn tick(&self) -> u64 {
+        match self {
+            HookEvent::TickComplete { tick, .. }
+            | HookEvent::RuleChanged { tick, .. }
+            | HookEvent::ComponentBorn { tick, .. } => *tick,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            HookEvent::TickComplete { tick, population } =>
+                format!("tick {}: population {}", tick, population),
+            HookEvent::RuleChanged { tick, rule } =>
+                format!("tick {}: rule changed to {}", tick, rule),
+            HookEvent::ComponentBorn { tick, count, components } =>
+                format!("tick {}: {} new component(s), {} total", tick, count, components),
+        }
+    }
+}
+
+
+pub type HookCallback = Box<dyn FnMut(&HookEvent) + Send + Sync>;
+
+pub enum HookAction {
+    Callback(HookCallback),
+    // a console command, see `console::execute`.
+    Script(String),
+}
+
+pub struct Hook {
+    pub id: usize,
+    pub kind: HookKind,
+    pub action: HookAction,
+    pub enabled: bool,
+}
+
+
+pub struct Hooks {
+    hooks: Vec<Hook>,
+    next_id: usize,
+
+    last_tick: u64,
+    last_rule: Option<Rule>,
+    components: Option<usize>,
+
+    log: VecDeque<String>,
+    new_kind: HookKind,
+    new_script: String,
+}
+
+impl Hooks {
+    pub fn new() -> Hooks {
+        Hooks {
+            hooks: vec![],
+            next_id: 0,
+            last_tick: 0,
+            last_rule: None,
+            components: None,
+            log: VecDeque::with_capacity(LOG_LENGTH),
+            new_kind: HookKind::TickComplete,
+            new_script: String::new(),
+        }
+    }
+
+    // returns an id for `unsubscribe`.
+    pub fn subscribe(&mut self, kind: HookKind, action: HookAction) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.hooks.push(Hook { id, kind, action, enabled: true });
+        id
+    }
+
+    #[allow(dead_code)]
+    pub fn subscribe_fn<F>(&mut self, kind: HookKind, f: F) -> usize
+        where F: FnMut(&HookEvent) + Send + Sync + 'static
+    {
+        self.subscribe(kind, HookAction::Callback(Box::new(f)))
+    }
+
+    pub fn unsubscribe(&mut self, id: usize) {
+        self.hooks.retain(|hook| hook.id != id);
+    }
+
+    fn wants(&self, kind: HookKind) -> bool {
+        self.hooks.iter().any(|hook| hook.enabled && hook.kind == kind)
+    }
+
+    fn log(&mut self, line: String) {
+        if self.log.len() == LOG_LENGTH {
+            self.log.pop_front();
+        }
+        self.log.push_back(line);
+    }
+
+    fn collect(&mut self, sims: &Sims) -> Vec<HookEvent> {
+        let mut events = vec![];
+        let tick = sims.tick();
+
+        if self.last_rule.as_ref() != Some(sims.rule()) {
+            // the first rule isn't a change.
+            if self.last_rule.is_some() {
+                events.push(HookEvent::RuleChanged { tick, rule: sims.rule().clone() });
+            }
+            self.last_rule = Some(sims.rule().clone());
+        }
+
+        if tick != self.last_tick {
+            self.last_tick = tick;
+            events.push(HookEvent::TickComplete { tick, population: sims.cell_count() });
+
+            // the flood fill is slow, only done while someone listens.
+            let components = self.wants(HookKind::ComponentBorn)
+                .then(|| triggers::count_components(sims.renderer()));
+            match (std::mem::replace(&mut self.components, components), components) {
+                (Some(old), Some(new)) if new > old => {
+                    events.push(HookEvent::ComponentBorn { tick, count: new - old, components: new });
+                }
+                _ => (),
+            }
+        }
+        events
+    }
+
+    fn dispatch(&mut self, event: &HookEvent, sims: &Sims, actions: &mut EventWriter<Action>) {
+        // ticks would flood the log.
+        let mut output = match event.kind() {
+            HookKind::TickComplete => vec![],
+            _ => vec![event.describe()],
+        };
+        for hook in self.hooks.iter_mut().filter(|h| h.enabled && h.kind == event.kind()) {
+            match &mut hook.action {
+                HookAction::Callback(callback) => callback(event),
+                HookAction::Script(line) => {
+                    let result = match console::execute(line, sims, actions) {
+                        Ok(result) => result,
+                        Err(error) => format!("error: {}", error),
+                    };
+                    if !result.is_empty() {
+                        output.push(format!("tick {}: {} -> {}", event.tick(), line, result));
+                    }
+                }
+            }
+        }
+        for line in output {
+            self.log(line);
+        }
+    }
+}
+
+
+pub fn update(
+    mut this: ResMut<Hooks>,
+    sims: Res<Sims>,
+    mut hook_events: EventWriter<HookEvent>,
+    mut actions: EventWriter<Action>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let events = this.collect(&sims);
+    for event in events {
+        this.dispatch(&event, &sims, &mut actions);
+        hook_events.send(event);
+    }
+
+    egui::Window::new("Hooks").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        let mut remove = None;
+        for hook in this.hooks.iter_mut() {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut hook.enabled, format!("{:?}", hook.kind));
+                match &hook.action {
+                    HookAction::Callback(_) => ui.weak("(callback)"),
+                    HookAction::Script(line) => ui.monospace(line),
+                };
+                if ui.small_button("x").clicked() {
+                    remove = Some(hook.id);
+                }
+            });
+        }
+        if let Some(id) = remove {
+            this.unsubscribe(id);
+        }
+
+        ui.horizontal(|ui| {
+            let mut kind = this.new_kind;
+            egui::ComboBox::from_id_source("new hook")
+                .selected_text(format!("{:?}", kind))
+                .show_ui(ui, |ui| {
+                    for k in HOOK_KINDS {
+                        ui.selectable_value(&mut kind, k, format!("{:?}", k));
+                    }
+                });
+            this.new_kind = kind;
+
+            ui.add(egui::TextEdit::singleline(&mut this.new_script)
+                .code_editor()
+                .hint_text("console command"));
+            if ui.button("add").clicked() && !this.new_script.trim().is_empty() {
+                let line = std::mem::take(&mut this.new_script).trim().to_string();
+                this.subscribe(kind, HookAction::Script(line));
+            }
+        });
+
+        egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+            for line in this.log.iter() {
+                ui.monospace(line);
+            }
+        });
+    });
+}
+
+
+pub struct HooksPlugin;
+impl Plugin for HooksPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(Hooks::new())
+        .add_event::<HookEvent>()
+        .add_system(update.after(SimsUpdate).with_run_criteria(simulating));
+    }
+}
@@ -0,0 +1,62 @@
+// everything but `main`, so the simulation can be driven without the app,
+// see headless.rs.
+
+pub mod action;
+pub mod app_state;
+pub mod backend_chart;
+pub mod background;
+pub mod bench;
+pub mod camera_export;
+pub mod cell_event;
+pub mod cell_mesh;
+pub mod cell_renderer;
+pub mod cells;
+pub mod command_palette;
+pub mod console;
+pub mod controls_window;
+pub mod demo;
+pub mod diagnostics;
+pub mod example_library;
+#[cfg(feature = "gpu_timing")]
+pub mod gpu_timing;
+pub mod governor;
+pub mod gpu_instances;
+pub mod graphics;
+pub mod grid_file;
+pub mod grid_hash;
+pub mod headless;
+pub mod hooks;
+pub mod neighbour_field;
+pub mod neighbours;
+pub mod network;
+pub mod overlay;
+pub mod plugins;
+#[cfg(feature = "post_process")]
+pub mod post_process;
+pub mod power;
+pub mod prediction;
+pub mod profiler;
+pub mod project;
+pub mod random_palette;
+pub mod rewind;
+pub mod rotating_camera;
+pub mod rule;
+pub mod rule_analysis;
+pub mod rule_history;
+pub mod rule_view;
+#[cfg(feature = "rule_packs")]
+pub mod rule_pack;
+pub mod seeding;
+pub mod self_test;
+pub mod shader_editor;
+pub mod soak;
+pub mod theme;
+pub mod threading;
+pub mod throttle;
+pub mod thumbnails;
+pub mod timeline;
+pub mod tour;
+pub mod triggers;
+pub mod utils;
+pub mod volume;
+pub mod window_title;
@@ -0,0 +1,115 @@
+use bevy::{prelude::*, render::view::NoFrustumCulling};
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    app_state::simulating,
+    cell_renderer::{CellRenderMode, DebugInstances, InstanceMaterialData},
+    cells::{Sims, SimsUpdate},
+    neighbours::NeighbourCount,
+    theme::{Palette, Theme},
+    threading::Threading,
+};
+
+#[derive(Component)]
+pub struct FieldVoxels;
+
+
+// a debug view of the neighbor counts of the dead cells, drawn as
+// translucent voxels. shows where the next births can happen.
+pub struct NeighbourField {
+    pub enabled: bool,
+    pub half_resolution: bool,
+    pub min: NeighbourCount,
+    // only counts in the birth rule.
+    pub births_only: bool,
+    pub opacity: f32,
+}
+
+impl NeighbourField {
+    pub fn new() -> NeighbourField {
+        NeighbourField {
+            enabled: false,
+            half_resolution: true,
+            min: 1,
+            births_only: false,
+            opacity: 0.25,
+        }
+    }
+}
+
+// dark purple for few neighbors to pale yellow for many, independent of the
+// cell colors so the field stands out.
+fn field_color(count: NeighbourCount, max: NeighbourCount, opacity: f32) -> [f32; 4] {
+    let palette = Palette { theme: Theme::Magma, color1: Color::BLACK, color2: Color::WHITE };
+    let t = 0.2 + 0.8 * count as f32 / max.max(1) as f32;
+    let mut color = palette.sample(t).as_rgba_f32();
+    color[3] = opacity;
+    color
+}
+
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    commands.spawn().insert_bundle((
+        meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+        Transform::default(),
+        GlobalTransform::default(),
+        InstanceMaterialData(vec![]),
+        Visibility::default(),
+        ComputedVisibility::default(),
+        NoFrustumCulling,
+        CellRenderMode::Translucent,
+        FieldVoxels,
+        DebugInstances,
+    ));
+}
+
+pub fn update(
+    mut this: ResMut<NeighbourField>,
+    mut sims: ResMut<Sims>,
+    threading: Res<Threading>,
+    mut voxels: Query<&mut InstanceMaterialData, With<FieldVoxels>>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let max = sims.rule().neighbour_method.max_neighbours();
+
+    egui::Window::new("Neighbor field").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.checkbox(&mut this.enabled, "show the neighbor counts");
+        ui.checkbox(&mut this.half_resolution, "half resolution");
+        ui.checkbox(&mut this.births_only, "only counts that cause births");
+        ui.add(egui::Slider::new(&mut this.min, 1..=max).text("min neighbors"));
+        ui.add(egui::Slider::new(&mut this.opacity, 0.02..=1.0).text("opacity"));
+        ui.weak("needs every cell rendered each frame, which is slower.");
+    });
+
+    let mut instances = match voxels.iter_mut().next() {
+        Some(instances) => instances,
+        None => return,
+    };
+    if !this.enabled {
+        instances.0.clear();
+        return;
+    }
+    sims.keep_neighbors = true;
+
+    let (min, births_only, opacity) = (this.min, this.births_only, this.opacity);
+    let rule = sims.rule();
+    let keep = |count: NeighbourCount| count >= min
+        && (!births_only || rule.birth_rule.in_range(count));
+    let color = |count: NeighbourCount| field_color(count, max, opacity);
+    let scale = if this.half_resolution { 2 } else { 1 };
+    sims.renderer().extract_neighbour_field(scale, keep, color, threading.pool(), &mut instances.0);
+}
+
+
+pub struct NeighbourFieldPlugin;
+impl Plugin for NeighbourFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(NeighbourField::new())
+        .add_startup_system(setup)
+        .add_system(update.after(SimsUpdate).with_run_criteria(simulating));
+    }
+}
@@ -1,7 +1,12 @@
 use bevy::math::{const_ivec3, IVec3};
+use serde::{Deserialize, Serialize};
+
+// the type of neighbor counters. u8 is enough for 26 neighbors, but not for
+// larger neighborhoods or weighted counts.
+pub type NeighbourCount = u16;
 
 #[allow(dead_code)]
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum NeighbourMethod {
     Moore,
     VonNeuman,
@@ -14,6 +19,11 @@ impl NeighbourMethod {
             NeighbourMethod::VonNeuman => &VONNEUMAN_NEIGHBOURS[..],
         }
     }
+
+    // the highest neighbor count this neighborhood can produce.
+    pub fn max_neighbours(&self) -> NeighbourCount {
+        self.get_neighbour_iter().len() as NeighbourCount
+    }
 }
 
 pub static VONNEUMAN_NEIGHBOURS: [IVec3; 6] = [
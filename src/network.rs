@@ -0,0 +1,678 @@
+// one instance simulates and streams what changed every frame to viewers,
+// which only render, eg: a desktop driving a laptop display. plain tcp, one
+// message is a kind byte and a u32 length, then the payload, little endian:
+//   0 world:        bounds i32, then the rule as ron. viewers start over on it.
+//   1 changes:      tick u64, then (index u32, value u16) per changed cell.
+//   2 hello:        the viewer's name, sent first.
+//   3 set rule:     a rule edit from a viewer, as ron.
+//   4 spawn noise:  from a viewer, empty.
+//   5 participants: the host's and all viewers' names, one per line.
+// a new viewer gets a world and then every live cell as one change list.
+// viewers edit the rule in the usual window, the host applies the edit and
+// sends everyone a new world, so all participants end up with one rule.
+// start with `--host [address]` or `--view <address>`, or from the window.
+
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    action::Action,
+    app_state::simulating,
+    cells::{SimCapabilities, Sims, SimsUpdate},
+    grid_hash::GridHash,
+    logged,
+    rule::{CellValue, Rule},
+    utils,
+};
+
+pub const DEFAULT_PORT: u16 = 7777;
+// a change list of a 256³ grid fits, anything larger is garbage.
+const MAX_MESSAGE: usize = 1 << 28;
+// what the host reads from viewers: a name, a rule or nothing. anyone can
+// connect, so they don't get to make the host allocate much.
+const MAX_REQUEST: usize = 1 << 14;
+
+const WORLD: u8 = 0;
+const CHANGES: u8 = 1;
+const HELLO: u8 = 2;
+const SET_RULE: u8 = 3;
+const SPAWN_NOISE: u8 = 4;
+const PARTICIPANTS: u8 = 5;
+
+const MAX_NAME: usize = 32;
+
+
+pub enum Message {
+    World { bounds: i32, rule: Rule },
+    Changes { tick: u64, cells: Vec<(u32, CellValue)> },
+    Hello { name: String },
+    SetRule(Rule),
+    SpawnNoise,
+    Participants(Vec<String>),
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        let (kind, payload) = match self {
+            Message::World { bounds, rule } => {
+                let mut payload = bounds.to_le_bytes().to_vec();
+                payload.extend(ron::to_string(rule).unwrap().as_bytes());
+                (WORLD, payload)
+            }
+            Message::Changes { tick, cells } => {
+                let mut payload = Vec::with_capacity(8 + cells.len() * 6);
+                payload.extend(tick.to_le_bytes());
+                for (index, value) in cells {
+                    payload.extend(index.to_le_bytes());
+                    payload.extend(u16::from(*value).to_le_bytes());
+                }
+                (CHANGES, payload)
+            }
+            Message::Hello { name } => (HELLO, name.as_bytes().to_vec()),
+            Message::SetRule(rule) => (SET_RULE, ron::to_string(rule).unwrap().into_bytes()),
+            Message::SpawnNoise => (SPAWN_NOISE, vec![]),
+            Message::Participants(names) => (PARTICIPANTS, names.join("\n").into_bytes()),
+        };
+        let mut bytes = Vec::with_capacity(5 + payload.len());
+        bytes.push(kind);
+        bytes.extend((payload.len() as u32).to_le_bytes());
+        bytes.extend(payload);
+        bytes
+    }
+
+    // `limit` is checked before the payload is allocated.
+    pub fn read(stream: &mut impl Read, limit: usize) -> Result<Message, String> {
+        let mut header = [0u8; 5];
+        stream.read_exact(&mut header).map_err(|e| e.to_string())?;
+        let len = u32::from_le_bytes(header[1..5].try_into().unwrap()) as usize;
+        if len > limit {
+            return Err(format!("message of {} bytes, more than the limit of {}", len, limit));
+        }
+        let mut payload = vec![0u8; len];
+        stream.read_exact(&mut payload).map_err(|e| e.to_string())?;
+
+        let text = |bytes: &[u8]| std::str::from_utf8(bytes).map(str::to_string).map_err(|e| e.to_string());
+        let rule = |bytes: &[u8]| -> Result<Rule, String> {
+            ron::from_str(&text(bytes)?).map_err(|e| e.to_string())
+        };
+        match header[0] {
+            WORLD if len >= 4 => {
+                let bounds = i32::from_le_bytes(payload[0..4].try_into().unwrap());
+                Ok(Message::World { bounds, rule: rule(&payload[4..])? })
+            }
+            CHANGES if len >= 8 && (len - 8) % 6 == 0 => {
+                let tick = u64::from_le_bytes(payload[0..8].try_into().unwrap());
+                let cells = payload[8..].chunks_exact(6)
+                    .map(|entry| (
+                        u32::from_le_bytes(entry[0..4].try_into().unwrap()),
+                        CellValue::try_from(u16::from_le_bytes(entry[4..6].try_into().unwrap()))
+                            .unwrap_or(CellValue::MAX)))
+                    .collect();
+                Ok(Message::Changes { tick, cells })
+            }
+            HELLO => Ok(Message::Hello { name: text(&payload)? }),
+            SET_RULE => Ok(Message::SetRule(rule(&payload)?)),
+            SPAWN_NOISE => Ok(Message::SpawnNoise),
+            PARTICIPANTS => Ok(Message::Participants(text(&payload)?.lines().map(str::to_string).collect())),
+            kind => Err(format!("malformed message of kind {} and {} bytes", kind, len)),
+        }
+    }
+}
+
+
+struct Client {
+    address: SocketAddr,
+    // the address until the hello arrives.
+    name: String,
+    // written by a thread per client, so a slow viewer doesn't stall the
+    // frame. closed when the write fails.
+    sender: mpsc::Sender<Arc<Vec<u8>>>,
+    synced: bool,
+}
+
+pub struct Host {
+    address: SocketAddr,
+    name: String,
+    clients: Arc<Mutex<Vec<Client>>>,
+    // everything the viewers send, read by a thread per client.
+    requests: Mutex<mpsc::Receiver<(SocketAddr, Message)>>,
+    // off makes it a broadcast, viewers can only watch.
+    pub allow_edits: bool,
+    participants: Vec<String>,
+    // the grid as the viewers have it.
+    values: Vec<CellValue>,
+    // `CellRenderer::hash` of `values`, an unchanged grid skips the diff.
+    hash: u64,
+    bounds: i32,
+    rule: Option<Rule>,
+}
+
+impl Host {
+    pub fn start(address: &str, name: &str) -> Result<Host, String> {
+        let listener = TcpListener::bind(address).map_err(|e| format!("{}: {}", address, e))?;
+        let address = listener.local_addr().map_err(|e| e.to_string())?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let (requests, received) = mpsc::channel();
+
+        let accepted = clients.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let address = match stream.peer_addr() {
+                    Ok(address) => address,
+                    Err(_) => continue,
+                };
+                // the change lists are small and frequent.
+                stream.set_nodelay(true).ok();
+                let mut reader = match stream.try_clone() {
+                    Ok(reader) => reader,
+                    Err(_) => continue,
+                };
+                let requests = requests.clone();
+                thread::spawn(move || {
+                    while let Ok(message) = Message::read(&mut reader, MAX_REQUEST) {
+                        if requests.send((address, message)).is_err() {
+                            return;
+                        }
+                    }
+                });
+                let (sender, receiver) = mpsc::channel::<Arc<Vec<u8>>>();
+                thread::spawn(move || {
+                    for bytes in receiver {
+                        if stream.write_all(&bytes).is_err() {
+                            return;
+                        }
+                    }
+                });
+                logged!(info, "network: {} connected", address);
+                let name = address.to_string();
+                accepted.lock().unwrap().push(Client { address, name, sender, synced: false });
+            }
+        });
+
+        Ok(Host {
+            address,
+            name: name.to_string(),
+            clients,
+            requests: Mutex::new(received),
+            allow_edits: true,
+            participants: vec![],
+            values: vec![],
+            hash: GridHash::new(0).value(),
+            bounds: 0,
+            rule: None,
+        })
+    }
+
+    // the host first.
+    pub fn participants(&self) -> &[String] {
+        &self.participants
+    }
+
+    fn handle_requests(&mut self, actions: &mut EventWriter<Action>) {
+        let requests: Vec<_> = self.requests.lock().unwrap().try_iter().collect();
+        let mut clients = self.clients.lock().unwrap();
+        for (address, message) in requests {
+            let client = match clients.iter_mut().find(|client| client.address == address) {
+                Some(client) => client,
+                None => continue,
+            };
+            match message {
+                Message::Hello { name } => {
+                    // names are sent one per line.
+                    client.name = name.chars().filter(|c| !c.is_control()).collect::<String>()
+                        .trim().chars().take(MAX_NAME).collect();
+                }
+                Message::SetRule(rule) if self.allow_edits => {
+                    logged!(info, "network: {} changed the rule", client.name);
+                    actions.send(Action::SetRule(rule));
+                }
+                Message::SpawnNoise if self.allow_edits => {
+                    logged!(info, "network: {} spawned noise", client.name);
+                    actions.send(Action::SpawnNoise);
+                }
+                // the viewer already shows its own edit, the world puts it
+                // back on the host's rule.
+                Message::SetRule(_) => client.synced = false,
+                _ => (),
+            }
+        }
+    }
+
+    fn update(&mut self, sims: &Sims, actions: &mut EventWriter<Action>) {
+        self.handle_requests(actions);
+
+        let renderer = sims.renderer();
+        if renderer.bounds != self.bounds || self.rule.as_ref() != Some(sims.rule()) {
+            self.bounds = renderer.bounds;
+            self.rule = Some(sims.rule().clone());
+            self.values = vec![0; renderer.values.len()];
+            self.hash = GridHash::new(renderer.bounds).value();
+            for client in self.clients.lock().unwrap().iter_mut() {
+                client.synced = false;
+            }
+        }
+
+        let mut changes = vec![];
+        if renderer.hash() != self.hash {
+            self.hash = renderer.hash();
+            for (index, (old, new)) in self.values.iter_mut().zip(renderer.values.iter()).enumerate() {
+                if old != new {
+                    *old = *new;
+                    changes.push((index as u32, *new));
+                }
+            }
+        }
+
+        let mut clients = self.clients.lock().unwrap();
+        let tick = sims.tick();
+        let delta = match changes.is_empty() {
+            true  => None,
+            false => Some(Arc::new(Message::Changes { tick, cells: changes }.encode())),
+        };
+        let mut full = None;
+        clients.retain_mut(|client| {
+            if !client.synced {
+                client.synced = true;
+                let (world, cells) = full.get_or_insert_with(|| {
+                    let world = Message::World { bounds: self.bounds, rule: self.rule.clone().unwrap() };
+                    let cells = self.values.iter().enumerate()
+                        .filter(|(_, value)| **value != 0)
+                        .map(|(index, value)| (index as u32, *value))
+                        .collect();
+                    (Arc::new(world.encode()), Arc::new(Message::Changes { tick, cells }.encode()))
+                });
+                return client.sender.send(world.clone()).is_ok()
+                    && client.sender.send(cells.clone()).is_ok();
+            }
+            match &delta {
+                Some(delta) => client.sender.send(delta.clone()).is_ok(),
+                None => true,
+            }
+        });
+
+        let participants: Vec<String> = std::iter::once(self.name.clone())
+            .chain(clients.iter().map(|client| client.name.clone()))
+            .collect();
+        if participants != self.participants || full.is_some() {
+            let message = Arc::new(Message::Participants(participants.clone()).encode());
+            for client in clients.iter() {
+                client.sender.send(message.clone()).ok();
+            }
+            self.participants = participants;
+        }
+    }
+}
+
+
+pub struct Viewer {
+    address: String,
+    receiver: Mutex<mpsc::Receiver<Result<Message, String>>>,
+    // queued until the connection stands.
+    sender: mpsc::Sender<Vec<u8>>,
+    // the host's rule, a different one is an edit made here.
+    rule: Option<Rule>,
+    participants: Vec<String>,
+    tick: u64,
+    connected: bool,
+}
+
+impl Viewer {
+    // connects in the background, errors show up as the status.
+    pub fn connect(address: &str, name: &str) -> Viewer {
+        let (sender, receiver) = mpsc::channel();
+        let (outgoing, queued) = mpsc::channel::<Vec<u8>>();
+        outgoing.send(Message::Hello { name: name.to_string() }.encode()).ok();
+        let target = address.to_string();
+        thread::spawn(move || {
+            let stream = TcpStream::connect(&target).and_then(|stream| {
+                let writer = stream.try_clone()?;
+                Ok((stream, writer))
+            });
+            let (mut stream, mut writer) = match stream {
+                Ok(streams) => streams,
+                Err(e) => {
+                    sender.send(Err(format!("{}: {}", target, e))).ok();
+                    return;
+                }
+            };
+            thread::spawn(move || {
+                for bytes in queued {
+                    if writer.write_all(&bytes).is_err() {
+                        return;
+                    }
+                }
+            });
+            loop {
+                let message = Message::read(&mut stream, MAX_MESSAGE);
+                let failed = message.is_err();
+                if sender.send(message).is_err() || failed {
+                    return;
+                }
+            }
+        });
+        Viewer {
+            address: address.to_string(),
+            receiver: Mutex::new(receiver),
+            sender: outgoing,
+            rule: None,
+            participants: vec![],
+            tick: 0,
+            connected: false,
+        }
+    }
+
+    pub fn spawn_noise(&self) {
+        self.sender.send(Message::SpawnNoise.encode()).ok();
+    }
+
+    fn update(&mut self, sims: &mut Sims) -> Result<(), String> {
+        // the host does the simulating.
+        sims.set_paused(true);
+
+        if let Some(rule) = &self.rule {
+            if sims.rule() != rule {
+                self.sender.send(Message::SetRule(sims.rule().clone()).encode()).ok();
+                self.rule = Some(sims.rule().clone());
+            }
+        }
+
+        let messages: Vec<_> = self.receiver.lock().unwrap().try_iter().collect();
+        for message in messages {
+            match message? {
+                Message::World { bounds, rule } => {
+                    if !sims.capabilities().contains(SimCapabilities::SET_CELL) {
+                        return Err(format!("{} can't show a remote grid, pick another simulator", sims.active_name()));
+                    }
+                    sims.set_rule(rule);
+                    if sims.bounds() != bounds {
+                        sims.set_bounds(bounds.clamp(1, sims.active_max_bounds()));
+                    }
+                    if sims.bounds() != bounds {
+                        return Err(format!("{} doesn't support the host's bounds of {}", sims.active_name(), bounds));
+                    }
+                    sims.reset();
+                    self.rule = Some(sims.rule().clone());
+                    self.connected = true;
+                }
+                Message::Changes { tick, cells } => {
+                    let bounds = sims.bounds();
+                    let size = (bounds*bounds*bounds) as usize;
+                    // the host isn't trusted, indices past the grid turn
+                    // into positions outside it, negative ones past i32.
+                    let cells: Vec<_> = cells.into_iter()
+                        .filter(|(index, _)| (*index as usize) < size)
+                        .map(|(index, value)| (utils::index_to_pos(index as usize, bounds), value))
+                        .collect();
+                    sims.set_cells(&cells);
+                    self.tick = tick;
+                }
+                Message::Participants(names) => self.participants = names,
+                // only the host takes requests.
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+
+pub enum NetworkMode {
+    Off,
+    Host(Host),
+    View(Viewer),
+}
+
+pub struct Network {
+    pub mode: NetworkMode,
+    address: String,
+    // shown to the other participants.
+    name: String,
+    error: Option<String>,
+}
+
+impl Network {
+    pub fn host(&mut self, address: &str) {
+        self.error = None;
+        self.mode = match Host::start(address, &self.name) {
+            Ok(host) => NetworkMode::Host(host),
+            Err(error) => {
+                self.error = Some(error);
+                NetworkMode::Off
+            }
+        };
+    }
+
+    pub fn view(&mut self, address: &str) {
+        self.error = None;
+        self.mode = NetworkMode::View(Viewer::connect(address, &self.name));
+    }
+
+    pub fn stop(&mut self) {
+        self.mode = NetworkMode::Off;
+    }
+
+    fn from_args() -> Network {
+        let mut this = Network {
+            mode: NetworkMode::Off,
+            address: format!("127.0.0.1:{}", DEFAULT_PORT),
+            name: std::env::var("USER").or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "anonymous".into()),
+            error: None,
+        };
+        let args: Vec<String> = std::env::args().collect();
+        let value = |i: usize| args.get(i + 1).filter(|arg| !arg.starts_with("--")).cloned();
+        for (i, arg) in args.iter().enumerate() {
+            match arg.as_str() {
+                "--host" => {
+                    let address = value(i).unwrap_or_else(|| format!("0.0.0.0:{}", DEFAULT_PORT));
+                    this.host(&address);
+                }
+                "--view" => match value(i) {
+                    Some(address) => {
+                        this.address = address.clone();
+                        this.view(&address);
+                    }
+                    None => this.error = Some("--view needs an address".into()),
+                },
+                _ => (),
+            }
+        }
+        if let Some(error) = &this.error {
+            logged!(warn, "network: {}", error);
+        }
+        this
+    }
+}
+
+
+fn update_host(
+    mut this: ResMut<Network>,
+    sims: Res<Sims>,
+    mut actions: EventWriter<Action>,
+) {
+    if let NetworkMode::Host(host) = &mut this.mode {
+        host.update(&sims, &mut actions);
+    }
+}
+
+fn update_viewer(
+    mut this: ResMut<Network>,
+    mut sims: ResMut<Sims>,
+) {
+    let result = match &mut this.mode {
+        NetworkMode::View(viewer) => viewer.update(&mut sims),
+        _ => return,
+    };
+    if let Err(error) = result {
+        logged!(warn, "network: {}", error);
+        this.error = Some(error);
+        this.mode = NetworkMode::Off;
+    }
+}
+
+fn participants_ui(ui: &mut egui::Ui, participants: &[String]) {
+    ui.label(format!("{} participants:", participants.len()));
+    for (i, name) in participants.iter().enumerate() {
+        match i {
+            0 => ui.weak(format!("{} (host)", name)),
+            _ => ui.weak(name),
+        };
+    }
+}
+
+fn update_ui(
+    mut this: ResMut<Network>,
+    sims: Res<Sims>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let this = &mut *this;
+    egui::Window::new("Network").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        match &mut this.mode {
+            NetworkMode::Off => {
+                ui.horizontal(|ui| {
+                    ui.label("address");
+                    ui.text_edit_singleline(&mut this.address);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("name");
+                    ui.add(egui::TextEdit::singleline(&mut this.name).char_limit(MAX_NAME));
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("host").on_hover_text("simulate here and stream to viewers").clicked() {
+                        let port = this.address.rsplit(':').next().unwrap_or("").to_string();
+                        this.host(&format!("0.0.0.0:{}", port));
+                    }
+                    // the remote grid is written into the active backend.
+                    let can_view = sims.capabilities().contains(SimCapabilities::SET_CELL);
+                    if ui.add_enabled(can_view, egui::Button::new("view"))
+                        .on_hover_text("only render what the host at the address simulates")
+                        .on_disabled_hover_text(format!("`{}` doesn't support cell access", sims.active_name()))
+                        .clicked()
+                    {
+                        let address = this.address.clone();
+                        this.view(&address);
+                    }
+                });
+            }
+            NetworkMode::Host(host) => {
+                ui.label(format!("hosting on {}", host.address));
+                ui.checkbox(&mut host.allow_edits, "viewers can change the rule and spawn noise");
+                participants_ui(ui, host.participants());
+            }
+            NetworkMode::View(viewer) => {
+                match viewer.connected {
+                    true  => ui.label(format!("viewing {}, tick {}", viewer.address, viewer.tick)),
+                    false => ui.label(format!("connecting to {}", viewer.address)),
+                };
+                if ui.add_enabled(viewer.connected, egui::Button::new("spawn noise on the host")).clicked() {
+                    viewer.spawn_noise();
+                }
+                ui.weak("rule edits go to the host, if it allows them");
+                participants_ui(ui, &viewer.participants);
+            }
+        }
+        if !matches!(this.mode, NetworkMode::Off) && ui.button("stop").clicked() {
+            this.stop();
+        }
+        if let Some(error) = &this.error {
+            ui.colored_label(egui::Color32::YELLOW, error);
+        }
+    });
+}
+
+
+pub struct NetworkPlugin;
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(Network::from_args())
+        .add_system(update_ui.with_run_criteria(simulating))
+        .add_system(update_viewer.with_run_criteria(simulating).before(SimsUpdate))
+        .add_system(update_host.with_run_criteria(simulating).after(SimsUpdate));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::example_library::fallback_example;
+
+    fn round_trip(message: Message) -> Message {
+        Message::read(&mut message.encode().as_slice(), MAX_MESSAGE).unwrap()
+    }
+
+    fn framed(kind: u8, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![kind];
+        bytes.extend((payload.len() as u32).to_le_bytes());
+        bytes.extend(payload);
+        bytes
+    }
+
+    #[test]
+    fn every_kind_round_trips() {
+        let rule = fallback_example().rule;
+        match round_trip(Message::World { bounds: 48, rule: rule.clone() }) {
+            Message::World { bounds, rule: read } => assert!(bounds == 48 && read == rule),
+            _ => panic!("not a world"),
+        }
+        let cells = vec![(0, 1), (7, 0), (u32::MAX, CellValue::MAX)];
+        match round_trip(Message::Changes { tick: 1 << 40, cells: cells.clone() }) {
+            Message::Changes { tick, cells: read } => assert!(tick == 1 << 40 && read == cells),
+            _ => panic!("not a change list"),
+        }
+        match round_trip(Message::Hello { name: "viewer".to_string() }) {
+            Message::Hello { name } => assert_eq!(name, "viewer"),
+            _ => panic!("not a hello"),
+        }
+        match round_trip(Message::SetRule(rule.clone())) {
+            Message::SetRule(read) => assert!(read == rule),
+            _ => panic!("not a rule"),
+        }
+        assert!(matches!(round_trip(Message::SpawnNoise), Message::SpawnNoise));
+        let names = vec!["host".to_string(), "viewer".to_string()];
+        match round_trip(Message::Participants(names.clone())) {
+            Message::Participants(read) => assert_eq!(read, names),
+            _ => panic!("not the participants"),
+        }
+    }
+
+    #[test]
+    fn malformed_messages_are_rejected() {
+        let mut truncated = Message::Hello { name: "viewer".to_string() }.encode();
+        truncated.pop();
+        assert!(Message::read(&mut truncated.as_slice(), MAX_MESSAGE).is_err());
+        assert!(Message::read(&mut &truncated[..3], MAX_MESSAGE).is_err());
+
+        // a tick and one change missing a byte of its value.
+        let changes = framed(CHANGES, &[0; 8 + 5]);
+        assert!(Message::read(&mut changes.as_slice(), MAX_MESSAGE).is_err());
+        let world = framed(WORLD, &[0; 3]);
+        assert!(Message::read(&mut world.as_slice(), MAX_MESSAGE).is_err());
+        let unknown = framed(PARTICIPANTS + 1, &[]);
+        assert!(Message::read(&mut unknown.as_slice(), MAX_MESSAGE).is_err());
+    }
+
+    #[test]
+    fn oversized_messages_are_rejected_before_reading_them() {
+        let mut header = vec![HELLO];
+        header.extend((MAX_REQUEST as u32 + 1).to_le_bytes());
+        let error = Message::read(&mut header.as_slice(), MAX_REQUEST).err().unwrap();
+        assert!(error.contains("limit"), "{}", error);
+
+        // fine for a viewer, which then runs out of bytes.
+        let error = Message::read(&mut header.as_slice(), MAX_MESSAGE).err().unwrap();
+        assert!(!error.contains("limit"), "{}", error);
+    }
+}
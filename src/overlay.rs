@@ -0,0 +1,135 @@
+use bevy::{prelude::*, window::WindowId};
+use bevy_egui::{egui, EguiContext};
+
+use crate::{app_state::simulating, cells::{Sims, SimsUpdate}};
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    fn anchor(&self) -> (egui::Align2, egui::Vec2) {
+        let margin = 16.0;
+        match self {
+            Corner::TopLeft     => (egui::Align2::LEFT_TOP,     egui::vec2( margin,  margin)),
+            Corner::TopRight    => (egui::Align2::RIGHT_TOP,    egui::vec2(-margin,  margin)),
+            Corner::BottomLeft  => (egui::Align2::LEFT_BOTTOM,  egui::vec2( margin, -margin)),
+            Corner::BottomRight => (egui::Align2::RIGHT_BOTTOM, egui::vec2(-margin, -margin)),
+        }
+    }
+}
+
+
+// captions for recordings, drawn on top of the scene. F6 toggles it.
+pub struct Overlay {
+    pub enabled: bool,
+    pub corner: Corner,
+    pub size: f32,
+    pub background: bool,
+    pub show_rule: bool,
+    pub show_tick: bool,
+    pub show_population: bool,
+    pub show_backend: bool,
+}
+
+impl Default for Overlay {
+    fn default() -> Self {
+        Overlay {
+            enabled: false,
+            corner: Corner::TopRight,
+            size: 20.0,
+            background: true,
+            show_rule: true,
+            show_tick: true,
+            show_population: true,
+            show_backend: false,
+        }
+    }
+}
+
+
+pub fn update(
+    mut this: ResMut<Overlay>,
+    sims: Res<Sims>,
+    keys: Res<Input<KeyCode>>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    if !egui_context.ctx_mut().wants_keyboard_input() && keys.just_pressed(KeyCode::F6) {
+        this.enabled = !this.enabled;
+    }
+
+    egui::Window::new("Overlay").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.checkbox(&mut this.enabled, "show overlay (F6)");
+        egui::ComboBox::from_label("corner")
+            .selected_text(format!("{:?}", this.corner))
+            .show_ui(ui, |ui| {
+                for corner in [Corner::TopLeft, Corner::TopRight, Corner::BottomLeft, Corner::BottomRight] {
+                    ui.selectable_value(&mut this.corner, corner, format!("{:?}", corner));
+                }
+            });
+        ui.add(egui::Slider::new(&mut this.size, 10.0..=64.0).text("text size"));
+        ui.checkbox(&mut this.background, "background");
+        ui.checkbox(&mut this.show_rule, "rule");
+        ui.checkbox(&mut this.show_tick, "tick");
+        ui.checkbox(&mut this.show_population, "population");
+        ui.checkbox(&mut this.show_backend, "backend");
+    });
+
+    if !this.enabled {
+        return;
+    }
+
+    let mut lines = vec![];
+    if this.show_rule {
+        lines.push(sims.rule().to_string());
+    }
+    if this.show_tick {
+        lines.push(format!("tick {}", sims.tick()));
+    }
+    if this.show_population {
+        lines.push(format!("{} cells", sims.cell_count()));
+    }
+    if this.show_backend {
+        lines.push(sims.active_name().to_string());
+    }
+
+    let (align, offset) = this.corner.anchor();
+    let frame = match this.background {
+        true  => egui::Frame {
+            fill: egui::Color32::from_black_alpha(160),
+            margin: egui::vec2(8.0, 8.0),
+            corner_radius: 4.0,
+            ..egui::Frame::none()
+        },
+        false => egui::Frame::none(),
+    };
+    // always on the view, also when the controls have a window of their own.
+    egui::Area::new("overlay")
+        .anchor(align, offset)
+        .interactable(false)
+        .show(egui_context.ctx_for_window_mut(WindowId::primary()), |ui| {
+            frame.show(ui, |ui| {
+                for line in lines {
+                    ui.label(egui::RichText::new(line)
+                        .size(this.size)
+                        .monospace()
+                        .color(egui::Color32::WHITE));
+                }
+            });
+        });
+}
+
+
+pub struct OverlayPlugin;
+impl Plugin for OverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<Overlay>()
+        .add_system(update.after(SimsUpdate).with_run_criteria(simulating));
+    }
+}
@@ -0,0 +1,165 @@
+// the app's own plugins and startup, everything but bevy's `DefaultPlugins`,
+// so main.rs and tests/app.rs build the same app on different bevy plugins.
+
+use bevy::{prelude::*, render::view::NoFrustumCulling};
+use bevy_egui::EguiPlugin;
+
+use crate::{
+    *,
+    cell_event::CellStatesChangedEvent,
+    cell_renderer::*,
+    cells::modifiers::{Modified, Symmetry, Statistics},
+    rotating_camera::{RotatingCamera, RotatingCameraPlugin},
+};
+
+
+pub fn add_plugins(app: &mut App) {
+    app
+        .add_plugin(EguiPlugin)
+        .add_plugin(controls_window::ControlsWindowPlugin)
+        .insert_resource(ClearColor(Color::rgb(0.65f32, 0.9f32, 0.96f32)))
+        .add_event::<CellStatesChangedEvent>()
+        .add_plugin(RotatingCameraPlugin)
+        .add_plugin(camera_export::CameraExportPlugin)
+        .add_plugin(CellMaterialPlugin)
+        .add_plugin(gpu_instances::GpuInstancesPlugin)
+        .add_plugin(background::BackgroundPlugin)
+        .add_plugin(threading::ThreadingPlugin)
+        .add_plugin(cells::SimsPlugin)
+        .add_plugin(action::ActionPlugin)
+        .add_plugin(app_state::AppStatePlugin { backends })
+        .add_plugin(self_test::SelfTestPlugin { backends })
+        .add_plugin(triggers::TriggersPlugin)
+        .add_plugin(throttle::ThrottlePlugin)
+        .add_plugin(governor::GovernorPlugin)
+        .add_plugin(hooks::HooksPlugin)
+        .add_plugin(console::ConsolePlugin)
+        .add_plugin(command_palette::CommandPalettePlugin)
+        .add_plugin(network::NetworkPlugin)
+        .add_plugin(graphics::GraphicsPlugin)
+        .add_plugin(volume::VolumePlugin)
+        .add_plugin(neighbour_field::NeighbourFieldPlugin)
+        .add_plugin(prediction::PredictionPlugin)
+        .add_plugin(profiler::ProfilerPlugin)
+        .add_plugin(diagnostics::DiagnosticsPlugin)
+        .add_plugin(backend_chart::BackendChartPlugin)
+        .add_plugin(timeline::TimelinePlugin)
+        .add_plugin(rewind::RewindPlugin)
+        .add_plugin(rule_view::RuleViewPlugin)
+        .add_plugin(rule_history::RuleHistoryPlugin)
+        .add_plugin(random_palette::RandomPalettePlugin)
+        .add_plugin(seeding::SeedingPlugin)
+        .add_plugin(project::ProjectPlugin)
+        .add_plugin(example_library::ExampleLibraryPlugin)
+        .add_plugin(demo::DemoPlugin)
+        .add_plugin(overlay::OverlayPlugin)
+        .add_plugin(tour::TourPlugin)
+        .add_plugin(thumbnails::ThumbnailsPlugin)
+        .add_plugin(shader_editor::ShaderEditorPlugin)
+        .add_plugin(window_title::WindowTitlePlugin)
+        .add_plugin(power::PowerPlugin)
+        .add_startup_system(setup);
+
+    #[cfg(feature = "post_process")]
+    app.add_plugin(post_process::PostProcessPlugin);
+    #[cfg(feature = "rule_packs")]
+    app.add_plugin(rule_pack::RulePackPlugin);
+}
+
+pub fn backends() -> Vec<(String, Box<dyn cells::Sim>)> {
+    let mut backends: Vec<(String, Box<dyn cells::Sim>)> = vec![];
+    let mut add = |name: &str, sim: Box<dyn cells::Sim>| backends.push((name.into(), sim));
+
+    add("tantan single-threaded",
+        Box::new(cells::tantan::CellsSinglethreaded::new()));
+
+    add("tantan multi-threaded",
+        Box::new(cells::tantan::CellsMultithreaded::new()));
+
+    add("leddoo single-threaded",
+        Box::new(cells::leddoo::LeddooSingleThreaded::new()));
+
+    add("leddoo single-threaded (morton)",
+        Box::new(cells::leddoo::LeddooSingleThreaded::with_layout(utils::CellLayout::Morton)));
+
+    add("leddoo bit-packed (1-2 states)",
+        Box::new(cells::leddoo::LeddooBitPacked::new()));
+
+    add("leddoo atomic",
+        Box::new(cells::leddoo::LeddooAtomic::new()));
+
+    add("leddoo atomic + symmetry",
+        Box::new(Modified::new(Box::new(cells::leddoo::LeddooAtomic::new()))
+            .with(Box::new(Symmetry::default()))));
+
+    add("leddoo single-threaded + symmetry + statistics",
+        Box::new(Modified::new(Box::new(cells::leddoo::LeddooSingleThreaded::new()))
+            .with(Box::new(Symmetry::default()))
+            .with(Box::new(Statistics::new())));
+
+    add("2d plane",
+        Box::new(cells::flat::Plane2d::new()));
+
+    add("1d automaton over time",
+        Box::new(cells::flat::Elementary1d::new()));
+
+    add("2d automaton over time",
+        Box::new(cells::flat::Stacked2d::new()));
+
+    backends
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut sims: ResMut<cells::Sims>,
+) {
+    for (name, sim) in backends() {
+        sims.add_sim(name, sim);
+    }
+
+    for example in example_library::load_examples(example_library::EXAMPLE_DIR) {
+        sims.add_example(example);
+    }
+    if sims.examples().is_empty() {
+        sims.add_example(example_library::fallback_example());
+    }
+    sims.set_example(0);
+
+
+    commands.spawn().insert_bundle((
+        meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+        Transform::from_xyz(0.0, 0.0, 0.0),
+        GlobalTransform::default(),
+        InstanceMaterialData(
+            (1..=10)
+                .flat_map(|x| (1..=100).map(move |y| (x, y)))
+                .map(|(x, y)| InstanceData::cell(
+                    IVec3::new(x, y, 0),
+                    1.0,
+                    Color::hsla(x as f32 * 36., y as f32 / 10.0, 0.5, 1.0).as_rgba_f32(),
+                    f32::MIN,
+                ))
+                .collect(),
+        ),
+        Visibility::default(),
+        ComputedVisibility::default(),
+        // NOTE: Frustum culling is done based on the Aabb of the Mesh and the GlobalTransform.
+        // As the cube is at the origin, if its Aabb moves outside the view frustum, all the
+        // instanced cubes will be culled.
+        // The InstanceMaterialData contains the 'GlobalTransform' information for this custom
+        // instancing, and that is not taken into account with the built-in frustum culling.
+        // We must disable the built-in frustum culling by adding the `NoFrustumCulling` marker
+        // component to avoid incorrect culling.
+        NoFrustumCulling,
+        CellRenderMode::Mesh,
+    ));
+
+    // camera
+    commands
+        .spawn_bundle(PerspectiveCameraBundle {
+            transform: Transform::from_xyz(0.0, 0.0, 15.0).looking_at(Vec3::ZERO, Vec3::Y),
+            ..Default::default()
+        })
+        .insert(RotatingCamera::default());
+}
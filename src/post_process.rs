@@ -0,0 +1,624 @@
+// bloom, depth of field and outlines, a pass in the 3d render graph after
+// the main pass. while any effect is on, the main pass draws into offscreen textures
+// instead of the window, see `queue_post_process`, and the pass composites
+// them into the window. post_process.wgsl has the shaders.
+//
+// bloom: the bright parts of the frame, and every emissive cell, blurred at
+// half resolution and added on top. the cell shader marks the emissive cells
+// with 0 alpha.
+// depth of field: every pixel gathers a disc of its neighbors, sized by how
+// far its depth is from the focus distance.
+// outline: lines where the depth jumps or the normal turns. the normals are
+// reconstructed from the depth, the main pass doesn't write any.
+
+use bevy::{
+    core_pipeline::{draw_3d_graph, Transparent3d, ViewDepthTexture},
+    ecs::query::QueryState,
+    prelude::*,
+    render::{
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+        render_phase::RenderPhase,
+        render_resource::*,
+        renderer::{RenderContext, RenderDevice},
+        texture::{BevyDefault, TextureCache},
+        view::{ExtractedView, Msaa, ViewTarget},
+        RenderApp, RenderStage,
+    },
+    utils::HashMap,
+};
+use bevy_egui::{egui, EguiContext};
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    app_state::simulating,
+    cell_renderer::CellShaderSettings,
+    cells::{color_picker, Sims, SimsUpdate},
+    rotating_camera::RotatingCamera,
+    volume::VolumeTransform,
+};
+
+const POST_PASS: &str = "post_process";
+const BLOOM_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+const NORMALS_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+
+#[derive(Clone)]
+pub struct PostProcessSettings {
+    pub bloom: bool,
+    pub bloom_intensity: f32,
+    // luminance above which a pixel glows, emissive cells always do.
+    pub bloom_threshold: f32,
+    // distance between the blur taps, in half resolution pixels.
+    pub bloom_spread: f32,
+
+    pub depth_of_field: bool,
+    // distance from the camera that is sharp, and how much further or
+    // closer the blur is at its largest.
+    pub focus_distance: f32,
+    pub focus_range: f32,
+    // radius in pixels.
+    pub max_blur: f32,
+    // keep `focus_distance` on the cell in the middle of the view, or on the
+    // volume's center when there is none. see `auto_focus`.
+    pub auto_focus: bool,
+
+    pub outline: bool,
+    // in pixels.
+    pub outline_thickness: f32,
+    pub outline_color: Color,
+    // relative to the distance, a jump of 0.05 at 100 units is 5 units.
+    pub outline_depth_threshold: f32,
+    // 1 - cos of the angle, 1 only outlines right angles and sharper.
+    pub outline_normal_threshold: f32,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        PostProcessSettings {
+            bloom: false,
+            bloom_intensity: 1.0,
+            bloom_threshold: 0.8,
+            bloom_spread: 1.5,
+            depth_of_field: false,
+            focus_distance: 150.0,
+            focus_range: 60.0,
+            max_blur: 8.0,
+            auto_focus: true,
+            outline: false,
+            outline_thickness: 2.0,
+            outline_color: Color::rgb(0.05, 0.05, 0.08),
+            outline_depth_threshold: 0.05,
+            outline_normal_threshold: 0.3,
+        }
+    }
+}
+
+impl PostProcessSettings {
+    fn any(&self) -> bool {
+        self.bloom || self.depth_of_field || self.outline
+    }
+
+    fn uniform(&self, projection: Mat4) -> PostUniform {
+        let flag = |enabled: bool| if enabled { 1.0 } else { 0.0 };
+        PostUniform {
+            bloom: Vec4::new(flag(self.bloom), self.bloom_intensity, self.bloom_threshold, self.bloom_spread),
+            depth_of_field: Vec4::new(flag(self.depth_of_field), self.focus_distance, self.focus_range, self.max_blur),
+            outline: Vec4::new(
+                flag(self.outline),
+                self.outline_thickness,
+                self.outline_depth_threshold,
+                self.outline_normal_threshold),
+            outline_color: self.outline_color.as_linear_rgba_f32().into(),
+            projection: Vec4::new(projection.w_axis.z, projection.x_axis.x, projection.y_axis.y, 0.0),
+        }
+    }
+}
+
+// must match `PostSettings` in post_process.wgsl.
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct PostUniform {
+    bloom: Vec4,
+    depth_of_field: Vec4,
+    outline: Vec4,
+    outline_color: Vec4,
+    projection: Vec4,
+}
+
+
+fn update(
+    mut settings: ResMut<PostProcessSettings>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Post processing").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.checkbox(&mut settings.bloom, "bloom");
+        if settings.bloom {
+            ui.add(egui::Slider::new(&mut settings.bloom_intensity, 0.0..=4.0)
+                .text("intensity"));
+            ui.add(egui::Slider::new(&mut settings.bloom_threshold, 0.0..=1.0)
+                .text("threshold"));
+            ui.add(egui::Slider::new(&mut settings.bloom_spread, 0.5..=4.0)
+                .text("spread"));
+            ui.weak("emissive cells always glow, see the materials in the rule settings.");
+        }
+
+        ui.add_space(12.0);
+
+        ui.checkbox(&mut settings.depth_of_field, "depth of field");
+        if settings.depth_of_field {
+            ui.checkbox(&mut settings.auto_focus, "focus on the cell in the middle of the view");
+            ui.add_enabled(!settings.auto_focus,
+                egui::Slider::new(&mut settings.focus_distance, 0.0..=400.0)
+                .text("focus distance"));
+            ui.add(egui::Slider::new(&mut settings.focus_range, 1.0..=200.0)
+                .text("focus range"));
+            ui.add(egui::Slider::new(&mut settings.max_blur, 1.0..=16.0)
+                .text("max blur (px)"));
+        }
+
+        ui.add_space(12.0);
+
+        ui.checkbox(&mut settings.outline, "outline");
+        if settings.outline {
+            ui.add(egui::Slider::new(&mut settings.outline_thickness, 1.0..=8.0)
+                .text("thickness (px)"));
+            color_picker(ui, &mut settings.outline_color);
+            ui.add(egui::Slider::new(&mut settings.outline_depth_threshold, 0.005..=0.5)
+                .logarithmic(true)
+                .text("depth threshold"));
+            ui.add(egui::Slider::new(&mut settings.outline_normal_threshold, 0.05..=1.0)
+                .text("normal threshold"));
+        }
+    });
+}
+
+// eases the focus distance onto the first live cell straight ahead of the
+// camera, so it stays in focus while the camera orbits. when the ray misses,
+// onto the volume's center.
+fn auto_focus(
+    sims: Res<Sims>,
+    time: Res<Time>,
+    volume: Res<VolumeTransform>,
+    cameras: Query<&Transform, With<RotatingCamera>>,
+    mut settings: ResMut<PostProcessSettings>,
+) {
+    if !settings.depth_of_field || !settings.auto_focus {
+        return;
+    }
+    let camera = match cameras.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    let (origin, direction) = volume.ray_to_volume(camera.translation, camera.forward());
+    let distance = sims.renderer().raycast(origin, direction)
+        .unwrap_or_else(|| (volume.translation - camera.translation).dot(camera.forward()));
+    let t = (time.delta_seconds() * 8.0).min(1.0);
+    settings.focus_distance += (distance.max(0.0) - settings.focus_distance) * t;
+}
+
+// the emissive cells only need marking while something reads the mark.
+fn update_emissive_mask(
+    settings: Res<PostProcessSettings>,
+    mut shader_settings: ResMut<CellShaderSettings>,
+) {
+    if shader_settings.emissive_mask != settings.bloom {
+        shader_settings.emissive_mask = settings.bloom;
+    }
+}
+
+fn extract_settings(mut commands: Commands, settings: Res<PostProcessSettings>) {
+    commands.insert_resource(settings.clone());
+}
+
+
+#[derive(Clone, Copy)]
+struct PostProcessPipelineIds {
+    bloom_prefilter: CachedPipelineId,
+    bloom_blur_horizontal: CachedPipelineId,
+    bloom_blur_vertical: CachedPipelineId,
+    reconstruct_normals: CachedPipelineId,
+    composite: CachedPipelineId,
+}
+
+impl PostProcessPipelineIds {
+    fn all(&self) -> [CachedPipelineId; 5] {
+        [
+            self.bloom_prefilter,
+            self.bloom_blur_horizontal,
+            self.bloom_blur_vertical,
+            self.reconstruct_normals,
+            self.composite,
+        ]
+    }
+}
+
+pub struct PostProcessPipeline {
+    shader: Handle<Shader>,
+    sampler: Sampler,
+    bloom_layout: BindGroupLayout,
+    // by whether the depth texture is multisampled.
+    normals_layouts: [BindGroupLayout; 2],
+    composite_layouts: [BindGroupLayout; 2],
+    // by msaa sample count, queued on first use.
+    pipelines: HashMap<u32, PostProcessPipelineIds>,
+}
+
+impl FromWorld for PostProcessPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let world = world.cell();
+        let asset_server = world.get_resource::<AssetServer>().unwrap();
+        let shader = asset_server.load("shaders/post_process.wgsl");
+
+        let render_device = world.get_resource::<RenderDevice>().unwrap();
+        let entry = |binding, ty| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty,
+            count: None,
+        };
+        let texture = |binding, sample_type, multisampled| entry(binding, BindingType::Texture {
+            sample_type,
+            view_dimension: TextureViewDimension::D2,
+            multisampled,
+        });
+        let common = [
+            texture(0, TextureSampleType::Float { filterable: true }, false),
+            entry(1, BindingType::Sampler(SamplerBindingType::Filtering)),
+            entry(2, BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: BufferSize::new(std::mem::size_of::<PostUniform>() as u64),
+            }),
+        ];
+
+        let normals_layout = |multisampled| render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("post process normals layout"),
+            entries: &[common[2], texture(4, TextureSampleType::Depth, multisampled)],
+        });
+        let bloom_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("bloom layout"),
+            entries: &common,
+        });
+        let composite_layout = |multisampled| {
+            let mut entries = common.to_vec();
+            entries.push(texture(3, TextureSampleType::Float { filterable: true }, false));
+            entries.push(texture(4, TextureSampleType::Depth, multisampled));
+            entries.push(texture(5, TextureSampleType::Float { filterable: true }, false));
+            render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("post process composite layout"),
+                entries: &entries,
+            })
+        };
+
+        PostProcessPipeline {
+            shader,
+            sampler: render_device.create_sampler(&SamplerDescriptor {
+                label: Some("post process sampler"),
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                ..Default::default()
+            }),
+            normals_layouts: [normals_layout(false), normals_layout(true)],
+            composite_layouts: [composite_layout(false), composite_layout(true)],
+            bloom_layout,
+            pipelines: HashMap::default(),
+        }
+    }
+}
+
+impl PostProcessPipeline {
+    fn descriptor(&self,
+        entry_point: &'static str, layout: &BindGroupLayout, format: TextureFormat, shader_defs: &[String],
+    ) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some(entry_point.into()),
+            layout: Some(vec![layout.clone()]),
+            vertex: VertexState {
+                shader: self.shader.clone(),
+                shader_defs: shader_defs.to_vec(),
+                entry_point: "vertex".into(),
+                buffers: vec![],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                shader: self.shader.clone(),
+                shader_defs: shader_defs.to_vec(),
+                entry_point: entry_point.into(),
+                targets: vec![ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                }],
+            }),
+        }
+    }
+
+    fn pipelines(&mut self, pipeline_cache: &mut RenderPipelineCache, samples: u32) -> PostProcessPipelineIds {
+        if let Some(ids) = self.pipelines.get(&samples) {
+            return *ids;
+        }
+        let multisampled = samples > 1;
+        let shader_defs = if multisampled { vec!["MULTISAMPLED".to_string()] } else { vec![] };
+        let normals_layout = &self.normals_layouts[multisampled as usize];
+        let composite_layout = &self.composite_layouts[multisampled as usize];
+        let ids = PostProcessPipelineIds {
+            bloom_prefilter: pipeline_cache.queue(
+                self.descriptor("bloom_prefilter", &self.bloom_layout, BLOOM_FORMAT, &shader_defs)),
+            bloom_blur_horizontal: pipeline_cache.queue(
+                self.descriptor("bloom_blur_horizontal", &self.bloom_layout, BLOOM_FORMAT, &shader_defs)),
+            bloom_blur_vertical: pipeline_cache.queue(
+                self.descriptor("bloom_blur_vertical", &self.bloom_layout, BLOOM_FORMAT, &shader_defs)),
+            reconstruct_normals: pipeline_cache.queue(
+                self.descriptor("reconstruct_normals", normals_layout, NORMALS_FORMAT, &shader_defs)),
+            composite: pipeline_cache.queue(
+                self.descriptor("composite", composite_layout, TextureFormat::bevy_default(), &shader_defs)),
+        };
+        self.pipelines.insert(samples, ids);
+        ids
+    }
+}
+
+
+// what the pass of a view reads and writes, see `PostProcessNode`.
+#[derive(Component)]
+pub struct PostProcessTarget {
+    // the window's texture, the main pass draws elsewhere.
+    output: TextureView,
+    // the half resolution bloom textures, the blur goes back and forth.
+    bloom: [TextureView; 2],
+    normals: TextureView,
+    bloom_enabled: bool,
+    outline_enabled: bool,
+    pipelines: PostProcessPipelineIds,
+    bloom_prefilter: BindGroup,
+    bloom_blur_horizontal: BindGroup,
+    bloom_blur_vertical: BindGroup,
+    reconstruct_normals: BindGroup,
+    composite: BindGroup,
+}
+
+// points the main pass of every 3d view at offscreen color and depth
+// textures the pass can read, and keeps the window's texture for the pass to
+// write. with msaa, the offscreen color texture is the resolve target.
+// nothing is redirected until the pipelines are compiled, so the first
+// frames are drawn without the effects rather than not at all.
+#[allow(clippy::too_many_arguments)]
+fn queue_post_process(
+    mut commands: Commands,
+    settings: Res<PostProcessSettings>,
+    msaa: Res<Msaa>,
+    mut pipeline: ResMut<PostProcessPipeline>,
+    mut pipeline_cache: ResMut<RenderPipelineCache>,
+    mut texture_cache: ResMut<TextureCache>,
+    render_device: Res<RenderDevice>,
+    mut views: Query<(Entity, &ExtractedView, &mut ViewTarget, &mut ViewDepthTexture), With<RenderPhase<Transparent3d>>>,
+) {
+    if !settings.any() {
+        return;
+    }
+    let pipelines = pipeline.pipelines(&mut pipeline_cache, msaa.samples);
+    if pipelines.all().iter().any(|id| pipeline_cache.get(*id).is_none()) {
+        return;
+    }
+    let normals_layout = &pipeline.normals_layouts[(msaa.samples > 1) as usize];
+    let composite_layout = &pipeline.composite_layouts[(msaa.samples > 1) as usize];
+
+    for (entity, view, mut target, mut depth) in views.iter_mut() {
+        let size = Extent3d {
+            width: view.width,
+            height: view.height,
+            depth_or_array_layers: 1,
+        };
+        let half_size = Extent3d {
+            width: (view.width / 2).max(1),
+            height: (view.height / 2).max(1),
+            depth_or_array_layers: 1,
+        };
+        let mut texture = |label, size, sample_count, format| texture_cache.get(&render_device, TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        });
+        let scene = texture("post process scene", size, 1, TextureFormat::bevy_default());
+        let scene_depth = texture("post process depth", size, msaa.samples, TextureFormat::Depth32Float);
+        let bloom = [
+            texture("bloom a", half_size, 1, BLOOM_FORMAT).default_view,
+            texture("bloom b", half_size, 1, BLOOM_FORMAT).default_view,
+        ];
+        let normals = texture("post process normals", size, 1, NORMALS_FORMAT).default_view;
+
+        let output = std::mem::replace(&mut target.view, scene.default_view.clone());
+        *depth = ViewDepthTexture {
+            texture: scene_depth.texture,
+            view: scene_depth.default_view.clone(),
+        };
+
+        let uniform = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("post process uniform buffer"),
+            contents: bytemuck::bytes_of(&settings.uniform(view.projection)),
+            usage: BufferUsages::UNIFORM,
+        });
+        let uniform_entry = BindGroupEntry {
+            binding: 2,
+            resource: uniform.as_entire_binding(),
+        };
+        let bind_group = |label, layout: &BindGroupLayout, source: &TextureView, extra: &[BindGroupEntry]| {
+            let mut entries = vec![
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(source),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&pipeline.sampler),
+                },
+                uniform_entry.clone(),
+            ];
+            entries.extend_from_slice(extra);
+            render_device.create_bind_group(&BindGroupDescriptor {
+                label: Some(label),
+                layout,
+                entries: &entries,
+            })
+        };
+
+        let depth_entry = BindGroupEntry {
+            binding: 4,
+            resource: BindingResource::TextureView(&scene_depth.default_view),
+        };
+        let reconstruct_normals = render_device.create_bind_group(&BindGroupDescriptor {
+            label: Some("post process normals"),
+            layout: normals_layout,
+            entries: &[uniform_entry.clone(), depth_entry.clone()],
+        });
+
+        commands.entity(entity).insert(PostProcessTarget {
+            bloom_prefilter: bind_group("bloom prefilter", &pipeline.bloom_layout, &scene.default_view, &[]),
+            bloom_blur_horizontal: bind_group("bloom blur", &pipeline.bloom_layout, &bloom[0], &[]),
+            bloom_blur_vertical: bind_group("bloom blur", &pipeline.bloom_layout, &bloom[1], &[]),
+            composite: bind_group("post process composite", composite_layout, &scene.default_view, &[
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(&bloom[0]),
+                },
+                depth_entry,
+                BindGroupEntry {
+                    binding: 5,
+                    resource: BindingResource::TextureView(&normals),
+                },
+            ]),
+            reconstruct_normals,
+            output,
+            bloom,
+            normals,
+            bloom_enabled: settings.bloom,
+            outline_enabled: settings.outline,
+            pipelines,
+        });
+    }
+}
+
+
+pub struct PostProcessNode {
+    query: QueryState<&'static PostProcessTarget>,
+}
+
+impl PostProcessNode {
+    pub const IN_VIEW: &'static str = "view";
+
+    pub fn new(world: &mut World) -> Self {
+        PostProcessNode {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for PostProcessNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![SlotInfo::new(PostProcessNode::IN_VIEW, SlotType::Entity)]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(&self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(PostProcessNode::IN_VIEW)?;
+        // the effects are off, or the pipelines aren't compiled yet.
+        let target = match self.query.get_manual(world, view_entity) {
+            Ok(target) => target,
+            Err(_) => return Ok(()),
+        };
+        let pipeline_cache = world.get_resource::<RenderPipelineCache>().unwrap();
+        let encoder = &mut render_context.command_encoder;
+        let mut draw = |label, pipeline: CachedPipelineId, bind_group: &BindGroup, output: &TextureView| {
+            let pipeline = match pipeline_cache.get(pipeline) {
+                Some(pipeline) => pipeline,
+                None => return,
+            };
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[RenderPassColorAttachment {
+                    view: output,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK.into()),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        };
+
+        let pipelines = &target.pipelines;
+        if target.bloom_enabled {
+            draw("bloom prefilter", pipelines.bloom_prefilter, &target.bloom_prefilter, &target.bloom[0]);
+            draw("bloom blur", pipelines.bloom_blur_horizontal, &target.bloom_blur_horizontal, &target.bloom[1]);
+            draw("bloom blur", pipelines.bloom_blur_vertical, &target.bloom_blur_vertical, &target.bloom[0]);
+        }
+        if target.outline_enabled {
+            draw("reconstruct normals", pipelines.reconstruct_normals, &target.reconstruct_normals, &target.normals);
+        }
+        draw("post process composite", pipelines.composite, &target.composite, &target.output);
+        Ok(())
+    }
+}
+
+
+pub struct PostProcessPlugin;
+impl Plugin for PostProcessPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<PostProcessSettings>()
+        .add_system(update.with_run_criteria(simulating))
+        .add_system(auto_focus.with_run_criteria(simulating).after(SimsUpdate))
+        .add_system(update_emissive_mask);
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .init_resource::<PostProcessPipeline>()
+            .add_system_to_stage(RenderStage::Extract, extract_settings)
+            .add_system_to_stage(RenderStage::Queue, queue_post_process);
+
+        let node = PostProcessNode::new(&mut render_app.world);
+        let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        let draw_3d = graph.get_sub_graph_mut(draw_3d_graph::NAME).unwrap();
+        draw_3d.add_node(POST_PASS, node);
+        let input_node = draw_3d.input_node().unwrap().id;
+        draw_3d.add_slot_edge(input_node, draw_3d_graph::input::VIEW_ENTITY, POST_PASS, PostProcessNode::IN_VIEW).unwrap();
+        draw_3d.add_node_edge(draw_3d_graph::node::MAIN_PASS, POST_PASS).unwrap();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::camera::CameraProjection;
+
+    // post_process.wgsl turns the device depth back into a distance as
+    // near / depth, which only holds for bevy's infinite reverse z projection.
+    #[test]
+    fn depth_is_linearized_with_the_near_plane() {
+        let projection = PerspectiveProjection::default().get_projection_matrix();
+        let uniform = PostProcessSettings::default().uniform(projection);
+        for distance in [0.5, 25.0, 900.0] {
+            let clip = projection * Vec4::new(0.0, 0.0, -distance, 1.0);
+            let depth = clip.z / clip.w;
+            assert!((uniform.projection.x / depth - distance).abs() < distance * 1e-4);
+        }
+    }
+}
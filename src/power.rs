@@ -0,0 +1,133 @@
+use std::time::{Duration, Instant};
+
+use bevy::{
+    input::{keyboard::KeyboardInput, mouse::{MouseButtonInput, MouseWheel}},
+    prelude::*,
+    window::{CursorMoved, WindowFocused},
+};
+use bevy_egui::{egui, EguiContext};
+
+use crate::{app_state::simulating, cells::Sims, rotating_camera::RotatingCamera};
+
+// seconds without input before a paused, still scene counts as idle.
+const IDLE_AFTER: f64 = 1.0;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerState {
+    Full,
+    Unfocused,
+    // paused, the camera stands still and nobody touched anything.
+    Idle,
+}
+
+// caps the frame rate while the window is in the background, or while
+// nothing on screen changes. the sims tick once per frame (times ticks per
+// frame), so the tick rate drops with it. bevy 0.6 always redraws, so the
+// cap sleeps at the end of the frame.
+pub struct PowerSaving {
+    pub enabled: bool,
+    pub unfocused_fps: u32,
+    pub idle_fps: u32,
+    focused: bool,
+    last_input: f64,
+    state: PowerState,
+    frame_start: Option<Instant>,
+}
+
+impl Default for PowerSaving {
+    fn default() -> Self {
+        PowerSaving {
+            enabled: true,
+            unfocused_fps: 10,
+            idle_fps: 15,
+            focused: true,
+            last_input: 0.0,
+            state: PowerState::Full,
+            frame_start: None,
+        }
+    }
+}
+
+impl PowerSaving {
+    pub fn state(&self) -> PowerState {
+        self.state
+    }
+
+    fn frame_time(&self) -> Option<Duration> {
+        let fps = match self.state {
+            PowerState::Full => return None,
+            PowerState::Unfocused => self.unfocused_fps,
+            PowerState::Idle => self.idle_fps,
+        };
+        Some(Duration::from_secs_f64(1.0 / fps.max(1) as f64))
+    }
+}
+
+
+#[allow(clippy::too_many_arguments)]
+fn update(
+    mut this: ResMut<PowerSaving>,
+    sims: Res<Sims>,
+    time: Res<Time>,
+    cameras: Query<&RotatingCamera>,
+    mut focus: EventReader<WindowFocused>,
+    mut keys: EventReader<KeyboardInput>,
+    mut buttons: EventReader<MouseButtonInput>,
+    mut wheel: EventReader<MouseWheel>,
+    mut cursor: EventReader<CursorMoved>,
+) {
+    for event in focus.iter() {
+        this.focused = event.focused;
+    }
+    let now = time.seconds_since_startup();
+    let input = keys.iter().count() + buttons.iter().count() + wheel.iter().count() + cursor.iter().count();
+    if input > 0 {
+        this.last_input = now;
+    }
+
+    let still = sims.is_paused() && cameras.iter().all(|camera| camera.paused);
+    this.state = match (this.enabled, this.focused) {
+        (false, _) => PowerState::Full,
+        (true, false) => PowerState::Unfocused,
+        (true, true) if still && now - this.last_input > IDLE_AFTER => PowerState::Idle,
+        (true, true) => PowerState::Full,
+    };
+}
+
+fn limit_frame_rate(mut this: ResMut<PowerSaving>) {
+    if let (Some(frame_time), Some(start)) = (this.frame_time(), this.frame_start) {
+        if let Some(rest) = frame_time.checked_sub(start.elapsed()) {
+            std::thread::sleep(rest);
+        }
+    }
+    this.frame_start = Some(Instant::now());
+}
+
+fn update_ui(
+    mut this: ResMut<PowerSaving>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Power saving").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.checkbox(&mut this.enabled, "lower the frame rate when idle");
+        ui.add(egui::Slider::new(&mut this.unfocused_fps, 1..=60).text("fps in the background"));
+        ui.add(egui::Slider::new(&mut this.idle_fps, 1..=60).text("fps when paused and still"));
+        ui.weak(match this.state {
+            PowerState::Full => "full speed",
+            PowerState::Unfocused => "in the background",
+            PowerState::Idle => "idle",
+        });
+    });
+}
+
+
+pub struct PowerPlugin;
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<PowerSaving>()
+        .add_system(update.with_run_criteria(simulating))
+        .add_system(update_ui.with_run_criteria(simulating))
+        .add_system_to_stage(CoreStage::Last, limit_frame_rate);
+    }
+}
@@ -0,0 +1,152 @@
+use bevy::{prelude::*, render::view::NoFrustumCulling};
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    app_state::simulating,
+    cell_renderer::{CellRenderMode, DebugInstances, InstanceData, InstanceMaterialData},
+    cells::{Sims, SimsUpdate},
+    rule::CellValue,
+    threading::Threading,
+    utils,
+};
+
+// cells per task.
+const SLICE: usize = 32 * 32 * 32;
+
+#[derive(Component)]
+pub struct Ghosts;
+
+
+// shows what the next tick does without running it: births as green ghosts,
+// cells that stop surviving tinted red. the rule is applied to a copy of the
+// rendered cells.
+pub struct Prediction {
+    pub enabled: bool,
+    pub show_births: bool,
+    pub show_deaths: bool,
+    pub opacity: f32,
+    scratch: Vec<CellValue>,
+    births: usize,
+    deaths: usize,
+}
+
+impl Prediction {
+    pub fn new() -> Prediction {
+        Prediction {
+            enabled: false,
+            show_births: true,
+            show_deaths: true,
+            opacity: 0.4,
+            scratch: vec![],
+            births: 0,
+            deaths: 0,
+        }
+    }
+}
+
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    commands.spawn().insert_bundle((
+        meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+        Transform::default(),
+        GlobalTransform::default(),
+        InstanceMaterialData(vec![]),
+        Visibility::default(),
+        ComputedVisibility::default(),
+        NoFrustumCulling,
+        CellRenderMode::Translucent,
+        Ghosts,
+        DebugInstances,
+    ));
+}
+
+pub fn update(
+    mut this: ResMut<Prediction>,
+    mut sims: ResMut<Sims>,
+    threading: Res<Threading>,
+    mut ghosts: Query<&mut InstanceMaterialData, With<Ghosts>>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Prediction").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.checkbox(&mut this.enabled, "show the next tick");
+        ui.checkbox(&mut this.show_births, "births (green)");
+        ui.checkbox(&mut this.show_deaths, "deaths (red)");
+        ui.add(egui::Slider::new(&mut this.opacity, 0.05..=1.0).text("opacity"));
+        if this.enabled {
+            ui.label(format!("next tick: {} births, {} deaths", this.births, this.deaths));
+        }
+        ui.weak("works best paused, see \"step\" in the main window.");
+    });
+
+    let mut instances = match ghosts.iter_mut().next() {
+        Some(instances) => instances,
+        None => return,
+    };
+    if !this.enabled {
+        instances.0.clear();
+        return;
+    }
+    sims.keep_neighbors = true;
+
+    let this = &mut *this;
+    let rule = sims.rule();
+    let renderer = sims.renderer();
+    let bounds = renderer.bounds;
+    let (show_births, show_deaths, opacity) = (this.show_births, this.show_deaths, this.opacity);
+
+    this.scratch.resize(renderer.values.len(), 0);
+    let slices = threading.pool().scope(|scope| {
+        for (i, next) in this.scratch.chunks_mut(SLICE).enumerate() {
+            scope.spawn(async move {
+                let mut ghosts = vec![];
+                let (mut births, mut deaths) = (0, 0);
+                for (j, next) in next.iter_mut().enumerate() {
+                    let index = i*SLICE + j;
+                    let value = renderer.values[index];
+                    *next = rule.next_value(value, renderer.neighbors[index]);
+
+                    let pos = utils::index_to_pos(index, bounds);
+                    let (color, scale) =
+                        if value == 0 && *next != 0 {
+                            births += 1;
+                            if !show_births { continue; }
+                            ([0.2, 1.0, 0.3, opacity], 0.7)
+                        }
+                        else if value == rule.states && *next < rule.states {
+                            deaths += 1;
+                            if !show_deaths { continue; }
+                            ([1.0, 0.15, 0.1, opacity], 1.1)
+                        }
+                        else {
+                            continue;
+                        };
+                    ghosts.push(InstanceData::cell(pos, scale, color, f32::MIN));
+                }
+                (ghosts, births, deaths)
+            });
+        }
+    });
+
+    instances.0.clear();
+    this.births = 0;
+    this.deaths = 0;
+    for (ghosts, births, deaths) in slices {
+        instances.0.extend(ghosts);
+        this.births += births;
+        this.deaths += deaths;
+    }
+}
+
+
+pub struct PredictionPlugin;
+impl Plugin for PredictionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(Prediction::new())
+        .add_startup_system(setup)
+        .add_system(update.after(SimsUpdate).with_run_criteria(simulating));
+    }
+}
@@ -0,0 +1,103 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use bevy::{prelude::*, render::RenderApp};
+use bevy_egui::{egui, EguiContext};
+
+use crate::{app_state::simulating, cells::{Sims, SimsUpdate}};
+
+
+// written by the render world and shown in the profiler window.
+#[derive(Clone, Copy, Default)]
+pub struct RenderTimes {
+    // `prepare_instance_buffers`, the instances are copied into a new buffer
+    // every frame.
+    pub upload: Duration,
+    pub upload_bytes: usize,
+    // the main 3d pass on the gpu, see gpu_timing.rs. only measured while
+    // `measure_gpu` is set by the profiler window.
+    pub gpu_pass: Option<Duration>,
+    pub measure_gpu: bool,
+    // the instances gpu_instances.rs built, read back a few frames late.
+    // none while they're extracted on the cpu.
+    pub gpu_instances: Option<u32>,
+}
+
+// the same mutex is inserted into both worlds.
+#[derive(Clone, Default)]
+pub struct SharedRenderTimes(pub Arc<Mutex<RenderTimes>>);
+
+impl SharedRenderTimes {
+    pub fn get(&self) -> RenderTimes {
+        *self.0.lock().unwrap()
+    }
+
+    pub fn modify(&self, f: impl FnOnce(&mut RenderTimes)) {
+        f(&mut self.0.lock().unwrap());
+    }
+}
+
+
+fn row(ui: &mut egui::Ui, name: &str, value: String) {
+    ui.label(name);
+    ui.monospace(value);
+    ui.end_row();
+}
+
+pub fn update(
+    sims: Res<Sims>,
+    render_times: Res<SharedRenderTimes>,
+    time: Res<Time>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Profiler").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        let (update, render, extract) = sims.frame_times();
+        let mut times = render_times.get();
+
+        egui::Grid::new("profiler").num_columns(2).show(ui, |ui| {
+            row(ui, "frame", format!("{:.2?}", time.delta()));
+            ui.strong("cpu");
+            ui.end_row();
+            row(ui, "sim update (per tick)", format!("{:.2?}", update));
+            row(ui, "render", format!("{:.2?}", render));
+            row(ui, "extract instances", format!("{:.2?}", extract));
+            row(ui, "instance upload", format!("{:.2?} ({} KiB)",
+                times.upload, times.upload_bytes >> 10));
+            ui.strong("gpu");
+            ui.end_row();
+            row(ui, "main pass", match (cfg!(feature = "gpu_timing"), times.measure_gpu, times.gpu_pass) {
+                (false, _, _) => "build with --features gpu_timing".into(),
+                (true, false, _) => "off".into(),
+                (true, true, None) => "not supported by this gpu".into(),
+                (true, true, Some(pass)) => format!("{:.2?}", pass),
+            });
+            row(ui, "instances built", match times.gpu_instances {
+                Some(count) => count.to_string(),
+                None => "on the cpu".into(),
+            });
+        });
+
+        if cfg!(feature = "gpu_timing") {
+            if ui.checkbox(&mut times.measure_gpu, "measure the gpu").changed() {
+                render_times.modify(|t| t.measure_gpu = times.measure_gpu);
+            }
+            ui.weak("waits for the gpu every frame, which costs some fps.");
+        }
+    });
+}
+
+
+pub struct ProfilerPlugin;
+impl Plugin for ProfilerPlugin {
+    fn build(&self, app: &mut App) {
+        let render_times = SharedRenderTimes::default();
+        app
+        .insert_resource(render_times.clone())
+        .add_system(update.after(SimsUpdate).with_run_criteria(simulating));
+
+        app.sub_app_mut(RenderApp)
+            .insert_resource(render_times);
+
+        #[cfg(feature = "gpu_timing")]
+        crate::gpu_timing::build(app);
+    }
+}
@@ -0,0 +1,299 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::simulating,
+    cells::{Sims, SimsUpdate},
+    grid_file::{self, GridSave},
+    logged,
+    rotating_camera::RotatingCamera,
+    rule::{CellValue, ColorMethod, Rule},
+    seeding::Seeding,
+    theme::Theme,
+    utils,
+};
+
+pub const EXTENSION: &str = "ca3d";
+
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct CameraView {
+    pub rotation: f32,
+    // radians per second. projects saved before this was per frame turn
+    // about 60 times slower.
+    pub speed: f32,
+    pub dist: f32,
+    #[serde(default)]
+    pub height: f32,
+    pub center: Vec3,
+    #[serde(default)]
+    pub paused: bool,
+}
+
+// everything that makes up "my setup" in one ron file. the cells are
+// optional, without them the project starts from noise like an example.
+#[derive(Serialize, Deserialize)]
+pub struct Project {
+    pub rule: Rule,
+    pub backend: String,
+    pub bounds: i32,
+    pub color_method: ColorMethod,
+    pub color1: Color,
+    pub color2: Color,
+    #[serde(default)]
+    pub theme: Theme,
+    pub seeding: Seeding,
+    pub camera: Option<CameraView>,
+    #[serde(default)]
+    pub cells: Option<Vec<(IVec3, CellValue)>>,
+    // large grids keep their cells in a grid file next to the project
+    // instead, see grid_file.rs. the file name, relative to the project.
+    #[serde(default)]
+    pub grid: Option<String>,
+    // see `Example::tags`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Project {
+    pub fn capture(sims: &Sims, seeding: &Seeding, camera: Option<&RotatingCamera>, with_cells: bool) -> Project {
+        let (color_method, color1, color2) = sims.colors();
+        Project {
+            rule: sims.rule().clone(),
+            backend: sims.active_name().to_string(),
+            bounds: sims.bounds(),
+            color_method,
+            color1,
+            color2,
+            theme: sims.theme(),
+            seeding: seeding.clone(),
+            camera: camera.map(|camera| CameraView {
+                rotation: camera.rotation,
+                speed: camera.speed,
+                dist: camera.dist,
+                height: camera.height,
+                center: camera.center,
+                paused: camera.paused,
+            }),
+            cells: if with_cells { Some(sims.cells()) } else { None },
+            grid: None,
+            tags: sims.tags().to_vec(),
+        }
+    }
+
+    // a missing backend keeps the current one, the rest still applies.
+    pub fn apply(self, sims: &mut Sims, seeding: &mut Seeding, camera: Option<&mut RotatingCamera>) -> Result<(), String> {
+        let backend = sims.sim_names().position(|name| name == self.backend);
+
+        sims.checkpoint();
+        sims.set_rule(self.rule);
+        if let Some(index) = backend {
+            sims.set_sim(index);
+        }
+        sims.set_bounds(self.bounds);
+        sims.set_colors(self.color_method, self.color1, self.color2);
+        sims.set_theme(self.theme);
+        sims.set_tags(self.tags);
+        if let Some(cells) = self.cells {
+            // the backend may not support the saved bounds, and the file may
+            // have been edited by hand.
+            let bounds = sims.bounds();
+            let states = sims.rule().states;
+            let cells: Vec<_> = cells.into_iter()
+                .filter(|(pos, _)| pos.min_element() >= 0 && utils::is_in_bounds(*pos, bounds))
+                .map(|(pos, value)| (pos, value.min(states)))
+                .collect();
+            sims.reset();
+            sims.set_cells(&cells);
+        }
+        *seeding = self.seeding;
+
+        if let (Some(view), Some(camera)) = (self.camera, camera) {
+            camera.rotation = view.rotation;
+            camera.speed = view.speed;
+            camera.dist = view.dist;
+            camera.height = view.height;
+            camera.center = view.center;
+            camera.paused = view.paused;
+        }
+
+        match backend {
+            Some(_) => Ok(()),
+            None => Err(format!("unknown simulator `{}`, kept the current one", self.backend)),
+        }
+    }
+}
+
+
+pub fn save(path: &Path, project: &Project) -> Result<(), String> {
+    let text = ron::ser::to_string_pretty(project, ron::ser::PrettyConfig::default())
+        .map_err(|e| e.to_string())?;
+    fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn load(path: &Path) -> Result<Project, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut project: Project = ron::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if let Some(grid) = &project.grid {
+        let (_, cells) = grid_file::load(&path.with_file_name(grid))?;
+        project.cells = Some(cells);
+    }
+    Ok(project)
+}
+
+
+pub struct ProjectFile {
+    pub path: String,
+    pub with_cells: bool,
+    // opened on the first frame, eg. from the command line.
+    pending: Option<PathBuf>,
+    status: Option<Result<String, String>>,
+    grid_save: Option<GridSave>,
+    // written once `grid_save` is done, so it never points to a grid file
+    // that isn't there.
+    unsaved: Option<(PathBuf, Project)>,
+}
+
+// `celluar_automata my_setup.ca3d` opens the project on start.
+pub fn path_from_args(args: impl Iterator<Item = String>) -> Option<PathBuf> {
+    args.map(PathBuf::from)
+        .find(|path| path.extension().map(|e| e == EXTENSION).unwrap_or(false))
+}
+
+impl ProjectFile {
+    pub fn from_args(args: impl Iterator<Item = String>) -> ProjectFile {
+        let pending = path_from_args(args);
+        ProjectFile {
+            path: pending.as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|| format!("project.{}", EXTENSION)),
+            with_cells: false,
+            pending,
+            status: None,
+            grid_save: None,
+            unsaved: None,
+        }
+    }
+}
+
+
+pub fn update(
+    mut this: ResMut<ProjectFile>,
+    mut sims: ResMut<Sims>,
+    mut seeding: ResMut<Seeding>,
+    mut cameras: Query<&mut RotatingCamera>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let mut open = this.pending.take();
+    let mut camera = cameras.iter_mut().next();
+
+    if let Some(result) = this.grid_save.as_ref().and_then(GridSave::poll) {
+        let grid_path = this.grid_save.take().unwrap().path;
+        let (path, project) = this.unsaved.take().unwrap();
+        this.status = Some(result
+            .map_err(|error| format!("the cells weren't saved, so neither was {}: {}", path.display(), error))
+            .and_then(|()| save(&path, &project))
+            .map(|()| format!("saved {} and {}", path.display(), grid_path.display())));
+    }
+
+    egui::Window::new("Project").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("file");
+            ui.text_edit_singleline(&mut this.path);
+        });
+        ui.checkbox(&mut this.with_cells, "include the cells");
+        ui.horizontal(|ui| {
+            if ui.add_enabled(this.grid_save.is_none(), egui::Button::new("save")).clicked() {
+                let path = PathBuf::from(&this.path);
+                let large = this.with_cells && sims.bounds() >= grid_file::MIN_BOUNDS;
+                let mut project = Project::capture(&sims, &seeding, camera.as_deref(), this.with_cells && !large);
+                if large {
+                    let grid_path = path.with_extension(grid_file::EXTENSION);
+                    project.grid = grid_path.file_name().map(|name| name.to_string_lossy().into_owned());
+                    let renderer = sims.renderer();
+                    this.grid_save = Some(GridSave::start(&grid_path, renderer.bounds, renderer.values.clone()));
+                    this.unsaved = Some((path, project));
+                    this.status = Some(Ok("writing the cells".into()));
+                }
+                else {
+                    this.status = Some(save(&path, &project).map(|()| format!("saved {}", this.path)));
+                }
+            }
+            if ui.button("open").clicked() {
+                open = Some(PathBuf::from(&this.path));
+            }
+        });
+        if let Some(grid_save) = &this.grid_save {
+            ui.horizontal(|ui| {
+                ui.add(egui::ProgressBar::new(grid_save.progress())
+                    .text(format!("{}", grid_save.path.display())));
+                if ui.button("cancel").clicked() {
+                    grid_save.cancel();
+                }
+            });
+        }
+        match &this.status {
+            Some(Ok(message)) => { ui.label(message.as_str()); }
+            Some(Err(error)) => { ui.colored_label(egui::Color32::RED, error.as_str()); }
+            None => (),
+        }
+    });
+
+    if let Some(path) = open {
+        this.status = Some(load(&path)
+            .and_then(|project| project.apply(&mut sims, &mut seeding, camera.as_deref_mut()))
+            .map(|()| format!("opened {}", path.display())));
+        if let Some(Err(error)) = &this.status {
+            logged!(warn, "{}", error);
+        }
+    }
+}
+
+
+pub struct ProjectPlugin;
+impl Plugin for ProjectPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(ProjectFile::from_args(std::env::args().skip(1)))
+        .add_system(update.before(SimsUpdate).with_run_criteria(simulating));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::ivec3;
+
+    use super::*;
+    use crate::{cells::tantan::CellsSinglethreaded, example_library};
+
+    #[test]
+    fn cells_outside_the_grid_are_dropped() {
+        let mut sims = Sims::new();
+        sims.add_sim("tantan single-threaded".into(), Box::new(CellsSinglethreaded::new()));
+        sims.add_example(example_library::fallback_example());
+        sims.start(0, 0, 16);
+        let mut seeding = Seeding::new();
+
+        let states = sims.rule().states;
+        let mut project = Project::capture(&sims, &seeding, None, false);
+        project.cells = Some(vec![
+            (ivec3(1, 2, 3), states),
+            (ivec3(-1, 2, 3), states),
+            (ivec3(1, 16, 3), states),
+            (ivec3(4, 4, 4), CellValue::MAX),
+        ]);
+        let path = std::env::temp_dir().join(format!("out_of_range-{}.{}", std::process::id(), EXTENSION));
+        save(&path, &project).unwrap();
+        let loaded = load(&path);
+        fs::remove_file(&path).ok();
+        loaded.unwrap().apply(&mut sims, &mut seeding, None).unwrap();
+
+        assert_eq!(sims.cell_count(), 2);
+        assert_eq!(sims.get_cell(ivec3(1, 2, 3)), states);
+        assert_eq!(sims.get_cell(ivec3(4, 4, 4)), states);
+    }
+}
@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use rand::Rng;
+
+use crate::{
+    action::Action,
+    app_state::simulating,
+    cells::{color_picker, Sims},
+    theme::Theme,
+};
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    // opposite hues.
+    Complementary,
+    // neighboring hues, a calm gradient.
+    Analogous,
+    Triadic,
+    // the two hues next to the complement.
+    SplitComplementary,
+}
+
+pub const SCHEMES: [Scheme; 4] = [
+    Scheme::Complementary, Scheme::Analogous, Scheme::Triadic, Scheme::SplitComplementary,
+];
+
+impl Scheme {
+    // degrees from the first hue to the second.
+    fn hue_offset(&self, rand: &mut impl Rng) -> f32 {
+        let sign = if rand.gen_bool(0.5) { 1.0 } else { -1.0 };
+        match self {
+            Scheme::Complementary => 180.0,
+            Scheme::Analogous => sign * rand.gen_range(25.0..45.0),
+            Scheme::Triadic => sign * 120.0,
+            Scheme::SplitComplementary => sign * 150.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lock {
+    None,
+    Color1,
+    Color2,
+}
+
+fn hsl(color: Color) -> (f32, f32, f32) {
+    match color.as_hsla() {
+        Color::Hsla { hue, saturation, lightness, .. } => (hue, saturation, lightness),
+        _ => unreachable!(),
+    }
+}
+
+// two colors of the scheme, one dark and one light so the gradient between
+// them reads, eg. for the state lerp. a locked color is kept and the other
+// one picked to go with it.
+pub fn generate(scheme: Scheme, lock: Lock, colors: (Color, Color), rand: &mut impl Rng) -> (Color, Color) {
+    let locked = match lock {
+        Lock::None => None,
+        Lock::Color1 => Some(colors.0),
+        Lock::Color2 => Some(colors.1),
+    };
+    let (hue, saturation, lightness) = match locked {
+        Some(color) => hsl(color),
+        None => (rand.gen_range(0.0..360.0), rand.gen_range(0.5..0.9), rand.gen_range(0.2..0.4)),
+    };
+    let other_lightness = match lightness < 0.5 {
+        true  => rand.gen_range(0.6..0.8),
+        false => rand.gen_range(0.2..0.4),
+    };
+    let other = Color::hsl(
+        (hue + scheme.hue_offset(rand)).rem_euclid(360.0),
+        (saturation + rand.gen_range(-0.15..0.15)).clamp(0.35, 0.95),
+        other_lightness);
+    match lock {
+        Lock::Color2 => (other, colors.1),
+        _ => (locked.unwrap_or_else(|| Color::hsl(hue, saturation, lightness)), other),
+    }
+}
+
+
+// "surprise me": a random harmonious pair for the custom theme.
+pub struct RandomPalette {
+    // none picks a scheme at random each time.
+    pub scheme: Option<Scheme>,
+    pub lock: Lock,
+    last: Option<Scheme>,
+}
+
+impl Default for RandomPalette {
+    fn default() -> Self {
+        RandomPalette {
+            scheme: None,
+            lock: Lock::None,
+            last: None,
+        }
+    }
+}
+
+
+fn update_ui(
+    mut this: ResMut<RandomPalette>,
+    sims: Res<Sims>,
+    mut actions: EventWriter<Action>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let this = &mut *this;
+    egui::Window::new("Random palette").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        egui::ComboBox::from_label("scheme")
+            .selected_text(match this.scheme {
+                Some(scheme) => format!("{:?}", scheme),
+                None => "any".into(),
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut this.scheme, None, "any");
+                for scheme in SCHEMES {
+                    ui.selectable_value(&mut this.scheme, Some(scheme), format!("{:?}", scheme));
+                }
+            });
+
+        let (color_method, mut color1, mut color2) = sims.colors();
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut this.lock, Lock::None, "no lock");
+            ui.selectable_value(&mut this.lock, Lock::Color1, "lock 1");
+            ui.selectable_value(&mut this.lock, Lock::Color2, "lock 2");
+        });
+        ui.horizontal(|ui| {
+            changed |= color_picker(ui, &mut color1);
+            changed |= color_picker(ui, &mut color2);
+        });
+
+        if ui.button("surprise me").clicked() {
+            let mut rand = rand::thread_rng();
+            let scheme = this.scheme.unwrap_or_else(|| SCHEMES[rand.gen_range(0..SCHEMES.len())]);
+            let colors = generate(scheme, this.lock, (color1, color2), &mut rand);
+            color1 = colors.0;
+            color2 = colors.1;
+            this.last = Some(scheme);
+            changed = true;
+        }
+        if let Some(last) = this.last {
+            ui.weak(format!("last: {:?}", last));
+        }
+        if changed {
+            // the colors only show with the custom theme.
+            if sims.theme() != Theme::Custom {
+                actions.send(Action::SetTheme(Theme::Custom));
+            }
+            actions.send(Action::SetColors(color_method, color1, color2));
+        }
+    });
+}
+
+
+pub struct RandomPalettePlugin;
+impl Plugin for RandomPalettePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<RandomPalette>()
+        .add_system(update_ui.with_run_criteria(simulating));
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    action::Action,
+    app_state::simulating,
+    cell_renderer::CellRenderer,
+    cells::{Sims, SimCapabilities, SimsUpdate},
+    grid_file,
+    logged,
+    rule::CellValue,
+    utils,
+};
+
+// every this many frames the whole grid is stored, the ones in between only
+// hold the difference to the frame before. scrubbing decodes at most this
+// many frames.
+const KEY_INTERVAL: usize = 32;
+const MB: usize = 1024 * 1024;
+
+
+// one recorded frame, run length encoded (see `grid_file::encode_runs`).
+// deltas are the wrapping difference to the previous frame, mostly zeros.
+struct Frame {
+    tick: u64,
+    key: bool,
+    data: Vec<u8>,
+}
+
+// the last frames of the grid, to scrub back through. recorded after each
+// update that advanced the tick and kept within `budget_mb`, the oldest
+// frames go first. resuming from an earlier frame drops the ones after it.
+pub struct Rewind {
+    pub recording: bool,
+    pub budget_mb: usize,
+    frames: VecDeque<Frame>,
+    bytes: usize,
+    bounds: i32,
+    // the grid of the newest frame, or of the scrubbed frame.
+    last: Vec<CellValue>,
+    last_tick: Option<u64>,
+    // the frame shown while scrubbing.
+    scrub: Option<usize>,
+    scratch: Vec<CellValue>,
+    encoded: Vec<u8>,
+    error: Option<String>,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Rewind {
+            recording: false,
+            budget_mb: 256,
+            frames: VecDeque::new(),
+            bytes: 0,
+            bounds: 0,
+            last: vec![],
+            last_tick: None,
+            scrub: None,
+            scratch: vec![],
+            encoded: vec![],
+            error: None,
+        }
+    }
+}
+
+impl Rewind {
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.bytes = 0;
+        self.last.clear();
+        self.last_tick = None;
+        self.scrub = None;
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    fn record(&mut self, renderer: &CellRenderer, tick: u64) -> Result<(), String> {
+        if renderer.bounds != self.bounds || renderer.values.len() != self.last.len() && !self.frames.is_empty() {
+            self.clear();
+            self.bounds = renderer.bounds;
+        }
+        if self.last_tick == Some(tick) {
+            return Ok(());
+        }
+        self.last_tick = Some(tick);
+        if let Some(scrub) = self.scrub.take() {
+            // `last` already holds the scrubbed frame.
+            for frame in self.frames.drain(scrub + 1..) {
+                self.bytes -= frame.data.len();
+            }
+        }
+
+        let since_key = self.frames.iter().rev().position(|frame| frame.key);
+        let key = since_key.map(|since| since + 1 >= KEY_INTERVAL).unwrap_or(true);
+        if key {
+            grid_file::encode_runs(&renderer.values, &mut self.encoded);
+        } else {
+            self.scratch.clear();
+            self.scratch.extend(renderer.values.iter().zip(self.last.iter()).map(|(new, old)| new.wrapping_sub(*old)));
+            grid_file::encode_runs(&self.scratch, &mut self.encoded);
+        }
+        self.last.clear();
+        self.last.extend_from_slice(&renderer.values);
+        self.bytes += self.encoded.len();
+        self.frames.push_back(Frame { tick, key, data: self.encoded.clone() });
+
+        while self.bytes > self.budget_mb * MB && self.frames.len() > 1 {
+            self.evict()?;
+        }
+        Ok(())
+    }
+
+    // drops the oldest frame, the next one becomes a key frame.
+    fn evict(&mut self) -> Result<(), String> {
+        let oldest = self.frames.pop_front().unwrap();
+        self.bytes -= oldest.data.len();
+        let len = self.last.len();
+        let next = &mut self.frames[0];
+        if next.key {
+            return Ok(());
+        }
+        let mut grid = Vec::with_capacity(len);
+        grid_file::decode_runs(&oldest.data, len, &mut grid)?;
+        apply_delta(&next.data, &mut grid, &mut self.scratch)?;
+        grid_file::encode_runs(&grid, &mut self.encoded);
+        self.bytes = self.bytes - next.data.len() + self.encoded.len();
+        next.data = self.encoded.clone();
+        next.key = true;
+        Ok(())
+    }
+
+    // the grid of frame `index`, from the key frame before it.
+    fn decode(&mut self, index: usize) -> Result<Vec<CellValue>, String> {
+        let key = (0..=index).rev().find(|i| self.frames[*i].key).unwrap_or(0);
+        let len = self.last.len();
+        let mut grid = Vec::with_capacity(len);
+        grid_file::decode_runs(&self.frames[key].data, len, &mut grid)?;
+        for frame in self.frames.range(key + 1..=index) {
+            apply_delta(&frame.data, &mut grid, &mut self.scratch)?;
+        }
+        if grid.len() != len {
+            return Err(format!("frame {} has {} cells, expected {}", index, grid.len(), len));
+        }
+        Ok(grid)
+    }
+
+    // shows frame `index`: the cells that differ from what is shown are sent
+    // as one `Action::SetCells`.
+    fn show(&mut self, index: usize, sims: &Sims, actions: &mut EventWriter<Action>) -> Result<(), String> {
+        let grid = self.decode(index)?;
+        let renderer = sims.renderer();
+        let changes: Vec<_> = grid.iter().zip(renderer.values.iter()).enumerate()
+            .filter(|(_, (new, old))| new != old)
+            .map(|(i, (new, _))| (utils::index_to_pos(i, self.bounds), *new))
+            .collect();
+        actions.send(Action::SetPaused(true));
+        if !changes.is_empty() {
+            actions.send(Action::SetCells(changes));
+        }
+        self.last = grid;
+        self.scrub = match index + 1 == self.frames.len() {
+            true  => None,
+            false => Some(index),
+        };
+        Ok(())
+    }
+}
+
+fn apply_delta(data: &[u8], grid: &mut [CellValue], scratch: &mut Vec<CellValue>) -> Result<(), String> {
+    scratch.clear();
+    grid_file::decode_runs(data, grid.len(), scratch)?;
+    if scratch.len() != grid.len() {
+        return Err(format!("delta of {} cells, expected {}", scratch.len(), grid.len()));
+    }
+    for (value, delta) in grid.iter_mut().zip(scratch.iter()) {
+        *value = value.wrapping_add(*delta);
+    }
+    Ok(())
+}
+
+
+fn record(
+    mut this: ResMut<Rewind>,
+    sims: Res<Sims>,
+) {
+    if !this.recording {
+        return;
+    }
+    if let Err(error) = this.record(sims.renderer(), sims.tick()) {
+        logged!(warn, "rewind: {}", error);
+        this.error = Some(error);
+        this.clear();
+    }
+}
+
+fn update_ui(
+    mut this: ResMut<Rewind>,
+    sims: Res<Sims>,
+    mut actions: EventWriter<Action>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let this = &mut *this;
+    let mut show = None;
+    egui::Window::new("Rewind").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            if ui.checkbox(&mut this.recording, "record").changed() && !this.recording {
+                this.clear();
+            }
+            ui.add(egui::Slider::new(&mut this.budget_mb, 16..=4096).logarithmic(true).text("MB"));
+        });
+        ui.label(format!("{} frames, {:.1} of {} MB", this.len(), this.bytes() as f32 / MB as f32, this.budget_mb));
+
+        if let (Some(first), Some(last)) = (this.frames.front(), this.frames.back()) {
+            let (first, last) = (first.tick, last.tick);
+            let newest = this.len() - 1;
+            let mut index = this.scrub.unwrap_or(newest);
+            let tick = this.frames[index].tick;
+            // showing a frame writes the cells back into the backend.
+            let can_show = sims.capabilities().contains(SimCapabilities::SET_CELL);
+            ui.horizontal(|ui| {
+                if ui.add_enabled(can_show, egui::Slider::new(&mut index, 0..=newest).show_value(false)).changed() {
+                    show = Some(index);
+                }
+                ui.label(format!("tick {} ({}..{})", tick, first, last));
+            });
+            ui.horizontal(|ui| {
+                if ui.add_enabled(can_show && index > 0, egui::Button::new("back")).clicked() {
+                    show = Some(index - 1);
+                }
+                if ui.add_enabled(can_show && index < newest, egui::Button::new("forward")).clicked() {
+                    show = Some(index + 1);
+                }
+                if ui.add_enabled(can_show && this.scrub.is_some(), egui::Button::new("newest")).clicked() {
+                    show = Some(newest);
+                }
+            });
+            if !can_show {
+                ui.weak(format!("`{}` doesn't support cell access", sims.active_name()));
+            } else if this.scrub.is_some() {
+                ui.weak("resuming drops the frames after this one");
+            }
+        }
+        if let Some(error) = &this.error {
+            ui.colored_label(egui::Color32::RED, error.as_str());
+        }
+    });
+
+    if let Some(index) = show {
+        if let Err(error) = this.show(index, &sims, &mut actions) {
+            this.error = Some(error);
+            this.clear();
+        }
+    }
+}
+
+
+pub struct RewindPlugin;
+impl Plugin for RewindPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<Rewind>()
+        .add_system(record.with_run_criteria(simulating).after(SimsUpdate))
+        .add_system(update_ui.with_run_criteria(simulating));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a different grid every tick, with a few cells that stay the same.
+    fn step(renderer: &mut CellRenderer, tick: u64) {
+        for (i, value) in renderer.values.iter_mut().enumerate() {
+            *value = match i % 3 {
+                0 => 1,
+                _ => ((i as u64 * 31 + tick * 7) % 5) as CellValue,
+            };
+        }
+    }
+
+    fn recorded(rewind: &mut Rewind, ticks: u64) -> Vec<Vec<CellValue>> {
+        let mut renderer = CellRenderer::new();
+        renderer.set_bounds(8);
+        (0..ticks).map(|tick| {
+            step(&mut renderer, tick);
+            rewind.record(&renderer, tick).unwrap();
+            renderer.values.clone()
+        }).collect()
+    }
+
+    // past a key interval, so some frames are decoded through deltas.
+    #[test]
+    fn rewinding_restores_the_grid() {
+        let mut rewind = Rewind::default();
+        let grids = recorded(&mut rewind, KEY_INTERVAL as u64 + 8);
+        assert_eq!(rewind.len(), grids.len());
+        for (index, grid) in grids.iter().enumerate().rev() {
+            assert_eq!(&rewind.decode(index).unwrap(), grid, "frame {}", index);
+        }
+    }
+
+    #[test]
+    fn evicting_keeps_the_newest_grid() {
+        let mut rewind = Rewind::default();
+        let grids = recorded(&mut rewind, 10);
+        rewind.evict().unwrap();
+        assert_eq!(rewind.len(), 9);
+        for (index, grid) in grids[1..].iter().enumerate() {
+            assert_eq!(&rewind.decode(index).unwrap(), grid, "frame {}", index);
+        }
+    }
+}
@@ -1,13 +1,27 @@
 use bevy::math::{vec3, Quat};
 use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{app_state::simulating, cells::{Sims, SimsUpdate}, utils, volume::VolumeTransform};
 
 #[derive(Component)]
 pub struct RotatingCamera {
     pub rotation: f32,
     pub last_tick: f32,
+    // radians per second.
     pub speed: f32,
     pub dist: f32,
+    // above the center, the camera still looks at the center.
+    pub height: f32,
     pub center: Vec3,
+    // stops the turntable, eased like a change of speed.
+    pub paused: bool,
+
+    // `speed` is the target, the camera eases from the old speed to it.
+    current_speed: f32,
+    speed_from: f32,
+    speed_to: f32,
+    speed_elapsed: f32,
 }
 
 impl Default for RotatingCamera {
@@ -15,28 +29,234 @@ impl Default for RotatingCamera {
         Self {
             rotation: 0f32,
             last_tick: 0f32,
-            speed: 0.01f32,
+            speed: 0.6f32,
             dist: 150f32,
+            height: 0f32,
             center: vec3(0.0, 0.0, 0.0),
+            paused: false,
+            current_speed: 0.6f32,
+            speed_from: 0.6f32,
+            speed_to: 0.6f32,
+            speed_elapsed: 0f32,
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ease {
+    Linear,
+    SmoothStep,
+    EaseIn,
+    EaseOut,
+}
+
+pub const EASES: [Ease; 4] = [Ease::Linear, Ease::SmoothStep, Ease::EaseIn, Ease::EaseOut];
+
+impl Ease {
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Ease::Linear     => t,
+            Ease::SmoothStep => t*t*(3.0 - 2.0*t),
+            Ease::EaseIn     => t*t*t,
+            Ease::EaseOut    => 1.0 - (1.0 - t).powi(3),
+        }
+    }
+}
+
+
+// optional polish for recording footage.
+pub struct CameraMotion {
+    pub ease: Ease,
+    // how long a change of rotation speed takes. 0 is instant.
+    pub ease_seconds: f32,
+    // units per second the camera moves closer while the sim runs.
+    pub push_in: f32,
+    pub min_dist: f32,
+    // handheld shake, in units, and how many wobbles per second.
+    pub shake: f32,
+    pub shake_frequency: f32,
+}
+
+impl Default for CameraMotion {
+    fn default() -> Self {
+        CameraMotion {
+            ease: Ease::SmoothStep,
+            ease_seconds: 0.0,
+            push_in: 0.0,
+            min_dist: 20.0,
+            shake: 0.0,
+            shake_frequency: 0.5,
         }
     }
 }
 
+pub fn push_in(
+    motion: Res<CameraMotion>,
+    auto_frame: Res<AutoFrame>,
+    sims: Res<Sims>,
+    time: Res<Time>,
+    mut cameras: Query<&mut RotatingCamera>,
+) {
+    // auto-frame owns the distance while it's on.
+    if motion.push_in == 0.0 || auto_frame.enabled || sims.is_paused() {
+        return;
+    }
+    for mut camera in cameras.iter_mut() {
+        if camera.dist > motion.min_dist {
+            camera.dist = (camera.dist - motion.push_in * time.delta_seconds()).max(motion.min_dist);
+        }
+    }
+}
+
+
+// moves the camera so the live cells fill `fill` of the view.
+pub struct AutoFrame {
+    pub enabled: bool,
+    pub fill: f32,
+    // how quickly the camera catches up, per second.
+    pub speed: f32,
+}
+
+impl Default for AutoFrame {
+    fn default() -> Self {
+        AutoFrame {
+            enabled: false,
+            fill: 0.7,
+            speed: 2.0,
+        }
+    }
+}
+
+pub fn auto_frame(
+    this: Res<AutoFrame>,
+    sims: Res<Sims>,
+    volume: Res<VolumeTransform>,
+    time: Res<Time>,
+    mut cameras: Query<(&mut RotatingCamera, &PerspectiveProjection)>,
+) {
+    if !this.enabled {
+        return;
+    }
+    let renderer = sims.renderer();
+    let (min, max) = match renderer.live_aabb() {
+        Some(aabb) => aabb,
+        None => return,
+    };
+
+    // cells are unit cubes around `pos - center`.
+    let offset = utils::center(renderer.bounds).as_vec3();
+    let center = volume.to_world((min.as_vec3() + max.as_vec3()) / 2.0 - offset);
+    let radius = ((max - min).as_vec3() + 1.0).length() / 2.0 * volume.scale;
+    let t = 1.0 - (-this.speed * time.delta_seconds()).exp();
+
+    for (mut camera, projection) in cameras.iter_mut() {
+        let dist = radius / (this.fill.max(0.05) * (projection.fov / 2.0).tan());
+        camera.center = camera.center.lerp(center, t);
+        camera.dist  += (dist - camera.dist) * t;
+    }
+}
+
+pub fn update_ui(
+    mut this: ResMut<AutoFrame>,
+    mut motion: ResMut<CameraMotion>,
+    mut cameras: Query<&mut RotatingCamera>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Camera").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        for mut camera in cameras.iter_mut() {
+            ui.checkbox(&mut camera.paused, "pause rotation");
+            ui.add(egui::Slider::new(&mut camera.speed, -3.0..=3.0)
+                .text("speed (radians per second)"));
+            ui.add(egui::Slider::new(&mut camera.height, -200.0..=200.0)
+                .text("height"));
+            // auto-frame and push-in move the distance themselves.
+            ui.add_enabled(!this.enabled, egui::Slider::new(&mut camera.dist, 1.0..=500.0)
+                .text("distance"));
+        }
+
+        ui.separator();
+        ui.checkbox(&mut this.enabled, "auto-frame");
+        ui.add_enabled(this.enabled, egui::Slider::new(&mut this.fill, 0.1..=1.0)
+            .text("fill"));
+        ui.add_enabled(this.enabled, egui::Slider::new(&mut this.speed, 0.1..=10.0)
+            .text("follow speed"));
+
+        ui.separator();
+        egui::ComboBox::from_label("speed easing")
+            .selected_text(format!("{:?}", motion.ease))
+            .show_ui(ui, |ui| {
+                for ease in EASES {
+                    ui.selectable_value(&mut motion.ease, ease, format!("{:?}", ease));
+                }
+            });
+        ui.add(egui::Slider::new(&mut motion.ease_seconds, 0.0..=10.0)
+            .text("easing seconds"));
+        ui.add_enabled(!this.enabled, egui::Slider::new(&mut motion.push_in, 0.0..=10.0)
+            .text("push-in per second"));
+        ui.add_enabled(!this.enabled, egui::Slider::new(&mut motion.min_dist, 1.0..=200.0)
+            .text("closest distance"));
+        ui.add(egui::Slider::new(&mut motion.shake, 0.0..=5.0)
+            .text("shake"));
+        ui.add_enabled(motion.shake > 0.0, egui::Slider::new(&mut motion.shake_frequency, 0.05..=5.0)
+            .text("shake frequency"));
+    });
+}
+
+
 pub struct RotatingCameraPlugin;
 impl Plugin for RotatingCameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(update_tick);
+        app
+        .init_resource::<AutoFrame>()
+        .init_resource::<CameraMotion>()
+        .add_system(update_ui.with_run_criteria(simulating))
+        .add_system(auto_frame.after(SimsUpdate).with_run_criteria(simulating))
+        .add_system(push_in)
+        .add_system(update_tick);
     }
 }
 
 pub fn update_tick(
+    motion: Res<CameraMotion>,
+    time: Res<Time>,
     mut cameras: Query<(&mut RotatingCamera, &mut Transform)>,
 ) {
     for (mut camera, mut transform) in cameras.iter_mut() {
-        let delta = 1.0f32;
-        camera.rotation += delta * camera.speed;
+        let speed = if camera.paused { 0.0 } else { camera.speed };
+        if speed != camera.speed_to {
+            camera.speed_from = camera.current_speed;
+            camera.speed_to = speed;
+            camera.speed_elapsed = 0.0;
+        }
+        camera.speed_elapsed += time.delta_seconds();
+        camera.current_speed = match motion.ease_seconds > 0.0 {
+            true => {
+                let t = motion.ease.apply(camera.speed_elapsed / motion.ease_seconds);
+                camera.speed_from + (camera.speed_to - camera.speed_from) * t
+            }
+            false => speed,
+        };
+
+        camera.rotation += time.delta_seconds() * camera.current_speed;
         let rotation = Quat::from_axis_angle(Vec3::Y, camera.rotation);
-        transform.translation = camera.center + (rotation * Vec3::Z * camera.dist);
-        transform.look_at(camera.center, Vec3::Y);
+        transform.translation = camera.center
+            + rotation * Vec3::Z * camera.dist
+            + Vec3::Y * camera.height;
+
+        // the position and the look target wobble independently, which
+        // reads as a slight hand held tilt rather than a sliding camera.
+        let mut target = camera.center;
+        if motion.shake > 0.0 {
+            let t = time.seconds_since_startup() as f32 * motion.shake_frequency;
+            let wobble = |seed: u64| vec3(
+                utils::gradient_noise(vec3(t, 0.0, 0.0), seed),
+                utils::gradient_noise(vec3(t, 10.0, 0.0), seed),
+                utils::gradient_noise(vec3(t, 20.0, 0.0), seed));
+            transform.translation += wobble(1) * motion.shake;
+            target += wobble(2) * motion.shake;
+        }
+        transform.look_at(target, Vec3::Y);
     }
 }
@@ -1,71 +1,223 @@
 use bevy::prelude::Color;
-use std::ops::RangeInclusive;
+use serde::{Deserialize, Serialize};
+use std::{fmt, ops::RangeInclusive};
 
-use crate::{neighbours::NeighbourMethod, utils};
+use crate::{neighbours::{NeighbourCount, NeighbourMethod}, theme::Palette};
 
-#[derive(Clone, Copy, PartialEq)]
-pub struct Value ([bool; 27]);
+// the enabled neighbor counts. a bitset that grows with the largest entry,
+// so it fits any neighborhood size. serialized as the list of entries.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<NeighbourCount>", into = "Vec<NeighbourCount>")]
+pub struct Value {
+    // no trailing zero words, so the derived `PartialEq` works.
+    words: Vec<u64>,
+}
+
+impl From<Vec<NeighbourCount>> for Value {
+    fn from(indices: Vec<NeighbourCount>) -> Self {
+        Value::new(&indices)
+    }
+}
+
+impl From<Value> for Vec<NeighbourCount> {
+    fn from(value: Value) -> Self {
+        value.iter().collect()
+    }
+}
 
 impl Value {
-    pub fn new(indices: &[u8]) -> Self {
-        let mut result = Value([false; 27]);
+    pub fn new(indices: &[NeighbourCount]) -> Self {
+        let mut result = Value::default();
         for index in indices {
-            result.0[*index as usize] = true;
+            result.set(*index, true);
         }
         result
     }
 
-    pub fn from_range(indices: RangeInclusive<u8>) -> Self {
-        let mut result = Value([false; 27]);
+    #[allow(dead_code)]
+    pub fn from_range(indices: RangeInclusive<NeighbourCount>) -> Self {
+        let mut result = Value::default();
         for index in indices {
-            result.0[index as usize] = true;
+            result.set(index, true);
         }
         result
     }
 
-    #[allow(dead_code)]
-    pub fn in_range(&self, value: u8) -> bool {
-        self.0[value as usize]
+    // counts past the largest entry are never in range.
+    pub fn in_range(&self, value: NeighbourCount) -> bool {
+        let value = value as usize;
+        self.words.get(value / 64).map(|word| (word >> (value % 64)) & 1 == 1).unwrap_or(false)
+    }
+
+    pub fn set(&mut self, value: NeighbourCount, enabled: bool) {
+        let value = value as usize;
+        if enabled {
+            if self.words.len() <= value / 64 {
+                self.words.resize(value / 64 + 1, 0);
+            }
+            self.words[value / 64] |= 1 << (value % 64);
+        }
+        else if let Some(word) = self.words.get_mut(value / 64) {
+            *word &= !(1 << (value % 64));
+            while self.words.last() == Some(&0) {
+                self.words.pop();
+            }
+        }
+    }
+
+    // one past the largest entry that can be enabled without growing.
+    pub fn capacity(&self) -> usize {
+        self.words.len() * 64
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = NeighbourCount> + '_ {
+        (0..self.capacity())
+            .map(|n| n as NeighbourCount)
+            .filter(move |n| self.in_range(*n))
     }
+}
+
+// "2,6,9-12" style, consecutive entries are collapsed into ranges.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut ranges: Vec<(NeighbourCount, NeighbourCount)> = vec![];
+        for n in self.iter() {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == n => *end = n,
+                _ => ranges.push((n, n)),
+            }
+        }
 
-    pub fn in_range_incorrect(&self, value: u8) -> bool {
-        *self.0.get(value as usize).unwrap_or(&false)
+        for (i, (start, end)) in ranges.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if start == end {
+                write!(f, "{}", start)?;
+            }
+            else {
+                write!(f, "{}-{}", start, end)?;
+            }
+        }
+        Ok(())
     }
 }
 
 
 #[allow(dead_code)]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ColorMethod {
     Single,
     StateLerp,
     DistToCenter,
     Neighbour,
+    Metadata,
+    Energy,
 }
 
+pub const COLOR_METHODS: [ColorMethod; 6] = [
+    ColorMethod::Single, ColorMethod::StateLerp, ColorMethod::DistToCenter,
+    ColorMethod::Neighbour, ColorMethod::Metadata, ColorMethod::Energy,
+];
+
 impl ColorMethod {
-    pub fn color(&self, c1: Color, c2: Color, states: u8, state: u8, neighbours: u8, dist_to_center: f32) -> Color {
+    #[allow(clippy::too_many_arguments)]
+    pub fn color(&self,
+        palette: &Palette,
+        states: CellValue, state: CellValue,
+        neighbours: NeighbourCount, max_neighbours: NeighbourCount,
+        metadata: u8, energy: u8, dist_to_center: f32,
+    ) -> Color {
         match self {
-            ColorMethod::Single => c1,
+            ColorMethod::Single => palette.sample(0.0),
             ColorMethod::StateLerp => {
-                let dt = state as f32 / states as f32;
-                utils::lerp_color(c1, c2, dt)
+                let dt = state as f32 / states.max(1) as f32;
+                palette.sample(dt)
             }
             ColorMethod::DistToCenter => {
-                utils::lerp_color(c1, c2, dist_to_center)
+                palette.sample(dist_to_center)
             }
             ColorMethod::Neighbour => {
-                let dt = neighbours as f32 / 26f32;
-                utils::lerp_color(c1, c2, dt)
+                let dt = neighbours as f32 / max_neighbours.max(1) as f32;
+                palette.sample(dt)
+            }
+            ColorMethod::Metadata => {
+                let dt = metadata as f32 / 255f32;
+                palette.sample(dt)
+            }
+            ColorMethod::Energy => {
+                let dt = energy as f32 / 255f32;
+                palette.sample(dt)
             }
         }
     }
 }
 
-#[derive(Clone, PartialEq)]
+// the type of cell values, `Rule::states` included. u8 unless built with
+// `wide_states`, for decay gradients longer than 255 states.
+#[cfg(not(feature = "wide_states"))]
+pub type CellValue = u8;
+#[cfg(feature = "wide_states")]
+pub type CellValue = u16;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct Rule {
     pub survival_rule: Value,
     pub birth_rule: Value,
-    pub states: u8,
+    pub states: CellValue,
     pub neighbour_method: NeighbourMethod,
 }
+
+impl Rule {
+    // zero states would make newborn cells dead right away, while their
+    // neighbors were already counted. every other rule is valid.
+    pub fn sanitized(mut self) -> Rule {
+        self.states = self.states.max(1);
+        self
+    }
+
+    // the transition every backend implements: dead cells with a birth count
+    // are born at `states`, cells at `states` stay while they survive, and
+    // everything else decays by one.
+    pub fn next_value(&self, value: CellValue, neighbors: NeighbourCount) -> CellValue {
+        if value == 0 {
+            match self.birth_rule.in_range(neighbors) {
+                true  => self.states,
+                false => 0,
+            }
+        }
+        else if value == self.states && self.survival_rule.in_range(neighbors) {
+            value
+        }
+        else {
+            value - 1
+        }
+    }
+
+    // how the degenerate rules behave, they are allowed but easy to make
+    // by accident.
+    pub fn notes(&self) -> Vec<&'static str> {
+        let mut notes = vec![];
+        if self.states <= 1 {
+            notes.push("1 state: cells die immediately instead of decaying.");
+        }
+        if self.birth_rule.iter().next().is_none() {
+            notes.push("no birth counts: nothing new is ever born.");
+        }
+        if self.survival_rule.iter().next().is_none() {
+            notes.push("no survival counts: every cell starts decaying the tick after it's born.");
+        }
+        notes
+    }
+}
+
+// survival/birth/states/neighbourhood, eg: "4/4/5/M".
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let method = match self.neighbour_method {
+            NeighbourMethod::Moore     => "M",
+            NeighbourMethod::VonNeuman => "N",
+        };
+        write!(f, "{}/{}/{}/{}", self.survival_rule, self.birth_rule, self.states, method)
+    }
+}
@@ -0,0 +1,124 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    neighbours::{NeighbourCount, NeighbourMethod},
+    rule::{CellValue, Rule},
+    utils,
+};
+
+// past this, the decay only adds longer trails.
+const WASTEFUL_STATES: CellValue = 20;
+
+// rules that are valid but most likely not what was meant, eg. a rule that
+// fills the volume in a few ticks. unlike `Rule::notes` these are guesses.
+pub struct Warning {
+    pub title: &'static str,
+    pub explanation: String,
+}
+
+
+// fraction of the noise cube that starts alive.
+fn noise_density() -> f64 {
+    let side = (2 * utils::NOISE_RADIUS + 1) as f64;
+    let volume = side * side * side;
+    1.0 - (1.0 - 1.0 / volume).powf(utils::NOISE_AMOUNT as f64)
+}
+
+// chance that `count` of `n` neighbors are alive, if each one is with
+// chance `p`.
+fn binomial(n: NeighbourCount, count: NeighbourCount, p: f64) -> f64 {
+    if count > n {
+        return 0.0;
+    }
+    let mut choose = 1.0;
+    for i in 0..count {
+        choose = choose * (n - i) as f64 / (i + 1) as f64;
+    }
+    choose * p.powi(count as i32) * (1.0 - p).powi((n - count) as i32)
+}
+
+// births per tick in the noise right after it spawned. ignores the cells
+// around the cube, those have fewer neighbors.
+fn noise_births(rule: &Rule) -> f64 {
+    let p = noise_density();
+    let n = rule.neighbour_method.max_neighbours();
+    let side = (2 * utils::NOISE_RADIUS + 1) as f64;
+    let dead = side * side * side * (1.0 - p);
+    dead * rule.birth_rule.iter().map(|count| binomial(n, count, p)).sum::<f64>()
+}
+
+// births on this many neighbors or fewer grow every shape. the cells
+// diagonally off the corners of any shape see only one or two live cells.
+// Von Neumann doesn't reach diagonally, so only births on a single neighbor
+// do.
+fn explosive_births(method: NeighbourMethod) -> NeighbourCount {
+    match method {
+        NeighbourMethod::Moore     => 2,
+        NeighbourMethod::VonNeuman => 1,
+    }
+}
+
+// the states that suit the rule. decaying cells can't be born again, so more
+// states leave a longer gap behind anything that grows, which tames fast
+// rules. rules that need many neighbors for a birth grow slowly, their extra
+// states only fade out.
+pub fn suggested_states(rule: &Rule) -> RangeInclusive<CellValue> {
+    let max = rule.neighbour_method.max_neighbours();
+    match rule.birth_rule.iter().next() {
+        None                                                        => 1..=WASTEFUL_STATES,
+        Some(min) if min <= explosive_births(rule.neighbour_method) => 5..=WASTEFUL_STATES,
+        Some(min) if 2 * min > max                                  => 2..=6,
+        Some(_)                                                     => 2..=12,
+    }
+}
+
+// tags that follow from the rule, for filtering the examples.
+pub fn tags(rule: &Rule) -> Vec<&'static str> {
+    let mut tags = vec![match rule.neighbour_method {
+        NeighbourMethod::Moore     => "Moore",
+        NeighbourMethod::VonNeuman => "VN",
+    }];
+    match rule.birth_rule.iter().next() {
+        Some(min) if min <= explosive_births(rule.neighbour_method) => tags.push("explosive"),
+        Some(_) if noise_births(rule) < 1.0 => tags.push("dead"),
+        _ => (),
+    }
+    tags
+}
+
+pub fn analyze(rule: &Rule) -> Vec<Warning> {
+    let mut warnings = vec![];
+
+    let explosive = explosive_births(rule.neighbour_method);
+    match rule.birth_rule.iter().next() {
+        Some(0) => warnings.push(Warning {
+            title: "explosive: birth on 0",
+            explanation: "dead cells with no live neighbors are born, so the whole volume fills on the first tick.".into(),
+        }),
+        Some(min) if min <= explosive => warnings.push(Warning {
+            title: "explosive: birth on few neighbors",
+            explanation: format!(
+                "cells are born next to as few as {} live cell(s). every live cell seeds births around it, \
+                so the shape grows outward each tick until the volume is full.", min),
+        }),
+        Some(min) if noise_births(rule) < 1.0 => warnings.push(Warning {
+            title: "dead: no births from noise",
+            explanation: format!(
+                "the starting noise is about {:.0}% dense, so dead cells rarely have {} or more live neighbors \
+                ({:.2} births expected on the first tick). the noise just decays.",
+                noise_density() * 100.0, min, noise_births(rule)),
+        }),
+        _ => (),
+    }
+
+    if rule.states > WASTEFUL_STATES {
+        warnings.push(Warning {
+            title: "wasteful: many states",
+            explanation: format!(
+                "only the newest state counts as a neighbor, the other {} just fade out. they make longer \
+                trails to render and store but don't change what the rule does.", rule.states - 1),
+        });
+    }
+
+    warnings
+}
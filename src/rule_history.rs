@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    app_state::simulating,
+    cells::{Sims, SimsUpdate},
+    hooks::HookEvent,
+    rule::Rule,
+    rule_view::RuleChange,
+};
+
+
+pub struct RuleEdit {
+    // seconds since startup.
+    pub time: f64,
+    pub tick: u64,
+    pub old: Rule,
+    pub new: Rule,
+}
+
+
+// every change to the active rule during the session, whatever made it
+// (the ui, the console, timelines, hooks, ...).
+pub struct RuleHistory {
+    edits: Vec<RuleEdit>,
+    start: Option<Rule>,
+    current: Option<Rule>,
+}
+
+impl RuleHistory {
+    pub fn new() -> RuleHistory {
+        RuleHistory {
+            edits: vec![],
+            start: None,
+            current: None,
+        }
+    }
+
+    fn record(&mut self, time: f64, tick: u64, rule: &Rule) {
+        if let Some(old) = self.current.replace(rule.clone()) {
+            if old != *rule {
+                self.edits.push(RuleEdit { time, tick, old, new: rule.clone() });
+            }
+        }
+    }
+}
+
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+
+pub fn update(
+    mut this: ResMut<RuleHistory>,
+    mut sims: ResMut<Sims>,
+    mut hook_events: EventReader<HookEvent>,
+    time: Res<Time>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    if this.start.is_none() {
+        this.start   = Some(sims.rule().clone());
+        this.current = Some(sims.rule().clone());
+    }
+    for event in hook_events.iter() {
+        if let HookEvent::RuleChanged { tick, rule } = event {
+            this.record(time.seconds_since_startup(), *tick, rule);
+        }
+    }
+
+    let mut restore = None;
+    egui::Window::new("Rule history").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+            if let Some(start) = &this.start {
+                ui.horizontal(|ui| {
+                    ui.monospace(format!("session start: {}", start));
+                    if ui.small_button("restore").clicked() {
+                        restore = Some(start.clone());
+                    }
+                });
+            }
+            for edit in this.edits.iter() {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.monospace(format!("{} tick {}", format_time(edit.time), edit.tick));
+                    if ui.small_button("restore").clicked() {
+                        restore = Some(edit.new.clone());
+                    }
+                });
+                ui.monospace(format!("{} -> {}", edit.old, edit.new));
+                let changes = RuleChange::diff(&edit.old, &edit.new).iter()
+                    .map(|change| change.describe())
+                    .collect::<Vec<_>>();
+                ui.weak(changes.join(", "));
+            }
+        });
+
+        if !this.edits.is_empty() && ui.button("clear").clicked() {
+            this.edits.clear();
+        }
+    });
+
+    // restoring is an edit too, and shows up at the end of the list.
+    if let Some(rule) = restore {
+        if rule != *sims.rule() {
+            sims.checkpoint();
+            sims.set_rule(rule);
+        }
+    }
+}
+
+
+pub struct RuleHistoryPlugin;
+impl Plugin for RuleHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(RuleHistory::new())
+        .add_system(update.after(SimsUpdate).with_run_criteria(simulating));
+    }
+}
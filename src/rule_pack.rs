@@ -0,0 +1,110 @@
+use bevy::{prelude::*, tasks::{IoTaskPool, Task}};
+use bevy_egui::{egui, EguiContext};
+use futures_lite::future;
+use serde::Deserialize;
+
+use crate::{app_state::simulating, cells::{Example, Sims}, example_library::ExampleLibrary};
+
+
+// a pack is a list of examples with a name and an author, as RON or JSON.
+#[derive(Deserialize)]
+pub struct RulePack {
+    pub name: String,
+    pub author: String,
+    pub examples: Vec<Example>,
+}
+
+impl RulePack {
+    // examples without an author of their own are attributed to the pack.
+    fn into_examples(self) -> Vec<Example> {
+        let author = format!("{} ({})", self.author, self.name);
+        self.examples.into_iter()
+            .map(|mut example| {
+                example.author.get_or_insert_with(|| author.clone());
+                example
+            })
+            .collect()
+    }
+}
+
+fn parse(text: &str) -> Result<RulePack, String> {
+    match text.trim_start().starts_with('{') {
+        true  => serde_json::from_str(text).map_err(|e| e.to_string()),
+        false => ron::from_str(text).map_err(|e| e.to_string()),
+    }
+}
+
+fn fetch(url: &str) -> Result<RulePack, String> {
+    let text = ureq::get(url).call()
+        .map_err(|e| e.to_string())?
+        .into_string()
+        .map_err(|e| e.to_string())?;
+    parse(&text)
+}
+
+
+pub struct RulePacks {
+    pub url: String,
+    download: Option<Task<Result<RulePack, String>>>,
+    status: String,
+}
+
+impl RulePacks {
+    pub fn new() -> RulePacks {
+        RulePacks {
+            url: String::new(),
+            download: None,
+            status: String::new(),
+        }
+    }
+}
+
+
+pub fn update(
+    mut this: ResMut<RulePacks>,
+    mut library: ResMut<ExampleLibrary>,
+    mut sims: ResMut<Sims>,
+    io_pool: Res<IoTaskPool>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    if let Some(download) = &mut this.download {
+        if let Some(result) = future::block_on(future::poll_once(download)) {
+            this.download = None;
+            this.status = match result {
+                Ok(pack) => {
+                    let status = format!("added {} examples from \"{}\" by {}",
+                        pack.examples.len(), pack.name, pack.author);
+                    library.add_remote(pack.into_examples());
+                    sims.set_examples(library.examples());
+                    status
+                }
+                Err(error) => format!("download failed: {}", error),
+            };
+        }
+    }
+
+    egui::Window::new("Rule packs").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.add(egui::TextEdit::singleline(&mut this.url)
+            .hint_text("https://.../pack.ron"));
+
+        let can_fetch = this.download.is_none() && !this.url.is_empty();
+        if ui.add_enabled(can_fetch, egui::Button::new("fetch")).clicked() {
+            let url = this.url.clone();
+            this.download = Some(io_pool.spawn(async move { fetch(&url) }));
+            this.status = "downloading...".into();
+        }
+        if !this.status.is_empty() {
+            ui.label(&this.status);
+        }
+    });
+}
+
+
+pub struct RulePackPlugin;
+impl Plugin for RulePackPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(RulePacks::new())
+        .add_system(update.with_run_criteria(simulating));
+    }
+}
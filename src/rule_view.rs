@@ -0,0 +1,272 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{app_state::simulating, cells::{Sims, SimsUpdate}, neighbours::{NeighbourCount, NeighbourMethod}, rule::{CellValue, Rule}, rule_analysis, theme::Palette};
+
+const CELL_SIZE: f32 = 14.0;
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RuleChange {
+    Survival(NeighbourCount, bool),
+    Birth(NeighbourCount, bool),
+    States(CellValue),
+    Neighbours(NeighbourMethod),
+}
+
+impl RuleChange {
+    // the changes that turn `from` into `to`. neighbor counts first, states
+    // and the neighborhood last, since those restart the simulation.
+    pub fn diff(from: &Rule, to: &Rule) -> Vec<RuleChange> {
+        let mut changes = vec![];
+        let len = [&from.survival_rule, &from.birth_rule, &to.survival_rule, &to.birth_rule]
+            .iter().map(|value| value.capacity()).max().unwrap_or(0);
+        for n in 0..len as NeighbourCount {
+            if from.survival_rule.in_range(n) != to.survival_rule.in_range(n) {
+                changes.push(RuleChange::Survival(n, to.survival_rule.in_range(n)));
+            }
+            if from.birth_rule.in_range(n) != to.birth_rule.in_range(n) {
+                changes.push(RuleChange::Birth(n, to.birth_rule.in_range(n)));
+            }
+        }
+        if from.states != to.states {
+            changes.push(RuleChange::States(to.states));
+        }
+        if from.neighbour_method != to.neighbour_method {
+            changes.push(RuleChange::Neighbours(to.neighbour_method));
+        }
+        changes
+    }
+
+    pub fn apply(&self, rule: &mut Rule) {
+        match *self {
+            RuleChange::Survival(n, enabled) => rule.survival_rule.set(n, enabled),
+            RuleChange::Birth(n, enabled)    => rule.birth_rule.set(n, enabled),
+            RuleChange::States(states)       => rule.states = states,
+            RuleChange::Neighbours(method)   => rule.neighbour_method = method,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let sign = |enabled: bool| if enabled { "+" } else { "-" };
+        match self {
+            RuleChange::Survival(n, enabled) => format!("survival {}{}", sign(*enabled), n),
+            RuleChange::Birth(n, enabled)    => format!("birth {}{}", sign(*enabled), n),
+            RuleChange::States(states)       => format!("states = {}", states),
+            RuleChange::Neighbours(method)   => format!("neighbors = {:?}", method),
+        }
+    }
+}
+
+
+pub struct RuleView {
+    compare: Option<usize>,
+    // changes still to be applied, front first.
+    pending: Vec<RuleChange>,
+    interval: u64,
+    last_change_tick: u64,
+}
+
+impl RuleView {
+    pub fn new() -> RuleView {
+        RuleView {
+            compare: None,
+            pending: vec![],
+            interval: 30,
+            last_change_tick: 0,
+        }
+    }
+}
+
+
+fn to_color32(color: Color) -> egui::Color32 {
+    let [r, g, b, _] = color.as_rgba_f32();
+    egui::Color32::from_rgb((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8)
+}
+
+// 0..=26 (or the neighborhood size, if larger) with one row per set:
+// survival on top, birth below. entries that differ from `other` are outlined.
+fn number_line(ui: &mut egui::Ui, rule: &Rule, other: Option<&Rule>) {
+    let max = rule.neighbour_method.max_neighbours();
+    let last = max.max(26);
+    let size = egui::vec2((last + 1) as f32 * CELL_SIZE + 60.0, 3.0 * CELL_SIZE);
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let origin = response.rect.min + egui::vec2(60.0, 0.0);
+    let font = egui::FontId::monospace(9.0);
+    let text_color = ui.visuals().text_color();
+
+    let rows = [("survival", &rule.survival_rule, other.map(|o| &o.survival_rule), egui::Color32::from_rgb(80, 160, 240)),
+                ("birth",    &rule.birth_rule,    other.map(|o| &o.birth_rule),    egui::Color32::from_rgb(90, 200, 110))];
+    for (row, (name, value, other, color)) in rows.iter().enumerate() {
+        let y = origin.y + row as f32 * CELL_SIZE;
+        painter.text(egui::pos2(response.rect.min.x, y + CELL_SIZE / 2.0),
+            egui::Align2::LEFT_CENTER, name, font.clone(), text_color);
+
+        for n in 0..=last {
+            let rect = egui::Rect::from_min_size(
+                egui::pos2(origin.x + n as f32 * CELL_SIZE, y),
+                egui::vec2(CELL_SIZE - 2.0, CELL_SIZE - 2.0));
+            let fill = match (value.in_range(n), n <= max) {
+                (true, true)  => *color,
+                (true, false) => color.linear_multiply(0.3),
+                (false, _)    => ui.visuals().extreme_bg_color,
+            };
+            painter.rect_filled(rect, 2.0, fill);
+            if other.map(|o| o.in_range(n) != value.in_range(n)).unwrap_or(false) {
+                painter.rect_stroke(rect, 2.0, egui::Stroke::new(1.5, egui::Color32::from_rgb(240, 120, 40)));
+            }
+        }
+    }
+
+    // neighbor counts, counts above the neighborhood size can never happen.
+    for n in (0..=last).step_by(2) {
+        let x = origin.x + n as f32 * CELL_SIZE + (CELL_SIZE - 2.0) / 2.0;
+        let color = if n <= max { text_color } else { ui.visuals().weak_text_color() };
+        painter.text(egui::pos2(x, origin.y + 2.5 * CELL_SIZE),
+            egui::Align2::CENTER_CENTER, n.to_string(), font.clone(), color);
+    }
+}
+
+// a newly born cell starts at `states` and decays by one per tick once it
+// stops surviving. only cells at `states` count as neighbors.
+fn decay_ladder(ui: &mut egui::Ui, rule: &Rule, palette: &Palette) {
+    let states = rule.states.max(1);
+    let width = (states as f32 * CELL_SIZE).min(27.0 * CELL_SIZE);
+    let step = width / states as f32;
+    let (response, painter) = ui.allocate_painter(egui::vec2(width + 60.0, CELL_SIZE), egui::Sense::hover());
+    let origin = response.rect.min + egui::vec2(60.0, 0.0);
+    painter.text(egui::pos2(response.rect.min.x, origin.y + CELL_SIZE / 2.0),
+        egui::Align2::LEFT_CENTER, "states", egui::FontId::monospace(9.0), ui.visuals().text_color());
+
+    for i in 0..states {
+        let state = states - i;
+        let rect = egui::Rect::from_min_size(
+            egui::pos2(origin.x + i as f32 * step, origin.y),
+            egui::vec2((step - 2.0).max(1.0), CELL_SIZE - 2.0));
+        let color = palette.sample(state as f32 / states as f32);
+        painter.rect_filled(rect, 2.0, to_color32(color));
+        if state == states {
+            painter.rect_stroke(rect, 2.0, egui::Stroke::new(1.0, ui.visuals().text_color()));
+        }
+    }
+}
+
+// the neighborhood offsets, projected isometrically. the center cell is
+// drawn outlined.
+fn neighborhood(ui: &mut egui::Ui, rule: &Rule) {
+    let size = 96.0;
+    let (response, painter) = ui.allocate_painter(egui::vec2(size, size), egui::Sense::hover());
+    let center = response.rect.center();
+    let project = |p: Vec3| {
+        let x = (p.x - p.z) * 0.866;
+        let y = (p.x + p.z) * 0.5 - p.y;
+        center + egui::vec2(x, y) * size * 0.25
+    };
+
+    let mut offsets: Vec<Vec3> = rule.neighbour_method.get_neighbour_iter()
+        .iter().map(|o| o.as_vec3()).collect();
+    // back to front.
+    offsets.sort_by(|a, b| (a.x + a.z - a.y).partial_cmp(&(b.x + b.z - b.y)).unwrap());
+
+    let color = ui.visuals().widgets.active.bg_fill;
+    for offset in offsets {
+        painter.circle_filled(project(offset), 5.0, color);
+    }
+    painter.circle_stroke(project(Vec3::ZERO), 5.0, egui::Stroke::new(1.5, ui.visuals().text_color()));
+}
+
+
+pub fn update(
+    mut this: ResMut<RuleView>,
+    mut sims: ResMut<Sims>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    if !this.pending.is_empty() && sims.tick() >= this.last_change_tick + this.interval {
+        let change = this.pending.remove(0);
+        let mut rule = sims.rule().clone();
+        change.apply(&mut rule);
+        sims.set_rule(rule);
+        this.last_change_tick = sims.tick();
+    }
+
+    egui::Window::new("Rule").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        let rule = sims.rule().clone();
+        let palette = sims.palette();
+        let other = this.compare
+            .and_then(|i| sims.examples().get(i))
+            .map(|e| e.rule.clone());
+
+        ui.monospace(rule.to_string());
+        ui.add_space(8.0);
+        number_line(ui, &rule, other.as_ref());
+        ui.add_space(8.0);
+        decay_ladder(ui, &rule, &palette);
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            neighborhood(ui, &rule);
+            ui.label(format!("{:?}\n{} neighbors",
+                rule.neighbour_method, rule.neighbour_method.get_neighbour_iter().len()));
+        });
+
+        let warnings = rule_analysis::analyze(&rule);
+        if !warnings.is_empty() {
+            ui.add_space(8.0);
+            for warning in warnings {
+                ui.colored_label(egui::Color32::YELLOW, warning.title);
+                ui.label(warning.explanation);
+            }
+        }
+
+        ui.separator();
+
+        let mut compare = this.compare;
+        egui::ComboBox::from_label("compare with")
+            .selected_text(compare.and_then(|i| sims.examples().get(i)).map(|e| e.name.as_str()).unwrap_or("nothing"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut compare, None, "nothing");
+                for (i, example) in sims.examples().iter().enumerate() {
+                    ui.selectable_value(&mut compare, Some(i), &example.name);
+                }
+            });
+        this.compare = compare;
+
+        if let Some(other) = &other {
+            let changes = RuleChange::diff(&rule, other);
+            ui.monospace(other.to_string());
+            if changes.is_empty() {
+                ui.label("the rules are identical.");
+            }
+            for change in changes.iter() {
+                ui.label(change.describe());
+            }
+
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!changes.is_empty(), egui::Button::new("apply gradually")).clicked() {
+                    this.pending = changes;
+                    this.last_change_tick = sims.tick();
+                }
+                ui.add(egui::DragValue::new(&mut this.interval).clamp_range(1..=10_000));
+                ui.label("ticks per change");
+            });
+        }
+
+        if !this.pending.is_empty() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} change(s) pending", this.pending.len()));
+                if ui.small_button("cancel").clicked() {
+                    this.pending.clear();
+                }
+            });
+        }
+    });
+}
+
+
+pub struct RuleViewPlugin;
+impl Plugin for RuleViewPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(RuleView::new())
+        .add_system(update.before(SimsUpdate).with_run_criteria(simulating));
+    }
+}
@@ -0,0 +1,363 @@
+use bevy::{math::ivec3, prelude::*, tasks::TaskPool};
+use bevy_egui::{egui, EguiContext};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::simulating,
+    cells::{Sims, SimCapabilities, SimsUpdate},
+    rule::CellValue,
+    threading::Threading,
+    utils::{self, SplitMix64},
+};
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SeedShape {
+    Volume,
+    Box,
+    Sphere,
+}
+
+impl SeedShape {
+    // `size` is relative to the bounds: the box edge or the sphere diameter.
+    pub fn contains(&self, pos: IVec3, bounds: i32, size: f32) -> bool {
+        let offset = (pos - utils::center(bounds)).as_vec3();
+        let half = size * bounds as f32 / 2.0;
+        match self {
+            SeedShape::Volume => true,
+            SeedShape::Box    => offset.abs().max_element() <= half,
+            SeedShape::Sphere => offset.length() <= half,
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SeedGenerator {
+    Random,
+    Noise,
+    Menger,
+    Sierpinski,
+    GrayShells,
+}
+
+impl SeedGenerator {
+    pub fn is_alive(&self, seeding: &Seeding, pos: IVec3, bounds: i32, rng: &mut impl Rng) -> bool {
+        match self {
+            SeedGenerator::Random => rng.gen::<f32>() < seeding.probability,
+
+            SeedGenerator::Noise => {
+                let mut value = 0.0;
+                let mut frequency = 1.0 / seeding.noise_scale;
+                let mut amplitude = 1.0;
+                for octave in 0..seeding.noise_octaves {
+                    let p = pos.as_vec3() * frequency;
+                    value += utils::gradient_noise(p, seeding.seed.wrapping_add(octave as u64)) * amplitude;
+                    frequency *= 2.0;
+                    amplitude *= 0.5;
+                }
+                value > seeding.noise_threshold
+            }
+
+            // a cell is removed if, at any level, two of its base 3 digits
+            // are the middle digit.
+            SeedGenerator::Menger => {
+                let size = 3i32.pow(seeding.fractal_level);
+                let mut p = pos * size / bounds;
+                for _ in 0..seeding.fractal_level {
+                    let middle = (p % 3).to_array().iter().filter(|d| **d == 1).count();
+                    if middle >= 2 {
+                        return false;
+                    }
+                    p /= 3;
+                }
+                true
+            }
+
+            // the 3d pascal pyramid mod 2.
+            SeedGenerator::Sierpinski => {
+                let size = 1i32 << seeding.fractal_level;
+                let p = pos * size / bounds;
+                p.x & p.y == 0 && p.y & p.z == 0 && p.x & p.z == 0
+            }
+
+            // concentric shells, alive where the lowest bit of the shell
+            // index' gray code is set.
+            SeedGenerator::GrayShells => {
+                let dist = (pos - utils::center(bounds)).as_vec3().length();
+                let shell = (dist / seeding.shell_thickness) as u32;
+                (shell ^ (shell >> 1)) & 1 == 1
+            }
+        }
+    }
+}
+
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Seeding {
+    pub generator: SeedGenerator,
+    // seeding with the same seed and settings always gives the same cells.
+    pub seed: u64,
+    pub noise_scale: f32,
+    pub noise_octaves: u32,
+    pub noise_threshold: f32,
+    pub fractal_level: u32,
+    pub shell_thickness: f32,
+
+    pub shape: SeedShape,
+    pub size: f32,
+    // chance for each cell in the shape to be alive.
+    pub probability: f32,
+    pub clear: bool,
+    // fills on the sims' task pool. the random fill then draws from one
+    // stream per cell instead of one for the volume, so it differs from the
+    // serial fill, but not between thread counts.
+    #[serde(default)]
+    pub parallel: bool,
+
+    pub image_path: String,
+    // luminance above the threshold is alive. with `image_states`, the
+    // luminance picks the state instead.
+    pub image_threshold: f32,
+    pub image_states: bool,
+    pub image_invert: bool,
+    pub image_z: i32,
+    pub image_depth: i32,
+    #[serde(skip)]
+    image_error: Option<String>,
+}
+
+impl Seeding {
+    pub fn new() -> Seeding {
+        Seeding {
+            generator: SeedGenerator::Random,
+            seed: 0,
+            noise_scale: 12.0,
+            noise_octaves: 2,
+            noise_threshold: 0.1,
+            fractal_level: 3,
+            shell_thickness: 3.0,
+            shape: SeedShape::Volume,
+            size: 0.5,
+            probability: 0.5,
+            clear: true,
+            parallel: false,
+            image_path: "assets/seeds/logo.png".into(),
+            image_threshold: 0.5,
+            image_states: false,
+            image_invert: false,
+            image_z: 32,
+            image_depth: 1,
+            image_error: None,
+        }
+    }
+
+    fn is_alive(&self, pos: IVec3, bounds: i32, rng: &mut impl Rng) -> bool {
+        self.shape.contains(pos, bounds, self.size)
+            && self.generator.is_alive(self, pos, bounds, rng)
+    }
+
+    pub fn seed(&self, sims: &mut Sims) {
+        let bounds = sims.bounds();
+        let states = sims.rule().states;
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        let mut cells = vec![];
+        for index in 0..(bounds*bounds*bounds) as usize {
+            let pos = utils::index_to_pos(index, bounds);
+            if self.is_alive(pos, bounds, &mut rng) {
+                cells.push((pos, states));
+            }
+        }
+
+        if self.clear {
+            sims.reset();
+        }
+        sims.set_cells(&cells);
+    }
+
+    // like `seed`, split into ranges of cells over the pool. every cell
+    // draws from `SplitMix64::at(seed, index)`, so the result only depends
+    // on the settings.
+    pub fn seed_parallel(&self, sims: &mut Sims, tasks: &TaskPool) {
+        let bounds = sims.bounds();
+        let states = sims.rule().states;
+        let count = (bounds*bounds*bounds) as usize;
+        let per_task = (count / (4 * tasks.thread_num()).max(1)).max(1);
+
+        let ranges = tasks.scope(|scope| {
+            for start in (0..count).step_by(per_task) {
+                scope.spawn(async move {
+                    (start..(start + per_task).min(count))
+                        .map(|index| (index, utils::index_to_pos(index, bounds)))
+                        .filter(|(index, pos)| self.is_alive(*pos, bounds, &mut SplitMix64::at(self.seed, *index as u64)))
+                        .map(|(_, pos)| (pos, states))
+                        .collect::<Vec<_>>()
+                });
+            }
+        });
+        let cells: Vec<_> = ranges.into_iter().flatten().collect();
+
+        if self.clear {
+            sims.reset();
+        }
+        sims.set_cells(&cells);
+    }
+
+    // stamps the image into the slices `image_z..image_z + image_depth`,
+    // scaled to cover the bounds. the top row of the image ends up on top.
+    pub fn import_image(&self, sims: &mut Sims) -> Result<(), String> {
+        let image = image::open(&self.image_path)
+            .map_err(|e| format!("failed to load {}: {}", self.image_path, e))?
+            .to_luma8();
+        let (width, height) = image.dimensions();
+
+        let bounds = sims.bounds();
+        let states = sims.rule().states;
+        let mut cells = vec![];
+        for y in 0..bounds {
+            for x in 0..bounds {
+                let px = (x as u32 * width / bounds as u32).min(width - 1);
+                let py = ((bounds - 1 - y) as u32 * height / bounds as u32).min(height - 1);
+                let mut luminance = image.get_pixel(px, py).0[0] as f32 / 255.0;
+                if self.image_invert {
+                    luminance = 1.0 - luminance;
+                }
+
+                let value = match self.image_states {
+                    true  => (luminance * states as f32).round() as CellValue,
+                    false => if luminance > self.image_threshold { states } else { 0 },
+                };
+                if value == 0 {
+                    continue;
+                }
+                for z in self.image_z..self.image_z + self.image_depth {
+                    if (0..bounds).contains(&z) {
+                        cells.push((ivec3(x, y, z), value));
+                    }
+                }
+            }
+        }
+
+        if self.clear {
+            sims.reset();
+        }
+        sims.set_cells(&cells);
+        Ok(())
+    }
+}
+
+
+pub fn update(
+    mut this: ResMut<Seeding>,
+    mut sims: ResMut<Sims>,
+    threading: Res<Threading>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Seeding").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        if !sims.capabilities().contains(SimCapabilities::SET_CELL) {
+            ui.weak(format!("`{}` doesn't support cell access", sims.active_name()));
+            return;
+        }
+
+        egui::ComboBox::from_label("generator")
+            .selected_text(format!("{:?}", this.generator))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut this.generator, SeedGenerator::Random, "Random fill");
+                ui.selectable_value(&mut this.generator, SeedGenerator::Noise, "Gradient noise");
+                ui.selectable_value(&mut this.generator, SeedGenerator::Menger, "Menger sponge");
+                ui.selectable_value(&mut this.generator, SeedGenerator::Sierpinski, "Sierpinski tetrahedron");
+                ui.selectable_value(&mut this.generator, SeedGenerator::GrayShells, "Gray code shells");
+            });
+        ui.horizontal(|ui| {
+            ui.label("seed");
+            ui.add(egui::DragValue::new(&mut this.seed));
+            if ui.small_button("random").clicked() {
+                this.seed = rand::random();
+            }
+        });
+
+        match this.generator {
+            SeedGenerator::Random => {
+                ui.add(egui::Slider::new(&mut this.probability, 0.0..=1.0)
+                    .text("fill probability"));
+            }
+            SeedGenerator::Noise => {
+                ui.add(egui::Slider::new(&mut this.noise_scale, 2.0..=64.0)
+                    .text("scale"));
+                ui.add(egui::Slider::new(&mut this.noise_octaves, 1..=5)
+                    .text("octaves"));
+                ui.add(egui::Slider::new(&mut this.noise_threshold, -1.0..=1.0)
+                    .text("threshold"));
+            }
+            SeedGenerator::Menger | SeedGenerator::Sierpinski => {
+                ui.add(egui::Slider::new(&mut this.fractal_level, 1..=6)
+                    .text("level"));
+            }
+            SeedGenerator::GrayShells => {
+                ui.add(egui::Slider::new(&mut this.shell_thickness, 1.0..=16.0)
+                    .text("shell thickness"));
+            }
+        }
+
+        egui::ComboBox::from_label("shape")
+            .selected_text(format!("{:?}", this.shape))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut this.shape, SeedShape::Volume, "Whole volume");
+                ui.selectable_value(&mut this.shape, SeedShape::Box, "Box");
+                ui.selectable_value(&mut this.shape, SeedShape::Sphere, "Sphere");
+            });
+        if this.shape != SeedShape::Volume {
+            ui.add(egui::Slider::new(&mut this.size, 0.05..=1.0)
+                .text("size"));
+        }
+        ui.checkbox(&mut this.clear, "clear first");
+        ui.checkbox(&mut this.parallel, "parallel")
+            .on_hover_text("same cells for any thread count, but the random fill differs from the serial one");
+
+        if ui.button("seed").clicked() {
+            sims.checkpoint();
+            match this.parallel {
+                true  => this.seed_parallel(&mut sims, threading.pool()),
+                false => this.seed(&mut sims),
+            }
+        }
+
+        ui.separator();
+
+        ui.label("Image:");
+        ui.text_edit_singleline(&mut this.image_path);
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut this.image_states, "luminance to states");
+            ui.checkbox(&mut this.image_invert, "invert");
+        });
+        if !this.image_states {
+            ui.add(egui::Slider::new(&mut this.image_threshold, 0.0..=1.0)
+                .text("threshold"));
+        }
+        let bounds = sims.bounds();
+        ui.add(egui::Slider::new(&mut this.image_z, 0..=bounds - 1)
+            .text("z slice"));
+        ui.add(egui::Slider::new(&mut this.image_depth, 1..=bounds)
+            .text("extrude depth"));
+
+        if ui.button("import").clicked() {
+            sims.checkpoint();
+            this.image_error = this.import_image(&mut sims).err();
+        }
+        if let Some(error) = &this.image_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+    });
+}
+
+
+pub struct SeedingPlugin;
+impl Plugin for SeedingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(Seeding::new())
+        .add_system(update.before(SimsUpdate).with_run_criteria(simulating));
+    }
+}
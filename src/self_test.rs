@@ -0,0 +1,120 @@
+// runs every backend on a tiny seeded grid at startup and compares it with
+// the reference backend, see headless.rs. on in debug builds, or with
+// `--self-test`. disagreements are logged and listed in a window.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    cells::Sim,
+    example_library,
+    headless::{self, GridSnapshot},
+    logged,
+};
+
+const BOUNDS: i32 = 16;
+const TICKS: u32 = 8;
+const SEED: u64 = 0;
+
+
+pub enum Outcome {
+    Agrees,
+    Disagrees { expected: GridSnapshot, got: GridSnapshot },
+    Skipped(String),
+}
+
+pub struct SelfTest {
+    pub results: Vec<(String, Outcome)>,
+    dismissed: bool,
+}
+
+impl SelfTest {
+    pub fn disagreements(&self) -> impl Iterator<Item = &(String, Outcome)> {
+        self.results.iter().filter(|(_, outcome)| matches!(outcome, Outcome::Disagrees { .. }))
+    }
+}
+
+
+// the reference runs at the bounds each backend ends up with, some only
+// support multiples of their chunk size.
+pub fn run(backends: Vec<(String, Box<dyn Sim>)>) -> Vec<(String, Outcome)> {
+    let rule = example_library::fallback_example().rule;
+    let mut references: Vec<GridSnapshot> = vec![];
+
+    backends.into_iter().map(|(name, mut sim)| {
+        if !sim.follows_rule() {
+            return (name, Outcome::Skipped("changes the rule on purpose".into()));
+        }
+        let got = match headless::run_headless_with(&mut *sim, &rule, SEED, BOUNDS, TICKS) {
+            Ok(got) => got,
+            Err(error) => return (name, Outcome::Skipped(error)),
+        };
+        let expected = match references.iter().find(|r| r.bounds == got.bounds) {
+            Some(expected) => expected.clone(),
+            None => {
+                let expected = headless::run_headless(&rule, SEED, got.bounds, TICKS);
+                references.push(expected.clone());
+                expected
+            }
+        };
+        match got == expected {
+            true  => (name, Outcome::Agrees),
+            false => (name, Outcome::Disagrees { expected, got }),
+        }
+    })
+    .collect()
+}
+
+fn enabled() -> bool {
+    cfg!(debug_assertions) || std::env::args().any(|arg| arg == "--self-test")
+}
+
+
+fn update(
+    mut this: ResMut<SelfTest>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    if this.dismissed || this.disagreements().next().is_none() {
+        return;
+    }
+
+    let mut dismissed = false;
+    egui::Window::new("Self-test").show(egui_context.ctx_mut(), |ui| {
+        ui.colored_label(egui::Color32::YELLOW,
+            format!("these backends disagree with the reference after {} ticks at {}³:", TICKS, BOUNDS));
+        for (name, outcome) in this.disagreements() {
+            if let Outcome::Disagrees { expected, got } = outcome {
+                ui.label(format!("{}: {} live cells, expected {}", name, got.population(), expected.population()));
+            }
+        }
+        dismissed = ui.button("dismiss").clicked();
+    });
+    this.dismissed = dismissed;
+}
+
+
+pub struct SelfTestPlugin {
+    pub backends: fn() -> Vec<(String, Box<dyn Sim>)>,
+}
+
+impl Plugin for SelfTestPlugin {
+    fn build(&self, app: &mut App) {
+        let results = match enabled() {
+            true  => run((self.backends)()),
+            false => vec![],
+        };
+        for (name, outcome) in results.iter() {
+            match outcome {
+                Outcome::Agrees => (),
+                Outcome::Disagrees { expected, got } => logged!(warn,
+                    "self-test: {} disagrees with the reference, {} live cells (hash {:x}), expected {} (hash {:x})",
+                    name, got.population(), got.hash(), expected.population(), expected.hash()),
+                Outcome::Skipped(reason) => logged!(info, "self-test: skipped {}: {}", name, reason),
+            }
+        }
+
+        app
+        .insert_resource(SelfTest { results, dismissed: false })
+        .add_system(update);
+    }
+}
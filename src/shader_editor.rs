@@ -0,0 +1,226 @@
+use std::{collections::HashMap, fs};
+use bevy::{
+    prelude::*,
+    render::render_resource::{Shader, ShaderImport, ShaderProcessor},
+};
+use bevy_egui::{egui::{self, text::LayoutJob}, EguiContext};
+
+use crate::app_state::simulating;
+
+// the asset server watches this file, saving is enough to reload the shader.
+const SHADER_PATH: &str = "assets/shaders/cell.wgsl";
+
+const KEYWORDS: &[&str] = &[
+    "fn", "let", "var", "struct", "return", "if", "else", "for", "loop",
+    "break", "continue", "discard", "switch", "case", "default", "true",
+    "false", "uniform", "storage", "private", "function", "workgroup",
+];
+
+
+// the cell pipeline specializes the shader with these defs, each variant is
+// checked on its own.
+fn shader_def_sets() -> Vec<Vec<String>> {
+    [vec![], vec!["BILLBOARD"], vec!["BACKGROUND"]].iter()
+        .map(|defs| {
+            let mut defs: Vec<String> = defs.iter().map(|d| d.to_string()).collect();
+            if cfg!(feature = "post_process") && !defs.contains(&"BACKGROUND".to_string()) {
+                defs.push("EMISSIVE_MASK".into());
+            }
+            defs
+        })
+        .collect()
+}
+
+// runs the source through the same preprocessing as the render world (the
+// bevy_pbr imports are resolved from the loaded shaders), then parses and
+// validates it with naga.
+fn check(source: &str, shaders: &Assets<Shader>) -> Vec<String> {
+    let shader = Shader::from_wgsl(source.to_string());
+    let all: HashMap<Handle<Shader>, Shader> = shaders.iter()
+        .map(|(id, shader)| (Handle::weak(id), shader.clone()))
+        .collect();
+    let imports: HashMap<ShaderImport, Handle<Shader>> = all.iter()
+        .filter_map(|(handle, shader)| Some((shader.import_path()?.clone(), handle.clone_weak())))
+        .collect();
+
+    let processor = ShaderProcessor::default();
+    let mut errors = vec![];
+    for defs in shader_def_sets() {
+        let result = processor.process(&shader, &defs, &all, &imports)
+            .map_err(|e| e.to_string())
+            .and_then(|processed| processed.reflect().map(|_| ()).map_err(|e| e.to_string()));
+        if let Err(error) = result {
+            let error = format!("[{}] {}", defs.join(", "), error);
+            if !errors.contains(&error) {
+                errors.push(error);
+            }
+        }
+    }
+    errors
+}
+
+
+// a rough wgsl highlighter: comments, preprocessor lines, keywords, types
+// and numbers.
+fn highlight(text: &str, font: egui::FontId, dark: bool) -> LayoutJob {
+    let color = |light: (u8, u8, u8), dark_color: (u8, u8, u8)| {
+        let (r, g, b) = if dark { dark_color } else { light };
+        egui::Color32::from_rgb(r, g, b)
+    };
+    let plain     = color((40, 40, 40),  (210, 210, 210));
+    let comment   = color((60, 130, 60), (110, 160, 100));
+    let directive = color((150, 90, 20), (220, 160, 90));
+    let keyword   = color((150, 40, 150), (200, 130, 220));
+    let ty        = color((20, 110, 150), (90, 180, 220));
+    let number    = color((160, 80, 30), (230, 170, 110));
+
+    let mut job = LayoutJob::default();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let line_end = rest.find('\n').map(|i| i + 1).unwrap_or(rest.len());
+        let (len, color) =
+            if rest.starts_with("//") {
+                (line_end, comment)
+            }
+            else if rest.starts_with('#') {
+                (line_end, directive)
+            }
+            else if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+                let word = &rest[..len];
+                let color =
+                    if KEYWORDS.contains(&word) { keyword }
+                    else if is_type(word) { ty }
+                    else { plain };
+                (len, color)
+            }
+            else if rest.starts_with(|c: char| c.is_ascii_digit()) {
+                let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '.')).unwrap_or(rest.len());
+                (len, number)
+            }
+            else {
+                (rest.chars().next().unwrap().len_utf8(), plain)
+            };
+        job.append(&rest[..len], 0.0, egui::TextFormat::simple(font.clone(), color));
+        rest = &rest[len..];
+    }
+    job
+}
+
+fn is_type(word: &str) -> bool {
+    matches!(word, "f32" | "i32" | "u32" | "bool" | "array" | "sampler")
+        || word.starts_with("vec")
+        || word.starts_with("mat")
+        || word.starts_with("texture")
+        || word.starts_with(char::is_uppercase)
+}
+
+
+pub struct ShaderEditor {
+    text: String,
+    // what's on disk, to tell if there are unsaved edits.
+    saved: String,
+    errors: Vec<String>,
+    status: String,
+}
+
+impl ShaderEditor {
+    pub fn new() -> ShaderEditor {
+        let mut editor = ShaderEditor {
+            text: String::new(),
+            saved: String::new(),
+            errors: vec![],
+            status: String::new(),
+        };
+        editor.revert();
+        editor
+    }
+
+    fn revert(&mut self) {
+        match fs::read_to_string(SHADER_PATH) {
+            Ok(text) => {
+                self.text  = text.clone();
+                self.saved = text;
+                self.status.clear();
+            }
+            Err(error) => self.status = format!("failed to read {}: {}", SHADER_PATH, error),
+        }
+        self.errors.clear();
+    }
+
+    // only writes shaders that compile, a broken shader would stop the
+    // cells from rendering at all.
+    fn save(&mut self, shaders: &Assets<Shader>) {
+        self.errors = check(&self.text, shaders);
+        if !self.errors.is_empty() {
+            self.status = "not saved, the shader has errors.".into();
+            return;
+        }
+        self.status = match fs::write(SHADER_PATH, &self.text) {
+            Ok(()) => {
+                self.saved = self.text.clone();
+                "saved.".into()
+            }
+            Err(error) => format!("failed to write {}: {}", SHADER_PATH, error),
+        };
+    }
+}
+
+
+pub fn update(
+    mut this: ResMut<ShaderEditor>,
+    shaders: Res<Assets<Shader>>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Shader editor").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            if ui.button("check").clicked() {
+                this.errors = check(&this.text, &shaders);
+                this.status = match this.errors.is_empty() {
+                    true  => "no errors.".into(),
+                    false => String::new(),
+                };
+            }
+            if ui.button("save").clicked() {
+                this.save(&shaders);
+            }
+            if ui.button("revert").clicked() {
+                this.revert();
+            }
+            if this.text != this.saved {
+                ui.weak("(modified)");
+            }
+        });
+
+        let dark = ui.visuals().dark_mode;
+        let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+            let mut job = highlight(text, egui::FontId::monospace(12.0), dark);
+            job.wrap_width = wrap_width;
+            ui.fonts().layout_job(job)
+        };
+        egui::ScrollArea::vertical().max_height(480.0).show(ui, |ui| {
+            ui.add(egui::TextEdit::multiline(&mut this.text)
+                .code_editor()
+                .desired_rows(30)
+                .desired_width(f32::INFINITY)
+                .layouter(&mut layouter));
+        });
+
+        for error in this.errors.iter() {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        if !this.status.is_empty() {
+            ui.label(&this.status);
+        }
+    });
+}
+
+
+pub struct ShaderEditorPlugin;
+impl Plugin for ShaderEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(ShaderEditor::new())
+        .add_system(update.with_run_criteria(simulating));
+    }
+}
@@ -0,0 +1,186 @@
+use std::{fs, io::Write, time::{Duration, Instant}};
+
+use bevy::prelude::Color;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    cell_renderer::{CellRenderer, InstanceColoring, InstanceData, NeighbourFilter},
+    cells::Sim,
+    example_library,
+    graphics::InstanceFilter,
+    neighbours::NeighbourCount,
+    rule::ColorMethod,
+    theme::{Palette, Theme},
+    threading::Threading,
+};
+
+const USAGE: &str = "\
+--soak [hours]                  cycles backends, bounds, resets and noise without a window
+    --soak-log PATH             samples as csv (default soak.csv)
+    --soak-tolerance PERCENT    allowed growth over the warmup peak (default 25)
+    --threads N                 see threading.rs";
+
+// ticks per cycle, about what a user watches before changing something.
+const TICKS_PER_CYCLE: u32 = 50;
+
+
+// resident set size in bytes. only available on linux.
+fn rss() -> Option<u64> {
+    let statm = fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * 4096)
+}
+
+struct Sample {
+    elapsed: Duration,
+    cycle: usize,
+    backend: String,
+    bounds: i32,
+    rss: Option<u64>,
+    // bytes held by the renderer and the instance buffer.
+    buffers: usize,
+}
+
+fn buffer_bytes(renderer: &CellRenderer, instances: &Vec<InstanceData>) -> usize {
+    renderer.values.capacity()
+        + renderer.neighbors.capacity() * std::mem::size_of::<NeighbourCount>()
+        + renderer.metadata.capacity()
+        + renderer.decay.capacity()
+        + renderer.energy.capacity()
+        + renderer.spawn_times.capacity() * std::mem::size_of::<f32>()
+        + instances.capacity() * std::mem::size_of::<InstanceData>()
+}
+
+
+// one cycle: switch to a backend, resize, reset, spawn noise and run it
+// like a frame of `Sims::update` would.
+fn cycle(
+    sim: &mut dyn Sim, bounds: i32,
+    renderer: &mut CellRenderer, instances: &mut Vec<InstanceData>,
+    threading: &Threading,
+) -> i32 {
+    let rule = example_library::fallback_example().rule;
+    let bounds = sim.set_bounds(bounds);
+    sim.reset();
+    sim.spawn_noise(&rule);
+    renderer.set_bounds(bounds);
+    renderer.clear();
+
+    let coloring = InstanceColoring {
+        method: ColorMethod::StateLerp,
+        palette: Palette { theme: Theme::Custom, color1: Color::YELLOW, color2: Color::RED },
+        states: rule.states,
+        max_neighbours: rule.neighbour_method.max_neighbours(),
+        defect_color: None,
+        materials: None,
+    };
+    for _ in 0..TICKS_PER_CYCLE {
+        sim.update(&rule, threading.pool());
+        sim.render_delta(renderer);
+        renderer.extract_instances(&coloring, &InstanceFilter::All, &NeighbourFilter::default(),
+            threading.pool(), instances);
+    }
+    bounds
+}
+
+// runs until `duration` is over. the first round visits every backend at
+// the largest bounds, its peak rss is the baseline. fails once the rss
+// grows past the baseline by more than `tolerance` percent.
+pub fn run(mut backends: Vec<(String, Box<dyn Sim>)>, duration: Duration, tolerance: f64, log_path: &str, threading: &Threading) -> Result<(), String> {
+    let mut log = fs::File::create(log_path).map_err(|e| format!("{}: {}", log_path, e))?;
+    writeln!(log, "seconds,cycle,backend,bounds,rss,buffers").map_err(|e| e.to_string())?;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut renderer = CellRenderer::new();
+    let mut instances = vec![];
+    let mut baseline: Option<u64> = None;
+    let mut warmup_peak = 0;
+    let start = Instant::now();
+
+    let mut index = 0;
+    while start.elapsed() < duration {
+        let warmup = index < backends.len();
+        let backend = match warmup {
+            true  => index,
+            false => rng.gen_range(0..backends.len()),
+        };
+        let (name, sim) = &mut backends[backend];
+        let bounds = match warmup {
+            true  => sim.max_bounds(),
+            false => rng.gen_range(8..=sim.max_bounds()),
+        };
+        let bounds = cycle(sim.as_mut(), bounds, &mut renderer, &mut instances, threading);
+
+        let sample = Sample {
+            elapsed: start.elapsed(),
+            cycle: index,
+            backend: name.clone(),
+            bounds,
+            rss: rss(),
+            buffers: buffer_bytes(&renderer, &instances),
+        };
+        writeln!(log, "{:.1},{},\"{}\",{},{},{}",
+            sample.elapsed.as_secs_f64(), sample.cycle, sample.backend, sample.bounds,
+            sample.rss.map(|r| r.to_string()).unwrap_or_default(), sample.buffers)
+            .map_err(|e| e.to_string())?;
+
+        if let Some(rss) = sample.rss {
+            if warmup {
+                warmup_peak = warmup_peak.max(rss);
+            }
+            else {
+                let baseline = *baseline.get_or_insert(warmup_peak);
+                let limit = baseline as f64 * (1.0 + tolerance / 100.0);
+                if rss as f64 > limit {
+                    return Err(format!(
+                        "rss grew to {} MiB after {:.0?} (cycle {}, {} at {}), the warmup peak was {} MiB",
+                        rss >> 20, sample.elapsed, sample.cycle, sample.backend, sample.bounds, baseline >> 20));
+                }
+            }
+        }
+        if index % 100 == 0 {
+            println!("{:.0?}: cycle {}, rss {} MiB, buffers {} MiB",
+                sample.elapsed, index, sample.rss.unwrap_or(0) >> 20, sample.buffers >> 20);
+        }
+        index += 1;
+    }
+
+    if baseline.is_none() {
+        println!("note: rss isn't available on this platform, only the log was written.");
+    }
+    Ok(())
+}
+
+
+// handles `--soak`. returns false if it wasn't given.
+pub fn run_from_args(args: &[String], backends: impl FnOnce() -> Vec<(String, Box<dyn Sim>)>) -> bool {
+    let i = match args.iter().position(|a| a == "--soak") {
+        Some(i) => i,
+        None => return false,
+    };
+    let value = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1));
+    let number = |arg: Option<&String>, default: f64| match arg {
+        Some(n) if !n.starts_with("--") => n.parse::<f64>().unwrap_or_else(|_| {
+            eprintln!("`{}` is not a number\n{}", n, USAGE);
+            std::process::exit(2)
+        }),
+        _ => default,
+    };
+
+    let hours = number(args.get(i + 1), 1.0);
+    let tolerance = number(value("--soak-tolerance"), 25.0);
+    let log_path = value("--soak-log").map(|p| p.as_str()).unwrap_or("soak.csv");
+    let threading = Threading::from_args(args.iter().cloned());
+
+    println!("soaking for {} hour(s), logging to {}", hours, log_path);
+    match run(backends(), Duration::from_secs_f64(hours * 3600.0), tolerance, log_path, &threading) {
+        Ok(()) => {
+            println!("done, no unbounded growth.");
+            true
+        }
+        Err(error) => {
+            eprintln!("soak failed: {}", error);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use bevy::prelude::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::utils;
+
+
+// where the color methods get their gradient from. `Custom` blends the two
+// picked colors, the others are fixed color maps.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Theme {
+    Custom,
+    Viridis,
+    Magma,
+    // colorblind safe, from dark blue to yellow.
+    Cividis,
+    // colorblind safe (okabe-ito colors), blue to orange through white.
+    BlueOrange,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::Custom
+    }
+}
+
+pub const THEMES: [Theme; 5] = [
+    Theme::Custom, Theme::Viridis, Theme::Magma, Theme::Cividis, Theme::BlueOrange,
+];
+
+impl Theme {
+    // evenly spaced stops, as srgb.
+    fn stops(&self) -> &'static [(u8, u8, u8)] {
+        match self {
+            Theme::Custom => &[],
+            Theme::Viridis => &[
+                (0x44, 0x01, 0x54), (0x47, 0x2c, 0x7a), (0x3b, 0x51, 0x8b),
+                (0x2c, 0x71, 0x8e), (0x21, 0x90, 0x8d), (0x27, 0xad, 0x81),
+                (0x5c, 0xc8, 0x63), (0xaa, 0xdc, 0x32), (0xfd, 0xe7, 0x25),
+            ],
+            Theme::Magma => &[
+                (0x00, 0x00, 0x04), (0x1c, 0x10, 0x44), (0x4f, 0x12, 0x7b),
+                (0x81, 0x25, 0x81), (0xb5, 0x36, 0x7a), (0xe5, 0x50, 0x64),
+                (0xfb, 0x87, 0x61), (0xfe, 0xc2, 0x87), (0xfc, 0xfd, 0xbf),
+            ],
+            Theme::Cividis => &[
+                (0x00, 0x20, 0x4c), (0x21, 0x3d, 0x6b), (0x55, 0x5b, 0x6c),
+                (0x7b, 0x7a, 0x77), (0xa5, 0x9c, 0x74), (0xd3, 0xc0, 0x64),
+                (0xff, 0xe9, 0x45),
+            ],
+            Theme::BlueOrange => &[
+                (0x00, 0x72, 0xb2), (0x56, 0xb4, 0xe9), (0xf7, 0xf7, 0xf7),
+                (0xf0, 0xb4, 0x40), (0xe6, 0x9f, 0x00),
+            ],
+        }
+    }
+}
+
+
+// a theme together with the custom colors, everything the color methods
+// need to turn a 0..1 value into a color.
+#[derive(Clone, Copy)]
+pub struct Palette {
+    pub theme: Theme,
+    pub color1: Color,
+    pub color2: Color,
+}
+
+impl Palette {
+    pub fn sample(&self, t: f32) -> Color {
+        let stops = self.theme.stops();
+        if stops.is_empty() {
+            return utils::lerp_color(self.color1, self.color2, t);
+        }
+
+        let t = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let i = (t as usize).min(stops.len() - 2);
+        let to_color = |(r, g, b): (u8, u8, u8)| Color::rgb_u8(r, g, b);
+        utils::lerp_color(to_color(stops[i]), to_color(stops[i + 1]), t - i as f32)
+    }
+}
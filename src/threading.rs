@@ -0,0 +1,115 @@
+use bevy::{
+    prelude::*,
+    tasks::{logical_core_count, TaskPool, TaskPoolBuilder},
+};
+use bevy_egui::{egui, EguiContext};
+
+use crate::{app_state::{has_ui, simulating}, cells::SimsUpdate, logged};
+
+
+// the sims get a task pool of their own, so the thread count can change at
+// runtime. bevy's pools are fixed once the app is built.
+pub struct Threading {
+    pub threads: usize,
+    // runs the updates on a pool with a single thread, to compare against
+    // the multi-threaded numbers.
+    pub single_thread: bool,
+    // 0 splits the cells evenly over the threads.
+    pub cells_per_task: usize,
+    pool: TaskPool,
+    pool_threads: usize,
+}
+
+impl Threading {
+    pub fn new(threads: usize) -> Threading {
+        let threads = threads.max(1);
+        Threading {
+            threads,
+            single_thread: false,
+            cells_per_task: 0,
+            pool: build_pool(threads),
+            pool_threads: threads,
+        }
+    }
+
+    // eg: `--threads 4 --cells-per-task 4096 --single-thread`.
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Threading {
+        let mut this = Threading::new(logical_core_count());
+        while let Some(arg) = args.next() {
+            let mut number = || args.next().and_then(|n| n.parse::<usize>().ok());
+            match arg.as_str() {
+                "--threads" => match number() {
+                    Some(n) => this.threads = n.max(1),
+                    None => logged!(warn, "--threads expects a number"),
+                },
+                "--cells-per-task" => match number() {
+                    Some(n) => this.cells_per_task = n,
+                    None => logged!(warn, "--cells-per-task expects a number"),
+                },
+                "--single-thread" => this.single_thread = true,
+                _ => (),
+            }
+        }
+        this.apply();
+        this
+    }
+
+    pub fn pool(&self) -> &TaskPool {
+        &self.pool
+    }
+
+    pub fn active_threads(&self) -> usize {
+        if self.single_thread { 1 } else { self.threads }
+    }
+
+    // rebuilds the pool if the thread count changed. the old pool's threads
+    // finish their tasks and exit when it's dropped.
+    pub fn apply(&mut self) {
+        let threads = self.active_threads();
+        if threads != self.pool_threads {
+            self.pool = build_pool(threads);
+            self.pool_threads = threads;
+        }
+    }
+}
+
+fn build_pool(threads: usize) -> TaskPool {
+    TaskPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name("sims".into())
+        .build()
+}
+
+
+pub fn update(
+    mut this: ResMut<Threading>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Threading").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.add_enabled(!this.single_thread,
+            egui::Slider::new(&mut this.threads, 1..=logical_core_count().max(this.threads))
+            .text("threads"));
+        ui.checkbox(&mut this.single_thread, "single thread");
+
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut this.cells_per_task)
+                .speed(64.0)
+                .clamp_range(0..=1_000_000));
+            ui.label("cells per task");
+        });
+        ui.weak("0 splits the cells evenly over the threads. leddoo atomic always uses 32³ chunks.");
+    });
+
+    this.apply();
+}
+
+
+pub struct ThreadingPlugin;
+impl Plugin for ThreadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Threading::from_args(std::env::args().skip(1)));
+        if has_ui(app) {
+            app.add_system(update.before(SimsUpdate).with_run_criteria(simulating));
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    action::Action,
+    app_state::simulating,
+    cells::{Sims, SimsUpdate},
+};
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThrottleResponse {
+    Pause,
+    // one tick per frame until the growth calms down, then the old ticks
+    // per frame again.
+    Slow,
+}
+
+// watches the population for runaway growth: doubling within `window`
+// ticks, above `min_population`. it has to hold for `window` / 2 ticks in
+// a row before the throttle engages, and growth has to fall below
+// `RELEASE_GROWTH` before it lets go, so noise and single bursts don't
+// trip it.
+pub struct Throttle {
+    pub enabled: bool,
+    pub window: usize,
+    pub min_population: usize,
+    pub response: ThrottleResponse,
+    last_tick: u64,
+    // population per tick, newest last.
+    history: VecDeque<usize>,
+    // ticks in a row the population was running away.
+    running_away: usize,
+    engaged: bool,
+    // ticks per frame before slowing down.
+    restore_ticks: Option<u32>,
+    notice: Option<String>,
+}
+
+const RELEASE_GROWTH: f32 = 1.25;
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Throttle {
+            enabled: true,
+            window: 8,
+            min_population: 50_000,
+            response: ThrottleResponse::Slow,
+            last_tick: 0,
+            history: VecDeque::new(),
+            running_away: 0,
+            engaged: false,
+            restore_ticks: None,
+            notice: None,
+        }
+    }
+}
+
+impl Throttle {
+    pub fn engaged(&self) -> bool {
+        self.engaged
+    }
+
+    // population now over `window` ticks ago.
+    fn growth(&self) -> Option<f32> {
+        if self.history.len() <= self.window {
+            return None;
+        }
+        let now = *self.history.back()?;
+        let then = self.history[self.history.len() - 1 - self.window];
+        Some(now as f32 / then.max(1) as f32)
+    }
+
+    fn reset(&mut self) {
+        self.history.clear();
+        self.running_away = 0;
+    }
+
+    // returns the actions to take for this tick.
+    fn observe(&mut self, tick: u64, population: usize, ticks_per_frame: u32) -> Vec<Action> {
+        // a jump back (undo, load) or far ahead isn't growth.
+        if tick <= self.last_tick || tick > self.last_tick + ticks_per_frame as u64 {
+            self.reset();
+        }
+        self.last_tick = tick;
+        if self.history.len() > self.window {
+            self.history.pop_front();
+        }
+        self.history.push_back(population);
+
+        let growth = match self.growth() {
+            Some(growth) => growth,
+            None => return vec![],
+        };
+        let mut actions = vec![];
+        if !self.engaged {
+            self.running_away = match growth >= 2.0 && population >= self.min_population {
+                true  => self.running_away + 1,
+                false => 0,
+            };
+            if self.running_away >= (self.window / 2).max(1) {
+                self.engaged = true;
+                self.running_away = 0;
+                self.notice = Some(format!("tick {}: the population grew {:.1}x in {} ticks, to {}",
+                    tick, growth, self.window, population));
+                match self.response {
+                    ThrottleResponse::Pause => actions.push(Action::SetPaused(true)),
+                    ThrottleResponse::Slow => {
+                        self.restore_ticks = Some(ticks_per_frame);
+                        actions.push(Action::SetTicksPerFrame(1));
+                    }
+                }
+            }
+        }
+        else if growth < RELEASE_GROWTH {
+            self.release(&mut actions);
+        }
+        actions
+    }
+
+    fn release(&mut self, actions: &mut Vec<Action>) {
+        self.engaged = false;
+        if let Some(ticks) = self.restore_ticks.take() {
+            actions.push(Action::SetTicksPerFrame(ticks));
+        }
+    }
+}
+
+
+fn update(
+    mut this: ResMut<Throttle>,
+    sims: Res<Sims>,
+    mut actions: EventWriter<Action>,
+) {
+    if !this.enabled || sims.tick() == this.last_tick {
+        return;
+    }
+    for action in this.observe(sims.tick(), sims.cell_count(), sims.ticks_per_frame()) {
+        actions.send(action);
+    }
+}
+
+fn update_ui(
+    mut this: ResMut<Throttle>,
+    mut actions: EventWriter<Action>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let this = &mut *this;
+    let ctx = egui_context.ctx_mut();
+
+    egui::Window::new("Auto-throttle").default_open(false).show(ctx, |ui| {
+        let was_enabled = this.enabled;
+        ui.checkbox(&mut this.enabled, "stop runaway growth");
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut this.response, ThrottleResponse::Slow, "slow down");
+            ui.selectable_value(&mut this.response, ThrottleResponse::Pause, "pause");
+        });
+        ui.add(egui::Slider::new(&mut this.window, 2..=64).text("doubling within ticks"));
+        ui.add(egui::DragValue::new(&mut this.min_population).speed(1000.0).prefix("ignore below: "));
+        if let Some(growth) = this.growth() {
+            ui.weak(format!("growth over {} ticks: {:.2}x", this.window, growth));
+        }
+        if this.engaged {
+            ui.label("throttled");
+        }
+        if was_enabled && !this.enabled {
+            let mut released = vec![];
+            this.release(&mut released);
+            this.reset();
+            for action in released {
+                actions.send(action);
+            }
+        }
+    });
+
+    let mut dismiss = false;
+    if let Some(notice) = &this.notice {
+        egui::Window::new("Runaway growth").collapsible(false).show(ctx, |ui| {
+            ui.label(notice.as_str());
+            ui.label(match this.response {
+                ThrottleResponse::Pause => "paused the simulation.",
+                ThrottleResponse::Slow => "slowed down to one tick per frame until it calms down.",
+            });
+            dismiss = ui.button("ok").clicked();
+        });
+    }
+    if dismiss {
+        this.notice = None;
+    }
+}
+
+
+pub struct ThrottlePlugin;
+impl Plugin for ThrottlePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<Throttle>()
+        .add_system(update.after(SimsUpdate).with_run_criteria(simulating))
+        .add_system(update_ui.with_run_criteria(simulating));
+    }
+}
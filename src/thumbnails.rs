@@ -0,0 +1,197 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+};
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    tasks::{AsyncComputeTaskPool, Task, TaskPool},
+};
+use bevy_egui::{egui, EguiContext};
+use futures_lite::future;
+
+use crate::{
+    app_state::simulating,
+    cell_renderer::CellRenderer,
+    cells::{leddoo::LeddooSingleThreaded, Example, Sim, Sims, SimsUpdate},
+    logged,
+    theme::Palette,
+    utils,
+};
+
+const CACHE_DIR: &str = "thumbnails";
+const BOUNDS: i32 = 48;
+const TICKS: usize = 60;
+const SIZE: u32 = 96;
+
+
+// runs the example headlessly and projects the result along z: every pixel
+// shows the front-most live cell, darkened with depth. this is a cpu
+// rendering, the cell pipeline has no offscreen path.
+pub fn render_thumbnail(example: &Example, task_pool: &TaskPool) -> image::RgbaImage {
+    let rule = &example.rule;
+    let mut sim = LeddooSingleThreaded::new();
+    Sim::set_bounds(&mut sim, BOUNDS);
+    Sim::spawn_noise(&mut sim, rule);
+    for _ in 0..TICKS {
+        Sim::update(&mut sim, rule, task_pool);
+    }
+
+    let mut renderer = CellRenderer::new();
+    renderer.set_bounds(BOUNDS);
+    Sim::render(&sim, &mut renderer);
+
+    let palette = Palette { theme: example.theme, color1: example.color1, color2: example.color2 };
+    let background = image::Rgba([24, 24, 28, 255]);
+    image::RgbaImage::from_fn(SIZE, SIZE, |px, py| {
+        let x = (px as i32 * BOUNDS / SIZE as i32).min(BOUNDS - 1);
+        let y = ((SIZE - 1 - py) as i32 * BOUNDS / SIZE as i32).min(BOUNDS - 1);
+        for z in (0..BOUNDS).rev() {
+            let pos = IVec3::new(x, y, z);
+            let index = utils::pos_to_index(pos, BOUNDS);
+            let value = renderer.values[index];
+            if value == 0 {
+                continue;
+            }
+            let color = example.color_method.color(
+                &palette, rule.states,
+                value, renderer.neighbors[index], rule.neighbour_method.max_neighbours(),
+                renderer.metadata[index], renderer.energy[index],
+                utils::dist_to_center(pos, BOUNDS));
+            let shade = 0.35 + 0.65 * (z as f32 / (BOUNDS - 1) as f32);
+            let [r, g, b, _] = color.as_rgba_f32();
+            let channel = |c: f32| ((c * shade).clamp(0.0, 1.0) * 255.0) as u8;
+            return image::Rgba([channel(r), channel(g), channel(b), 255]);
+        }
+        background
+    })
+}
+
+// the name keeps the cache browsable, the hash invalidates it when the
+// rule or the colors change.
+fn cache_path(example: &Example) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    example.rule.to_string().hash(&mut hasher);
+    format!("{:?}{:?}{:?}{:?}", example.color_method, example.color1, example.color2, example.theme).hash(&mut hasher);
+    let name: String = example.name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    PathBuf::from(CACHE_DIR).join(format!("{}_{:016x}.png", name, hasher.finish()))
+}
+
+fn load_or_render(example: &Example, task_pool: &TaskPool) -> image::RgbaImage {
+    let path = cache_path(example);
+    if let Ok(image) = image::open(&path) {
+        return image.to_rgba8();
+    }
+    let image = render_thumbnail(example, task_pool);
+    let saved = fs::create_dir_all(CACHE_DIR).map_err(|e| e.to_string())
+        .and_then(|_| image.save(&path).map_err(|e| e.to_string()));
+    if let Err(error) = saved {
+        logged!(warn, "failed to cache thumbnail {}: {}", path.display(), error);
+    }
+    image
+}
+
+
+struct Thumbnail {
+    name: String,
+    texture: Option<egui::TextureId>,
+    task: Option<Task<image::RgbaImage>>,
+}
+
+pub struct Thumbnails {
+    thumbnails: Vec<Thumbnail>,
+}
+
+impl Thumbnails {
+    pub fn new() -> Thumbnails {
+        Thumbnails { thumbnails: vec![] }
+    }
+
+    // starts generating thumbnails for examples that don't have one yet.
+    fn sync(&mut self, examples: &[Example], task_pool: &AsyncComputeTaskPool) {
+        let up_to_date = self.thumbnails.len() == examples.len()
+            && self.thumbnails.iter().zip(examples).all(|(t, e)| t.name == e.name);
+        if up_to_date {
+            return;
+        }
+
+        self.thumbnails = examples.iter().map(|example| {
+            let example = example.clone();
+            let pool = task_pool.0.clone();
+            Thumbnail {
+                name: example.name.clone(),
+                texture: None,
+                task: Some(task_pool.spawn(async move { load_or_render(&example, &pool) })),
+            }
+        }).collect();
+    }
+}
+
+
+pub fn update(
+    mut this: ResMut<Thumbnails>,
+    mut sims: ResMut<Sims>,
+    task_pool: Res<AsyncComputeTaskPool>,
+    mut images: ResMut<Assets<Image>>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    this.sync(sims.examples(), &task_pool);
+
+    for thumbnail in this.thumbnails.iter_mut() {
+        let done = match &mut thumbnail.task {
+            Some(task) => future::block_on(future::poll_once(task)),
+            None => None,
+        };
+        if let Some(pixels) = done {
+            let image = Image::new(
+                Extent3d { width: pixels.width(), height: pixels.height(), depth_or_array_layers: 1 },
+                TextureDimension::D2,
+                pixels.into_raw(),
+                TextureFormat::Rgba8UnormSrgb);
+            thumbnail.texture = Some(egui_context.add_image(images.add(image)));
+            thumbnail.task = None;
+        }
+    }
+
+    let mut clicked = None;
+    egui::Window::new("Example browser").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        egui::ScrollArea::vertical().max_height(420.0).show(ui, |ui| {
+            egui::Grid::new("example thumbnails").show(ui, |ui| {
+                for (i, thumbnail) in this.thumbnails.iter().enumerate() {
+                    ui.vertical(|ui| {
+                        let size = egui::vec2(SIZE as f32, SIZE as f32);
+                        let response = match thumbnail.texture {
+                            Some(texture) => ui.add(egui::ImageButton::new(texture, size)),
+                            None => ui.add_sized(size, egui::Button::new("...")),
+                        };
+                        if response.clicked() {
+                            clicked = Some(i);
+                        }
+                        ui.label(&thumbnail.name);
+                    });
+                    if i % 3 == 2 {
+                        ui.end_row();
+                    }
+                }
+            });
+        });
+    });
+
+    if let Some(i) = clicked {
+        sims.set_example(i);
+    }
+}
+
+
+pub struct ThumbnailsPlugin;
+impl Plugin for ThumbnailsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(Thumbnails::new())
+        .add_system(update.before(SimsUpdate).with_run_criteria(simulating));
+    }
+}
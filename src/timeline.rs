@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    action::{Action, ActionHandler},
+    app_state::simulating,
+    cells::{Sims, SimsUpdate},
+    rotating_camera::RotatingCamera,
+    rule::ColorMethod,
+};
+
+
+#[derive(Clone, Debug)]
+pub enum KeyframeAction {
+    Example(usize),
+    Camera { rotation: f32, speed: f32, dist: f32 },
+    TicksPerFrame(u32),
+    Colors { method: ColorMethod, color1: Color, color2: Color },
+}
+
+#[derive(Clone, Debug)]
+pub struct Keyframe {
+    // relative to the start of the playback.
+    pub tick: u64,
+    pub action: KeyframeAction,
+}
+
+
+pub struct Timeline {
+    keyframes: Vec<Keyframe>,
+    playing: bool,
+    start_tick: u64,
+    next: usize,
+    new_tick: u64,
+    new_example: usize,
+}
+
+impl Timeline {
+    pub fn new() -> Timeline {
+        Timeline {
+            keyframes: vec![],
+            playing: false,
+            start_tick: 0,
+            next: 0,
+            new_tick: 0,
+            new_example: 0,
+        }
+    }
+
+    pub fn add(&mut self, keyframe: Keyframe) {
+        // stable, so keyframes on the same tick apply in insertion order.
+        let index = self.keyframes.partition_point(|k| k.tick <= keyframe.tick);
+        self.keyframes.insert(index, keyframe);
+    }
+
+    pub fn play(&mut self, sims: &Sims) {
+        self.playing = true;
+        self.next = 0;
+        self.start_tick = sims.tick();
+    }
+
+    fn apply(action: &KeyframeAction, actions: &mut EventWriter<Action>, cameras: &mut Query<&mut RotatingCamera>) {
+        match action {
+            KeyframeAction::Example(index) => actions.send(Action::LoadExample(*index)),
+            KeyframeAction::Camera { rotation, speed, dist } => {
+                for mut camera in cameras.iter_mut() {
+                    camera.rotation = *rotation;
+                    camera.speed = *speed;
+                    camera.dist = *dist;
+                }
+            }
+            KeyframeAction::TicksPerFrame(ticks) => actions.send(Action::SetTicksPerFrame(*ticks)),
+            KeyframeAction::Colors { method, color1, color2 } => {
+                actions.send(Action::SetColors(*method, *color1, *color2));
+            }
+        }
+    }
+}
+
+fn describe(action: &KeyframeAction, sims: &Sims) -> String {
+    match action {
+        KeyframeAction::Example(index) => format!("example: {}",
+            sims.examples().get(*index).map(|e| e.name.as_str()).unwrap_or("?")),
+        KeyframeAction::Camera { dist, .. } => format!("camera: dist {:.0}", dist),
+        KeyframeAction::TicksPerFrame(ticks) => format!("ticks per frame: {}", ticks),
+        KeyframeAction::Colors { method, .. } => format!("colors: {:?}", method),
+    }
+}
+
+
+pub fn update(
+    mut this: ResMut<Timeline>,
+    sims: Res<Sims>,
+    mut cameras: Query<&mut RotatingCamera>,
+    mut actions: EventWriter<Action>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    if this.playing {
+        let elapsed = sims.tick() - this.start_tick;
+        while let Some(keyframe) = this.keyframes.get(this.next) {
+            if keyframe.tick > elapsed {
+                break;
+            }
+            let action = keyframe.action.clone();
+            Timeline::apply(&action, &mut actions, &mut cameras);
+            this.next += 1;
+        }
+        if this.next >= this.keyframes.len() {
+            this.playing = false;
+        }
+    }
+
+    egui::Window::new("Timeline").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            if this.playing {
+                ui.label(format!("playing: tick {}", sims.tick() - this.start_tick));
+                if ui.button("stop").clicked() {
+                    this.playing = false;
+                }
+            }
+            else if ui.add_enabled(!this.keyframes.is_empty(), egui::Button::new("play")).clicked() {
+                this.play(&sims);
+            }
+        });
+
+        ui.separator();
+
+        let mut remove = None;
+        for (i, keyframe) in this.keyframes.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let text = format!("{:>6}  {}", keyframe.tick, describe(&keyframe.action, &sims));
+                match this.playing && i < this.next {
+                    true  => ui.weak(text),
+                    false => ui.monospace(text),
+                };
+                if ui.small_button("x").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            this.keyframes.remove(i);
+        }
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("add at tick");
+            ui.add(egui::DragValue::new(&mut this.new_tick).speed(10.0));
+        });
+
+        let tick = this.new_tick;
+        ui.horizontal(|ui| {
+            let mut example = this.new_example.min(sims.examples().len().saturating_sub(1));
+            egui::ComboBox::from_id_source("timeline example")
+                .selected_text(sims.examples().get(example).map(|e| e.name.as_str()).unwrap_or(""))
+                .show_ui(ui, |ui| {
+                    for (i, e) in sims.examples().iter().enumerate() {
+                        ui.selectable_value(&mut example, i, &e.name);
+                    }
+                });
+            this.new_example = example;
+
+            if ui.button("example").clicked() {
+                this.add(Keyframe { tick, action: KeyframeAction::Example(example) });
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("camera").clicked() {
+                if let Some(camera) = cameras.iter().next() {
+                    this.add(Keyframe { tick, action: KeyframeAction::Camera {
+                        rotation: camera.rotation,
+                        speed: camera.speed,
+                        dist: camera.dist,
+                    }});
+                }
+            }
+            if ui.button("tick rate").clicked() {
+                this.add(Keyframe { tick, action: KeyframeAction::TicksPerFrame(sims.ticks_per_frame()) });
+            }
+            if ui.button("colors").clicked() {
+                let (method, color1, color2) = sims.colors();
+                this.add(Keyframe { tick, action: KeyframeAction::Colors { method, color1, color2 } });
+            }
+        });
+        ui.weak("camera, tick rate and colors capture the current values.");
+    });
+}
+
+
+pub struct TimelinePlugin;
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(Timeline::new())
+        .add_system(update.before(ActionHandler).before(SimsUpdate).with_run_criteria(simulating));
+    }
+}
@@ -0,0 +1,193 @@
+use std::fs;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{app_state::simulating, cells::{Sims, SimsUpdate}, logged, rule::Rule};
+
+// exists once the tour was finished or skipped.
+const DONE_PATH: &str = ".tour_done";
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Step {
+    Welcome,
+    SpawnNoise,
+    StepSim,
+    ChangeExample,
+    EditBirth,
+    SwitchBackend,
+    Done,
+}
+
+const STEPS: [Step; 7] = [
+    Step::Welcome, Step::SpawnNoise, Step::StepSim, Step::ChangeExample,
+    Step::EditBirth, Step::SwitchBackend, Step::Done,
+];
+
+impl Step {
+    fn text(&self) -> &'static str {
+        match self {
+            Step::Welcome =>
+                "every cell in the volume is alive or dead. each tick, dead cells with the right number \
+                of live neighbors are born and live cells with the wrong number start to decay.",
+            Step::SpawnNoise =>
+                "press \"spawn noise\" to drop a cube of random cells into the middle.",
+            Step::StepSim =>
+                "check \"paused\", then press \"step\" to advance a single tick.",
+            Step::ChangeExample =>
+                "pick one of the examples at the bottom. each one is a different rule with its own colors.",
+            Step::EditBirth =>
+                "open the console and type `rule.birth.add(5)`. cells with 5 neighbors are now born too.",
+            Step::SwitchBackend =>
+                "switch the simulator. they all run the same rule, some are faster for big volumes.",
+            Step::Done =>
+                "that's it. the other windows have more: camera, rule view, seeding, timeline and so on. \
+                F1 starts this tour again.",
+        }
+    }
+
+    // the window to outline.
+    fn window(&self) -> Option<&'static str> {
+        match self {
+            Step::Welcome | Step::Done => None,
+            Step::EditBirth => Some("Console"),
+            _ => Some("Celluar!"),
+        }
+    }
+}
+
+
+// what the sim looked like when the step began, to tell when it's done.
+struct Baseline {
+    noise_frames: u64,
+    rule: Rule,
+    backend: String,
+}
+
+impl Baseline {
+    fn new(sims: &Sims) -> Baseline {
+        Baseline {
+            noise_frames: sims.noise_frames(),
+            rule: sims.rule().clone(),
+            backend: sims.active_name().to_string(),
+        }
+    }
+}
+
+
+// a first-run walk through the basics. each step waits for the user to
+// do the thing, then moves on.
+pub struct Tour {
+    step: Option<usize>,
+    baseline: Option<Baseline>,
+    // the tick when the sim got paused, during `Step::StepSim`.
+    paused_at: Option<u64>,
+}
+
+impl Tour {
+    pub fn new() -> Tour {
+        let first_run = !std::path::Path::new(DONE_PATH).exists();
+        Tour {
+            step: if first_run { Some(0) } else { None },
+            baseline: None,
+            paused_at: None,
+        }
+    }
+
+    fn advance(&mut self) {
+        self.step = self.step.map(|s| s + 1).filter(|s| *s < STEPS.len());
+        self.baseline = None;
+        self.paused_at = None;
+        if self.step.is_none() {
+            self.finish();
+        }
+    }
+
+    fn finish(&mut self) {
+        self.step = None;
+        if let Err(error) = fs::write(DONE_PATH, "") {
+            logged!(warn, "failed to write {}: {}", DONE_PATH, error);
+        }
+    }
+
+    fn completed(&mut self, step: Step, sims: &Sims) -> bool {
+        let baseline = self.baseline.get_or_insert_with(|| Baseline::new(sims));
+        match step {
+            Step::Welcome | Step::Done => false,
+            Step::SpawnNoise => sims.noise_frames() > baseline.noise_frames,
+            Step::StepSim => {
+                if !sims.is_paused() {
+                    self.paused_at = None;
+                    return false;
+                }
+                sims.tick() > *self.paused_at.get_or_insert(sims.tick())
+            }
+            Step::ChangeExample => *sims.rule() != baseline.rule,
+            Step::EditBirth => sims.rule().birth_rule != baseline.rule.birth_rule,
+            Step::SwitchBackend => sims.active_name() != baseline.backend,
+        }
+    }
+}
+
+
+pub fn update(
+    mut this: ResMut<Tour>,
+    sims: Res<Sims>,
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    if !egui_context.ctx_mut().wants_keyboard_input() && keys.just_pressed(KeyCode::F1) {
+        *this = Tour { step: Some(0), baseline: None, paused_at: None };
+    }
+
+    let index = match this.step {
+        Some(index) => index,
+        None => return,
+    };
+    let step = STEPS[index];
+    if this.completed(step, &sims) {
+        this.advance();
+        return;
+    }
+
+    let ctx = egui_context.ctx_mut();
+    if let Some(rect) = step.window().and_then(|window| ctx.memory().area_rect(egui::Id::new(window))) {
+        let pulse = 0.5 + 0.5 * (time.seconds_since_startup() as f32 * 4.0).sin();
+        let color = egui::Color32::from_rgb(255, 200, 40).linear_multiply(0.4 + 0.6 * pulse);
+        ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("tour highlight")))
+            .rect_stroke(rect.expand(4.0), 6.0, egui::Stroke::new(3.0, color));
+    }
+
+    egui::Area::new("tour")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 16.0))
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_max_width(360.0);
+                ui.label(egui::RichText::new(format!("Tour {}/{}", index + 1, STEPS.len())).strong());
+                ui.label(step.text());
+                ui.horizontal(|ui| {
+                    match step {
+                        Step::Welcome => if ui.button("start").clicked() { this.advance(); },
+                        Step::Done => if ui.button("finish").clicked() { this.finish(); },
+                        _ => if ui.button("skip step").clicked() { this.advance(); },
+                    }
+                    if step != Step::Done && ui.button("end tour").clicked() {
+                        this.finish();
+                    }
+                });
+            });
+        });
+}
+
+
+pub struct TourPlugin;
+impl Plugin for TourPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(Tour::new())
+        .add_system(update.after(SimsUpdate).with_run_criteria(simulating));
+    }
+}
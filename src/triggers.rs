@@ -0,0 +1,246 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    app_state::simulating,
+    cell_renderer::CellRenderer,
+    cells::{Sims, SimsUpdate},
+    neighbours::VONNEUMAN_NEIGHBOURS,
+    utils,
+};
+
+// how many past ticks are remembered for period detection.
+const PERIOD_HISTORY: usize = 64;
+// a grid that repeats every tick has stopped changing, that isn't a period.
+const MIN_PERIOD: usize = 2;
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TriggerKind {
+    PopulationAbove,
+    PopulationBelow,
+    ReachesBoundary,
+    PeriodDetected,
+    ComponentCountChanged,
+}
+
+impl TriggerKind {
+    fn uses_threshold(&self) -> bool {
+        matches!(self, TriggerKind::PopulationAbove | TriggerKind::PopulationBelow)
+    }
+}
+
+pub struct Trigger {
+    pub kind: TriggerKind,
+    pub threshold: usize,
+    pub enabled: bool,
+}
+
+// cheap per-tick measurements, computed once and shared by all triggers.
+struct TickStats {
+    population: usize,
+    touches_boundary: bool,
+    period: Option<usize>,
+    components: Option<usize>,
+}
+
+
+pub struct Triggers {
+    triggers: Vec<Trigger>,
+    last_tick: u64,
+    // `Sims::restarts` when the history was started.
+    restarts: u64,
+    history: VecDeque<u64>,
+    components: Option<usize>,
+    fired: Option<String>,
+    new_kind: TriggerKind,
+}
+
+impl Triggers {
+    pub fn new() -> Triggers {
+        Triggers {
+            triggers: vec![],
+            last_tick: 0,
+            restarts: 0,
+            history: VecDeque::with_capacity(PERIOD_HISTORY),
+            components: None,
+            fired: None,
+            new_kind: TriggerKind::PopulationAbove,
+        }
+    }
+
+    pub fn add(&mut self, kind: TriggerKind, threshold: usize) {
+        self.triggers.push(Trigger { kind, threshold, enabled: true });
+    }
+
+    fn wants(&self, kind: TriggerKind) -> bool {
+        self.triggers.iter().any(|t| t.enabled && t.kind == kind)
+    }
+
+    fn measure(&mut self, renderer: &CellRenderer) -> TickStats {
+        let bounds = renderer.bounds;
+        let mut population = 0;
+        let mut touches_boundary = false;
+        for (index, value) in renderer.values.iter().enumerate() {
+            if *value == 0 {
+                continue;
+            }
+            population += 1;
+            if !touches_boundary {
+                let pos = utils::index_to_pos(index, bounds);
+                touches_boundary = pos.min_element() == 0 || pos.max_element() == bounds - 1;
+            }
+        }
+
+        let hash = renderer.hash();
+        let period = self.history.iter().rev().position(|h| *h == hash)
+            .map(|p| p + 1)
+            .filter(|p| *p >= MIN_PERIOD);
+        if self.history.len() == PERIOD_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(hash);
+
+        let components = match self.wants(TriggerKind::ComponentCountChanged) {
+            true  => Some(count_components(renderer)),
+            false => None,
+        };
+
+        TickStats { population, touches_boundary, period, components }
+    }
+
+    fn evaluate(&mut self, renderer: &CellRenderer) -> Option<String> {
+        let stats = self.measure(renderer);
+        let old_components = std::mem::replace(&mut self.components, stats.components);
+
+        let mut message = None;
+        for trigger in self.triggers.iter_mut().filter(|t| t.enabled) {
+            let fired = match trigger.kind {
+                TriggerKind::PopulationAbove => (stats.population > trigger.threshold)
+                    .then(|| format!("population {} > {}", stats.population, trigger.threshold)),
+                TriggerKind::PopulationBelow => (stats.population < trigger.threshold)
+                    .then(|| format!("population {} < {}", stats.population, trigger.threshold)),
+                TriggerKind::ReachesBoundary => stats.touches_boundary
+                    .then(|| "a cell reached the boundary".to_string()),
+                TriggerKind::PeriodDetected => stats.period
+                    .map(|p| format!("period {} detected", p)),
+                TriggerKind::ComponentCountChanged => match (old_components, stats.components) {
+                    (Some(old), Some(new)) if old != new =>
+                        Some(format!("components changed {} -> {}", old, new)),
+                    _ => None,
+                },
+            };
+
+            // triggers disarm after firing, otherwise unpausing would immediately pause again.
+            if fired.is_some() {
+                trigger.enabled = false;
+                message = message.or(fired);
+            }
+        }
+        message
+    }
+}
+
+
+// 6-connected flood fill over live cells. wraps around like the sims do.
+pub fn count_components(renderer: &CellRenderer) -> usize {
+    let bounds = renderer.bounds;
+    let mut visited = vec![false; renderer.values.len()];
+    let mut stack = vec![];
+    let mut count = 0;
+
+    for start in 0..renderer.values.len() {
+        if visited[start] || renderer.values[start] == 0 {
+            continue;
+        }
+        count += 1;
+        visited[start] = true;
+        stack.push(start);
+        while let Some(index) = stack.pop() {
+            let pos = utils::index_to_pos(index, bounds);
+            for dir in VONNEUMAN_NEIGHBOURS.iter() {
+                let neighbour = utils::pos_to_index(utils::wrap(pos + *dir, bounds), bounds);
+                if !visited[neighbour] && renderer.values[neighbour] != 0 {
+                    visited[neighbour] = true;
+                    stack.push(neighbour);
+                }
+            }
+        }
+    }
+    count
+}
+
+
+pub fn update(
+    mut this: ResMut<Triggers>,
+    mut sims: ResMut<Sims>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    // the history of another run would match periods that never happened.
+    if sims.restarts() != this.restarts {
+        this.restarts = sims.restarts();
+        this.history.clear();
+        this.components = None;
+    }
+    if sims.tick() != this.last_tick {
+        this.last_tick = sims.tick();
+        if !this.triggers.is_empty() {
+            if let Some(message) = this.evaluate(sims.renderer()) {
+                sims.set_paused(true);
+                this.fired = Some(format!("tick {}: {}", sims.tick(), message));
+            }
+        }
+    }
+
+    egui::Window::new("Triggers").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        let mut remove = None;
+        for (i, trigger) in this.triggers.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut trigger.enabled, format!("{:?}", trigger.kind));
+                if trigger.kind.uses_threshold() {
+                    ui.add(egui::DragValue::new(&mut trigger.threshold).speed(100.0));
+                }
+                if ui.small_button("x").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            this.triggers.remove(i);
+        }
+
+        ui.horizontal(|ui| {
+            let mut kind = this.new_kind;
+            egui::ComboBox::from_id_source("new trigger")
+                .selected_text(format!("{:?}", kind))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut kind, TriggerKind::PopulationAbove, "Population above");
+                    ui.selectable_value(&mut kind, TriggerKind::PopulationBelow, "Population below");
+                    ui.selectable_value(&mut kind, TriggerKind::ReachesBoundary, "Reaches boundary");
+                    ui.selectable_value(&mut kind, TriggerKind::PeriodDetected, "Period detected");
+                    ui.selectable_value(&mut kind, TriggerKind::ComponentCountChanged, "Component count changed");
+                });
+            this.new_kind = kind;
+
+            if ui.button("add").clicked() {
+                this.add(kind, 10_000);
+            }
+        });
+
+        if let Some(fired) = &this.fired {
+            ui.label(format!("paused at {}", fired));
+        }
+    });
+}
+
+
+pub struct TriggersPlugin;
+impl Plugin for TriggersPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(Triggers::new())
+        .add_system(update.after(SimsUpdate).with_run_criteria(simulating));
+    }
+}
@@ -1,5 +1,5 @@
 use bevy::{
-    math::{ivec3, IVec3, Vec4},
+    math::{ivec3, vec3, IVec3, Vec3, Vec4},
     prelude::Color,
 };
 use std::ops::RangeInclusive;
@@ -17,14 +17,24 @@ pub fn wrap(pos: IVec3, bounds: i32) -> IVec3 {
     (pos + bounds) % bounds
 }
 
+// `wrap` for positions from outside the backends (the ui, the console, the
+// network), which can be any distance off the grid.
+pub fn wrap_any(pos: IVec3, bounds: i32) -> IVec3 {
+    ivec3(pos.x.rem_euclid(bounds), pos.y.rem_euclid(bounds), pos.z.rem_euclid(bounds))
+}
+
 pub fn dist_to_center(cell_pos: IVec3, bounds: i32) -> f32 {
     let cell_pos = cell_pos - center(bounds);
     let max = bounds as f32 / 2.0;
     cell_pos.as_vec3().length() / max
 }
 
-pub fn make_some_noise<F: FnMut(IVec3)>(center: IVec3, radius: i32, amount: usize, mut f: F) {
-    let mut rand = rand::thread_rng();
+pub fn make_some_noise<F: FnMut(IVec3)>(center: IVec3, radius: i32, amount: usize, f: F) {
+    make_some_noise_with(&mut rand::thread_rng(), center, radius, amount, f)
+}
+
+// with a seeded rng, the same seed gives the same noise.
+pub fn make_some_noise_with<R: Rng, F: FnMut(IVec3)>(rand: &mut R, center: IVec3, radius: i32, amount: usize, mut f: F) {
     (0..amount).for_each(|_| {
         f(center + ivec3(
             rand.gen_range(-radius..=radius),
@@ -34,8 +44,98 @@ pub fn make_some_noise<F: FnMut(IVec3)>(center: IVec3, radius: i32, amount: usiz
     });
 }
 
+pub const NOISE_RADIUS: i32 = 6;
+pub const NOISE_AMOUNT: usize = 12*12*12;
+
 pub fn make_some_noise_default<F: FnMut(IVec3)>(center: IVec3, f: F) {
-    make_some_noise(center, 6, 12*12*12, f)
+    make_some_noise(center, NOISE_RADIUS, NOISE_AMOUNT, f)
+}
+
+// splitmix64 finalizer. stable across runs, good enough to pick "random" cells.
+pub fn hash_u64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(GOLDEN_GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+const GOLDEN_GAMMA: u64 = 0x9e3779b97f4a7c15;
+
+// splitmix64 as an rng. the nth number of a stream only depends on the seed
+// and n, so `at` can start a stream anywhere, eg. one per cell index, and
+// each cell gets the same numbers no matter which thread draws them.
+pub struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> SplitMix64 {
+        SplitMix64(seed)
+    }
+
+    // the stream of `seed`, skipped ahead by `n` numbers.
+    pub fn at(seed: u64, n: u64) -> SplitMix64 {
+        SplitMix64(seed.wrapping_add(n.wrapping_mul(GOLDEN_GAMMA)))
+    }
+}
+
+impl rand::RngCore for SplitMix64 {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let value = hash_u64(self.0);
+        self.0 = self.0.wrapping_add(GOLDEN_GAMMA);
+        value
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// 3d gradient (perlin style) noise, roughly in -1..1. deterministic for a seed.
+pub fn gradient_noise(p: Vec3, seed: u64) -> f32 {
+    let cell = p.floor();
+    let f = p - cell;
+    let cell = cell.as_ivec3();
+
+    let gradient = |corner: IVec3| {
+        let key = (corner.x as u32 as u64)
+            ^ (corner.y as u32 as u64) << 21
+            ^ (corner.z as u32 as u64) << 42;
+        let hash = hash_u64(key ^ hash_u64(seed));
+        // the 12 edge directions of a cube.
+        match hash % 12 {
+            0  => vec3( 1.0,  1.0,  0.0), 1  => vec3(-1.0,  1.0,  0.0),
+            2  => vec3( 1.0, -1.0,  0.0), 3  => vec3(-1.0, -1.0,  0.0),
+            4  => vec3( 1.0,  0.0,  1.0), 5  => vec3(-1.0,  0.0,  1.0),
+            6  => vec3( 1.0,  0.0, -1.0), 7  => vec3(-1.0,  0.0, -1.0),
+            8  => vec3( 0.0,  1.0,  1.0), 9  => vec3( 0.0, -1.0,  1.0),
+            10 => vec3( 0.0,  1.0, -1.0), _  => vec3( 0.0, -1.0, -1.0),
+        }
+    };
+    let fade = |t: f32| t*t*t*(t*(t*6.0 - 15.0) + 10.0);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a)*t;
+
+    let mut corners = [0.0; 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+        let offset = ivec3(i as i32 & 1, (i as i32 >> 1) & 1, (i as i32 >> 2) & 1);
+        *corner = gradient(cell + offset).dot(f - offset.as_vec3());
+    }
+    let (u, v, w) = (fade(f.x), fade(f.y), fade(f.z));
+    let x00 = lerp(corners[0], corners[1], u);
+    let x10 = lerp(corners[2], corners[3], u);
+    let x01 = lerp(corners[4], corners[5], u);
+    let x11 = lerp(corners[6], corners[7], u);
+    lerp(lerp(x00, x10, v), lerp(x01, x11, v), w)
 }
 
 pub fn lerp_color(color_1: Color, color_2: Color, dt: f32) -> Color {
@@ -47,6 +147,8 @@ pub fn lerp_color(color_1: Color, color_2: Color, dt: f32) -> Color {
 
 
 pub fn index_to_pos(index: usize, bounds: i32) -> IVec3 {
+    #[cfg(feature = "bounds_audit")]
+    audit::check_index(index, (bounds*bounds*bounds) as usize, "index_to_pos");
     ivec3(
         index as i32 % bounds,
         index as i32 / bounds % bounds,
@@ -54,13 +156,86 @@ pub fn index_to_pos(index: usize, bounds: i32) -> IVec3 {
 }
 
 pub fn pos_to_index(pos: IVec3, bounds: i32) -> usize {
+    #[cfg(feature = "bounds_audit")]
+    audit::check_pos(pos, bounds, "pos_to_index");
     let x = pos.x as usize;
     let y = pos.y as usize;
     let z = pos.z as usize;
     let bounds = bounds as usize;
     x + y*bounds + z*bounds*bounds
 }
-    
+
+
+// the lower 21 bits of `x`, with two zero bits between each.
+fn spread_bits(x: u64) -> u64 {
+    let mut x = x & 0x1f_ffff;
+    x = (x | x << 32) & 0x1f_0000_0000_ffff;
+    x = (x | x << 16) & 0x1f_0000_ff00_00ff;
+    x = (x | x <<  8) & 0x100f_00f0_0f00_f00f;
+    x = (x | x <<  4) & 0x10c3_0c30_c30c_30c3;
+    x = (x | x <<  2) & 0x1249_2492_4924_9249;
+    x
+}
+
+fn compact_bits(x: u64) -> u64 {
+    let mut x = x & 0x1249_2492_4924_9249;
+    x = (x | x >>  2) & 0x10c3_0c30_c30c_30c3;
+    x = (x | x >>  4) & 0x100f_00f0_0f00_f00f;
+    x = (x | x >>  8) & 0x1f_0000_ff00_00ff;
+    x = (x | x >> 16) & 0x1f_0000_0000_ffff;
+    x = (x | x >> 32) & 0x1f_ffff;
+    x
+}
+
+// z-order: interleaves the bits of x, y and z, so cells that are close in
+// space tend to be close in memory. only dense for power of two bounds.
+pub fn morton_encode(pos: IVec3) -> usize {
+    let x = spread_bits(pos.x as u64);
+    let y = spread_bits(pos.y as u64);
+    let z = spread_bits(pos.z as u64);
+    (x | y << 1 | z << 2) as usize
+}
+
+pub fn morton_decode(index: usize) -> IVec3 {
+    let index = index as u64;
+    ivec3(
+        compact_bits(index) as i32,
+        compact_bits(index >> 1) as i32,
+        compact_bits(index >> 2) as i32)
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CellLayout {
+    RowMajor,
+    Morton,
+}
+
+impl CellLayout {
+    // morton storage is padded to the next power of two.
+    pub fn storage_size(&self, bounds: i32) -> usize {
+        let side = match self {
+            CellLayout::RowMajor => bounds as usize,
+            CellLayout::Morton   => (bounds as usize).next_power_of_two(),
+        };
+        side*side*side
+    }
+
+    pub fn pos_to_index(&self, pos: IVec3, bounds: i32) -> usize {
+        match self {
+            CellLayout::RowMajor => pos_to_index(pos, bounds),
+            CellLayout::Morton   => morton_encode(pos),
+        }
+    }
+
+    pub fn index_to_pos(&self, index: usize, bounds: i32) -> IVec3 {
+        match self {
+            CellLayout::RowMajor => index_to_pos(index, bounds),
+            CellLayout::Morton   => morton_decode(index),
+        }
+    }
+}
+
 
 pub fn get_bounding_ranges(bounds: i32)
     -> (RangeInclusive<i32>, RangeInclusive<i32>, RangeInclusive<i32>)
@@ -75,3 +250,66 @@ pub fn center(bounds: i32) -> IVec3 {
     let center = bounds/2;
     ivec3(center, center, center)
 }
+
+
+// checked index math for development, enabled with the `bounds_audit`
+// feature. violations panic with the position, the bounds and the backend
+// that was updating at the time.
+#[cfg(feature = "bounds_audit")]
+pub mod audit {
+    use bevy::math::IVec3;
+    use std::sync::Mutex;
+
+    static BACKEND: Mutex<String> = Mutex::new(String::new());
+
+    pub fn set_backend(name: &str) {
+        let mut backend = BACKEND.lock().unwrap();
+        backend.clear();
+        backend.push_str(name);
+    }
+
+    fn backend() -> String {
+        BACKEND.lock().map(|b| b.clone()).unwrap_or_default()
+    }
+
+    pub fn check_pos(pos: IVec3, bounds: i32, what: &str) {
+        if pos.min_element() < 0 || pos.max_element() >= bounds {
+            panic!("bounds audit: {} got {} outside of bounds {} (backend: `{}`)",
+                what, pos, bounds, backend());
+        }
+    }
+
+    pub fn check_index(index: usize, len: usize, what: &str) {
+        if index >= len {
+            panic!("bounds audit: {} got index {} outside of 0..{} (backend: `{}`)",
+                what, index, len, backend());
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn morton_round_trip() {
+        let bounds = 64;
+        for index in 0..(bounds*bounds*bounds) as usize {
+            let pos = morton_decode(index);
+            assert!(pos.min_element() >= 0 && pos.max_element() < bounds, "{} -> {}", index, pos);
+            assert_eq!(morton_encode(pos), index, "{}", pos);
+        }
+    }
+
+    #[test]
+    fn morton_interleaves_the_axes() {
+        assert_eq!(morton_encode(ivec3(1, 0, 0)), 0b001);
+        assert_eq!(morton_encode(ivec3(0, 1, 0)), 0b010);
+        assert_eq!(morton_encode(ivec3(0, 0, 1)), 0b100);
+        assert_eq!(morton_encode(ivec3(3, 3, 3)), 0b111_111);
+        // the largest coordinate that fits, 21 bits per axis.
+        let max = (1 << 21) - 1;
+        assert_eq!(morton_decode(morton_encode(ivec3(max, 0, max))), ivec3(max, 0, max));
+    }
+}
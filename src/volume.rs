@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    app_state::simulating,
+    background::Background,
+    cell_renderer::InstanceMaterialData,
+};
+
+
+// places the simulation volume in the world. written to the `Transform` of
+// every cell entity, the shader applies it through the mesh uniform
+// (`mesh.model` in cell.wgsl), so moving the volume never rebuilds the
+// instances. the overlays (prediction, neighbour field) follow along, the
+// background sphere doesn't.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VolumeTransform {
+    pub translation: Vec3,
+    // euler angles in degrees, applied y, x, z.
+    pub rotation: Vec3,
+    pub scale: f32,
+}
+
+impl Default for VolumeTransform {
+    fn default() -> Self {
+        VolumeTransform {
+            translation: Vec3::ZERO,
+            rotation: Vec3::ZERO,
+            scale: 1.0,
+        }
+    }
+}
+
+impl VolumeTransform {
+    pub fn transform(&self) -> Transform {
+        let rotation = Quat::from_euler(EulerRot::YXZ,
+            self.rotation.y.to_radians(),
+            self.rotation.x.to_radians(),
+            self.rotation.z.to_radians());
+        Transform {
+            translation: self.translation,
+            rotation,
+            scale: Vec3::splat(self.scale),
+        }
+    }
+
+    pub fn to_world(&self, point: Vec3) -> Vec3 {
+        self.transform().compute_matrix().transform_point3(point)
+    }
+
+    // the ray in grid space. the direction isn't normalized again, so a
+    // distance along it is the same in both spaces.
+    pub fn ray_to_volume(&self, origin: Vec3, direction: Vec3) -> (Vec3, Vec3) {
+        let inverse = self.transform().compute_matrix().inverse();
+        (inverse.transform_point3(origin), inverse.transform_vector3(direction))
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == VolumeTransform::default()
+    }
+}
+
+
+fn apply(
+    this: Res<VolumeTransform>,
+    mut volumes: Query<&mut Transform, (With<InstanceMaterialData>, Without<Background>)>,
+) {
+    // every frame, overlays spawn with the default transform.
+    let transform = this.transform();
+    for mut volume in volumes.iter_mut() {
+        if *volume != transform {
+            *volume = transform;
+        }
+    }
+}
+
+fn update_ui(
+    mut this: ResMut<VolumeTransform>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Volume").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        let mut volume = *this;
+        ui.horizontal(|ui| {
+            ui.label("position");
+            ui.add(egui::DragValue::new(&mut volume.translation.x).speed(0.5).prefix("x: "));
+            ui.add(egui::DragValue::new(&mut volume.translation.y).speed(0.5).prefix("y: "));
+            ui.add(egui::DragValue::new(&mut volume.translation.z).speed(0.5).prefix("z: "));
+        });
+        ui.horizontal(|ui| {
+            ui.label("rotation");
+            ui.add(egui::DragValue::new(&mut volume.rotation.x).speed(1.0).clamp_range(-180.0..=180.0).prefix("x: ").suffix("°"));
+            ui.add(egui::DragValue::new(&mut volume.rotation.y).speed(1.0).clamp_range(-180.0..=180.0).prefix("y: ").suffix("°"));
+            ui.add(egui::DragValue::new(&mut volume.rotation.z).speed(1.0).clamp_range(-180.0..=180.0).prefix("z: ").suffix("°"));
+        });
+        ui.add(egui::Slider::new(&mut volume.scale, 0.1..=4.0).logarithmic(true).text("scale"));
+        if ui.add_enabled(!volume.is_identity(), egui::Button::new("reset")).clicked() {
+            volume = VolumeTransform::default();
+        }
+        *this = volume;
+    });
+}
+
+
+pub struct VolumePlugin;
+impl Plugin for VolumePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<VolumeTransform>()
+        .add_system(update_ui.with_run_criteria(simulating))
+        .add_system(apply);
+    }
+}
@@ -0,0 +1,150 @@
+use bevy::{prelude::*, window::WindowId, winit::WinitWindows};
+use bevy_egui::{egui, EguiContext};
+
+use crate::{app_state::simulating, cells::Sims, logged};
+
+const ICON_SIZE: u32 = 64;
+// seconds between title updates, the stats are averaged over it.
+const INTERVAL: f64 = 0.5;
+
+
+// the primary window's title, optionally with the rule, ticks per second and
+// fps. handy to tell apart several instances, eg. when comparing backends.
+pub struct WindowTitle {
+    pub title: String,
+    pub live_stats: bool,
+    last_update: f64,
+    last_tick: u64,
+    frames: u32,
+}
+
+impl Default for WindowTitle {
+    fn default() -> Self {
+        WindowTitle {
+            title: "celluar automata".into(),
+            live_stats: true,
+            last_update: 0.0,
+            last_tick: 0,
+            frames: 0,
+        }
+    }
+}
+
+
+pub fn update_ui(
+    mut this: ResMut<WindowTitle>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Window").default_open(false).show(egui_context.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("title");
+            ui.text_edit_singleline(&mut this.title);
+        });
+        ui.checkbox(&mut this.live_stats, "rule, ticks/s and fps in the title");
+    });
+}
+
+pub fn update_title(
+    mut this: ResMut<WindowTitle>,
+    sims: Res<Sims>,
+    time: Res<Time>,
+    mut windows: ResMut<Windows>,
+) {
+    this.frames += 1;
+    let now = time.seconds_since_startup();
+    let elapsed = now - this.last_update;
+    if elapsed < INTERVAL {
+        return;
+    }
+
+    let title = if this.live_stats {
+        let ticks = sims.tick().saturating_sub(this.last_tick);
+        format!("{} - {} - {:.0} ticks/s - {:.0} fps",
+            this.title, sims.rule(), ticks as f64 / elapsed, this.frames as f64 / elapsed)
+    }
+    else {
+        this.title.clone()
+    };
+    if let Some(window) = windows.get_primary_mut() {
+        if window.title() != title {
+            window.set_title(title);
+        }
+    }
+
+    this.last_update = now;
+    this.last_tick = sims.tick();
+    this.frames = 0;
+}
+
+
+// a cube of 3x3x3 cells from above, in the default colors.
+fn icon_rgba(size: u32) -> Vec<u8> {
+    let (cos, sin) = (30f32.to_radians().cos(), 30f32.to_radians().sin());
+    let down     = Vec2::new(0.0, 1.0);
+    let up_left  = Vec2::new(-cos, -sin);
+    let up_right = Vec2::new( cos, -sin);
+    let faces = [
+        (up_left, up_right, Color::YELLOW),
+        (up_left, down, Color::ORANGE),
+        (up_right, down, Color::RED),
+    ];
+
+    let mut rgba = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            // the corner the three faces share is the center of the icon.
+            let p = (Vec2::new(x as f32, y as f32) + 0.5) / size as f32 * 2.0 - 1.0;
+            let face = faces.iter().find_map(|(e1, e2, color)| {
+                let inverse = Mat2::from_cols(*e1, *e2).inverse();
+                let uv = inverse * p;
+                let inside = uv.min_element() >= 0.0 && uv.max_element() <= 1.0;
+                inside.then(|| (uv, *color))
+            });
+            let pixel = match face {
+                Some((uv, color)) => {
+                    // dark lines between the cells.
+                    let cell = (uv * 3.0).fract();
+                    let line = cell.min_element() < 0.08 || cell.max_element() > 0.92;
+                    let color = if line { color * 0.5 } else { color };
+                    let [r, g, b, _] = color.as_rgba_f32();
+                    [(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8, 255]
+                }
+                None => [0; 4],
+            };
+            rgba.extend(pixel);
+        }
+    }
+    rgba
+}
+
+// the winit window only exists after the first frames, so this retries
+// until it does.
+pub fn set_icon(
+    mut done: Local<bool>,
+    winit_windows: NonSend<WinitWindows>,
+) {
+    if *done {
+        return;
+    }
+    let window = match winit_windows.get_window(WindowId::primary()) {
+        Some(window) => window,
+        None => return,
+    };
+    match winit::window::Icon::from_rgba(icon_rgba(ICON_SIZE), ICON_SIZE, ICON_SIZE) {
+        Ok(icon) => window.set_window_icon(Some(icon)),
+        Err(error) => logged!(warn, "couldn't create the window icon: {}", error),
+    }
+    *done = true;
+}
+
+
+pub struct WindowTitlePlugin;
+impl Plugin for WindowTitlePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<WindowTitle>()
+        .add_system(update_ui.with_run_criteria(simulating))
+        .add_system(update_title)
+        .add_system(set_icon);
+    }
+}
@@ -0,0 +1,143 @@
+// runs a scripted session on the app, updated by hand. scripted actions go
+// through the same `Action` events as the ui, and every frame checks that
+// the sims and the renderer agree.
+// `minimal_app` has only the simulation's plugins on bevy's minimal ones, no
+// window, render app or ui, so it runs anywhere. `full_app` builds the whole
+// app like main.rs does, minus the window. its render plugin needs a gpu
+// adapter (a software one like lavapipe does), bevy 0.6 can't run without
+// one, so that one only runs with `cargo test -- --ignored`.
+
+use bevy::{prelude::*, winit::WinitPlugin};
+use celluar_automata::{
+    action::{Action, ActionPlugin},
+    app_state::{AppState, AppStatePlugin},
+    cell_renderer::{CellShaderSettings, InstanceMaterialData},
+    cells::{Sims, SimsPlugin},
+    example_library,
+    graphics::GraphicsSettings,
+    plugins,
+    threading::ThreadingPlugin,
+    utils,
+};
+
+const BOUNDS: i32 = 32;
+
+
+fn minimal_app() -> App {
+    let mut app = App::new();
+    app
+        .add_plugins(MinimalPlugins)
+        .add_plugin(ThreadingPlugin)
+        .add_plugin(SimsPlugin)
+        .add_plugin(ActionPlugin)
+        .add_plugin(AppStatePlugin { backends: plugins::backends })
+        // what the render and graphics plugins would add.
+        .init_resource::<GraphicsSettings>()
+        .init_resource::<CellShaderSettings>();
+    app.world.spawn().insert(InstanceMaterialData(vec![]));
+
+    let mut sims = app.world.get_resource_mut::<Sims>().unwrap();
+    for (name, sim) in plugins::backends() {
+        sims.add_sim(name, sim);
+    }
+    sims.add_example(example_library::fallback_example());
+    sims.set_example(0);
+    app.update();
+    app
+}
+
+fn full_app() -> App {
+    let mut app = App::new();
+    app.add_plugins_with(DefaultPlugins, |group| group.disable::<WinitPlugin>());
+    plugins::add_plugins(&mut app);
+    // the startup systems add the backends and examples.
+    app.update();
+    app
+}
+
+fn sims(app: &App) -> &Sims {
+    app.world.get_resource::<Sims>().unwrap()
+}
+
+fn send(app: &mut App, action: Action) {
+    app.world.get_resource_mut::<Events<Action>>().unwrap().send(action);
+}
+
+fn run(app: &mut App, frames: usize) {
+    for _ in 0..frames {
+        app.update();
+        check(app);
+    }
+}
+
+fn check(app: &App) {
+    let sims = sims(app);
+    let bounds = sims.bounds();
+    let renderer = sims.renderer();
+    let cells = (bounds*bounds*bounds) as usize;
+    assert_eq!(renderer.bounds, bounds, "{}: renderer bounds", sims.active_name());
+    assert_eq!(renderer.values.len(), cells, "{}: renderer buffer", sims.active_name());
+    assert!(sims.cell_count() <= cells, "{}: {} cells in {}³", sims.active_name(), sims.cell_count(), bounds);
+}
+
+// noise spawned while paused, so no tick changes it before the check.
+fn spawn_noise_paused(app: &mut App) -> usize {
+    send(app, Action::SetPaused(true));
+    run(app, 1);
+    send(app, Action::SpawnNoise);
+    run(app, 2);
+    sims(app).cell_count()
+}
+
+
+#[test]
+fn scripted_session() {
+    script(&mut minimal_app());
+}
+
+#[test]
+#[ignore = "needs a gpu adapter"]
+fn scripted_session_with_rendering() {
+    script(&mut full_app());
+}
+
+fn script(app: &mut App) {
+    app.world.get_resource_mut::<Sims>().unwrap().start(0, 0, BOUNDS);
+    app.world.get_resource_mut::<State<AppState>>().unwrap().set(AppState::Running).unwrap();
+    run(app, 2);
+    assert_eq!(sims(app).bounds(), BOUNDS);
+
+    let noise = spawn_noise_paused(app);
+    assert!(noise > 0 && noise <= utils::NOISE_AMOUNT, "{} cells of noise", noise);
+    send(app, Action::SetPaused(false));
+    run(app, 10);
+    assert!(sims(app).tick() > 0);
+
+    // every backend, each from fresh noise.
+    let names: Vec<String> = sims(app).sim_names().map(String::from).collect();
+    for (index, name) in names.iter().enumerate() {
+        send(app, Action::SetSim(index));
+        run(app, 1);
+        assert_eq!(sims(app).active_name(), name);
+        assert!(spawn_noise_paused(app) > 0, "{}: no cells after spawning noise", name);
+        send(app, Action::SetPaused(false));
+        run(app, 5);
+    }
+
+    send(app, Action::SetSim(0));
+    let examples: Vec<_> = sims(app).examples().iter().map(|example| example.rule.clone()).collect();
+    for rule in examples {
+        send(app, Action::SetRule(rule.clone()));
+        run(app, 5);
+        assert_eq!(sims(app).rule().states, rule.states);
+    }
+
+    send(app, Action::SetBounds(2 * BOUNDS));
+    run(app, 2);
+    assert_eq!(sims(app).bounds(), 2 * BOUNDS);
+
+    send(app, Action::SetPaused(true));
+    send(app, Action::Reset);
+    run(app, 1);
+    assert_eq!(sims(app).cell_count(), 0);
+}
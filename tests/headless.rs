@@ -0,0 +1,130 @@
+// golden values of the cpu backends, no bevy app or gpu needed. a hash that
+// changes is a change in behavior: if it's on purpose, update the value and
+// say so in the commit.
+
+use bevy::tasks::logical_core_count;
+use celluar_automata::{
+    cell_renderer::CellRenderer,
+    cells::leddoo::{LeddooBitPacked, LeddooSingleThreaded},
+    example_library,
+    headless::{run_headless, run_headless_with, GridSnapshot},
+    neighbours::{NeighbourCount, NeighbourMethod},
+    plugins,
+    rule::{CellValue, Rule, Value},
+    self_test::{self, Outcome},
+    threading::Threading,
+    utils::CellLayout,
+};
+
+fn rule(survival: &[NeighbourCount], birth: &[NeighbourCount], states: CellValue, neighbour_method: NeighbourMethod) -> Rule {
+    Rule {
+        survival_rule: Value::new(survival),
+        birth_rule: Value::new(birth),
+        states,
+        neighbour_method,
+    }
+}
+
+fn check(snapshot: &GridSnapshot, population: usize, hash: u64) {
+    assert_eq!(snapshot.population(), population, "population after {} ticks", snapshot.ticks);
+    assert_eq!(snapshot.hash(), hash, "hash after {} ticks", snapshot.ticks);
+}
+
+
+#[test]
+fn golden_445() {
+    let rule = rule(&[4], &[4], 5, NeighbourMethod::Moore);
+    check(&run_headless(&rule, 3, 32, 100), 100, 0x22dee6d032bd76a0);
+}
+
+#[test]
+fn golden_von_neumann_pyramid() {
+    let rule = rule(&[0, 1, 2, 3, 4, 5, 6], &[1, 3], 2, NeighbourMethod::VonNeuman);
+    check(&run_headless(&rule, 1, 32, 50), 25566, 0x5d0289701d24070e);
+}
+
+#[test]
+fn golden_pretty_crystals() {
+    let rule = rule(&[5, 6, 7, 8], &[6, 7, 9], 10, NeighbourMethod::Moore);
+    check(&run_headless(&rule, 2, 32, 50), 2794, 0xc6e9aee8887371b7);
+}
+
+#[test]
+fn golden_two_states() {
+    let rule = rule(&[2, 3], &[3], 2, NeighbourMethod::Moore);
+    check(&run_headless(&rule, 4, 32, 50), 8828, 0x08ee7eed4ebbd3f2);
+}
+
+// the noise reaches past the edges of grids smaller than its radius.
+#[test]
+fn golden_small_grid() {
+    let rule = rule(&[0, 1, 2, 3, 4, 5, 6], &[1, 3], 2, NeighbourMethod::VonNeuman);
+    check(&run_headless(&rule, 0, 8, 10), 384, 0xbf2e9e91a0e02310);
+}
+
+#[test]
+fn same_seed_same_grid() {
+    let rule = rule(&[4], &[4], 5, NeighbourMethod::Moore);
+    assert_eq!(run_headless(&rule, 7, 32, 20), run_headless(&rule, 7, 32, 20));
+    assert_ne!(run_headless(&rule, 7, 32, 0).hash(), run_headless(&rule, 8, 32, 0).hash());
+}
+
+// the storage order doesn't change the simulation, 40 isn't a power of two
+// so the padding is in play too.
+#[test]
+fn morton_layout_matches_row_major() {
+    let rule = rule(&[5, 6, 7, 8], &[6, 7, 9], 10, NeighbourMethod::Moore);
+    let mut morton = LeddooSingleThreaded::with_layout(CellLayout::Morton);
+    let got = run_headless_with(&mut morton, &rule, 2, 40, 50).unwrap();
+    assert_eq!(got, run_headless(&rule, 2, 40, 50));
+}
+
+// the packed planes, not the fallback, since both rules have 2 states.
+#[test]
+fn bit_packed_matches_the_reference() {
+    let cases = [
+        (rule(&[2, 3], &[3], 2, NeighbourMethod::Moore), 4),
+        (rule(&[0, 1, 2, 3, 4, 5, 6], &[1, 3], 2, NeighbourMethod::VonNeuman), 5),
+    ];
+    for (rule, seed) in cases {
+        let got = run_headless_with(&mut LeddooBitPacked::new(), &rule, seed, 32, 50).unwrap();
+        let expected = run_headless(&rule, seed, 32, 50);
+        assert_eq!(got.population(), expected.population(), "seed {}", seed);
+        assert_eq!(got.hash(), expected.hash(), "seed {}", seed);
+    }
+}
+
+// every backend of the app, like the self test at startup.
+#[test]
+fn every_backend_agrees_with_the_reference() {
+    for (name, outcome) in self_test::run(plugins::backends()) {
+        assert!(!matches!(outcome, Outcome::Disagrees { .. }), "{} disagrees with the reference", name);
+    }
+}
+
+// what tests/app.rs checks per backend, without the app and the gpu.
+#[test]
+fn every_backend_keeps_its_invariants() {
+    let rule = example_library::fallback_example().rule;
+    let threading = Threading::new(logical_core_count());
+    for (name, mut sim) in plugins::backends() {
+        let bounds = sim.set_bounds(32);
+        assert!((1..=sim.max_bounds()).contains(&bounds), "{}: bounds of {}", name, bounds);
+        let cells = (bounds*bounds*bounds) as usize;
+
+        sim.spawn_noise(&rule);
+        assert!(sim.cell_count() > 0, "{}: no cells after spawning noise", name);
+        for _ in 0..5 {
+            sim.update(&rule, threading.pool());
+        }
+        assert!(sim.cell_count() <= cells, "{}: {} cells in {}³", name, sim.cell_count(), bounds);
+
+        let mut renderer = CellRenderer::new();
+        renderer.set_bounds(bounds);
+        sim.render(&mut renderer);
+        assert_eq!(renderer.values.len(), cells, "{}: renderer buffer", name);
+
+        sim.reset();
+        assert_eq!(sim.cell_count(), 0, "{}: cells after a reset", name);
+    }
+}